@@ -1,5 +1,6 @@
 use std::str::FromStr;
 
+use bytes::Bytes;
 use derive_builder::Builder;
 use rustify::{errors::ClientError, Client, Endpoint, MiddleWare};
 use rustify_derive::Endpoint;
@@ -62,7 +63,7 @@ impl MiddleWare for Middle {
     fn request<E: Endpoint>(
         &self,
         _: &E,
-        req: &mut http::Request<Vec<u8>>,
+        req: &mut http::Request<Bytes>,
     ) -> Result<(), ClientError> {
         // Prepending to the path of a URL is not a trivial task. Here we use
         // the `url` crate which offers better support for mutating a URL. We
@@ -79,7 +80,7 @@ impl MiddleWare for Middle {
     fn response<E: Endpoint>(
         &self,
         _: &E,
-        _: &mut http::Response<Vec<u8>>,
+        _: &mut http::Response<Bytes>,
     ) -> Result<(), ClientError> {
         Ok(())
     }
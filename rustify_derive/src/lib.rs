@@ -27,8 +27,11 @@ const ATTR_NAME: &str = "endpoint";
 #[derive(Debug, PartialEq, Eq, Hash)]
 pub(crate) enum EndpointAttribute {
     Body,
+    Cursor,
+    Header,
     Query,
     Raw,
+    Sensitive,
     Skip,
     Untagged,
 }
@@ -39,8 +42,11 @@ impl TryFrom<&Meta> for EndpointAttribute {
         match m.path().get_ident() {
             Some(i) => match i.to_string().to_lowercase().as_str() {
                 "body" => Ok(EndpointAttribute::Body),
+                "cursor" => Ok(EndpointAttribute::Cursor),
+                "header" => Ok(EndpointAttribute::Header),
                 "query" => Ok(EndpointAttribute::Query),
                 "raw" => Ok(EndpointAttribute::Raw),
+                "sensitive" => Ok(EndpointAttribute::Sensitive),
                 "skip" => Ok(EndpointAttribute::Skip),
                 _ => Err(Error::new(
                     m.span(),
@@ -56,11 +62,11 @@ impl TryFrom<&Meta> for EndpointAttribute {
 ///
 /// The string supplied by the end-user supports basic interpolation using curly
 /// braces. For example,
-/// ```
+/// ```text
 /// endpoint(path = "user/{self.name}")
 /// ```
 /// Should produce:
-/// ```
+/// ```text
 /// format!("user/{}", self.name);
 /// ```
 /// This is currently accomplished using a basic regular expression which
@@ -128,6 +134,66 @@ fn gen_query(
     }
 }
 
+/// Generates the headers method for generating request headers.
+///
+/// Any static headers passed to the `headers` parameter of the `endpoint`
+/// attribute are added first. Each field found with the [EndpointAttribute::Header] attribute is converted
+/// into a header using its string representation. The header name defaults to
+/// the field name with underscores replaced by dashes and can be overridden by
+/// passing a name to the attribute (i.e. `#[endpoint(header = "X-Token")]`).
+/// Fields which are an [Option] are only added when they contain a value. If
+/// no static headers are defined and the attribute is not found on any of the
+/// fields the headers method is not generated.
+fn gen_headers(
+    fields: &HashMap<EndpointAttribute, Vec<Field>>,
+    static_headers: &[(String, String)],
+) -> Result<proc_macro2::TokenStream, Error> {
+    let header_fields = fields
+        .get(&EndpointAttribute::Header)
+        .map(|v| v.as_slice())
+        .unwrap_or_default();
+    if header_fields.is_empty() && static_headers.is_empty() {
+        return Ok(quote! {});
+    }
+
+    let mut headers = static_headers
+        .iter()
+        .map(|(name, value)| {
+            quote! {
+                headers.push(build_header(#name, &#value)?);
+            }
+        })
+        .collect::<Vec<proc_macro2::TokenStream>>();
+    for field in header_fields {
+        let id = field.ident.clone().unwrap();
+        let name = match parse::field_value(field, "header")? {
+            Some(n) => n.value(),
+            None => id.to_string().replace('_', "-"),
+        };
+
+        if parse::is_std_option(&field.ty) {
+            headers.push(quote! {
+                if let Some(v) = &self.#id {
+                    headers.push(build_header(#name, v)?);
+                }
+            });
+        } else {
+            headers.push(quote! {
+                headers.push(build_header(#name, &self.#id)?);
+            });
+        }
+    }
+
+    Ok(quote! {
+        fn headers(&self) -> Result<Vec<(HeaderName, HeaderValue)>, ClientError> {
+            let mut headers = Vec::new();
+            #(#headers)*
+
+            Ok(headers)
+        }
+    })
+}
+
 /// Generates the body method for generating the request body.
 ///
 /// The final result is determined by which attributes are present and/or
@@ -186,6 +252,66 @@ fn gen_body(
     }
 }
 
+/// Generates the sensitive method for masking field values in logs.
+///
+/// The values of each field found with the [EndpointAttribute::Sensitive]
+/// attribute are collected using `sensitive_values`. If the attribute is not
+/// found on any of the fields the sensitive method is not generated.
+fn gen_sensitive(fields: &HashMap<EndpointAttribute, Vec<Field>>) -> proc_macro2::TokenStream {
+    let ids = match fields.get(&EndpointAttribute::Sensitive) {
+        Some(v) => v.iter().map(|f| f.ident.clone().unwrap()),
+        None => return quote! {},
+    };
+    quote! {
+        fn sensitive(&self) -> Vec<String> {
+            let mut values = Vec::new();
+            #(values.extend(rustify::http::sensitive_values(&self.#ids));)*
+            values
+        }
+    }
+}
+
+/// Generates an implementation of `CursorEndpoint` for paginating by cursor.
+///
+/// If a field is found with the [EndpointAttribute::Cursor] attribute, the
+/// cursor of the next page is assigned to that field using [Into]. Only a
+/// single field can be tagged with the attribute and it must also be tagged
+/// with [EndpointAttribute::Query] in order to be sent with the request.
+fn gen_cursor(
+    fields: &HashMap<EndpointAttribute, Vec<Field>>,
+    id: &Ident,
+    generics: &Generics,
+) -> Result<proc_macro2::TokenStream, Error> {
+    let field = match fields.get(&EndpointAttribute::Cursor) {
+        Some(v) if v.len() > 1 => {
+            return Err(Error::new(
+                v[1].span(),
+                "May only mark one field as the cursor",
+            ))
+        }
+        Some(v) => &v[0],
+        None => return Ok(quote! {}),
+    };
+
+    let mut query = fields.get(&EndpointAttribute::Query).into_iter().flatten();
+    if !query.any(|f| f.ident == field.ident) {
+        return Err(Error::new(
+            field.ident.span(),
+            "The cursor field must also be a query parameter",
+        ));
+    }
+    let field = field.ident.clone().unwrap();
+
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics rustify::pagination::CursorEndpoint for #id #ty_generics #where_clause {
+            fn set_cursor(&mut self, cursor: String) {
+                self.#field = cursor.into();
+            }
+        }
+    })
+}
+
 /// Generates `builder()` and `exec_*` helper methods for use with
 /// `derive_builder`.
 ///
@@ -211,13 +337,13 @@ fn gen_builder(id: &Ident, generics: &Generics) -> proc_macro2::TokenStream {
 /// struct.
 fn parse_params(attr: &Meta) -> Result<Parameters, Error> {
     // Parse the attribute as a key/value pair list
-    let kv = parse::attr_kv(attr)?;
+    let (kv, lists) = parse::attr_kv(attr)?;
 
     // Create map from key/value pair list
     let map = parse::to_map(&kv)?;
 
-    // Convert map to Parameters
-    params::Parameters::new(map)
+    // Convert map and lists to Parameters
+    params::Parameters::new(map, &lists)
 }
 
 /// Implements `Endpoint` on the provided struct.
@@ -271,7 +397,26 @@ fn endpoint_derive(s: synstructure::Structure) -> proc_macro2::TokenStream {
 
     // Find serde attributes
     let serde_attrs = parse::attributes(&s.ast().attrs, "serde");
-    let serde_attrs = serde_attrs.unwrap_or_default();
+    let mut serde_attrs = serde_attrs.unwrap_or_default();
+
+    // Apply any renaming rule to the generated query and body structs in place
+    // of the one set through serde
+    if let Some(rename_all) = &params.rename_all {
+        serde_attrs = parse::without_param(serde_attrs, "rename_all");
+        serde_attrs.push(syn::parse_quote! { serde(rename_all = #rename_all) });
+    }
+
+    // Generate operation name, defaulting to the name of the struct
+    let name = match &params.operation_id {
+        Some(o) => o.value(),
+        None => id.to_string(),
+    };
+
+    // Generate path template, dropping the receiver from interpolated fields
+    let template = Regex::new(r"\{\s*self\.")
+        .unwrap()
+        .replace_all(path.value().as_str(), "{")
+        .to_string();
 
     // Generate path string
     let path = match gen_path(&path) {
@@ -282,12 +427,100 @@ fn endpoint_derive(s: synstructure::Structure) -> proc_macro2::TokenStream {
     // Generate query function
     let query = gen_query(&field_attrs, &serde_attrs);
 
+    // Generate headers function
+    let headers = match gen_headers(&field_attrs, &params.headers) {
+        Ok(d) => d,
+        Err(e) => return e.into_tokens(),
+    };
+
+    // Generate sensitive function
+    let sensitive = gen_sensitive(&field_attrs);
+
     // Generate body function
     let body = match gen_body(&field_attrs, &serde_attrs) {
         Ok(d) => d,
         Err(e) => return e.into_tokens(),
     };
 
+    // Generate success_codes function when overriding the success codes
+    let success = match &params.success {
+        Some(codes) => {
+            let codes = codes.iter().map(|(start, end)| quote! { #start..=#end });
+            quote! {
+                fn success_codes(&self) -> Option<rustify::client::SuccessCodes> {
+                    Some(rustify::client::SuccessCodes::new(vec![#(#codes),*]))
+                }
+            }
+        }
+        None => quote! {},
+    };
+
+    // Generate accept function when overriding the Accept header
+    let accept = match &params.accept {
+        Some(a) => quote! {
+            fn accept(&self) -> Option<&str> {
+                Some(#a)
+            }
+        },
+        None => quote! {},
+    };
+
+    // Generate timeout function when a default timeout is given
+    let timeout = match params.timeout_ms {
+        Some(ms) => quote! {
+            fn timeout(&self) -> Option<std::time::Duration> {
+                Some(std::time::Duration::from_millis(#ms))
+            }
+        },
+        None => quote! {},
+    };
+
+    // Generate retry_policy function when overriding the retry policy
+    let retry = match (params.retries, &params.retry_on) {
+        (None, None) => quote! {},
+        (retries, codes) => {
+            let attempts = match retries {
+                Some(r) => quote! { rustify::retry::RetryPolicy::new(#r + 1) },
+                None => quote! { rustify::retry::RetryPolicy::default() },
+            };
+            let policy = match codes {
+                Some(codes) => {
+                    let codes = codes.iter().map(|(start, end)| quote! { #start..=#end });
+                    quote! {
+                        rustify::retry::RetryPolicy {
+                            retryable_codes: rustify::client::StatusCodes::new(vec![#(#codes),*]),
+                            ..#attempts
+                        }
+                    }
+                }
+                None => attempts,
+            };
+            quote! {
+                fn retry_policy(&self) -> Option<rustify::retry::RetryPolicy> {
+                    Some(#policy)
+                }
+            }
+        }
+    };
+
+    // Generate the error type, falling back to a generic JSON value
+    let error = match &params.error {
+        Some(e) => quote! { #e },
+        None => quote! { rustify::__private::serde_json::Value },
+    };
+
+    // Generate strict parsing constant when enabled
+    let strict = match params.strict {
+        true => quote! { const STRICT_PARSING: bool = true; },
+        false => quote! {},
+    };
+
+    // Generate cursor implementation when a field is tagged as the cursor
+    let cursor = match gen_cursor(&field_attrs, id, &s.ast().generics) {
+        Ok(d) => d,
+        Err(e) => return e.into_tokens(),
+    };
+
     // Generate helper functions when deriving Builder
     let builder = match params.builder {
         true => gen_builder(&s.ast().ident, &s.ast().generics),
@@ -304,9 +537,10 @@ fn endpoint_derive(s: synstructure::Structure) -> proc_macro2::TokenStream {
         #[allow(non_local_definitions)]
         const #const_ident: () = {
             use rustify::__private::serde::Serialize;
-            use rustify::http::{build_body, build_query};
+            use rustify::__private::http::{HeaderName, HeaderValue};
+            use rustify::http::{build_body, build_header, build_query};
             use rustify::client::Client;
-            use rustify::endpoint::Endpoint;
+            use rustify::endpoint::{Endpoint, ErrorEndpoint};
             use rustify::enums::{RequestMethod, RequestType, ResponseType};
             use rustify::errors::ClientError;
 
@@ -314,6 +548,12 @@ fn endpoint_derive(s: synstructure::Structure) -> proc_macro2::TokenStream {
                 type Response = #response;
                 const REQUEST_BODY_TYPE: RequestType = RequestType::#request_type;
                 const RESPONSE_BODY_TYPE: ResponseType = ResponseType::#response_type;
+                #strict
+                const PATH_TEMPLATE: Option<&'static str> = Some(#template);
+
+                fn name() -> &'static str {
+                    #name
+                }
 
                 fn path(&self) -> String {
                     #path
@@ -325,10 +565,27 @@ fn endpoint_derive(s: synstructure::Structure) -> proc_macro2::TokenStream {
 
                 #query
 
+                #headers
+
+                #sensitive
+
+                #accept
+
+                #success
+
+                #timeout
+
+                #retry
 
                 #body
             }
 
+            impl #impl_generics ErrorEndpoint for #id #ty_generics #where_clause {
+                type Error = #error;
+            }
+
+            #cursor
+
             #builder
         };
     }
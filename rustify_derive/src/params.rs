@@ -1,8 +1,8 @@
 use std::collections::HashMap;
 
-use crate::Error;
+use crate::{parse, Error};
 use proc_macro2::Span;
-use syn::{Expr, Ident, LitStr, Type};
+use syn::{spanned::Spanned, Expr, Ident, LitStr, MetaList, Type};
 
 /// Used for building the parameter list for the derive function
 #[derive(Default, Debug)]
@@ -10,9 +10,19 @@ pub struct ParametersBuilder {
     pub path: Option<LitStr>,
     pub method: Option<Expr>,
     pub response: Option<Type>,
+    pub error: Option<Type>,
     pub request_type: Option<Expr>,
     pub response_type: Option<Expr>,
     pub builder: Option<bool>,
+    pub rename_all: Option<LitStr>,
+    pub headers: Option<Vec<(String, String)>>,
+    pub accept: Option<LitStr>,
+    pub success: Option<Vec<(u16, u16)>>,
+    pub strict: Option<bool>,
+    pub timeout_ms: Option<u64>,
+    pub retries: Option<u32>,
+    pub retry_on: Option<Vec<(u16, u16)>>,
+    pub operation_id: Option<LitStr>,
 }
 
 /// Represents all valid parameters that can be passed to the derive function
@@ -21,19 +31,29 @@ pub struct Parameters {
     pub path: LitStr,
     pub method: Expr,
     pub response: Type,
+    pub error: Option<Type>,
     pub request_type: Expr,
     pub response_type: Expr,
     pub builder: bool,
+    pub rename_all: Option<LitStr>,
+    pub headers: Vec<(String, String)>,
+    pub accept: Option<LitStr>,
+    pub success: Option<Vec<(u16, u16)>>,
+    pub strict: bool,
+    pub timeout_ms: Option<u64>,
+    pub retries: Option<u32>,
+    pub retry_on: Option<Vec<(u16, u16)>>,
+    pub operation_id: Option<LitStr>,
 }
 
 impl Parameters {
-    /// Given a map of identities to literal strings, builds a new instance of
-    /// [Parameters] using the contents of the map.
+    /// Given a map of identities to literal strings and a list of list
+    /// parameters, builds a new instance of [Parameters] using their contents.
     ///
     /// The only required parameter is `path` and not providing it will cause
     /// the function to fail. All other parameters are optional and will have
     /// sane defaults provided if they are not found in the map.
-    pub fn new(map: HashMap<Ident, LitStr>) -> Result<Parameters, Error> {
+    pub fn new(map: HashMap<Ident, LitStr>, lists: &[MetaList]) -> Result<Parameters, Error> {
         let mut builder = ParametersBuilder::default();
         for key in map.keys() {
             match key.to_string().as_str() {
@@ -44,6 +64,9 @@ impl Parameters {
                 "response" => {
                     builder.response = Some(parse(&map[key])?);
                 }
+                "error" => {
+                    builder.error = Some(parse(&map[key])?);
+                }
                 "request_type" => {
                     builder.request_type = Some(parse(&map[key])?);
                 }
@@ -53,11 +76,44 @@ impl Parameters {
                 "builder" => {
                     builder.builder = Some(true);
                 }
+                "operation_id" => builder.operation_id = Some(map[key].clone()),
+                "rename_all" => builder.rename_all = Some(map[key].clone()),
+                "accept" => builder.accept = Some(map[key].clone()),
+                "success" => builder.success = Some(parse_codes(&map[key], "Success codes")?),
+                "strict" => {
+                    builder.strict = Some(true);
+                }
+                "retries" => {
+                    builder.retries =
+                        Some(map[key].value().parse().map_err(|_| {
+                            Error::new(map[key].span(), "Retries must be a number")
+                        })?);
+                }
+                "retry_on" => builder.retry_on = Some(parse_codes(&map[key], "Retry codes")?),
+                "timeout_ms" => {
+                    builder.timeout_ms = Some(map[key].value().parse().map_err(|_| {
+                        Error::new(map[key].span(), "Timeout must be a number of milliseconds")
+                    })?);
+                }
                 _ => {
                     return Err(Error::new(key.span(), "Unknown parameter"));
                 }
             }
         }
+        for list in lists {
+            match list.path.get_ident().map(|i| i.to_string()).as_deref() {
+                Some("headers") => {
+                    let mut headers = builder.headers.take().unwrap_or_default();
+                    for value in parse::list_values(list)? {
+                        headers.push(parse_header(&value)?);
+                    }
+                    builder.headers = Some(headers);
+                }
+                _ => {
+                    return Err(Error::new(list.path.span(), "Unknown parameter"));
+                }
+            }
+        }
 
         let params = Parameters {
             path: match builder.path {
@@ -75,6 +131,7 @@ impl Parameters {
             response: builder
                 .response
                 .unwrap_or_else(|| syn::parse_str("()").unwrap()),
+            error: builder.error,
             request_type: builder
                 .request_type
                 .unwrap_or_else(|| syn::parse_str("JSON").unwrap()),
@@ -82,6 +139,15 @@ impl Parameters {
                 .response_type
                 .unwrap_or_else(|| syn::parse_str("JSON").unwrap()),
             builder: builder.builder.unwrap_or(false),
+            rename_all: builder.rename_all,
+            headers: builder.headers.unwrap_or_default(),
+            accept: builder.accept,
+            success: builder.success,
+            strict: builder.strict.unwrap_or(false),
+            timeout_ms: builder.timeout_ms,
+            retries: builder.retries,
+            retry_on: builder.retry_on,
+            operation_id: builder.operation_id,
         };
 
         Ok(params)
@@ -94,3 +160,41 @@ fn parse<T: syn::parse::Parse>(value: &LitStr) -> Result<T, Error> {
         .parse()
         .map_err(|_| Error::new(value.span(), "Unable to parse value"))
 }
+
+/// Parses a [LitStr] in the form of `Name: Value` into a header name/value
+/// pair and returns an error if it fails
+fn parse_header(value: &LitStr) -> Result<(String, String), Error> {
+    match value.value().split_once(':') {
+        Some((name, val)) if !name.trim().is_empty() => {
+            Ok((name.trim().to_string(), val.trim().to_string()))
+        }
+        _ => Err(Error::new(
+            value.span(),
+            "Headers must be in the form of `Name: Value`",
+        )),
+    }
+}
+
+/// Parses a [LitStr] in the form of `200..=204, 404` into a list of inclusive
+/// ranges of response codes and returns an error if it fails
+fn parse_codes(value: &LitStr, name: &str) -> Result<Vec<(u16, u16)>, Error> {
+    let err = || {
+        Error::new(
+            value.span(),
+            format!("{} must be in the form of `200..=204, 404`", name).as_str(),
+        )
+    };
+    let code = |s: &str| s.trim().parse::<u16>().map_err(|_| err());
+    let mut codes = Vec::new();
+    for part in value.value().split(',') {
+        let range = match part.split_once("..=") {
+            Some((start, end)) => (code(start)?, code(end)?),
+            None => (code(part)?, code(part)?),
+        };
+        if range.0 > range.1 {
+            return Err(err());
+        }
+        codes.push(range);
+    }
+    Ok(codes)
+}
@@ -5,7 +5,8 @@ use std::{
 
 use crate::{EndpointAttribute, Error};
 use syn::{
-    spanned::Spanned, Attribute, Field, Ident, LitStr, Meta, MetaNameValue, NestedMeta, Type,
+    spanned::Spanned, Attribute, Field, Ident, LitStr, Meta, MetaList, MetaNameValue, NestedMeta,
+    Type,
 };
 
 /// Returns all [Meta] values contained in a [Meta::List].
@@ -40,25 +41,52 @@ pub(crate) fn attr_list(attr: &Meta) -> Result<Vec<Meta>, Error> {
     }
 }
 
-/// Returns all [MetaNameValue] values contained in a [Meta::List].
+/// Returns all [MetaNameValue] and nested [MetaList] values contained in a
+/// [Meta::List].
 ///
 /// For example:
+/// ```text
+/// #[endpoint(path = "my/path", method = "POST", headers("X-Version: 2"))]
 /// ```
-/// #[endpoint(path = "my/path", method = "POST")]
-/// ```
-/// Would return individual [MetaNameValue] values for `path` and `method`. This
-/// function fails if the [Meta::List] is empty, contains literals, or cannot
-/// be parsed as name/value pairs.
-pub(crate) fn attr_kv(attr: &Meta) -> Result<Vec<MetaNameValue>, Error> {
+/// Would return individual [MetaNameValue] values for `path` and `method` and
+/// a single [MetaList] value for `headers`. This function fails if the
+/// [Meta::List] is empty, contains literals, or contains any paths.
+pub(crate) fn attr_kv(attr: &Meta) -> Result<(Vec<MetaNameValue>, Vec<MetaList>), Error> {
     let meta_list = attr_list(attr)?;
-    let mut result = Vec::<MetaNameValue>::new();
+    let mut kv = Vec::<MetaNameValue>::new();
+    let mut lists = Vec::<MetaList>::new();
     for meta in meta_list.iter() {
-        if let syn::Meta::NameValue(nv_meta) = meta {
-            result.push(nv_meta.clone());
+        match meta {
+            Meta::NameValue(nv_meta) => kv.push(nv_meta.clone()),
+            Meta::List(list_meta) => lists.push(list_meta.clone()),
+            Meta::Path(_) => {
+                return Err(Error::new(
+                    attr.span(),
+                    "Cannot parse attribute as a key/value list",
+                ))
+            }
+        }
+    }
+    Ok((kv, lists))
+}
+
+/// Returns all string literals contained in a [MetaList].
+///
+/// For example:
+/// ```text
+/// headers("X-Version: 2", "Accept: application/json")
+/// ```
+/// Would return the [LitStr] values for each header. This function fails if
+/// the [MetaList] contains anything other than string literals.
+pub(crate) fn list_values(list: &MetaList) -> Result<Vec<LitStr>, Error> {
+    let mut result = Vec::<LitStr>::new();
+    for nested in list.nested.iter() {
+        if let NestedMeta::Lit(syn::Lit::Str(lit)) = nested {
+            result.push(lit.clone());
         } else {
             return Err(Error::new(
-                attr.span(),
-                "Cannot parse attribute as a key/value list",
+                nested.span(),
+                "Values must be in string literal form",
             ));
         }
     }
@@ -105,6 +133,37 @@ pub(crate) fn attributes(attrs: &[Attribute], name: &str) -> Result<Vec<Meta>, E
     Ok(result)
 }
 
+/// Removes the parameters with the given name from the given attributes,
+/// dropping any attribute which is left without parameters.
+///
+/// For example, removing `rename_all` from:
+/// ```text
+/// #[serde(rename_all = "snake_case", deny_unknown_fields)]
+/// ```
+/// Returns:
+/// ```text
+/// #[serde(deny_unknown_fields)]
+/// ```
+pub(crate) fn without_param(attrs: Vec<Meta>, name: &str) -> Vec<Meta> {
+    attrs
+        .into_iter()
+        .filter_map(|meta| match meta {
+            Meta::List(mut list) => {
+                list.nested = list
+                    .nested
+                    .into_iter()
+                    .filter(|n| !matches!(n, NestedMeta::Meta(m) if m.path().is_ident(name)))
+                    .collect();
+                match list.nested.is_empty() {
+                    true => None,
+                    false => Some(Meta::List(list)),
+                }
+            }
+            m => Some(m),
+        })
+        .collect()
+}
+
 /// Returns a mapping of endpoint attributes to a list of their fields.
 ///
 /// Parses all [Attribute]'s on the given [syn::Field]'s, searching for any
@@ -119,18 +178,6 @@ pub(crate) fn field_attributes(
             // Collect all `endpoint` attributes attached to this field
             let attrs = attributes(&field.attrs, crate::ATTR_NAME)?;
 
-            // Add field as untagged is no attributes were found
-            if attrs.is_empty() {
-                match result.get_mut(&EndpointAttribute::Untagged) {
-                    Some(r) => {
-                        r.push(field.clone());
-                    }
-                    None => {
-                        result.insert(EndpointAttribute::Untagged, vec![field.clone()]);
-                    }
-                }
-            }
-
             // Combine all meta parameters from each attribute
             let attrs = attrs
                 .iter()
@@ -138,11 +185,21 @@ pub(crate) fn field_attributes(
                 .collect::<Result<Vec<Vec<Meta>>, Error>>()?;
 
             // Flatten and eliminate duplicates
-            let attrs = attrs.into_iter().flatten().collect::<HashSet<Meta>>();
+            let attrs = attrs
+                .into_iter()
+                .flatten()
+                .map(|m| EndpointAttribute::try_from(&m))
+                .collect::<Result<HashSet<EndpointAttribute>, Error>>()?;
+
+            // Add field as untagged if no attributes were found which place it
+            // in the request, as the sensitive attribute only affects logging
+            let mut attrs = attrs.into_iter().collect::<Vec<EndpointAttribute>>();
+            if attrs.iter().all(|a| *a == EndpointAttribute::Sensitive) {
+                attrs.push(EndpointAttribute::Untagged);
+            }
 
             // Add this field to the list of fields for each attribute
-            for attr in attrs.iter() {
-                let attr_ty = EndpointAttribute::try_from(attr)?;
+            for attr_ty in attrs {
                 match result.get_mut(&attr_ty) {
                     Some(r) => {
                         r.push(field.clone());
@@ -158,6 +215,35 @@ pub(crate) fn field_attributes(
     Ok(result)
 }
 
+/// Returns the string value assigned to the given `endpoint` attribute
+/// parameter on a [Field], if any.
+///
+/// For example:
+/// ```text
+/// #[endpoint(header = "X-Token")]
+/// ```
+/// Would return the [LitStr] "X-Token" when searching for `header`. This
+/// function fails if the value is not a string literal.
+pub(crate) fn field_value(field: &Field, name: &str) -> Result<Option<LitStr>, Error> {
+    for attr in attributes(&field.attrs, crate::ATTR_NAME)?.iter() {
+        for meta in attr_list(attr)? {
+            if let Meta::NameValue(nv) = meta {
+                if nv.path.is_ident(name) {
+                    return match nv.lit {
+                        syn::Lit::Str(lit) => Ok(Some(lit)),
+                        _ => Err(Error::new(
+                            nv.span(),
+                            "Values must be in string literal form",
+                        )),
+                    };
+                }
+            }
+        }
+    }
+
+    Ok(None)
+}
+
 /// Creates and instantiates a struct from a list of [Field]s.
 ///
 /// This function effectively creates a new struct from a list [Field]s and then
@@ -0,0 +1,251 @@
+//! Contains token sources which fetch credentials from the instance metadata
+//! endpoints of cloud providers, allowing services running in the cloud to
+//! authenticate without static secrets.
+use crate::{
+    auth::{oauth2::AccessToken, set_bearer, AuthProvider},
+    client::Client,
+    enums::RequestMethod,
+    errors::ClientError,
+};
+use async_trait::async_trait;
+use bytes::Bytes;
+use http::{Method, Request};
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// The base URL of the GCP metadata server.
+pub const GCP_METADATA_URL: &str = "http://metadata.google.internal";
+
+/// The base URL of the Azure Instance Metadata Service.
+pub const AZURE_METADATA_URL: &str = "http://169.254.169.254";
+
+/// The base URL of the AWS Instance Metadata Service.
+pub const AWS_METADATA_URL: &str = "http://169.254.169.254";
+
+/// The cloud provider a [ManagedIdentity] fetches tokens from.
+enum Source {
+    Gcp {
+        scopes: Vec<String>,
+    },
+    Azure {
+        resource: String,
+        client_id: Option<String>,
+    },
+}
+
+/// An [AuthProvider] which authenticates requests using an access token for
+/// the managed identity of the machine it's running on.
+///
+/// Tokens are fetched from the metadata endpoint the given [Client] is
+/// configured with, which is normally one of [GCP_METADATA_URL] or
+/// [AZURE_METADATA_URL], and are cached until they're about to expire.
+///
+/// # Example
+/// ```
+/// use rustify::auth::cloud::{ManagedIdentity, GCP_METADATA_URL};
+/// use rustify::clients::reqwest::Client;
+///
+/// let auth = ManagedIdentity::gcp(
+///     Client::default(GCP_METADATA_URL),
+///     &["https://www.googleapis.com/auth/cloud-platform"],
+/// );
+///
+/// let mut client = Client::default("https://storage.googleapis.com");
+/// client.set_auth(auth);
+/// ```
+pub struct ManagedIdentity<C> {
+    client: C,
+    source: Source,
+    /// How long before a token expires that it's refreshed.
+    pub refresh_before: Duration,
+    token: Mutex<Option<AccessToken>>,
+}
+
+impl<C: Client> ManagedIdentity<C> {
+    /// Returns a new [ManagedIdentity] which fetches tokens with the given
+    /// scopes for the default service account from the GCP metadata server.
+    /// The default scopes of the service account are used if none are given.
+    pub fn gcp(client: C, scopes: &[&str]) -> Self {
+        ManagedIdentity::new(
+            client,
+            Source::Gcp {
+                scopes: scopes.iter().map(|s| s.to_string()).collect(),
+            },
+        )
+    }
+
+    /// Returns a new [ManagedIdentity] which fetches tokens for the given
+    /// resource from the Azure Instance Metadata Service. The `client_id`
+    /// selects a user-assigned identity, otherwise the system-assigned
+    /// identity is used.
+    pub fn azure(client: C, resource: &str, client_id: Option<&str>) -> Self {
+        ManagedIdentity::new(
+            client,
+            Source::Azure {
+                resource: resource.to_string(),
+                client_id: client_id.map(|c| c.to_string()),
+            },
+        )
+    }
+
+    fn new(client: C, source: Source) -> Self {
+        ManagedIdentity {
+            client,
+            source,
+            refresh_before: Duration::from_secs(60),
+            token: Mutex::new(None),
+        }
+    }
+
+    /// Returns the cached access token, fetching a new one if no token has
+    /// been fetched yet or the cached token is about to expire.
+    pub async fn token(&self) -> Result<String, ClientError> {
+        let mut token = self.token.lock().await;
+        match token.as_ref() {
+            Some(t) if !t.expires_within(self.refresh_before) => Ok(t.value.clone()),
+            _ => {
+                let fresh = self.fetch().await?;
+                *token = Some(fresh.clone());
+                Ok(fresh.value)
+            }
+        }
+    }
+
+    /// Fetches a new access token from the metadata endpoint.
+    #[cfg_attr(feature = "tracing", instrument(skip(self), err))]
+    async fn fetch(&self) -> Result<AccessToken, ClientError> {
+        let req = match &self.source {
+            Source::Gcp { scopes } => {
+                let mut path =
+                    "computeMetadata/v1/instance/service-accounts/default/token".to_string();
+                if !scopes.is_empty() {
+                    path = format!("{}?{}", path, query(&[("scopes", scopes.join(","))])?);
+                }
+                request(
+                    RequestMethod::GET,
+                    &url(self.client.base(), &path),
+                    &[("Metadata-Flavor", "Google")],
+                )?
+            }
+            Source::Azure {
+                resource,
+                client_id,
+            } => {
+                let mut params = vec![
+                    ("api-version", "2018-02-01".to_string()),
+                    ("resource", resource.clone()),
+                ];
+                if let Some(c) = client_id {
+                    params.push(("client_id", c.clone()));
+                }
+                let path = format!("metadata/identity/oauth2/token?{}", query(&params)?);
+                request(
+                    RequestMethod::GET,
+                    &url(self.client.base(), &path),
+                    &[("Metadata", "true")],
+                )?
+            }
+        };
+
+        let fetched = Instant::now();
+        let resp = self.client.execute(req).await?;
+        AccessToken::parse(resp.body(), fetched)
+    }
+}
+
+#[cfg_attr(not(feature = "non-send"), async_trait)]
+#[cfg_attr(feature = "non-send", async_trait(?Send))]
+impl<C: Client> AuthProvider for ManagedIdentity<C> {
+    async fn apply(&self, req: &mut Request<Bytes>) -> Result<(), ClientError> {
+        set_bearer(req, &self.token().await?)
+    }
+}
+
+/// Temporary AWS credentials for the IAM role attached to an EC2 instance.
+///
+/// AWS APIs don't accept bearer tokens, so these credentials must be used to
+/// sign requests with Signature Version 4 by a custom [AuthProvider].
+#[derive(Clone, Debug, Deserialize)]
+pub struct AwsCredentials {
+    #[serde(rename = "AccessKeyId")]
+    pub access_key_id: String,
+    #[serde(rename = "SecretAccessKey")]
+    pub secret_access_key: String,
+    #[serde(rename = "Token")]
+    pub session_token: Option<String>,
+    /// When the credentials expire, as an RFC 3339 timestamp.
+    #[serde(rename = "Expiration")]
+    pub expiration: Option<String>,
+}
+
+/// Fetches the credentials of the IAM role attached to the EC2 instance from
+/// the AWS Instance Metadata Service the given [Client] is configured with,
+/// which is normally [AWS_METADATA_URL]. IMDSv2 session tokens are used.
+#[cfg_attr(feature = "tracing", instrument(skip(client), err))]
+pub async fn aws_credentials(client: &impl Client) -> Result<AwsCredentials, ClientError> {
+    let req = request(
+        RequestMethod::PUT,
+        &url(client.base(), "latest/api/token"),
+        &[("X-aws-ec2-metadata-token-ttl-seconds", "21600")],
+    )?;
+    let session = text(client.execute(req).await?.body())?;
+    let headers = [("X-aws-ec2-metadata-token", session.as_str())];
+
+    let roles_url = url(client.base(), "latest/meta-data/iam/security-credentials/");
+    let req = request(RequestMethod::GET, &roles_url, &headers)?;
+    let roles = text(client.execute(req).await?.body())?;
+    let role = roles.lines().next().unwrap_or_default();
+
+    let req = request(
+        RequestMethod::GET,
+        &format!("{}{}", roles_url, role),
+        &headers,
+    )?;
+    let resp = client.execute(req).await?;
+    serde_json::from_slice(resp.body()).map_err(|e| ClientError::ResponseParseError {
+        source: e.into(),
+        content: String::from_utf8(resp.body().to_vec()).ok(),
+    })
+}
+
+/// Joins the given path to the given base URL.
+fn url(base: &str, path: &str) -> String {
+    format!("{}/{}", base.trim_end_matches('/'), path)
+}
+
+/// Encodes the given parameters as a query string.
+fn query(params: &[(&str, String)]) -> Result<String, ClientError> {
+    serde_urlencoded::to_string(params)
+        .map_err(|e| ClientError::UrlQueryParseError { source: e.into() })
+}
+
+/// Returns a new [Request] with an empty body and the given headers.
+fn request(
+    method: RequestMethod,
+    url: &str,
+    headers: &[(&str, &str)],
+) -> Result<Request<Bytes>, ClientError> {
+    let http_method: Method = method.clone().into();
+    let mut builder = Request::builder().method(http_method).uri(url);
+    for (k, v) in headers {
+        builder = builder.header(*k, *v);
+    }
+    builder
+        .body(Bytes::new())
+        .map_err(|e| ClientError::RequestBuildError {
+            source: e,
+            method,
+            url: url.to_string(),
+        })
+}
+
+/// Parses the given response body as UTF-8 text.
+fn text(body: &Bytes) -> Result<String, ClientError> {
+    String::from_utf8(body.to_vec())
+        .map(|s| s.trim().to_string())
+        .map_err(|e| ClientError::ResponseConversionError {
+            source: e.into(),
+            content: body.to_vec(),
+        })
+}
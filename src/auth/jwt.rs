@@ -0,0 +1,206 @@
+//! Contains an [AuthProvider] which authenticates requests using self-signed
+//! JSON Web Tokens.
+use crate::{
+    auth::{set_bearer, AuthProvider},
+    errors::ClientError,
+};
+use async_trait::async_trait;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use bytes::Bytes;
+use http::Request;
+use ring::{
+    rand::SystemRandom,
+    signature::{EcdsaKeyPair, RsaKeyPair, ECDSA_P256_SHA256_FIXED_SIGNING, RSA_PKCS1_SHA256},
+};
+use serde_json::{json, Map, Value};
+use std::{
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// The algorithm used for signing tokens.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JwtAlgorithm {
+    /// RSASSA-PKCS1-v1_5 using SHA-256.
+    RS256,
+    /// ECDSA using P-256 and SHA-256.
+    ES256,
+}
+
+impl JwtAlgorithm {
+    fn name(&self) -> &'static str {
+        match self {
+            JwtAlgorithm::RS256 => "RS256",
+            JwtAlgorithm::ES256 => "ES256",
+        }
+    }
+}
+
+enum SigningKey {
+    Rsa(RsaKeyPair),
+    Ecdsa(EcdsaKeyPair),
+}
+
+/// An [AuthProvider] which mints short-lived JSON Web Tokens signed with a
+/// local private key and sends them as bearer tokens.
+///
+/// This covers APIs which accept self-signed assertions in place of a static
+/// secret, such as Google service accounts (with the `audience` set to the
+/// API being called) and GitHub Apps (with the `issuer` set to the App ID).
+/// Tokens are reused until they're about to expire.
+///
+/// The `iat` claim is backdated by `clock_skew` to tolerate clocks which are
+/// slightly ahead of the server's.
+///
+/// # Example
+/// ```no_run
+/// use rustify::auth::jwt::{JwtAlgorithm, JwtAuth};
+/// use rustify::clients::reqwest::Client;
+///
+/// let pem = std::fs::read_to_string("app.pem").unwrap();
+/// let auth = JwtAuth::from_pem(JwtAlgorithm::RS256, &pem, "123456").unwrap();
+///
+/// let mut client = Client::default("https://api.github.com");
+/// client.set_auth(auth);
+/// ```
+pub struct JwtAuth {
+    key: SigningKey,
+    algorithm: JwtAlgorithm,
+    rng: SystemRandom,
+    /// The `iss` claim.
+    pub issuer: String,
+    /// The `sub` claim, if any.
+    pub subject: Option<String>,
+    /// The `aud` claim, if any.
+    pub audience: Option<String>,
+    /// The `kid` header, if any.
+    pub key_id: Option<String>,
+    /// Additional claims added to every token.
+    pub claims: Map<String, Value>,
+    /// How long a minted token is valid for.
+    pub lifetime: Duration,
+    /// How far the `iat` claim is backdated.
+    pub clock_skew: Duration,
+    token: Mutex<Option<(String, SystemTime)>>,
+}
+
+impl JwtAuth {
+    /// Returns a new [JwtAuth] using the given PEM encoded private key. RSA
+    /// keys may be given in either PKCS#1 or PKCS#8 format while ECDSA keys
+    /// must be given in PKCS#8 format.
+    pub fn from_pem(algorithm: JwtAlgorithm, pem: &str, issuer: &str) -> Result<Self, ClientError> {
+        let body: String = pem
+            .lines()
+            .filter(|l| !l.starts_with("-----"))
+            .map(|l| l.trim())
+            .collect();
+        let der = base64::engine::general_purpose::STANDARD
+            .decode(body)
+            .map_err(|e| ClientError::GenericError { source: e.into() })?;
+        JwtAuth::from_der(algorithm, &der, issuer)
+    }
+
+    /// Returns a new [JwtAuth] using the given DER encoded private key.
+    pub fn from_der(
+        algorithm: JwtAlgorithm,
+        der: &[u8],
+        issuer: &str,
+    ) -> Result<Self, ClientError> {
+        let rng = SystemRandom::new();
+        let key = match algorithm {
+            JwtAlgorithm::RS256 => RsaKeyPair::from_pkcs8(der)
+                .or_else(|_| RsaKeyPair::from_der(der))
+                .map(SigningKey::Rsa),
+            JwtAlgorithm::ES256 => {
+                EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, der, &rng)
+                    .map(SigningKey::Ecdsa)
+            }
+        }
+        .map_err(|e| ClientError::GenericError {
+            source: anyhow::anyhow!("Invalid signing key: {}", e),
+        })?;
+
+        Ok(JwtAuth {
+            key,
+            algorithm,
+            rng,
+            issuer: issuer.to_string(),
+            subject: None,
+            audience: None,
+            key_id: None,
+            claims: Map::new(),
+            lifetime: Duration::from_secs(300),
+            clock_skew: Duration::from_secs(60),
+            token: Mutex::new(None),
+        })
+    }
+
+    /// Returns a token which is valid for at least a quarter of its lifetime,
+    /// minting a new one if needed.
+    pub fn token(&self) -> Result<String, ClientError> {
+        let mut token = self.token.lock().unwrap();
+        let now = SystemTime::now();
+        match token.as_ref() {
+            Some((t, expires)) if now + self.lifetime / 4 < *expires => Ok(t.clone()),
+            _ => {
+                let (t, expires) = self.mint(now)?;
+                *token = Some((t.clone(), expires));
+                Ok(t)
+            }
+        }
+    }
+
+    /// Mints a new token issued at the given time, returning it along with
+    /// its expiration time.
+    fn mint(&self, now: SystemTime) -> Result<(String, SystemTime), ClientError> {
+        let secs = |t: SystemTime| t.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let expires = now + self.lifetime;
+
+        let mut header = json!({"alg": self.algorithm.name(), "typ": "JWT"});
+        if let Some(kid) = &self.key_id {
+            header["kid"] = json!(kid);
+        }
+        let mut claims = self.claims.clone();
+        claims.insert("iss".into(), json!(self.issuer));
+        claims.insert("iat".into(), json!(secs(now - self.clock_skew)));
+        claims.insert("exp".into(), json!(secs(expires)));
+        if let Some(sub) = &self.subject {
+            claims.insert("sub".into(), json!(sub));
+        }
+        if let Some(aud) = &self.audience {
+            claims.insert("aud".into(), json!(aud));
+        }
+
+        let message = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(header.to_string()),
+            URL_SAFE_NO_PAD.encode(Value::Object(claims).to_string())
+        );
+        let signature = match &self.key {
+            SigningKey::Rsa(k) => {
+                let mut sig = vec![0; k.public().modulus_len()];
+                k.sign(&RSA_PKCS1_SHA256, &self.rng, message.as_bytes(), &mut sig)
+                    .map(|_| sig)
+            }
+            SigningKey::Ecdsa(k) => k
+                .sign(&self.rng, message.as_bytes())
+                .map(|s| s.as_ref().to_vec()),
+        }
+        .map_err(|_| ClientError::GenericError {
+            source: anyhow::anyhow!("Error signing token"),
+        })?;
+
+        Ok((
+            format!("{}.{}", message, URL_SAFE_NO_PAD.encode(signature)),
+            expires,
+        ))
+    }
+}
+
+#[cfg_attr(not(feature = "non-send"), async_trait)]
+#[cfg_attr(feature = "non-send", async_trait(?Send))]
+impl AuthProvider for JwtAuth {
+    async fn apply(&self, req: &mut Request<Bytes>) -> Result<(), ClientError> {
+        set_bearer(req, &self.token()?)
+    }
+}
@@ -0,0 +1,173 @@
+//! Contains an [AuthProvider] implementing the OAuth2 client credentials
+//! flow.
+use crate::{
+    auth::{set_bearer, AuthProvider},
+    client::Client,
+    enums::RequestMethod,
+    errors::ClientError,
+};
+use async_trait::async_trait;
+use bytes::Bytes;
+use http::{header::CONTENT_TYPE, Method, Request};
+use serde::Deserialize;
+use serde_json::Value;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// An [AuthProvider] which authenticates requests using an access token
+/// obtained through the OAuth2 client credentials flow.
+///
+/// Tokens are fetched from the token endpoint using the given [Client] the
+/// first time a request is sent and are cached until they're about to expire,
+/// at which point a new token is fetched. Concurrent requests wait for a
+/// single refresh rather than each fetching their own token.
+///
+/// # Example
+/// ```
+/// use rustify::auth::oauth2::ClientCredentials;
+/// use rustify::clients::reqwest::Client;
+///
+/// let auth = ClientCredentials::new(
+///     Client::default("https://auth.myapi.com"),
+///     "https://auth.myapi.com/oauth/token",
+///     "myclient",
+///     "mysecret",
+/// )
+/// .scopes(&["read", "write"]);
+///
+/// let mut client = Client::default("https://myapi.com");
+/// client.set_auth(auth);
+/// ```
+pub struct ClientCredentials<C> {
+    client: C,
+    token_url: String,
+    client_id: String,
+    client_secret: String,
+    scopes: Vec<String>,
+    /// How long before a token expires that it's refreshed.
+    pub refresh_before: Duration,
+    token: Mutex<Option<AccessToken>>,
+}
+
+/// A cached access token.
+#[derive(Clone)]
+pub(crate) struct AccessToken {
+    pub value: String,
+    pub expires_at: Option<Instant>,
+}
+
+impl AccessToken {
+    /// Parses an OAuth2 token response which was fetched at the given time.
+    ///
+    /// Some providers return `expires_in` as a string rather than a number,
+    /// so both are accepted.
+    pub(crate) fn parse(body: &[u8], fetched: Instant) -> Result<Self, ClientError> {
+        let token: TokenResponse =
+            serde_json::from_slice(body).map_err(|e| ClientError::ResponseParseError {
+                source: e.into(),
+                content: String::from_utf8(body.to_vec()).ok(),
+            })?;
+        let expires_in = match token.expires_in {
+            Some(Value::Number(n)) => n.as_u64(),
+            Some(Value::String(s)) => s.parse().ok(),
+            _ => None,
+        };
+        Ok(AccessToken {
+            value: token.access_token,
+            expires_at: expires_in.map(|e| fetched + Duration::from_secs(e)),
+        })
+    }
+
+    /// Returns whether the token expires within the given window.
+    pub(crate) fn expires_within(&self, window: Duration) -> bool {
+        self.expires_at
+            .is_some_and(|e| e <= Instant::now() + window)
+    }
+}
+
+/// A successful response from an OAuth2 token endpoint.
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: Option<Value>,
+}
+
+impl<C: Client> ClientCredentials<C> {
+    /// Returns a new [ClientCredentials] which fetches tokens from the given
+    /// token endpoint using the given [Client]. Tokens are refreshed thirty
+    /// seconds before they expire by default.
+    pub fn new(client: C, token_url: &str, client_id: &str, client_secret: &str) -> Self {
+        ClientCredentials {
+            client,
+            token_url: token_url.to_string(),
+            client_id: client_id.to_string(),
+            client_secret: client_secret.to_string(),
+            scopes: Vec::new(),
+            refresh_before: Duration::from_secs(30),
+            token: Mutex::new(None),
+        }
+    }
+
+    /// Requests tokens with the given scopes.
+    pub fn scopes(mut self, scopes: &[&str]) -> Self {
+        self.scopes = scopes.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    /// Returns the cached access token, fetching a new one if no token has
+    /// been fetched yet or the cached token is about to expire.
+    pub async fn token(&self) -> Result<String, ClientError> {
+        let mut token = self.token.lock().await;
+        match token.as_ref() {
+            Some(t) if !t.expires_within(self.refresh_before) => Ok(t.value.clone()),
+            _ => {
+                let fresh = self.fetch().await?;
+                *token = Some(fresh.clone());
+                Ok(fresh.value)
+            }
+        }
+    }
+
+    /// Discards the cached access token, forcing a new one to be fetched for
+    /// the next request.
+    pub async fn invalidate(&self) {
+        *self.token.lock().await = None;
+    }
+
+    /// Fetches a new access token from the token endpoint.
+    #[cfg_attr(feature = "tracing", instrument(skip(self), fields(url = %self.token_url), err))]
+    async fn fetch(&self) -> Result<AccessToken, ClientError> {
+        let mut params = vec![
+            ("grant_type", "client_credentials".to_string()),
+            ("client_id", self.client_id.clone()),
+            ("client_secret", self.client_secret.clone()),
+        ];
+        if !self.scopes.is_empty() {
+            params.push(("scope", self.scopes.join(" ")));
+        }
+        let body = serde_urlencoded::to_string(params)
+            .map_err(|e| ClientError::DataParseError { source: e.into() })?;
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri(self.token_url.as_str())
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(Bytes::from(body))
+            .map_err(|e| ClientError::RequestBuildError {
+                source: e,
+                method: RequestMethod::POST,
+                url: self.token_url.clone(),
+            })?;
+
+        let fetched = Instant::now();
+        let resp = self.client.execute(req).await?;
+        AccessToken::parse(resp.body(), fetched)
+    }
+}
+
+#[cfg_attr(not(feature = "non-send"), async_trait)]
+#[cfg_attr(feature = "non-send", async_trait(?Send))]
+impl<C: Client> AuthProvider for ClientCredentials<C> {
+    async fn apply(&self, req: &mut Request<Bytes>) -> Result<(), ClientError> {
+        set_bearer(req, &self.token().await?)
+    }
+}
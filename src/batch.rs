@@ -0,0 +1,52 @@
+//! Contains helpers for executing many [Endpoints][crate::endpoint::Endpoint]
+//! concurrently.
+use crate::{
+    client::Client,
+    endpoint::{Endpoint, EndpointResult},
+    errors::ClientError,
+};
+use futures_util::{stream, StreamExt};
+
+/// Executes the given endpoints concurrently using the given [Client],
+/// returning the result of each endpoint in the order they were given.
+///
+/// At most `concurrency` endpoints are executed at the same time, with a
+/// minimum of one. A failed endpoint does not affect the others and its error
+/// is returned in place of its result.
+///
+/// # Example
+/// ```
+/// use rustify::batch::exec_batch;
+/// use rustify::clients::reqwest::Client;
+/// use rustify_derive::Endpoint;
+///
+/// #[derive(Endpoint)]
+/// #[endpoint(path = "users/{self.id}")]
+/// struct GetUser {
+///     #[endpoint(skip)]
+///     id: u64,
+/// }
+///
+/// # tokio_test::block_on(async {
+/// let client = Client::default("http://myapi.com");
+/// let endpoints = (1..=10).map(|id| GetUser { id });
+/// let results = exec_batch(&client, endpoints, 4).await;
+/// # })
+/// ```
+#[cfg_attr(feature = "tracing", instrument(skip(client, endpoints)))]
+pub async fn exec_batch<C, E, I>(
+    client: &C,
+    endpoints: I,
+    concurrency: usize,
+) -> Vec<Result<EndpointResult<E::Response>, ClientError>>
+where
+    C: Client,
+    E: Endpoint,
+    I: IntoIterator<Item = E>,
+{
+    stream::iter(endpoints)
+        .map(|e| async move { e.exec(client).await })
+        .buffered(concurrency.max(1))
+        .collect()
+        .await
+}
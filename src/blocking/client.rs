@@ -1,7 +1,18 @@
 //! Contains the blocking [Client] trait for executing
 //! [Endpoints][crate::endpoint::Endpoint].
-use crate::{client::HTTP_SUCCESS_CODES, errors::ClientError};
-use http::{Request, Response};
+use crate::{
+    auth::AuthProvider,
+    client::{response_error, Drain, RawErrorBody, SuccessCodes},
+    correlation::record_request_id,
+    endpoint::{Endpoint, MiddleWare},
+    errors::ClientError,
+    http::UrlJoin,
+    metrics::{MetricsObserver, PathTemplate, RequestMetrics},
+    retry::RetryPolicy,
+};
+use bytes::Bytes;
+use http::{HeaderMap, Request, Response};
+use std::time::{Duration, Instant};
 
 /// Represents an HTTP client which is capable of executing
 /// [Endpoints][crate::endpoint::Endpoint] by sending the [Request] generated
@@ -9,23 +20,155 @@ use http::{Request, Response};
 pub trait Client {
     /// Sends the given [Request] and returns a [Response]. Implementations
     /// should consolidate all errors into the [ClientError] type.
-    fn send(&self, req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, ClientError>;
+    fn send(&self, req: Request<Bytes>) -> Result<Response<Bytes>, ClientError>;
 
     /// Returns the base URL the client is configured with. This is used for
     /// creating the fully qualified URLs used when executing
     /// [Endpoints][crate::endpoint::Endpoint].
     fn base(&self) -> &str;
 
+    /// Returns the [Drain] used for tracking in-flight executions. Clients
+    /// which return [None] cannot be gracefully shut down.
+    fn drain(&self) -> Option<&Drain> {
+        None
+    }
+
+    /// Returns the [AuthProvider] used for attaching credentials to every
+    /// request sent by this client. Clients which return [None] send requests
+    /// as-is.
+    ///
+    /// The provider is driven to completion on a single-threaded runtime, so
+    /// blocking clients must not be used from within an async runtime.
+    fn auth(&self) -> Option<&dyn AuthProvider> {
+        None
+    }
+
+    /// Returns the [MetricsObserver] invoked with the metrics of every request
+    /// sent by this client. Clients which return [None] don't record metrics.
+    fn metrics(&self) -> Option<&dyn MetricsObserver> {
+        None
+    }
+
+    /// Gracefully shuts down the client, blocking until in-flight executions
+    /// have finished.
+    ///
+    /// See [Client::shutdown][crate::client::Client::shutdown] for details.
+    fn shutdown(&self, grace: Duration) -> Result<(), ClientError> {
+        match self.drain() {
+            Some(d) => d.shutdown_blocking(grace),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the [RetryPolicy] used for retrying failed executions. Clients
+    /// which return [None] do not retry executions.
+    fn retry_policy(&self) -> Option<&RetryPolicy> {
+        None
+    }
+
+    /// Returns the [SuccessCodes] used for determining whether a response was
+    /// successful. Clients which return [None] use
+    /// [HTTP_SUCCESS_CODES][crate::client::HTTP_SUCCESS_CODES].
+    fn success_codes(&self) -> Option<&SuccessCodes> {
+        None
+    }
+
+    /// Returns the headers added to every request sent by this client.
+    /// Headers set by the [Endpoint] take precedence over them. Clients which
+    /// return [None] send requests as-is.
+    fn default_headers(&self) -> Option<&HeaderMap> {
+        None
+    }
+
+    /// Returns the query parameters added to the URL of every request sent by
+    /// this client. Parameters set by the [Endpoint] take precedence over
+    /// them. Clients which return [None] send requests as-is.
+    fn default_query(&self) -> Option<&[(String, String)]> {
+        None
+    }
+
+    /// Returns the [UrlJoin] mode used for joining the path of an [Endpoint]
+    /// onto the base URL. Defaults to [UrlJoin::Lenient].
+    fn url_join(&self) -> UrlJoin {
+        UrlJoin::Lenient
+    }
+
+    /// Modifies a [Request] from an [Endpoint] before it's executed by this
+    /// client. This is used for applying client-level [MiddleWare] and does
+    /// nothing by default.
+    fn mutate_request<E: Endpoint>(
+        &self,
+        _endpoint: &E,
+        _req: &mut Request<Bytes>,
+    ) -> Result<(), ClientError>
+    where
+        Self: Sized,
+    {
+        Ok(())
+    }
+
+    /// Modifies a [Response] from an [Endpoint] after it's executed by this
+    /// client. This is used for applying client-level [MiddleWare] and does
+    /// nothing by default.
+    fn mutate_response<E: Endpoint>(
+        &self,
+        _endpoint: &E,
+        _resp: &mut Response<Bytes>,
+    ) -> Result<(), ClientError>
+    where
+        Self: Sized,
+    {
+        Ok(())
+    }
+
+    /// Returns a new [MutatedClient] which applies the given [MiddleWare] to
+    /// every [Endpoint] executed with it.
+    fn with_middleware<M: MiddleWare>(self, middleware: M) -> MutatedClient<Self, M>
+    where
+        Self: Sized,
+    {
+        MutatedClient::new(self, middleware)
+    }
+
     /// This method provides a common interface to
     /// [Endpoints][crate::endpoint::Endpoint] for execution.
-    #[instrument(skip(self, req), fields(uri=%req.uri(), method=%req.method()), err)]
-    fn execute(&self, req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, ClientError> {
+    #[cfg_attr(feature = "tracing", instrument(skip(self, req), fields(uri=%crate::http::redacted_uri(&req), method=%req.method(), request_id=tracing::field::Empty), err))]
+    fn execute(&self, mut req: Request<Bytes>) -> Result<Response<Bytes>, ClientError> {
+        let _guard = crate::client::register(self.drain())?;
+        if let Some(auth) = self.auth() {
+            apply_auth(auth, &mut req)?;
+        }
         debug!(
             name: "sending_request",
+            method=%req.method(),
+            uri=%crate::http::redacted_uri(&req),
             body_len=req.body().len(),
             "Sending Request",
         );
-        let response = self.send(req)?;
+        let success = SuccessCodes::resolve(&req, self.success_codes());
+        let raw = req.extensions().get::<RawErrorBody>().is_some();
+        record_request_id(req.headers());
+        let (method, path, request_size) = (
+            req.method().clone(),
+            req.uri().path().to_string(),
+            req.body().len(),
+        );
+        let template = req.extensions().get::<PathTemplate>().map(|t| t.0);
+        let start = Instant::now();
+        let response = self.send(req);
+        if let Some(m) = self.metrics() {
+            m.observe(&RequestMetrics {
+                method: &method,
+                path: &path,
+                template,
+                status: response.as_ref().ok().map(|r| r.status().as_u16()),
+                duration: start.elapsed(),
+                request_size,
+                response_size: response.as_ref().ok().map(|r| r.body().len()),
+            });
+        }
+        let response = response?;
+        record_request_id(response.headers());
         let status = response.status();
         debug!(
             name: "response_received",
@@ -36,14 +179,104 @@ pub trait Client {
         );
 
         // Check response
-        if !HTTP_SUCCESS_CODES.contains(&response.status().as_u16()) {
-            return Err(ClientError::ServerResponseError {
-                code: response.status().as_u16(),
-                content: String::from_utf8(response.body().to_vec()).ok(),
-            });
+        if !success.contains(status.as_u16()) {
+            return Err(response_error(status.as_u16(), response.into_body(), raw));
         }
 
         // Parse response content
         Ok(response)
     }
 }
+
+/// Attaches the credentials of the given [AuthProvider] to the given
+/// [Request], blocking on a single-threaded runtime until they're applied.
+pub(crate) fn apply_auth(
+    auth: &dyn AuthProvider,
+    req: &mut Request<Bytes>,
+) -> Result<(), ClientError> {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| ClientError::GenericError { source: e.into() })?
+        .block_on(auth.apply(req))
+}
+
+/// Represents a blocking [Client] that has had [MiddleWare] applied to it.
+///
+/// See [MutatedClient][crate::client::MutatedClient] for more details.
+pub struct MutatedClient<C: Client, M: MiddleWare> {
+    client: C,
+    middleware: M,
+}
+
+impl<C: Client, M: MiddleWare> MutatedClient<C, M> {
+    /// Returns a new [MutatedClient].
+    pub fn new(client: C, middleware: M) -> Self {
+        MutatedClient { client, middleware }
+    }
+
+    /// Returns a reference to the wrapped [Client].
+    pub fn inner(&self) -> &C {
+        &self.client
+    }
+}
+
+impl<C: Client, M: MiddleWare> Client for MutatedClient<C, M> {
+    fn send(&self, req: Request<Bytes>) -> Result<Response<Bytes>, ClientError> {
+        self.client.send(req)
+    }
+
+    fn base(&self) -> &str {
+        self.client.base()
+    }
+
+    fn drain(&self) -> Option<&Drain> {
+        self.client.drain()
+    }
+
+    fn auth(&self) -> Option<&dyn AuthProvider> {
+        self.client.auth()
+    }
+
+    fn metrics(&self) -> Option<&dyn MetricsObserver> {
+        self.client.metrics()
+    }
+
+    fn retry_policy(&self) -> Option<&RetryPolicy> {
+        self.client.retry_policy()
+    }
+
+    fn success_codes(&self) -> Option<&SuccessCodes> {
+        self.client.success_codes()
+    }
+
+    fn default_headers(&self) -> Option<&HeaderMap> {
+        self.client.default_headers()
+    }
+
+    fn default_query(&self) -> Option<&[(String, String)]> {
+        self.client.default_query()
+    }
+
+    fn url_join(&self) -> UrlJoin {
+        self.client.url_join()
+    }
+
+    fn mutate_request<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        req: &mut Request<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.client.mutate_request(endpoint, req)?;
+        self.middleware.request(endpoint, req)
+    }
+
+    fn mutate_response<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        resp: &mut Response<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.middleware.response(endpoint, resp)?;
+        self.client.mutate_response(endpoint, resp)
+    }
+}
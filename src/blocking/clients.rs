@@ -1,4 +1,6 @@
 //! Contains implementations of [Client][crate::blocking::client::Client] which
 //! use varying blocking HTTP clients.
+#[cfg(feature = "attohttpc")]
+pub mod attohttpc;
 #[cfg(feature = "reqwest")]
 pub mod reqwest;
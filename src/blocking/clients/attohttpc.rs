@@ -0,0 +1,324 @@
+//! Contains an implementation of the blocking
+//! [Client][crate::blocking::client::Client] being backed by the
+//! [attohttpc](https://docs.rs/attohttpc/) crate.
+
+use crate::{
+    blocking::client::Client as RustifyClient,
+    client::{Drain, RequestTimeout},
+    config::{unsupported, ClientConfig, HttpVersion},
+    errors::ClientError,
+    http::UrlJoin,
+    redirect::RedirectPolicy,
+};
+use bytes::Bytes;
+use http::{HeaderMap, Method, Request, Response};
+
+/// A client based on the [attohttpc::Session][1] which can be used for
+/// executing [Endpoints][crate::endpoint::Endpoint]. Unlike the blocking
+/// reqwest client, attohttpc does not spin up an async runtime in the
+/// background. The settings of the backing [attohttpc::Session][1] are applied
+/// to every request sent by this client. A base URL is required and is used to
+/// qualify the full path of any [Endpoints][crate::endpoint::Endpoint] which
+/// are executed by this client.
+///
+/// Note that requests using HTTP methods which are not supported by
+/// [attohttpc::Session][1] (i.e. `LIST`) are sent without the session
+/// settings.
+///
+/// # Example
+/// ```
+/// use rustify::blocking::clients::attohttpc::Client;
+/// use rustify::Endpoint;
+/// use rustify_derive::Endpoint;
+///
+/// #[derive(Debug, Endpoint)]
+/// #[endpoint(path = "my/endpoint")]
+/// struct MyEndpoint {}
+///
+/// let client = Client::default("http://myapi.com");
+/// let endpoint = MyEndpoint {};
+/// let result = endpoint.exec_block(&client);
+/// ```
+///
+/// Redirects are followed by the backing [attohttpc::Session][1] unless a
+/// [RedirectPolicy] is set with [set_redirect][Client::set_redirect], in which
+/// case the client follows them itself and records them on every response.
+///
+/// Clients created with [Client::with_config] support timeouts, proxies,
+/// default headers and root certificates, the latter requiring the
+/// `attohttpc-tls` feature. The remaining settings of [ClientConfig] aren't
+/// supported by attohttpc.
+///
+/// [1]: https://docs.rs/attohttpc/latest/attohttpc/struct.Session.html
+pub struct Client {
+    pub http: attohttpc::Session,
+    pub base: String,
+    drain: Drain,
+    pub redirect: Option<RedirectPolicy>,
+    pub headers: HeaderMap,
+    pub query: Vec<(String, String)>,
+    pub join: UrlJoin,
+}
+
+impl Client {
+    /// Creates a new instance of [Client] using the provided parameters.
+    pub fn new(base: &str, http: attohttpc::Session) -> Self {
+        Client {
+            base: base.to_string(),
+            drain: Drain::new(),
+            http,
+            redirect: None,
+            headers: crate::config::default_headers(),
+            query: Vec::new(),
+            join: UrlJoin::default(),
+        }
+    }
+
+    /// Creates a new instance of [Client] with a default instance of
+    /// [attohttpc::Session][1].
+    ///
+    /// [1]: https://docs.rs/attohttpc/latest/attohttpc/struct.Session.html
+    pub fn default(base: &str) -> Self {
+        Client::new(base, attohttpc::Session::new())
+    }
+
+    /// Creates a new instance of [Client] using the provided parameters,
+    /// failing if the base URL is invalid.
+    pub fn try_new(base: &str, http: attohttpc::Session) -> Result<Self, ClientError> {
+        crate::http::validate_base(base)?;
+        Ok(Client::new(base, http))
+    }
+
+    /// Creates a new instance of [Client] with a default instance of
+    /// [attohttpc::Session][1], failing if the base URL is invalid.
+    ///
+    /// [1]: https://docs.rs/attohttpc/latest/attohttpc/struct.Session.html
+    pub fn try_default(base: &str) -> Result<Self, ClientError> {
+        crate::http::validate_base(base)?;
+        Ok(Client::default(base))
+    }
+
+    /// Creates a new instance of [Client] with an [attohttpc::Session][1]
+    /// configured using the given [ClientConfig] which follows redirects
+    /// according to its [redirect][ClientConfig::redirect] policy. Fails if
+    /// the base URL is invalid.
+    ///
+    /// [1]: https://docs.rs/attohttpc/latest/attohttpc/struct.Session.html
+    pub fn with_config(base: &str, config: &ClientConfig) -> Result<Self, ClientError> {
+        crate::http::validate_base(base)?;
+        if config.identity.is_some() {
+            return Err(unsupported("attohttpc", "identity"));
+        }
+        if config.min_tls_version.is_some() {
+            return Err(unsupported("attohttpc", "min_tls_version"));
+        }
+        if !config.resolve.is_empty() {
+            return Err(unsupported("attohttpc", "resolve"));
+        }
+        if config.pool_idle_timeout.is_some() || config.pool_max_idle_per_host.is_some() {
+            return Err(unsupported("attohttpc", "connection pooling"));
+        }
+        if config.tcp_keepalive.is_some() {
+            return Err(unsupported("attohttpc", "tcp_keepalive"));
+        }
+        if config.http_version == HttpVersion::Http2PriorKnowledge {
+            return Err(unsupported("attohttpc", "HTTP/2"));
+        }
+        if config.http_version == HttpVersion::Http3 {
+            return Err(unsupported("attohttpc", "HTTP/3"));
+        }
+
+        let mut http = attohttpc::Session::new();
+        if let Some(timeout) = config.timeout {
+            http.timeout(timeout);
+        }
+        if let Some(timeout) = config.connect_timeout {
+            http.connect_timeout(timeout);
+        }
+        if let Some(url) = &config.proxy {
+            let url = url::Url::parse(url)
+                .map_err(|e| ClientError::ClientBuildError { source: e.into() })?;
+            http.proxy_settings(
+                attohttpc::ProxySettings::builder()
+                    .http_proxy(url.clone())
+                    .https_proxy(url)
+                    .build(),
+            );
+        }
+        root_certificates(&mut http, config)?;
+
+        let mut client = Client::new(base, http);
+        client.redirect = Some(config.redirect);
+        client.headers = config.headers()?;
+        client.query = config.default_query.clone();
+        client.join = config.url_join;
+        Ok(client)
+    }
+
+    /// Sets the headers added to every request sent by this client which
+    /// doesn't set them itself, replacing the
+    /// [DEFAULT_USER_AGENT][crate::config::DEFAULT_USER_AGENT] sent by
+    /// default.
+    pub fn set_default_headers(&mut self, headers: HeaderMap) {
+        self.headers = headers;
+    }
+
+    /// Sets the query parameters added to the URL of every request sent by
+    /// this client which doesn't set them itself.
+    pub fn set_default_query(&mut self, query: Vec<(String, String)>) {
+        self.query = query;
+    }
+
+    /// Sets the [UrlJoin] mode used for joining the path of every
+    /// [Endpoint][crate::endpoint::Endpoint] onto the base URL.
+    pub fn set_url_join(&mut self, join: UrlJoin) {
+        self.join = join;
+    }
+
+    /// Sets the [RedirectPolicy] used for following redirects.
+    pub fn set_redirect(&mut self, policy: RedirectPolicy) {
+        self.redirect = Some(policy);
+    }
+
+    /// Returns a [attohttpc::RequestBuilder] for the given method and URL
+    /// using the backing session where possible.
+    fn builder(&self, method: Method, url: &str) -> Result<attohttpc::RequestBuilder, ClientError> {
+        let builder = match method {
+            Method::GET => self.http.get(url),
+            Method::POST => self.http.post(url),
+            Method::PUT => self.http.put(url),
+            Method::DELETE => self.http.delete(url),
+            Method::HEAD => self.http.head(url),
+            Method::OPTIONS => self.http.options(url),
+            Method::PATCH => self.http.patch(url),
+            Method::TRACE => self.http.trace(url),
+            _ => attohttpc::RequestBuilder::try_new(method.clone(), url).map_err(|e| {
+                ClientError::RequestError {
+                    source: e.into(),
+                    url: url.to_string(),
+                    method: method.to_string(),
+                }
+            })?,
+        };
+        Ok(builder)
+    }
+}
+
+impl RustifyClient for Client {
+    fn base(&self) -> &str {
+        self.base.as_str()
+    }
+
+    fn drain(&self) -> Option<&Drain> {
+        Some(&self.drain)
+    }
+
+    fn default_headers(&self) -> Option<&HeaderMap> {
+        Some(&self.headers)
+    }
+
+    fn default_query(&self) -> Option<&[(String, String)]> {
+        Some(&self.query)
+    }
+
+    fn url_join(&self) -> UrlJoin {
+        self.join
+    }
+
+    #[cfg_attr(feature = "tracing", instrument(skip(self, req), err))]
+    fn send(&self, req: Request<Bytes>) -> Result<Response<Bytes>, ClientError> {
+        crate::redirect::follow_blocking(self.redirect.as_ref(), req, |req| self.dispatch(req))
+    }
+}
+
+impl Client {
+    /// Converts the given [Request] and sends it using the backing
+    /// [attohttpc::Session][1].
+    ///
+    /// [1]: https://docs.rs/attohttpc/latest/attohttpc/struct.Session.html
+    fn dispatch(&self, req: Request<Bytes>) -> Result<Response<Bytes>, ClientError> {
+        let (parts, body) = req.into_parts();
+        let url_err = parts.uri.to_string();
+        let method_err = parts.method.to_string();
+
+        let mut builder = self.builder(parts.method, url_err.as_str())?;
+        if let Some(timeout) = parts.extensions.get::<RequestTimeout>() {
+            builder = builder.timeout(timeout.0);
+        }
+        if self.redirect.is_some() {
+            builder = builder.follow_redirects(false);
+        }
+        for (name, value) in parts.headers.iter() {
+            builder =
+                builder
+                    .try_header_append(name, value)
+                    .map_err(|e| ClientError::RequestError {
+                        source: e.into(),
+                        url: url_err.clone(),
+                        method: method_err.clone(),
+                    })?;
+        }
+
+        let response = builder
+            .bytes(body)
+            .send()
+            .map_err(|e| ClientError::RequestError {
+                source: e.into(),
+                url: url_err,
+                method: method_err,
+            })?;
+
+        let (status, headers, reader) = response.split();
+        let mut http_resp = http::Response::builder().status(status);
+        for (name, value) in headers.iter() {
+            http_resp = http_resp.header(name, value);
+        }
+        http_resp
+            .body(
+                reader
+                    .bytes()
+                    .map_err(|e| ClientError::ResponseError { source: e.into() })?
+                    .into(),
+            )
+            .map_err(|e| ClientError::ResponseError { source: e.into() })
+    }
+}
+
+/// Adds the root certificates of the given [ClientConfig] to the given
+/// [attohttpc::Session][1].
+///
+/// [1]: https://docs.rs/attohttpc/latest/attohttpc/struct.Session.html
+#[cfg(feature = "attohttpc-tls")]
+fn root_certificates(
+    http: &mut attohttpc::Session,
+    config: &ClientConfig,
+) -> Result<(), ClientError> {
+    use crate::config::Certificate;
+
+    for cert in config.root_certificates.iter() {
+        let cert = match cert {
+            Certificate::Pem(pem) => native_tls::Certificate::from_pem(pem),
+            Certificate::Der(der) => native_tls::Certificate::from_der(der),
+        }
+        .map_err(|e| ClientError::CertificateParseError {
+            source: e.into(),
+            name: "root certificate".to_string(),
+        })?;
+        http.add_root_certificate(cert);
+    }
+    Ok(())
+}
+
+/// Fails if the given [ClientConfig] contains root certificates.
+#[cfg(not(feature = "attohttpc-tls"))]
+fn root_certificates(
+    _http: &mut attohttpc::Session,
+    config: &ClientConfig,
+) -> Result<(), ClientError> {
+    if !config.root_certificates.is_empty() {
+        return Err(ClientError::ClientBuildError {
+            source: anyhow::anyhow!("TLS settings require the attohttpc-tls feature"),
+        });
+    }
+    Ok(())
+}
@@ -2,9 +2,20 @@
 //! [Client][crate::blocking::client::Client] being backed by the
 //! [reqwest](https://docs.rs/reqwest/) crate.
 
-use crate::{blocking::client::Client as RustifyClient, errors::ClientError};
-use http::{Request, Response};
-use std::convert::TryFrom;
+use crate::{
+    auth::AuthProvider,
+    blocking::client::Client as RustifyClient,
+    client::{Backing, Drain, RequestTimeout},
+    clients::reqwest::{configure, Builder},
+    config::{Certificate, ClientConfig, Identity},
+    errors::ClientError,
+    http::UrlJoin,
+    metrics::MetricsObserver,
+    redirect::{RedirectPolicy, Redirects},
+};
+use bytes::Bytes;
+use http::{HeaderMap, Request, Response};
+use std::{convert::TryFrom, net::SocketAddr, path::Path, sync::Arc, time::Duration};
 
 /// A client based on the
 /// [reqwest::blocking::Client][1] which can be used for executing
@@ -30,67 +41,337 @@ use std::convert::TryFrom;
 /// let result = endpoint.exec_block(&client);
 /// ```
 ///
+/// Credentials can be attached to every request by setting an
+/// [AuthProvider] with [set_auth][Client::set_auth].
+///
+/// Redirects are followed by the backing [reqwest::blocking::Client][1],
+/// which only reports the final URL of a response. Clients created with
+/// [Client::with_redirect] or [Client::with_config] follow them according to
+/// their [RedirectPolicy] instead, which records the followed redirects on
+/// every response.
+///
 /// [1]: https://docs.rs/reqwest/latest/reqwest/blocking/struct.Client.html
+#[derive(Clone)]
 pub struct Client {
-    pub http: reqwest::blocking::Client,
+    http: Backing<reqwest::blocking::Client>,
     pub base: String,
+    drain: Drain,
+    pub auth: Option<Arc<dyn AuthProvider + Send + Sync>>,
+    pub metrics: Option<Arc<dyn MetricsObserver + Send + Sync>>,
+    pub redirect: Option<RedirectPolicy>,
+    pub headers: HeaderMap,
+    pub query: Vec<(String, String)>,
+    pub join: UrlJoin,
 }
 
 impl Client {
     /// Creates a new instance of [Client] using the provided parameters.
     pub fn new(base: &str, http: reqwest::blocking::Client) -> Self {
+        let drain = Drain::new();
         Client {
             base: base.to_string(),
-            http,
+            http: Backing::new(http, &drain),
+            drain,
+            auth: None,
+            metrics: None,
+            redirect: None,
+            headers: crate::config::default_headers(),
+            query: Vec::new(),
+            join: UrlJoin::default(),
         }
     }
 
+    /// Creates a new instance of [Client] using the provided parameters,
+    /// failing if the base URL is invalid.
+    pub fn try_new(base: &str, http: reqwest::blocking::Client) -> Result<Self, ClientError> {
+        crate::http::validate_base(base)?;
+        Ok(Client::new(base, http))
+    }
+
     /// Creates a new instance of [Client] with a default instance of
-    /// [reqwest::blocking::Client][1].
+    /// [reqwest::blocking::Client][1] which follows up to 10 redirects itself.
     ///
     /// [1]: https://docs.rs/reqwest/latest/reqwest/blocking/struct.Client.html
     pub fn default(base: &str) -> Self {
-        Client {
-            base: base.to_string(),
-            http: reqwest::blocking::Client::default(),
-        }
+        Client::new(base, reqwest::blocking::Client::default())
     }
-}
 
-impl RustifyClient for Client {
-    fn base(&self) -> &str {
-        self.base.as_str()
+    /// Creates a new instance of [Client] with a default instance of
+    /// [reqwest::blocking::Client][1] which follows up to 10 redirects
+    /// itself, failing if the base URL is invalid or the backing client fails
+    /// to initialize.
+    ///
+    /// [1]: https://docs.rs/reqwest/latest/reqwest/blocking/struct.Client.html
+    pub fn try_default(base: &str) -> Result<Self, ClientError> {
+        crate::http::validate_base(base)?;
+        let http = reqwest::blocking::Client::builder()
+            .build()
+            .map_err(|e| ClientError::ClientBuildError { source: e.into() })?;
+        Ok(Client::new(base, http))
+    }
+
+    /// Creates a new instance of [Client] with a default instance of
+    /// [reqwest::blocking::Client][1] which follows redirects according to
+    /// the given [RedirectPolicy].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the backing [reqwest::blocking::Client][1] fails to
+    /// initialize.
+    ///
+    /// [1]: https://docs.rs/reqwest/latest/reqwest/blocking/struct.Client.html
+    pub fn with_redirect(base: &str, policy: RedirectPolicy) -> Self {
+        let http = reqwest::blocking::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .expect("failed to initialize reqwest client");
+        let mut client = Client::new(base, http);
+        client.redirect = Some(policy);
+        client
+    }
+
+    /// Creates a new instance of [Client] with a
+    /// [reqwest::blocking::Client][1] configured using the given
+    /// [ClientConfig] which follows redirects according to its
+    /// [redirect][ClientConfig::redirect] policy. Fails if the base URL is
+    /// invalid.
+    ///
+    /// [1]: https://docs.rs/reqwest/latest/reqwest/blocking/struct.Client.html
+    pub fn with_config(base: &str, config: &ClientConfig) -> Result<Self, ClientError> {
+        crate::http::validate_base(base)?;
+        let builder =
+            reqwest::blocking::Client::builder().redirect(reqwest::redirect::Policy::none());
+        let http = configure(builder, config)?
+            .build()
+            .map_err(|e| ClientError::ClientBuildError { source: e.into() })?;
+        let mut client = Client::new(base, http);
+        client.redirect = Some(config.redirect);
+        client.headers = config.headers()?;
+        client.query = config.default_query.clone();
+        client.join = config.url_join;
+        Ok(client)
+    }
+
+    /// Creates a new instance of [Client] for the common mutual TLS setup,
+    /// which trusts the PEM encoded CA certificate and presents the PEM
+    /// encoded client certificate and PKCS#8 private key read from the given
+    /// files.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use rustify::blocking::clients::reqwest::Client;
+    ///
+    /// let client = Client::with_mtls(
+    ///     "https://vault.internal:8200",
+    ///     "/etc/vault/ca.pem",
+    ///     "/etc/vault/client.pem",
+    ///     "/etc/vault/client-key.pem",
+    /// )
+    /// .unwrap();
+    /// ```
+    pub fn with_mtls(
+        base: &str,
+        ca_path: impl AsRef<Path>,
+        cert_path: impl AsRef<Path>,
+        key_path: impl AsRef<Path>,
+    ) -> Result<Self, ClientError> {
+        let config = ClientConfig::default()
+            .root_certificate(Certificate::from_pem_file(ca_path)?)
+            .identity(Identity::from_pem_files(cert_path, key_path)?);
+        Client::with_config(base, &config)
+    }
+
+    /// Returns the backing [reqwest::blocking::Client][1], or [None] once the client has been
+    /// shut down and its connection pool released.
+    ///
+    /// [1]: https://docs.rs/reqwest/latest/reqwest/blocking/struct.Client.html
+    pub fn http(&self) -> Option<reqwest::blocking::Client> {
+        self.http.get()
+    }
+
+    /// Returns a new instance of [Client] which sends requests to the given
+    /// base URL, sharing the backing [reqwest::blocking::Client][1], its connection pool and the
+    /// settings of this client.
+    ///
+    /// [1]: https://docs.rs/reqwest/latest/reqwest/blocking/struct.Client.html
+    pub fn with_base(&self, base: &str) -> Self {
+        let mut client = self.clone();
+        client.base = base.to_string();
+        client
+    }
+
+    /// Sets the [AuthProvider] used for attaching credentials to every
+    /// request sent by this client.
+    pub fn set_auth(&mut self, auth: impl AuthProvider + Send + Sync + 'static) {
+        self.auth = Some(Arc::new(auth));
+    }
+
+    /// Sets the [MetricsObserver] invoked with the metrics of every request
+    /// sent by this client.
+    pub fn set_metrics(&mut self, metrics: impl MetricsObserver + Send + Sync + 'static) {
+        self.metrics = Some(Arc::new(metrics));
+    }
+
+    /// Sets the headers added to every request sent by this client which
+    /// doesn't set them itself, replacing the
+    /// [DEFAULT_USER_AGENT][crate::config::DEFAULT_USER_AGENT] sent by
+    /// default.
+    pub fn set_default_headers(&mut self, headers: HeaderMap) {
+        self.headers = headers;
+    }
+
+    /// Sets the query parameters added to the URL of every request sent by
+    /// this client which doesn't set them itself.
+    pub fn set_default_query(&mut self, query: Vec<(String, String)>) {
+        self.query = query;
+    }
+
+    /// Sets the [UrlJoin] mode used for joining the path of every
+    /// [Endpoint][crate::endpoint::Endpoint] onto the base URL.
+    pub fn set_url_join(&mut self, join: UrlJoin) {
+        self.join = join;
+    }
+
+    /// Sets the [RedirectPolicy] used for following redirects.
+    ///
+    /// The backing [reqwest::blocking::Client][1] must not follow redirects
+    /// itself, as is the case for clients created with [Client::with_redirect]
+    /// or [Client::with_config] but not with [Client::default].
+    ///
+    /// [1]: https://docs.rs/reqwest/latest/reqwest/blocking/struct.Client.html
+    pub fn set_redirect(&mut self, policy: RedirectPolicy) {
+        self.redirect = Some(policy);
     }
 
-    #[instrument(skip(self, req), err)]
-    fn send(&self, req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, ClientError> {
-        let request = reqwest::blocking::Request::try_from(req)
+    /// Converts the given [Request] and sends it using the backing
+    /// [reqwest::blocking::Client][1].
+    ///
+    /// [1]: https://docs.rs/reqwest/latest/reqwest/blocking/struct.Client.html
+    fn dispatch(&self, req: Request<Bytes>) -> Result<Response<Bytes>, ClientError> {
+        let timeout = req.extensions().get::<RequestTimeout>().map(|t| t.0);
+        let mut request = reqwest::blocking::Request::try_from(req)
             .map_err(|e| ClientError::ReqwestBuildError { source: e })?;
+        if timeout.is_some() {
+            *request.timeout_mut() = timeout;
+        }
 
         let url_err = request.url().to_string();
         let method_err = request.method().to_string();
-        let response = self
-            .http
-            .execute(request)
-            .map_err(|e| ClientError::RequestError {
-                source: e.into(),
-                url: url_err,
-                method: method_err,
-            })?;
-
-        let status_code = response.status().as_u16();
-        let mut headers = http::header::HeaderMap::new();
-        let http_resp = http::Response::builder().status(status_code);
+        let response =
+            self.http
+                .acquire()?
+                .execute(request)
+                .map_err(|e| ClientError::RequestError {
+                    source: e.into(),
+                    url: url_err,
+                    method: method_err,
+                })?;
+
+        let mut http_resp = http::Response::builder().status(response.status().as_u16());
         for v in response.headers().into_iter() {
-            headers.append::<http::header::HeaderName>(v.0.into(), v.1.into());
+            http_resp = http_resp.header(v.0, v.1);
+        }
+        if let Ok(url) = response.url().as_str().parse() {
+            http_resp = http_resp.extension(Redirects {
+                url,
+                chain: Vec::new(),
+            });
         }
         http_resp
             .body(
                 response
                     .bytes()
-                    .map_err(|e| ClientError::ResponseError { source: e.into() })?
-                    .to_vec(),
+                    .map_err(|e| ClientError::ResponseError { source: e.into() })?,
             )
             .map_err(|e| ClientError::ResponseError { source: e.into() })
     }
 }
+
+impl RustifyClient for Client {
+    fn base(&self) -> &str {
+        self.base.as_str()
+    }
+
+    fn drain(&self) -> Option<&Drain> {
+        Some(&self.drain)
+    }
+
+    fn auth(&self) -> Option<&dyn AuthProvider> {
+        self.auth.as_deref().map(|a| a as &dyn AuthProvider)
+    }
+
+    fn metrics(&self) -> Option<&dyn MetricsObserver> {
+        self.metrics.as_deref().map(|m| m as &dyn MetricsObserver)
+    }
+
+    fn default_headers(&self) -> Option<&HeaderMap> {
+        Some(&self.headers)
+    }
+
+    fn default_query(&self) -> Option<&[(String, String)]> {
+        Some(&self.query)
+    }
+
+    fn url_join(&self) -> UrlJoin {
+        self.join
+    }
+
+    #[cfg_attr(feature = "tracing", instrument(skip(self, req), err))]
+    fn send(&self, req: Request<Bytes>) -> Result<Response<Bytes>, ClientError> {
+        crate::redirect::follow_blocking(self.redirect.as_ref(), req, |req| self.dispatch(req))
+    }
+}
+
+impl Builder for reqwest::blocking::ClientBuilder {
+    fn timeout(self, timeout: Duration) -> Self {
+        self.timeout(timeout)
+    }
+
+    fn connect_timeout(self, timeout: Duration) -> Self {
+        self.connect_timeout(timeout)
+    }
+
+    fn proxy(self, proxy: reqwest::Proxy) -> Self {
+        self.proxy(proxy)
+    }
+
+    fn resolve_to_addrs(self, domain: &str, addrs: &[SocketAddr]) -> Self {
+        self.resolve_to_addrs(domain, addrs)
+    }
+
+    fn pool_idle_timeout(self, timeout: Duration) -> Self {
+        self.pool_idle_timeout(timeout)
+    }
+
+    fn pool_max_idle_per_host(self, max: usize) -> Self {
+        self.pool_max_idle_per_host(max)
+    }
+
+    fn tcp_keepalive(self, interval: Duration) -> Self {
+        self.tcp_keepalive(interval)
+    }
+
+    fn http1_only(self) -> Self {
+        self.http1_only()
+    }
+
+    #[cfg(feature = "http2")]
+    fn http2_prior_knowledge(self) -> Self {
+        self.http2_prior_knowledge()
+    }
+
+    #[cfg(any(feature = "native-tls", feature = "rustls-tls"))]
+    fn add_root_certificate(self, cert: reqwest::Certificate) -> Self {
+        self.add_root_certificate(cert)
+    }
+
+    #[cfg(any(feature = "native-tls", feature = "rustls-tls"))]
+    fn identity(self, identity: reqwest::Identity) -> Self {
+        self.identity(identity)
+    }
+
+    #[cfg(any(feature = "native-tls", feature = "rustls-tls"))]
+    fn min_tls_version(self, version: reqwest::tls::Version) -> Self {
+        self.min_tls_version(version)
+    }
+}
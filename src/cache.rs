@@ -0,0 +1,549 @@
+//! Contains a [Client] decorator for caching responses according to their
+//! `Cache-Control` directives and revalidating them using conditional
+//! requests.
+#[cfg(feature = "blocking")]
+use crate::blocking::client::Client as BlockingClient;
+use crate::{
+    auth::AuthProvider,
+    client::{ByteStream, Client, Drain, SuccessCodes},
+    endpoint::Endpoint,
+    errors::ClientError,
+    http::UrlJoin,
+    marker::MaybeSendSync,
+    metrics::MetricsObserver,
+    retry::RetryPolicy,
+};
+use async_trait::async_trait;
+use bytes::Bytes;
+use http::{header, HeaderMap, HeaderName, Method, Request, Response, StatusCode};
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime},
+};
+
+pub mod disk;
+
+/// A response stored by a [CachingClient].
+#[derive(Clone, Debug)]
+pub struct CachedResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+    /// When the response was received or last revalidated.
+    pub stored: SystemTime,
+    /// The values the request had for the headers named by the `Vary` header
+    /// of this response, which form the secondary cache key of the response.
+    pub vary: HeaderMap,
+}
+
+impl CachedResponse {
+    /// Returns a new [CachedResponse] holding a copy of the given response
+    /// to a request with the given headers.
+    pub fn new(req: &HeaderMap, resp: &Response<Bytes>) -> Self {
+        let mut vary = HeaderMap::new();
+        for name in vary_names(resp.headers()).unwrap_or_default() {
+            for value in req.get_all(&name) {
+                vary.append(name.clone(), value.clone());
+            }
+        }
+        CachedResponse {
+            status: resp.status(),
+            headers: resp.headers().clone(),
+            body: resp.body().clone(),
+            stored: SystemTime::now(),
+            vary,
+        }
+    }
+
+    /// Returns whether this response can be served for a request with the
+    /// given headers, which is the case when the request has the same values
+    /// for every header named by the `Vary` header of this response.
+    pub fn matches(&self, req: &HeaderMap) -> bool {
+        match vary_names(&self.headers) {
+            Some(names) => names
+                .iter()
+                .all(|n| self.vary.get_all(n).iter().eq(req.get_all(n).iter())),
+            None => false,
+        }
+    }
+
+    /// Returns how long this response is fresh for after being received,
+    /// taken from the `max-age` directive minus the `Age` header. Responses
+    /// with a `no-cache` directive are never fresh.
+    pub fn max_age(&self) -> Option<Duration> {
+        if directive(&self.headers, "no-cache").is_some() {
+            return None;
+        }
+        let max_age = directive(&self.headers, "max-age")??.parse::<u64>().ok()?;
+        let age = self
+            .headers
+            .get(header::AGE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(0);
+        Some(Duration::from_secs(max_age.saturating_sub(age)))
+    }
+
+    /// Returns whether this response can be served without contacting the
+    /// server.
+    pub fn is_fresh(&self) -> bool {
+        self.max_age()
+            .is_some_and(|m| self.stored.elapsed().unwrap_or_default() < m)
+    }
+
+    /// Returns whether this response can be cached, either because it's fresh
+    /// for some time or because it can be revalidated.
+    fn is_cacheable(&self) -> bool {
+        self.status == StatusCode::OK
+            && directive(&self.headers, "no-store").is_none()
+            && vary_names(&self.headers).is_some()
+            && (self.has_validator() || self.max_age().is_some_and(|m| !m.is_zero()))
+    }
+
+    /// Returns this response as a [Response].
+    fn response(&self) -> Response<Bytes> {
+        let mut resp = Response::new(self.body.clone());
+        *resp.status_mut() = self.status;
+        *resp.headers_mut() = self.headers.clone();
+        resp
+    }
+
+    /// Returns whether this response carries an `ETag` or `Last-Modified`
+    /// validator which can be used to revalidate it.
+    pub fn has_validator(&self) -> bool {
+        self.headers.contains_key(header::ETAG) || self.headers.contains_key(header::LAST_MODIFIED)
+    }
+
+    /// Adds the conditional headers for revalidating this response to the
+    /// given [Request].
+    fn apply_validators(&self, req: &mut Request<Bytes>) {
+        if let Some(etag) = self.headers.get(header::ETAG) {
+            req.headers_mut()
+                .insert(header::IF_NONE_MATCH, etag.clone());
+        }
+        if let Some(modified) = self.headers.get(header::LAST_MODIFIED) {
+            req.headers_mut()
+                .insert(header::IF_MODIFIED_SINCE, modified.clone());
+        }
+    }
+
+    /// Returns this response as a [Response], updating its headers with
+    /// those of the given `304 Not Modified` response and renewing its
+    /// freshness.
+    fn revalidated(&mut self, not_modified: &Response<Bytes>) -> Response<Bytes> {
+        for (name, value) in not_modified.headers() {
+            if name != header::CONTENT_LENGTH {
+                self.headers.insert(name, value.clone());
+            }
+        }
+        self.stored = SystemTime::now();
+        self.response()
+    }
+}
+
+/// Represents a backend which stores the responses cached by a
+/// [CachingClient].
+///
+/// Stores are keyed by the method and URL of the request. Failures to read or
+/// write the underlying storage should be treated as cache misses, as the
+/// cache is only an optimization.
+pub trait CacheStore: MaybeSendSync {
+    /// Returns the response stored under the given key, if any.
+    fn get(&self, key: &str) -> Option<CachedResponse>;
+
+    /// Stores the given response under the given key, replacing any existing
+    /// response.
+    fn put(&self, key: &str, response: CachedResponse);
+
+    /// Removes the response stored under the given key, if any.
+    fn evict(&self, key: &str);
+
+    /// Removes all stored responses.
+    fn clear(&self);
+}
+
+/// A [CacheStore] which keeps responses in memory, evicting the least
+/// recently used response once `max_entries` responses are stored.
+pub struct MemoryStore {
+    entries: Mutex<HashMap<String, (CachedResponse, Instant)>>,
+    max_entries: usize,
+}
+
+impl MemoryStore {
+    /// Returns a new [MemoryStore] which holds up to the given number of
+    /// responses.
+    pub fn new(max_entries: usize) -> Self {
+        MemoryStore {
+            entries: Mutex::new(HashMap::new()),
+            max_entries,
+        }
+    }
+
+    /// Returns the number of stored responses.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    /// Returns whether no responses are stored.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for MemoryStore {
+    /// Holds up to 1024 responses.
+    fn default() -> Self {
+        MemoryStore::new(1024)
+    }
+}
+
+impl CacheStore for MemoryStore {
+    fn get(&self, key: &str) -> Option<CachedResponse> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.get_mut(key).map(|(response, used)| {
+            *used = Instant::now();
+            response.clone()
+        })
+    }
+
+    fn put(&self, key: &str, response: CachedResponse) {
+        if self.max_entries == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        if !entries.contains_key(key) && entries.len() >= self.max_entries {
+            let lru = entries
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(k, _)| k.clone());
+            if let Some(lru) = lru {
+                entries.remove(&lru);
+            }
+        }
+        entries.insert(key.to_string(), (response, Instant::now()));
+    }
+
+    fn evict(&self, key: &str) {
+        self.entries.lock().unwrap().remove(key);
+    }
+
+    fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+/// A [Client] decorator which caches successful `GET` and `HEAD` responses in
+/// a [CacheStore] according to their `Cache-Control` directives.
+///
+/// Responses with a `max-age` directive are served from the cache without
+/// contacting the server until they become stale. Stale responses, and those
+/// with a `no-cache` directive, are revalidated if they carry an `ETag` or
+/// `Last-Modified` validator by adding the `If-None-Match` and
+/// `If-Modified-Since` headers to the request. If the server answers with
+/// `304 Not Modified`, the cached response is returned in its place with any
+/// updated headers. Responses with a `no-store` directive are never cached.
+///
+/// Requests with a `Cache-Control: no-cache` header always go to the server,
+/// and requests which already carry a conditional header are sent unmodified.
+/// Responses are cached by method and URL, keeping the latest response for
+/// each. A response with a `Vary` header is only served to requests with the
+/// same values for the listed request headers (i.e. `Authorization`) as the
+/// request it was received for, while responses with `Vary: *` are never
+/// cached. Responses are kept in a [MemoryStore] by default,
+/// while a [DiskStore][disk::DiskStore] can be used for caching responses
+/// across process restarts.
+///
+/// # Example
+/// ```no_run
+/// use rustify::cache::{disk::DiskStore, CachingClient};
+/// use rustify::clients::reqwest::Client;
+///
+/// let client = CachingClient::with_store(
+///     Client::default("http://myapi.com"),
+///     DiskStore::new("/tmp/myapi-cache"),
+/// );
+/// ```
+pub struct CachingClient<C, S = MemoryStore> {
+    client: C,
+    store: S,
+}
+
+impl<C> CachingClient<C> {
+    /// Returns a new [CachingClient] wrapping the given client which caches
+    /// responses in a default [MemoryStore].
+    pub fn new(client: C) -> Self {
+        CachingClient::with_store(client, MemoryStore::default())
+    }
+}
+
+impl<C, S: CacheStore> CachingClient<C, S> {
+    /// Returns a new [CachingClient] wrapping the given client which caches
+    /// responses in the given [CacheStore].
+    pub fn with_store(client: C, store: S) -> Self {
+        CachingClient { client, store }
+    }
+
+    /// Returns a reference to the wrapped client.
+    pub fn inner(&self) -> &C {
+        &self.client
+    }
+
+    /// Returns a reference to the [CacheStore] responses are cached in.
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Returns the cached response for the given method and URL, if any.
+    pub fn get(&self, method: &Method, url: &str) -> Option<CachedResponse> {
+        self.store.get(&key(method, url))
+    }
+
+    /// Removes the cached responses for the given URL, if any.
+    pub fn invalidate(&self, url: &str) {
+        for method in [Method::GET, Method::HEAD].iter() {
+            self.store.evict(&key(method, url));
+        }
+    }
+
+    /// Removes all cached responses.
+    pub fn clear(&self) {
+        self.store.clear();
+    }
+
+    /// Looks up the cached response for the given [Request], adding the
+    /// validators of a stale response to it.
+    fn prepare(&self, req: &mut Request<Bytes>) -> Lookup {
+        let conditional = req.headers().contains_key(header::IF_NONE_MATCH)
+            || req.headers().contains_key(header::IF_MODIFIED_SINCE);
+        if !matches!(*req.method(), Method::GET | Method::HEAD) || conditional {
+            return Lookup::Bypass;
+        }
+
+        let key = key(req.method(), &req.uri().to_string());
+        let no_cache = directive(req.headers(), "no-cache").is_some();
+        let cached = self.store.get(&key).filter(|c| c.matches(req.headers()));
+        if let Some(cached) = cached {
+            if cached.is_fresh() && !no_cache {
+                trace!(key = key.as_str(), "Serving fresh cached response");
+                return Lookup::Hit(cached.response());
+            }
+            cached.apply_validators(req);
+        }
+        Lookup::Miss(key, req.headers().clone())
+    }
+
+    /// Returns the response to the request with the given cache key and
+    /// headers, serving the cached response if the server answered `304 Not
+    /// Modified` and caching the response if it's cacheable.
+    fn complete(&self, key: String, req: &HeaderMap, resp: Response<Bytes>) -> Response<Bytes> {
+        if resp.status() == StatusCode::NOT_MODIFIED {
+            if let Some(mut cached) = self.store.get(&key) {
+                trace!(key = key.as_str(), "Serving revalidated cached response");
+                let resp = cached.revalidated(&resp);
+                self.store.put(&key, cached);
+                return resp;
+            }
+            return resp;
+        }
+
+        let cached = CachedResponse::new(req, &resp);
+        match cached.is_cacheable() {
+            true => self.store.put(&key, cached),
+            false => self.store.evict(&key),
+        }
+        resp
+    }
+}
+
+#[cfg_attr(not(feature = "non-send"), async_trait)]
+#[cfg_attr(feature = "non-send", async_trait(?Send))]
+impl<C: Client, S: CacheStore> Client for CachingClient<C, S> {
+    #[cfg_attr(feature = "tracing", instrument(skip(self, req), err))]
+    async fn send(&self, mut req: Request<Bytes>) -> Result<Response<Bytes>, ClientError> {
+        match self.prepare(&mut req) {
+            Lookup::Hit(resp) => Ok(resp),
+            Lookup::Miss(key, headers) => {
+                Ok(self.complete(key, &headers, self.client.send(req).await?))
+            }
+            Lookup::Bypass => self.client.send(req).await,
+        }
+    }
+
+    async fn send_stream(&self, req: Request<Bytes>) -> Result<Response<ByteStream>, ClientError> {
+        self.client.send_stream(req).await
+    }
+
+    fn base(&self) -> &str {
+        self.client.base()
+    }
+
+    fn drain(&self) -> Option<&Drain> {
+        self.client.drain()
+    }
+
+    fn auth(&self) -> Option<&dyn AuthProvider> {
+        self.client.auth()
+    }
+
+    fn metrics(&self) -> Option<&dyn MetricsObserver> {
+        self.client.metrics()
+    }
+
+    fn retry_policy(&self) -> Option<&RetryPolicy> {
+        self.client.retry_policy()
+    }
+
+    fn success_codes(&self) -> Option<&SuccessCodes> {
+        self.client.success_codes()
+    }
+
+    fn default_headers(&self) -> Option<&HeaderMap> {
+        self.client.default_headers()
+    }
+
+    fn default_query(&self) -> Option<&[(String, String)]> {
+        self.client.default_query()
+    }
+
+    fn url_join(&self) -> UrlJoin {
+        self.client.url_join()
+    }
+
+    fn mutate_request<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        req: &mut Request<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.client.mutate_request(endpoint, req)
+    }
+
+    fn mutate_response<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        resp: &mut Response<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.client.mutate_response(endpoint, resp)
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl<C: BlockingClient, S: CacheStore> BlockingClient for CachingClient<C, S> {
+    #[cfg_attr(feature = "tracing", instrument(skip(self, req), err))]
+    fn send(&self, mut req: Request<Bytes>) -> Result<Response<Bytes>, ClientError> {
+        match self.prepare(&mut req) {
+            Lookup::Hit(resp) => Ok(resp),
+            Lookup::Miss(key, headers) => Ok(self.complete(key, &headers, self.client.send(req)?)),
+            Lookup::Bypass => self.client.send(req),
+        }
+    }
+
+    fn base(&self) -> &str {
+        self.client.base()
+    }
+
+    fn drain(&self) -> Option<&Drain> {
+        self.client.drain()
+    }
+
+    fn auth(&self) -> Option<&dyn AuthProvider> {
+        self.client.auth()
+    }
+
+    fn metrics(&self) -> Option<&dyn MetricsObserver> {
+        self.client.metrics()
+    }
+
+    fn retry_policy(&self) -> Option<&RetryPolicy> {
+        self.client.retry_policy()
+    }
+
+    fn success_codes(&self) -> Option<&SuccessCodes> {
+        self.client.success_codes()
+    }
+
+    fn default_headers(&self) -> Option<&HeaderMap> {
+        self.client.default_headers()
+    }
+
+    fn default_query(&self) -> Option<&[(String, String)]> {
+        self.client.default_query()
+    }
+
+    fn url_join(&self) -> UrlJoin {
+        self.client.url_join()
+    }
+
+    fn mutate_request<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        req: &mut Request<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.client.mutate_request(endpoint, req)
+    }
+
+    fn mutate_response<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        resp: &mut Response<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.client.mutate_response(endpoint, resp)
+    }
+}
+
+/// The result of looking up a [Request] in a [CachingClient].
+enum Lookup {
+    /// A fresh response was found.
+    Hit(Response<Bytes>),
+    /// No fresh response was found and the response to the request with the
+    /// given headers should be cached using the given key.
+    Miss(String, HeaderMap),
+    /// The request can't be served from the cache.
+    Bypass,
+}
+
+/// Returns the cache key of the given method and URL.
+fn key(method: &Method, url: &str) -> String {
+    format!("{} {}", method, url)
+}
+
+/// Returns the names of the request headers listed by the `Vary` header in
+/// the given response headers, or [None] if the response varies on `*`.
+fn vary_names(headers: &HeaderMap) -> Option<Vec<HeaderName>> {
+    let mut names = Vec::new();
+    for name in headers
+        .get_all(header::VARY)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|n| n.trim())
+        .filter(|n| !n.is_empty())
+    {
+        if name == "*" {
+            return None;
+        }
+        if let Ok(name) = HeaderName::from_bytes(name.as_bytes()) {
+            names.push(name);
+        }
+    }
+    Some(names)
+}
+
+/// Finds the given `Cache-Control` directive in the given headers, returning
+/// its value if it has one.
+fn directive(headers: &HeaderMap, name: &str) -> Option<Option<String>> {
+    headers
+        .get_all(header::CACHE_CONTROL)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .find_map(|d| {
+            let mut parts = d.trim().splitn(2, '=');
+            match parts.next() {
+                Some(n) if n.trim().eq_ignore_ascii_case(name) => {
+                    Some(parts.next().map(|v| v.trim().trim_matches('"').to_string()))
+                }
+                _ => None,
+            }
+        })
+}
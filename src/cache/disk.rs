@@ -0,0 +1,197 @@
+//! Contains a [CacheStore] which persists responses on disk.
+use crate::cache::{CacheStore, CachedResponse};
+use bytes::Bytes;
+use http::{header::HeaderName, HeaderMap, HeaderValue, StatusCode};
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{Duration, UNIX_EPOCH},
+};
+
+/// A [CacheStore] which persists each response in its own file under a
+/// directory, allowing responses to be cached across process restarts.
+///
+/// Files are named after a hash of their key and hold the key, the time the
+/// response was stored, the status, the varying request headers, the headers
+/// and the raw body. Failures to
+/// read or write a file are logged and treated as cache misses. Files are
+/// written atomically, so several processes may safely share a directory.
+///
+/// # Example
+/// ```no_run
+/// use rustify::cache::{disk::DiskStore, CachingClient};
+/// use rustify::clients::reqwest::Client;
+///
+/// let store = DiskStore::new("/tmp/myapi-cache");
+/// let client = CachingClient::with_store(Client::default("http://myapi.com"), store);
+/// ```
+pub struct DiskStore {
+    dir: PathBuf,
+}
+
+impl DiskStore {
+    /// Returns a new [DiskStore] which stores responses in the given
+    /// directory. The directory is created when the first response is stored.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        DiskStore { dir: dir.into() }
+    }
+
+    /// Returns the directory responses are stored in.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns the path of the file storing the given key.
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{:016x}", fnv1a(key)))
+    }
+
+    fn read(&self, key: &str) -> io::Result<Option<CachedResponse>> {
+        let data = match fs::read(self.path(key)) {
+            Ok(d) => d,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        Ok(decode(key, data))
+    }
+
+    fn write(&self, key: &str, response: &CachedResponse) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.path(key);
+        let tmp = path.with_extension(format!("tmp{}", fastrand::u64(..)));
+        let result = fs::File::create(&tmp)
+            .and_then(|mut f| f.write_all(&encode(key, response)))
+            .and_then(|_| fs::rename(&tmp, &path));
+        if result.is_err() {
+            let _ = fs::remove_file(&tmp);
+        }
+        result
+    }
+}
+
+impl CacheStore for DiskStore {
+    fn get(&self, key: &str) -> Option<CachedResponse> {
+        self.read(key).unwrap_or_else(|_e| {
+            debug!(key, error = %_e, "Failed reading cached response");
+            None
+        })
+    }
+
+    fn put(&self, key: &str, response: CachedResponse) {
+        if let Err(_e) = self.write(key, &response) {
+            debug!(key, error = %_e, "Failed writing cached response");
+        }
+    }
+
+    fn evict(&self, key: &str) {
+        match fs::remove_file(self.path(key)) {
+            Err(_e) if _e.kind() != io::ErrorKind::NotFound => {
+                debug!(key, error = %_e, "Failed evicting cached response");
+            }
+            _ => {}
+        }
+    }
+
+    fn clear(&self) {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(e) => e,
+            Err(_) => return,
+        };
+        // Only remove the files named after a key, leaving anything else in
+        // the directory alone.
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if name.len() == 16 && name.chars().all(|c| c.is_ascii_hexdigit()) {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+}
+
+/// Serializes a response into the file format of a [DiskStore].
+fn encode(key: &str, response: &CachedResponse) -> Vec<u8> {
+    let stored = response
+        .stored
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let mut out = format!("{}\n{}\n{}\n", key, stored, response.status.as_u16()).into_bytes();
+    for headers in [&response.vary, &response.headers].iter() {
+        for (name, value) in headers.iter() {
+            out.extend_from_slice(name.as_str().as_bytes());
+            out.extend_from_slice(b": ");
+            out.extend_from_slice(value.as_bytes());
+            out.push(b'\n');
+        }
+        out.push(b'\n');
+    }
+    out.extend_from_slice(&response.body);
+    out
+}
+
+/// Deserializes a response from the file format of a [DiskStore], returning
+/// [None] if the file is malformed or stores a different key.
+fn decode(key: &str, data: Vec<u8>) -> Option<CachedResponse> {
+    let mut lines = Lines {
+        data: &data,
+        pos: 0,
+    };
+    if lines.next()? != key.as_bytes() {
+        return None;
+    }
+    let stored = std::str::from_utf8(lines.next()?).ok()?.parse().ok()?;
+    let status = StatusCode::from_bytes(lines.next()?).ok()?;
+
+    let vary = decode_headers(&mut lines)?;
+    let headers = decode_headers(&mut lines)?;
+
+    Some(CachedResponse {
+        status,
+        headers,
+        body: Bytes::copy_from_slice(&data[lines.pos..]),
+        stored: UNIX_EPOCH + Duration::from_secs(stored),
+        vary,
+    })
+}
+
+/// Deserializes a block of headers terminated by an empty line.
+fn decode_headers(lines: &mut Lines) -> Option<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    loop {
+        let line = lines.next()?;
+        if line.is_empty() {
+            return Some(headers);
+        }
+        let i = line.windows(2).position(|w| w == b": ")?;
+        headers.append(
+            HeaderName::from_bytes(&line[..i]).ok()?,
+            HeaderValue::from_bytes(&line[i + 2..]).ok()?,
+        );
+    }
+}
+
+/// Iterates over the newline terminated lines at the start of a file.
+struct Lines<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Iterator for Lines<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = &self.data[self.pos..];
+        let end = rest.iter().position(|b| *b == b'\n')?;
+        self.pos += end + 1;
+        Some(&rest[..end])
+    }
+}
+
+/// Returns the 64-bit FNV-1a hash of the given key.
+fn fnv1a(key: &str) -> u64 {
+    key.bytes().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
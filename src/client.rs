@@ -1,40 +1,329 @@
 //! Contains the [Client] trait for executing
 //! [Endpoints][crate::endpoint::Endpoint].
-use crate::errors::ClientError;
+#[cfg(feature = "blocking")]
+use crate::blocking::client::Client as BlockingClient;
+use crate::{
+    auth::AuthProvider,
+    correlation::record_request_id,
+    endpoint::{Endpoint, MiddleWare},
+    errors::ClientError,
+    http::UrlJoin,
+    marker::MaybeSendSync,
+    metrics::{MetricsObserver, PathTemplate, RequestMetrics},
+    retry::RetryPolicy,
+};
 use async_trait::async_trait;
-use http::{Request, Response};
-use std::ops::RangeInclusive;
+use bytes::Bytes;
+use futures_core::Stream;
+use futures_util::StreamExt;
+use http::{HeaderMap, Request, Response};
+use std::{
+    ops::RangeInclusive,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Condvar, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use tokio::sync::Notify;
 
 /// An array of HTTP response codes which indicate a successful response
 pub const HTTP_SUCCESS_CODES: RangeInclusive<u16> = 200..=208;
 
+/// A set of HTTP response codes, such as the codes which indicate a
+/// successful response or the codes which are retried by a
+/// [RetryPolicy][crate::retry::RetryPolicy].
+///
+/// Codes are given either as a list of ranges or as a predicate.
+#[derive(Clone)]
+pub struct StatusCodes(StatusCodesKind);
+
+/// A set of HTTP response codes which indicate a successful response.
+///
+/// Endpoints which accept a different set of response codes attach a
+/// [SuccessCodes] to the extensions of their [Request], which takes
+/// precedence over the [Client::success_codes] of the client executing them.
+/// When neither is set, [HTTP_SUCCESS_CODES] is used.
+pub type SuccessCodes = StatusCodes;
+
+#[derive(Clone)]
+enum StatusCodesKind {
+    Ranges(Vec<RangeInclusive<u16>>),
+    Predicate(Arc<dyn Fn(u16) -> bool + Send + Sync>),
+}
+
+impl StatusCodes {
+    /// Returns a new [StatusCodes] containing the given ranges of codes.
+    pub fn new(codes: Vec<RangeInclusive<u16>>) -> Self {
+        StatusCodes(StatusCodesKind::Ranges(codes))
+    }
+
+    /// Returns a new [StatusCodes] containing every code for which the given
+    /// predicate returns true.
+    pub fn from_fn<F>(f: F) -> Self
+    where
+        F: Fn(u16) -> bool + Send + Sync + 'static,
+    {
+        StatusCodes(StatusCodesKind::Predicate(Arc::new(f)))
+    }
+
+    /// Returns whether the given response code is contained in the set.
+    pub fn contains(&self, code: u16) -> bool {
+        match &self.0 {
+            StatusCodesKind::Ranges(r) => r.iter().any(|r| r.contains(&code)),
+            StatusCodesKind::Predicate(f) => f(code),
+        }
+    }
+
+    /// Returns the [SuccessCodes] for the given [Request], preferring any
+    /// attached to the request over the given client codes and falling back
+    /// to the default codes.
+    pub fn resolve<B>(req: &Request<B>, client: Option<&SuccessCodes>) -> Self {
+        req.extensions()
+            .get::<SuccessCodes>()
+            .or(client)
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+impl Default for StatusCodes {
+    fn default() -> Self {
+        StatusCodes::new(vec![HTTP_SUCCESS_CODES])
+    }
+}
+
+impl std::fmt::Debug for StatusCodes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.0 {
+            StatusCodesKind::Ranges(r) => f.debug_tuple("StatusCodes").field(r).finish(),
+            StatusCodesKind::Predicate(_) => f.write_str("StatusCodes(<fn>)"),
+        }
+    }
+}
+
+/// The maximum amount of time a [Request] may take to complete.
+///
+/// This is attached to the extensions of a [Request] by
+/// [Endpoint::exec_block_with_timeout][crate::endpoint::Endpoint::exec_block_with_timeout]
+/// and applied by the blocking clients, which can't otherwise be interrupted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RequestTimeout(pub Duration);
+
+/// Marks a [Request] whose unsuccessful response is returned as a
+/// [ClientError::ApiError] holding the raw body rather than a
+/// [ClientError::ServerResponseError].
+///
+/// This is attached to the extensions of a [Request] by
+/// [ErrorEndpoint][crate::endpoint::ErrorEndpoint] in order to parse the body
+/// into its error type.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct RawErrorBody;
+
+/// Returns the error for an unsuccessful response with the given status code
+/// and body, keeping the raw body when `raw` is set.
+pub(crate) fn response_error(code: u16, body: Bytes, raw: bool) -> ClientError {
+    match raw {
+        true => ClientError::ApiError { code, body },
+        false => ClientError::ServerResponseError {
+            code,
+            content: String::from_utf8(body.to_vec()).ok(),
+        },
+    }
+}
+
+/// A stream of chunks read from a response body.
+#[cfg(not(feature = "non-send"))]
+pub type ByteStream = Pin<Box<dyn Stream<Item = Result<Bytes, ClientError>> + Send>>;
+
+/// A stream of chunks read from a response body.
+#[cfg(feature = "non-send")]
+pub type ByteStream = Pin<Box<dyn Stream<Item = Result<Bytes, ClientError>>>>;
+
 /// Represents an HTTP client which is capable of executing
 /// [Endpoints][crate::endpoint::Endpoint] by sending the [Request] generated
 /// by the Endpoint and returning a [Response].
-#[async_trait]
-pub trait Client: Sync + Send {
+#[cfg_attr(not(feature = "non-send"), async_trait)]
+#[cfg_attr(feature = "non-send", async_trait(?Send))]
+pub trait Client: MaybeSendSync {
     /// Sends the given [Request] and returns a [Response]. Implementations
     /// should consolidate all errors into the [ClientError] type.
-    async fn send(&self, req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, ClientError>;
+    async fn send(&self, req: Request<Bytes>) -> Result<Response<Bytes>, ClientError>;
+
+    /// Sends the given [Request] and returns a [Response] whose body is
+    /// streamed rather than buffered into memory.
+    ///
+    /// The default implementation buffers the response using [Client::send]
+    /// and yields it as a single chunk. Implementations backed by an HTTP
+    /// client which supports streaming should override this method.
+    async fn send_stream(&self, req: Request<Bytes>) -> Result<Response<ByteStream>, ClientError> {
+        let resp = self.send(req).await?;
+        Ok(resp.map(crate::http::stream_body))
+    }
 
     /// Returns the base URL the client is configured with. This is used for
     /// creating the fully qualified URLs used when executing
     /// [Endpoints][crate::endpoint::Endpoint].
     fn base(&self) -> &str;
 
+    /// Returns the [Drain] used for tracking in-flight executions. Clients
+    /// which return [None] cannot be gracefully shut down.
+    fn drain(&self) -> Option<&Drain> {
+        None
+    }
+
+    /// Returns the [AuthProvider] used for attaching credentials to every
+    /// request sent by this client. Clients which return [None] send requests
+    /// as-is.
+    fn auth(&self) -> Option<&dyn AuthProvider> {
+        None
+    }
+
+    /// Returns the [MetricsObserver] invoked with the metrics of every request
+    /// sent by this client. Clients which return [None] don't record metrics.
+    fn metrics(&self) -> Option<&dyn MetricsObserver> {
+        None
+    }
+
+    /// Gracefully shuts down the client.
+    ///
+    /// New executions are rejected with [ClientError::ShutdownError] while
+    /// in-flight executions are given until the `grace` period expires to
+    /// finish. An execution is in-flight from the moment it starts until it
+    /// completes, including any retries and the time spent waiting between
+    /// them, every page requested by a
+    /// [PaginatedEndpoint][crate::pagination::PaginatedEndpoint] and the
+    /// streamed body of a response. Returns
+    /// [ClientError::ShutdownTimeoutError] if any executions are still running
+    /// once the grace period has elapsed.
+    ///
+    /// Once the in-flight executions have finished, or the grace period has
+    /// elapsed, the built-in clients release their backing HTTP client along
+    /// with its connection pool. Executions which are still running keep
+    /// their connection until they complete.
+    async fn shutdown(&self, grace: Duration) -> Result<(), ClientError> {
+        match self.drain() {
+            Some(d) => d.shutdown(grace).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the [RetryPolicy] used for retrying failed executions. Clients
+    /// which return [None] do not retry executions.
+    fn retry_policy(&self) -> Option<&RetryPolicy> {
+        None
+    }
+
+    /// Returns the [SuccessCodes] used for determining whether a response was
+    /// successful. Clients which return [None] use [HTTP_SUCCESS_CODES].
+    fn success_codes(&self) -> Option<&SuccessCodes> {
+        None
+    }
+
+    /// Returns the headers added to every request sent by this client.
+    /// Headers set by the [Endpoint] take precedence over them. Clients which
+    /// return [None] send requests as-is.
+    fn default_headers(&self) -> Option<&HeaderMap> {
+        None
+    }
+
+    /// Returns the query parameters added to the URL of every request sent by
+    /// this client. Parameters set by the [Endpoint] take precedence over
+    /// them. Clients which return [None] send requests as-is.
+    fn default_query(&self) -> Option<&[(String, String)]> {
+        None
+    }
+
+    /// Returns the [UrlJoin] mode used for joining the path of an [Endpoint]
+    /// onto the base URL. Defaults to [UrlJoin::Lenient].
+    fn url_join(&self) -> UrlJoin {
+        UrlJoin::Lenient
+    }
+
+    /// Modifies a [Request] from an [Endpoint] before it's executed by this
+    /// client. This is used for applying client-level [MiddleWare] and does
+    /// nothing by default.
+    fn mutate_request<E: Endpoint>(
+        &self,
+        _endpoint: &E,
+        _req: &mut Request<Bytes>,
+    ) -> Result<(), ClientError>
+    where
+        Self: Sized,
+    {
+        Ok(())
+    }
+
+    /// Modifies a [Response] from an [Endpoint] after it's executed by this
+    /// client. This is used for applying client-level [MiddleWare] and does
+    /// nothing by default.
+    fn mutate_response<E: Endpoint>(
+        &self,
+        _endpoint: &E,
+        _resp: &mut Response<Bytes>,
+    ) -> Result<(), ClientError>
+    where
+        Self: Sized,
+    {
+        Ok(())
+    }
+
+    /// Returns a new [MutatedClient] which applies the given [MiddleWare] to
+    /// every [Endpoint] executed with it.
+    fn with_middleware<M: MiddleWare>(self, middleware: M) -> MutatedClient<Self, M>
+    where
+        Self: Sized,
+    {
+        MutatedClient::new(self, middleware)
+    }
+
     /// This method provides a common interface to
     /// [Endpoints][crate::endpoint::Endpoint] for execution.
     // TODO: remove the allow when the upstream clippy issue is fixed:
     // <https://github.com/rust-lang/rust-clippy/issues/12281>
     #[allow(clippy::blocks_in_conditions)]
-    #[instrument(skip(self, req), fields(uri=%req.uri(), method=%req.method()), err)]
-    async fn execute(&self, req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, ClientError> {
+    #[cfg_attr(feature = "tracing", instrument(skip(self, req), fields(uri=%crate::http::redacted_uri(&req), method=%req.method(), request_id=tracing::field::Empty), err))]
+    async fn execute(&self, mut req: Request<Bytes>) -> Result<Response<Bytes>, ClientError> {
+        let _guard = match self.drain() {
+            Some(d) => Some(d.acquire()?),
+            None => None,
+        };
+        if let Some(auth) = self.auth() {
+            auth.apply(&mut req).await?;
+        }
+
         debug!(
             name: "sending_request",
+            method=%req.method(),
+            uri=%crate::http::redacted_uri(&req),
             body_len=req.body().len(),
             "Sending Request",
         );
-        let response = self.send(req).await?;
+        let success = SuccessCodes::resolve(&req, self.success_codes());
+        let raw = req.extensions().get::<RawErrorBody>().is_some();
+        record_request_id(req.headers());
+        let (method, path, request_size) = (
+            req.method().clone(),
+            req.uri().path().to_string(),
+            req.body().len(),
+        );
+        let template = req.extensions().get::<PathTemplate>().map(|t| t.0);
+        let start = Instant::now();
+        let response = self.send(req).await;
+        if let Some(m) = self.metrics() {
+            m.observe(&RequestMetrics {
+                method: &method,
+                path: &path,
+                template,
+                status: response.as_ref().ok().map(|r| r.status().as_u16()),
+                duration: start.elapsed(),
+                request_size,
+                response_size: response.as_ref().ok().map(|r| r.body().len()),
+            });
+        }
+        let response = response?;
+        record_request_id(response.headers());
         let status = response.status();
         debug!(
             name: "response_received",
@@ -45,14 +334,646 @@ pub trait Client: Sync + Send {
         );
 
         // Check response
-        if !HTTP_SUCCESS_CODES.contains(&response.status().as_u16()) {
-            return Err(ClientError::ServerResponseError {
-                code: response.status().as_u16(),
-                content: String::from_utf8(response.body().to_vec()).ok(),
-            });
+        if !success.contains(status.as_u16()) {
+            return Err(response_error(status.as_u16(), response.into_body(), raw));
         }
 
         // Parse response content
         Ok(response)
     }
+
+    /// The streaming variant of [Client::execute].
+    ///
+    /// The client is considered busy until the returned body has been
+    /// consumed or dropped.
+    #[allow(clippy::blocks_in_conditions)]
+    #[cfg_attr(feature = "tracing", instrument(skip(self, req), fields(uri=%crate::http::redacted_uri(&req), method=%req.method(), request_id=tracing::field::Empty), err))]
+    async fn execute_stream(
+        &self,
+        mut req: Request<Bytes>,
+    ) -> Result<Response<ByteStream>, ClientError> {
+        let guard = match self.drain() {
+            Some(d) => Some(d.acquire()?),
+            None => None,
+        };
+        if let Some(auth) = self.auth() {
+            auth.apply(&mut req).await?;
+        }
+
+        debug!(
+            name: "sending_request",
+            method=%req.method(),
+            uri=%crate::http::redacted_uri(&req),
+            body_len=req.body().len(),
+            "Sending Request",
+        );
+        let success = SuccessCodes::resolve(&req, self.success_codes());
+        let raw = req.extensions().get::<RawErrorBody>().is_some();
+        record_request_id(req.headers());
+        let (method, path, request_size) = (
+            req.method().clone(),
+            req.uri().path().to_string(),
+            req.body().len(),
+        );
+        let template = req.extensions().get::<PathTemplate>().map(|t| t.0);
+        let start = Instant::now();
+        let response = self.send_stream(req).await;
+        if let Some(m) = self.metrics() {
+            m.observe(&RequestMetrics {
+                method: &method,
+                path: &path,
+                template,
+                status: response.as_ref().ok().map(|r| r.status().as_u16()),
+                duration: start.elapsed(),
+                request_size,
+                response_size: None,
+            });
+        }
+        let response = response?;
+        record_request_id(response.headers());
+        let status = response.status();
+        debug!(
+            name: "response_received",
+            status=status.as_u16(),
+            is_error=status.is_client_error() || status.is_server_error(),
+            "Response Received",
+        );
+
+        // Check response
+        if !success.contains(status.as_u16()) {
+            return Err(
+                match crate::http::collect_body(response.into_body()).await {
+                    Ok(body) => response_error(status.as_u16(), body, raw),
+                    Err(_) => ClientError::ServerResponseError {
+                        code: status.as_u16(),
+                        content: None,
+                    },
+                },
+            );
+        }
+
+        Ok(match guard {
+            Some(g) => response.map(|body| hold(body, g)),
+            None => response,
+        })
+    }
+}
+
+/// Represents a [Client] that has had [MiddleWare] applied to it.
+///
+/// This type wraps [Client] by implementing it. Every [Endpoint] executed
+/// with it will have its request and response passed through the
+/// [MiddleWare]. Client-level [MiddleWare] is applied after any [MiddleWare]
+/// attached to the [Endpoint] for requests and before it for responses.
+/// Multiple [MiddleWare] can be chained by calling `with_middleware` more than
+/// once.
+pub struct MutatedClient<C: Client, M: MiddleWare> {
+    client: C,
+    middleware: M,
+}
+
+impl<C: Client, M: MiddleWare> MutatedClient<C, M> {
+    /// Returns a new [MutatedClient].
+    pub fn new(client: C, middleware: M) -> Self {
+        MutatedClient { client, middleware }
+    }
+
+    /// Returns a reference to the wrapped [Client].
+    pub fn inner(&self) -> &C {
+        &self.client
+    }
+}
+
+#[cfg_attr(not(feature = "non-send"), async_trait)]
+#[cfg_attr(feature = "non-send", async_trait(?Send))]
+impl<C: Client, M: MiddleWare> Client for MutatedClient<C, M> {
+    async fn send(&self, req: Request<Bytes>) -> Result<Response<Bytes>, ClientError> {
+        self.client.send(req).await
+    }
+
+    async fn send_stream(&self, req: Request<Bytes>) -> Result<Response<ByteStream>, ClientError> {
+        self.client.send_stream(req).await
+    }
+
+    fn base(&self) -> &str {
+        self.client.base()
+    }
+
+    fn retry_policy(&self) -> Option<&RetryPolicy> {
+        self.client.retry_policy()
+    }
+
+    fn success_codes(&self) -> Option<&SuccessCodes> {
+        self.client.success_codes()
+    }
+
+    fn default_headers(&self) -> Option<&HeaderMap> {
+        self.client.default_headers()
+    }
+
+    fn default_query(&self) -> Option<&[(String, String)]> {
+        self.client.default_query()
+    }
+
+    fn url_join(&self) -> UrlJoin {
+        self.client.url_join()
+    }
+
+    fn drain(&self) -> Option<&Drain> {
+        self.client.drain()
+    }
+
+    fn auth(&self) -> Option<&dyn AuthProvider> {
+        self.client.auth()
+    }
+
+    fn metrics(&self) -> Option<&dyn MetricsObserver> {
+        self.client.metrics()
+    }
+
+    fn mutate_request<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        req: &mut Request<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.client.mutate_request(endpoint, req)?;
+        self.middleware.request(endpoint, req)
+    }
+
+    fn mutate_response<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        resp: &mut Response<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.middleware.response(endpoint, resp)?;
+        self.client.mutate_response(endpoint, resp)
+    }
+}
+
+/// A [Client] decorator which determines whether responses were successful
+/// using the given [SuccessCodes] instead of [HTTP_SUCCESS_CODES].
+///
+/// This is useful for APIs with unusual status code conventions. Endpoints
+/// which define their own [Endpoint::success_codes] still take precedence.
+///
+/// # Example
+/// ```
+/// use rustify::client::{SuccessClient, SuccessCodes};
+/// use rustify::clients::reqwest::Client;
+///
+/// let client = SuccessClient::new(
+///     Client::default("http://myapi.com"),
+///     SuccessCodes::from_fn(|code| code < 400),
+/// );
+/// ```
+pub struct SuccessClient<C> {
+    client: C,
+    codes: SuccessCodes,
+}
+
+impl<C> SuccessClient<C> {
+    /// Returns a new [SuccessClient] wrapping the given client.
+    pub fn new(client: C, codes: SuccessCodes) -> Self {
+        SuccessClient { client, codes }
+    }
+
+    /// Returns a reference to the wrapped client.
+    pub fn inner(&self) -> &C {
+        &self.client
+    }
+}
+
+#[cfg_attr(not(feature = "non-send"), async_trait)]
+#[cfg_attr(feature = "non-send", async_trait(?Send))]
+impl<C: Client> Client for SuccessClient<C> {
+    async fn send(&self, req: Request<Bytes>) -> Result<Response<Bytes>, ClientError> {
+        self.client.send(req).await
+    }
+
+    async fn send_stream(&self, req: Request<Bytes>) -> Result<Response<ByteStream>, ClientError> {
+        self.client.send_stream(req).await
+    }
+
+    fn base(&self) -> &str {
+        self.client.base()
+    }
+
+    fn drain(&self) -> Option<&Drain> {
+        self.client.drain()
+    }
+
+    fn auth(&self) -> Option<&dyn AuthProvider> {
+        self.client.auth()
+    }
+
+    fn metrics(&self) -> Option<&dyn MetricsObserver> {
+        self.client.metrics()
+    }
+
+    fn retry_policy(&self) -> Option<&RetryPolicy> {
+        self.client.retry_policy()
+    }
+
+    fn success_codes(&self) -> Option<&SuccessCodes> {
+        Some(&self.codes)
+    }
+
+    fn default_headers(&self) -> Option<&HeaderMap> {
+        self.client.default_headers()
+    }
+
+    fn default_query(&self) -> Option<&[(String, String)]> {
+        self.client.default_query()
+    }
+
+    fn url_join(&self) -> UrlJoin {
+        self.client.url_join()
+    }
+
+    fn mutate_request<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        req: &mut Request<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.client.mutate_request(endpoint, req)
+    }
+
+    fn mutate_response<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        resp: &mut Response<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.client.mutate_response(endpoint, resp)
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl<C: BlockingClient> BlockingClient for SuccessClient<C> {
+    fn send(&self, req: Request<Bytes>) -> Result<Response<Bytes>, ClientError> {
+        self.client.send(req)
+    }
+
+    fn base(&self) -> &str {
+        self.client.base()
+    }
+
+    fn drain(&self) -> Option<&Drain> {
+        self.client.drain()
+    }
+
+    fn auth(&self) -> Option<&dyn AuthProvider> {
+        self.client.auth()
+    }
+
+    fn metrics(&self) -> Option<&dyn MetricsObserver> {
+        self.client.metrics()
+    }
+
+    fn retry_policy(&self) -> Option<&RetryPolicy> {
+        self.client.retry_policy()
+    }
+
+    fn success_codes(&self) -> Option<&SuccessCodes> {
+        Some(&self.codes)
+    }
+
+    fn default_headers(&self) -> Option<&HeaderMap> {
+        self.client.default_headers()
+    }
+
+    fn default_query(&self) -> Option<&[(String, String)]> {
+        self.client.default_query()
+    }
+
+    fn url_join(&self) -> UrlJoin {
+        self.client.url_join()
+    }
+
+    fn mutate_request<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        req: &mut Request<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.client.mutate_request(endpoint, req)
+    }
+
+    fn mutate_response<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        resp: &mut Response<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.client.mutate_response(endpoint, resp)
+    }
+}
+
+/// Tracks the in-flight executions of a [Client] in order to support graceful
+/// shutdowns.
+///
+/// Cloning a [Drain] returns a handle to the same underlying state. Each
+/// execution holds a [DrainGuard] for its lifetime which is released when the
+/// guard is dropped.
+#[derive(Clone, Debug, Default)]
+pub struct Drain {
+    inner: Arc<DrainState>,
+}
+
+#[derive(Debug, Default)]
+struct DrainState {
+    closed: AtomicBool,
+    active: AtomicUsize,
+    notify: Notify,
+    lock: Mutex<()>,
+    idle: Condvar,
+    teardown: Teardown,
+}
+
+/// The callbacks run by a [Drain] once it has shut down.
+#[derive(Default)]
+struct Teardown(Mutex<Vec<Box<dyn FnOnce() + Send>>>);
+
+impl std::fmt::Debug for Teardown {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Teardown").finish_non_exhaustive()
+    }
+}
+
+impl Teardown {
+    fn push(&self, f: Box<dyn FnOnce() + Send>) {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).push(f);
+    }
+
+    fn run(&self) {
+        let callbacks = std::mem::take(&mut *self.0.lock().unwrap_or_else(|e| e.into_inner()));
+        callbacks.into_iter().for_each(|f| f());
+    }
+}
+
+impl Drain {
+    /// Returns a new [Drain].
+    pub fn new() -> Self {
+        Drain::default()
+    }
+
+    /// Registers a new in-flight execution, returning a [DrainGuard] which
+    /// must be held until the execution is complete. Fails with
+    /// [ClientError::ShutdownError] if the drain has been closed.
+    pub fn acquire(&self) -> Result<DrainGuard, ClientError> {
+        self.inner.active.fetch_add(1, Ordering::SeqCst);
+        let guard = DrainGuard {
+            inner: self.inner.clone(),
+        };
+        if self.is_closed() {
+            return Err(ClientError::ShutdownError);
+        }
+        Ok(guard)
+    }
+
+    /// Returns whether the drain has been closed to new executions.
+    pub fn is_closed(&self) -> bool {
+        self.inner.closed.load(Ordering::SeqCst)
+    }
+
+    /// Returns the number of executions currently in-flight.
+    pub fn in_flight(&self) -> usize {
+        self.inner.active.load(Ordering::SeqCst)
+    }
+
+    /// Registers a callback which is run once the drain has shut down, such
+    /// as for releasing the connection pool of a client. The callback is run
+    /// after the in-flight executions have completed or the grace period has
+    /// elapsed, whichever comes first.
+    pub fn on_shutdown(&self, f: impl FnOnce() + Send + 'static) {
+        self.inner.teardown.push(Box::new(f));
+    }
+
+    /// Closes the drain to new executions and waits up to `grace` for all
+    /// in-flight executions to complete before running the callbacks
+    /// registered with [on_shutdown][Drain::on_shutdown].
+    #[cfg_attr(feature = "tracing", instrument(skip(self), err))]
+    pub async fn shutdown(&self, grace: Duration) -> Result<(), ClientError> {
+        self.inner.closed.store(true, Ordering::SeqCst);
+        let wait = async {
+            loop {
+                let notified = self.inner.notify.notified();
+                if self.in_flight() == 0 {
+                    break;
+                }
+                notified.await;
+            }
+        };
+
+        let result = tokio::time::timeout(grace, wait).await.map_err(|_| {
+            ClientError::ShutdownTimeoutError {
+                in_flight: self.in_flight(),
+            }
+        });
+        self.inner.teardown.run();
+        result
+    }
+
+    /// The blocking variant of [Drain::shutdown].
+    #[cfg_attr(feature = "tracing", instrument(skip(self), err))]
+    pub fn shutdown_blocking(&self, grace: Duration) -> Result<(), ClientError> {
+        self.inner.closed.store(true, Ordering::SeqCst);
+        let lock = self.inner.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let (lock, result) = self
+            .inner
+            .idle
+            .wait_timeout_while(lock, grace, |_| self.in_flight() > 0)
+            .unwrap_or_else(|e| e.into_inner());
+        drop(lock);
+        let result = match result.timed_out() {
+            true => Err(ClientError::ShutdownTimeoutError {
+                in_flight: self.in_flight(),
+            }),
+            false => Ok(()),
+        };
+        self.inner.teardown.run();
+        result
+    }
+}
+
+/// Holds the backing HTTP client of a built-in [Client], releasing it along
+/// with its connection pool once the [Drain] of the client has shut down.
+#[cfg(any(feature = "hyper", feature = "isahc", feature = "reqwest"))]
+#[derive(Clone)]
+pub(crate) struct Backing<T> {
+    inner: Arc<Mutex<Option<T>>>,
+}
+
+#[cfg(any(feature = "hyper", feature = "isahc", feature = "reqwest"))]
+impl<T: Clone + Send + 'static> Backing<T> {
+    /// Returns a new [Backing] which is released once the given [Drain] has
+    /// shut down.
+    pub(crate) fn new(http: T, drain: &Drain) -> Self {
+        let inner = Arc::new(Mutex::new(Some(http)));
+        let slot = inner.clone();
+        drain.on_shutdown(move || {
+            let http = slot.lock().unwrap_or_else(|e| e.into_inner()).take();
+            drop(http);
+        });
+        Backing { inner }
+    }
+
+    /// Returns the backing HTTP client, if it hasn't been released.
+    pub(crate) fn get(&self) -> Option<T> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Returns the backing HTTP client, failing with
+    /// [ClientError::ShutdownError] if it has been released.
+    pub(crate) fn acquire(&self) -> Result<T, ClientError> {
+        self.get().ok_or(ClientError::ShutdownError)
+    }
+}
+
+/// Represents a single in-flight execution registered with a [Drain].
+#[derive(Debug)]
+pub struct DrainGuard {
+    inner: Arc<DrainState>,
+}
+
+impl Drop for DrainGuard {
+    fn drop(&mut self) {
+        if self.inner.active.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.inner.notify.notify_waiters();
+            let _lock = self.inner.lock.lock();
+            self.inner.idle.notify_all();
+        }
+    }
+}
+
+/// Registers a new in-flight execution with the given [Drain], if any.
+pub(crate) fn register(drain: Option<&Drain>) -> Result<Option<DrainGuard>, ClientError> {
+    drain.map(Drain::acquire).transpose()
+}
+
+/// Returns a [ByteStream] which holds the given guard, such as a
+/// [DrainGuard], until the given stream has been consumed or dropped.
+pub(crate) fn hold<G: Send + 'static>(stream: ByteStream, guard: G) -> ByteStream {
+    Box::pin(stream.map(move |chunk| {
+        let _guard = &guard;
+        chunk
+    }))
+}
+
+/// A [Client] which sends the requests of an execution that's already
+/// registered with the [Drain] of the wrapped client, such as its retries or
+/// following pages, without registering each of them again. This allows an
+/// in-flight execution to finish once the wrapped client is shutting down.
+pub(crate) struct Registered<'a, C>(pub(crate) &'a C);
+
+#[cfg_attr(not(feature = "non-send"), async_trait)]
+#[cfg_attr(feature = "non-send", async_trait(?Send))]
+impl<C: Client> Client for Registered<'_, C> {
+    async fn send(&self, req: Request<Bytes>) -> Result<Response<Bytes>, ClientError> {
+        self.0.send(req).await
+    }
+
+    async fn send_stream(&self, req: Request<Bytes>) -> Result<Response<ByteStream>, ClientError> {
+        self.0.send_stream(req).await
+    }
+
+    fn base(&self) -> &str {
+        self.0.base()
+    }
+
+    fn auth(&self) -> Option<&dyn AuthProvider> {
+        self.0.auth()
+    }
+
+    fn metrics(&self) -> Option<&dyn MetricsObserver> {
+        self.0.metrics()
+    }
+
+    fn retry_policy(&self) -> Option<&RetryPolicy> {
+        self.0.retry_policy()
+    }
+
+    fn success_codes(&self) -> Option<&SuccessCodes> {
+        self.0.success_codes()
+    }
+
+    fn default_headers(&self) -> Option<&HeaderMap> {
+        self.0.default_headers()
+    }
+
+    fn default_query(&self) -> Option<&[(String, String)]> {
+        self.0.default_query()
+    }
+
+    fn url_join(&self) -> UrlJoin {
+        self.0.url_join()
+    }
+
+    fn mutate_request<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        req: &mut Request<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.0.mutate_request(endpoint, req)
+    }
+
+    fn mutate_response<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        resp: &mut Response<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.0.mutate_response(endpoint, resp)
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl<C: BlockingClient> BlockingClient for Registered<'_, C> {
+    fn send(&self, req: Request<Bytes>) -> Result<Response<Bytes>, ClientError> {
+        self.0.send(req)
+    }
+
+    fn base(&self) -> &str {
+        self.0.base()
+    }
+
+    fn auth(&self) -> Option<&dyn AuthProvider> {
+        self.0.auth()
+    }
+
+    fn metrics(&self) -> Option<&dyn MetricsObserver> {
+        self.0.metrics()
+    }
+
+    fn retry_policy(&self) -> Option<&RetryPolicy> {
+        self.0.retry_policy()
+    }
+
+    fn success_codes(&self) -> Option<&SuccessCodes> {
+        self.0.success_codes()
+    }
+
+    fn default_headers(&self) -> Option<&HeaderMap> {
+        self.0.default_headers()
+    }
+
+    fn default_query(&self) -> Option<&[(String, String)]> {
+        self.0.default_query()
+    }
+
+    fn url_join(&self) -> UrlJoin {
+        self.0.url_join()
+    }
+
+    fn mutate_request<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        req: &mut Request<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.0.mutate_request(endpoint, req)
+    }
+
+    fn mutate_response<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        resp: &mut Response<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.0.mutate_response(endpoint, resp)
+    }
 }
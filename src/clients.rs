@@ -1,4 +1,9 @@
 //! Contains implementations of [Client][crate::client::Client] which use
 //! varying HTTP clients.
+#[cfg(feature = "hyper")]
+pub mod hyper;
+#[cfg(feature = "isahc")]
+pub mod isahc;
+pub mod pool;
 #[cfg(feature = "reqwest")]
 pub mod reqwest;
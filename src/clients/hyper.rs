@@ -0,0 +1,387 @@
+//! Contains an implementation of [Client][crate::client::Client] being backed
+//! by the [hyper](https://docs.rs/hyper/) crate.
+
+use crate::{
+    client::{Backing, ByteStream, Client as RustifyClient, Drain},
+    config::{unsupported, ClientConfig, HttpVersion},
+    errors::ClientError,
+    http::UrlJoin,
+    redirect::RedirectPolicy,
+};
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::StreamExt;
+use http::{HeaderMap, Request, Response};
+use http_body_util::{BodyExt, BodyStream, Full};
+use hyper::body::Incoming;
+use hyper_util::{
+    client::legacy::{connect::HttpConnector, Client as HyperClient},
+    rt::TokioExecutor,
+};
+use std::time::Duration;
+
+/// The connector used by the backing [hyper client][1].
+///
+/// Supports HTTPS when the `hyper-tls` feature is enabled.
+///
+/// [1]: https://docs.rs/hyper-util/latest/hyper_util/client/legacy/struct.Client.html
+#[cfg(feature = "hyper-tls")]
+pub type Connector = hyper_tls::HttpsConnector<HttpConnector>;
+
+/// The connector used by the backing [hyper client][1].
+///
+/// Supports HTTPS when the `hyper-tls` feature is enabled.
+///
+/// [1]: https://docs.rs/hyper-util/latest/hyper_util/client/legacy/struct.Client.html
+#[cfg(not(feature = "hyper-tls"))]
+pub type Connector = HttpConnector;
+
+/// A client based on the [hyper][1] crate which can be used for executing
+/// [Endpoints][crate::endpoint::Endpoint]. A backing instance of a
+/// [hyper client][2] is used to pool connections across requests. A base URL
+/// is required and is used to qualify the full path of any
+/// [Endpoints][crate::endpoint::Endpoint] which are executed by this client.
+///
+/// This client requires a [tokio](https://docs.rs/tokio/) runtime. When the
+/// `decompression` feature is enabled, buffered responses are transparently
+/// decompressed using [decompress][crate::http::decompress]. Redirects are
+/// only followed once a [RedirectPolicy] is set with
+/// [set_redirect][Client::set_redirect].
+///
+/// Clients created with [Client::with_config] support every setting of
+/// [ClientConfig] except for proxies and DNS overrides. The timeout of
+/// streamed responses only covers receiving the response headers. TLS settings require
+/// the `hyper-tls` feature, which doesn't support requiring TLS 1.3.
+///
+/// # Example
+/// ```
+/// use rustify::clients::hyper::Client;
+/// use rustify::Endpoint;
+/// use rustify_derive::Endpoint;
+///
+/// #[derive(Debug, Endpoint)]
+/// #[endpoint(path = "my/endpoint")]
+/// struct MyEndpoint {}
+///
+/// # tokio_test::block_on(async {
+/// let client = Client::default("http://myapi.com");
+/// let endpoint = MyEndpoint {};
+/// let result = endpoint.exec(&client).await;
+/// # })
+/// ```
+///
+/// [1]: https://docs.rs/hyper/
+/// [2]: https://docs.rs/hyper-util/latest/hyper_util/client/legacy/struct.Client.html
+#[derive(Clone)]
+pub struct Client {
+    http: Backing<HyperClient<Connector, Full<Bytes>>>,
+    pub base: String,
+    drain: Drain,
+    pub redirect: Option<RedirectPolicy>,
+    pub headers: HeaderMap,
+    pub query: Vec<(String, String)>,
+    pub join: UrlJoin,
+    pub timeout: Option<Duration>,
+}
+
+impl Client {
+    /// Creates a new instance of [Client] using the provided parameters.
+    pub fn new(base: &str, http: HyperClient<Connector, Full<Bytes>>) -> Self {
+        let drain = Drain::new();
+        Client {
+            base: base.to_string(),
+            http: Backing::new(http, &drain),
+            drain,
+            redirect: None,
+            headers: crate::config::default_headers(),
+            query: Vec::new(),
+            join: UrlJoin::default(),
+            timeout: None,
+        }
+    }
+
+    /// Creates a new instance of [Client] using the provided parameters,
+    /// failing if the base URL is invalid.
+    pub fn try_new(
+        base: &str,
+        http: HyperClient<Connector, Full<Bytes>>,
+    ) -> Result<Self, ClientError> {
+        crate::http::validate_base(base)?;
+        Ok(Client::new(base, http))
+    }
+
+    /// Creates a new instance of [Client] with a default instance of the
+    /// [hyper client][1].
+    ///
+    /// [1]: https://docs.rs/hyper-util/latest/hyper_util/client/legacy/struct.Client.html
+    pub fn default(base: &str) -> Self {
+        Client::new(
+            base,
+            HyperClient::builder(TokioExecutor::new()).build(Connector::new()),
+        )
+    }
+
+    /// Creates a new instance of [Client] with a default instance of the
+    /// [hyper client][1], failing if the base URL is invalid.
+    ///
+    /// [1]: https://docs.rs/hyper-util/latest/hyper_util/client/legacy/struct.Client.html
+    pub fn try_default(base: &str) -> Result<Self, ClientError> {
+        crate::http::validate_base(base)?;
+        Ok(Client::default(base))
+    }
+
+    /// Creates a new instance of [Client] with a [hyper client][1]
+    /// configured using the given [ClientConfig] which follows redirects
+    /// according to its [redirect][ClientConfig::redirect] policy. Fails if
+    /// the base URL is invalid.
+    ///
+    /// [1]: https://docs.rs/hyper-util/latest/hyper_util/client/legacy/struct.Client.html
+    pub fn with_config(base: &str, config: &ClientConfig) -> Result<Self, ClientError> {
+        crate::http::validate_base(base)?;
+        if config.proxy.is_some() {
+            return Err(unsupported("hyper", "proxy"));
+        }
+        if !config.resolve.is_empty() {
+            return Err(unsupported("hyper", "resolve"));
+        }
+
+        let mut http = HttpConnector::new();
+        http.set_connect_timeout(config.connect_timeout);
+        http.set_keepalive(config.tcp_keepalive);
+        let connector = connector(http, config)?;
+
+        let mut builder = HyperClient::builder(TokioExecutor::new());
+        if let Some(timeout) = config.pool_idle_timeout {
+            builder.pool_idle_timeout(timeout);
+        }
+        if let Some(max) = config.pool_max_idle_per_host {
+            builder.pool_max_idle_per_host(max);
+        }
+        if config.http_version == HttpVersion::Http3 {
+            return Err(unsupported("hyper", "HTTP/3"));
+        }
+        if config.http_version == HttpVersion::Http2PriorKnowledge {
+            builder.http2_only(true);
+        }
+
+        let mut client = Client::new(base, builder.build(connector));
+        client.redirect = Some(config.redirect);
+        client.headers = config.headers()?;
+        client.query = config.default_query.clone();
+        client.join = config.url_join;
+        client.timeout = config.timeout;
+        Ok(client)
+    }
+
+    /// Returns the backing [hyper client][1], or [None] once the client has been
+    /// shut down and its connection pool released.
+    ///
+    /// [1]: https://docs.rs/hyper-util/latest/hyper_util/client/legacy/struct.Client.html
+    pub fn http(&self) -> Option<HyperClient<Connector, Full<Bytes>>> {
+        self.http.get()
+    }
+
+    /// Returns a new instance of [Client] which sends requests to the given
+    /// base URL, sharing the backing [hyper client][1], its connection pool and the
+    /// settings of this client.
+    ///
+    /// The returned client shares the [Drain] of this client, so shutting
+    /// down either of them shuts down both.
+    ///
+    /// [1]: https://docs.rs/hyper-util/latest/hyper_util/client/legacy/struct.Client.html
+    pub fn with_base(&self, base: &str) -> Self {
+        let mut client = self.clone();
+        client.base = base.to_string();
+        client
+    }
+
+    /// Sets the headers added to every request sent by this client which
+    /// doesn't set them itself, replacing the
+    /// [DEFAULT_USER_AGENT][crate::config::DEFAULT_USER_AGENT] sent by
+    /// default.
+    pub fn set_default_headers(&mut self, headers: HeaderMap) {
+        self.headers = headers;
+    }
+
+    /// Sets the query parameters added to the URL of every request sent by
+    /// this client which doesn't set them itself.
+    pub fn set_default_query(&mut self, query: Vec<(String, String)>) {
+        self.query = query;
+    }
+
+    /// Sets the [UrlJoin] mode used for joining the path of every
+    /// [Endpoint][crate::endpoint::Endpoint] onto the base URL.
+    pub fn set_url_join(&mut self, join: UrlJoin) {
+        self.join = join;
+    }
+
+    /// Sets the [RedirectPolicy] used for following redirects.
+    pub fn set_redirect(&mut self, policy: RedirectPolicy) {
+        self.redirect = Some(policy);
+    }
+}
+
+#[cfg_attr(not(feature = "non-send"), async_trait)]
+#[cfg_attr(feature = "non-send", async_trait(?Send))]
+impl RustifyClient for Client {
+    fn base(&self) -> &str {
+        self.base.as_str()
+    }
+
+    fn drain(&self) -> Option<&Drain> {
+        Some(&self.drain)
+    }
+
+    fn default_headers(&self) -> Option<&HeaderMap> {
+        Some(&self.headers)
+    }
+
+    fn default_query(&self) -> Option<&[(String, String)]> {
+        Some(&self.query)
+    }
+
+    fn url_join(&self) -> UrlJoin {
+        self.join
+    }
+
+    // TODO: remove the allow when the upstream clippy issue is fixed:
+    // <https://github.com/rust-lang/rust-clippy/issues/12281>
+    #[allow(clippy::blocks_in_conditions)]
+    #[cfg_attr(feature = "tracing", instrument(skip(self, req), err))]
+    #[allow(unused_mut)]
+    async fn send(&self, mut req: Request<Bytes>) -> Result<Response<Bytes>, ClientError> {
+        #[cfg(feature = "decompression")]
+        req.headers_mut()
+            .entry(http::header::ACCEPT_ENCODING)
+            .or_insert(http::HeaderValue::from_static(crate::http::ACCEPT_ENCODING));
+
+        let resp = crate::redirect::follow(self.redirect.as_ref(), req, |req| async move {
+            let (parts, body) = self.dispatch(req).await?.into_parts();
+            let body = body
+                .collect()
+                .await
+                .map_err(|e| ClientError::ResponseError { source: e.into() })?
+                .to_bytes();
+            let mut resp = Response::from_parts(parts, body);
+
+            #[cfg(feature = "decompression")]
+            crate::http::decompress(&mut resp)?;
+            Ok(resp)
+        });
+        self.timed(resp).await
+    }
+
+    #[allow(clippy::blocks_in_conditions)]
+    #[cfg_attr(feature = "tracing", instrument(skip(self, req), err))]
+    async fn send_stream(&self, req: Request<Bytes>) -> Result<Response<ByteStream>, ClientError> {
+        let resp = crate::redirect::follow(self.redirect.as_ref(), req, |req| async move {
+            let response = self.dispatch(req).await?;
+            Ok(response.map(|body| {
+                let stream = BodyStream::new(body).filter_map(|frame| async move {
+                    match frame {
+                        Ok(f) => f.into_data().ok().map(Ok),
+                        Err(e) => Some(Err(ClientError::ResponseError { source: e.into() })),
+                    }
+                });
+                Box::pin(stream) as ByteStream
+            }))
+        });
+        self.timed(resp).await
+    }
+}
+
+impl Client {
+    /// Converts the given [Request] and sends it using the backing
+    /// [hyper client][1].
+    ///
+    /// [1]: https://docs.rs/hyper-util/latest/hyper_util/client/legacy/struct.Client.html
+    async fn dispatch(&self, req: Request<Bytes>) -> Result<Response<Incoming>, ClientError> {
+        let (parts, body) = req.into_parts();
+        let request = Request::from_parts(parts, Full::new(body));
+
+        let url_err = request.uri().to_string();
+        let method_err = request.method().to_string();
+        self.http
+            .acquire()?
+            .request(request)
+            .await
+            .map_err(|e| ClientError::RequestError {
+                source: e.into(),
+                url: url_err,
+                method: method_err,
+            })
+    }
+
+    /// Awaits the given future, failing with [ClientError::Timeout] once the
+    /// timeout of this client has elapsed.
+    async fn timed<T>(
+        &self,
+        fut: impl std::future::Future<Output = Result<T, ClientError>>,
+    ) -> Result<T, ClientError> {
+        match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, fut)
+                .await
+                .map_err(|_| ClientError::Timeout { timeout })?,
+            None => fut.await,
+        }
+    }
+}
+
+/// Returns a [Connector] wrapping the given [HttpConnector] which applies the
+/// TLS settings of the given [ClientConfig].
+#[cfg(feature = "hyper-tls")]
+fn connector(mut http: HttpConnector, config: &ClientConfig) -> Result<Connector, ClientError> {
+    use crate::config::{Certificate, Identity, TlsVersion};
+
+    let mut tls = native_tls::TlsConnector::builder();
+    for cert in config.root_certificates.iter() {
+        let cert = match cert {
+            Certificate::Pem(pem) => native_tls::Certificate::from_pem(pem),
+            Certificate::Der(der) => native_tls::Certificate::from_der(der),
+        }
+        .map_err(|e| ClientError::CertificateParseError {
+            source: e.into(),
+            name: "root certificate".to_string(),
+        })?;
+        tls.add_root_certificate(cert);
+    }
+    if let Some(identity) = &config.identity {
+        let identity = match identity {
+            Identity::Pem { cert, key } => native_tls::Identity::from_pkcs8(cert, key),
+            Identity::Pkcs12 { der, password } => native_tls::Identity::from_pkcs12(der, password),
+        }
+        .map_err(|e| ClientError::CertificateParseError {
+            source: e.into(),
+            name: "client identity".to_string(),
+        })?;
+        tls.identity(identity);
+    }
+    match config.min_tls_version {
+        Some(TlsVersion::Tls1_3) => return Err(unsupported("hyper", "TLS 1.3 minimum version")),
+        Some(TlsVersion::Tls1_2) => {
+            tls.min_protocol_version(Some(native_tls::Protocol::Tlsv12));
+        }
+        None => {}
+    }
+    let tls = tls
+        .build()
+        .map_err(|e| ClientError::ClientBuildError { source: e.into() })?;
+
+    http.enforce_http(false);
+    Ok(Connector::from((http, tls.into())))
+}
+
+/// Returns the given [HttpConnector], failing if the given [ClientConfig]
+/// contains TLS settings.
+#[cfg(not(feature = "hyper-tls"))]
+fn connector(http: HttpConnector, config: &ClientConfig) -> Result<Connector, ClientError> {
+    if !config.root_certificates.is_empty()
+        || config.identity.is_some()
+        || config.min_tls_version.is_some()
+    {
+        return Err(ClientError::ClientBuildError {
+            source: anyhow::anyhow!("TLS settings require the hyper-tls feature"),
+        });
+    }
+    Ok(http)
+}
@@ -0,0 +1,314 @@
+//! Contains an implementation of [Client][crate::client::Client] being backed
+//! by the [isahc](https://docs.rs/isahc/) crate.
+
+use crate::{
+    client::{Backing, Client as RustifyClient, Drain},
+    config::{unsupported, ClientConfig, HttpVersion, Identity},
+    errors::ClientError,
+    http::UrlJoin,
+    redirect::RedirectPolicy,
+};
+use async_trait::async_trait;
+use bytes::Bytes;
+use http::{HeaderMap, Request, Response};
+use isahc::{
+    config::{ClientCertificate, Configurable, PrivateKey, VersionNegotiation},
+    AsyncReadResponseExt,
+};
+
+/// A client based on the [isahc::HttpClient][1] which can be used for
+/// executing [Endpoints][crate::endpoint::Endpoint]. The backing
+/// [isahc::HttpClient][1] uses libcurl which makes features like NTLM
+/// authentication, exotic proxies and fine-grained timeouts available through
+/// its configuration. A base URL is required and is used to qualify the full
+/// path of any [Endpoints][crate::endpoint::Endpoint] which are executed by
+/// this client.
+///
+/// # Example
+/// ```
+/// use rustify::clients::isahc::Client;
+/// use rustify::Endpoint;
+/// use rustify_derive::Endpoint;
+///
+/// #[derive(Debug, Endpoint)]
+/// #[endpoint(path = "my/endpoint")]
+/// struct MyEndpoint {}
+///
+/// # tokio_test::block_on(async {
+/// let client = Client::default("http://myapi.com");
+/// let endpoint = MyEndpoint {};
+/// let result = endpoint.exec(&client).await;
+/// # })
+/// ```
+///
+/// Redirects are only followed once a [RedirectPolicy] is set with
+/// [set_redirect][Client::set_redirect].
+///
+/// Clients created with [Client::with_config] support every setting of
+/// [ClientConfig] except for root certificates, the minimum TLS version, DNS
+/// overrides and the maximum number of idle connections per host, which
+/// libcurl doesn't expose in a compatible form.
+///
+/// [1]: https://docs.rs/isahc/latest/isahc/struct.HttpClient.html
+#[derive(Clone)]
+pub struct Client {
+    http: Backing<isahc::HttpClient>,
+    pub base: String,
+    drain: Drain,
+    pub redirect: Option<RedirectPolicy>,
+    pub headers: HeaderMap,
+    pub query: Vec<(String, String)>,
+    pub join: UrlJoin,
+}
+
+impl Client {
+    /// Creates a new instance of [Client] using the provided parameters.
+    pub fn new(base: &str, http: isahc::HttpClient) -> Self {
+        let drain = Drain::new();
+        Client {
+            base: base.to_string(),
+            http: Backing::new(http, &drain),
+            drain,
+            redirect: None,
+            headers: crate::config::default_headers(),
+            query: Vec::new(),
+            join: UrlJoin::default(),
+        }
+    }
+
+    /// Creates a new instance of [Client] with a default instance of
+    /// [isahc::HttpClient][1].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the backing libcurl client fails to initialize.
+    ///
+    /// [1]: https://docs.rs/isahc/latest/isahc/struct.HttpClient.html
+    pub fn default(base: &str) -> Self {
+        Client::new(
+            base,
+            isahc::HttpClient::new().expect("failed to initialize isahc client"),
+        )
+    }
+
+    /// Creates a new instance of [Client] using the provided parameters,
+    /// failing if the base URL is invalid.
+    pub fn try_new(base: &str, http: isahc::HttpClient) -> Result<Self, ClientError> {
+        crate::http::validate_base(base)?;
+        Ok(Client::new(base, http))
+    }
+
+    /// Creates a new instance of [Client] with a default instance of
+    /// [isahc::HttpClient][1], failing if the base URL is invalid or the
+    /// backing libcurl client fails to initialize.
+    ///
+    /// [1]: https://docs.rs/isahc/latest/isahc/struct.HttpClient.html
+    pub fn try_default(base: &str) -> Result<Self, ClientError> {
+        crate::http::validate_base(base)?;
+        let http = isahc::HttpClient::new()
+            .map_err(|e| ClientError::ClientBuildError { source: e.into() })?;
+        Ok(Client::new(base, http))
+    }
+
+    /// Creates a new instance of [Client] with an [isahc::HttpClient][1]
+    /// configured using the given [ClientConfig] which follows redirects
+    /// according to its [redirect][ClientConfig::redirect] policy. Fails if
+    /// the base URL is invalid.
+    ///
+    /// [1]: https://docs.rs/isahc/latest/isahc/struct.HttpClient.html
+    pub fn with_config(base: &str, config: &ClientConfig) -> Result<Self, ClientError> {
+        crate::http::validate_base(base)?;
+        if !config.root_certificates.is_empty() {
+            return Err(unsupported("isahc", "root_certificates"));
+        }
+        if config.min_tls_version.is_some() {
+            return Err(unsupported("isahc", "min_tls_version"));
+        }
+        if !config.resolve.is_empty() {
+            return Err(unsupported("isahc", "resolve"));
+        }
+        if config.pool_max_idle_per_host.is_some() {
+            return Err(unsupported("isahc", "pool_max_idle_per_host"));
+        }
+
+        let mut builder = isahc::HttpClient::builder();
+        if let Some(timeout) = config.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(timeout) = config.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(url) = &config.proxy {
+            let uri = url
+                .parse::<http02::Uri>()
+                .map_err(|e| ClientError::ClientBuildError { source: e.into() })?;
+            builder = builder.proxy(uri);
+        }
+        if let Some(identity) = &config.identity {
+            builder = builder.ssl_client_certificate(match identity {
+                Identity::Pem { cert, key } => ClientCertificate::pem(
+                    cert.clone(),
+                    PrivateKey::pem(key.clone(), None::<String>),
+                ),
+                Identity::Pkcs12 { der, password } => {
+                    ClientCertificate::pkcs12(der.clone(), password.clone())
+                }
+            });
+        }
+        if let Some(ttl) = config.pool_idle_timeout {
+            builder = builder.connection_cache_ttl(ttl);
+        }
+        if let Some(interval) = config.tcp_keepalive {
+            builder = builder.tcp_keepalive(interval);
+        }
+        builder = match config.http_version {
+            HttpVersion::Auto => builder,
+            HttpVersion::Http1Only => builder.version_negotiation(VersionNegotiation::http11()),
+            HttpVersion::Http2PriorKnowledge => {
+                builder.version_negotiation(VersionNegotiation::http2())
+            }
+            HttpVersion::Http3 => return Err(unsupported("isahc", "HTTP/3")),
+        };
+
+        let http = builder
+            .build()
+            .map_err(|e| ClientError::ClientBuildError { source: e.into() })?;
+        let mut client = Client::new(base, http);
+        client.redirect = Some(config.redirect);
+        client.headers = config.headers()?;
+        client.query = config.default_query.clone();
+        client.join = config.url_join;
+        Ok(client)
+    }
+
+    /// Returns the backing [isahc::HttpClient][1], or [None] once the client has been
+    /// shut down and its connection pool released.
+    ///
+    /// [1]: https://docs.rs/isahc/latest/isahc/struct.HttpClient.html
+    pub fn http(&self) -> Option<isahc::HttpClient> {
+        self.http.get()
+    }
+
+    /// Returns a new instance of [Client] which sends requests to the given
+    /// base URL, sharing the backing [isahc::HttpClient][1], its connection pool and the
+    /// settings of this client.
+    ///
+    /// The returned client shares the [Drain] of this client, so shutting
+    /// down either of them shuts down both.
+    ///
+    /// [1]: https://docs.rs/isahc/latest/isahc/struct.HttpClient.html
+    pub fn with_base(&self, base: &str) -> Self {
+        let mut client = self.clone();
+        client.base = base.to_string();
+        client
+    }
+
+    /// Sets the headers added to every request sent by this client which
+    /// doesn't set them itself, replacing the
+    /// [DEFAULT_USER_AGENT][crate::config::DEFAULT_USER_AGENT] sent by
+    /// default.
+    pub fn set_default_headers(&mut self, headers: HeaderMap) {
+        self.headers = headers;
+    }
+
+    /// Sets the query parameters added to the URL of every request sent by
+    /// this client which doesn't set them itself.
+    pub fn set_default_query(&mut self, query: Vec<(String, String)>) {
+        self.query = query;
+    }
+
+    /// Sets the [UrlJoin] mode used for joining the path of every
+    /// [Endpoint][crate::endpoint::Endpoint] onto the base URL.
+    pub fn set_url_join(&mut self, join: UrlJoin) {
+        self.join = join;
+    }
+
+    /// Sets the [RedirectPolicy] used for following redirects.
+    pub fn set_redirect(&mut self, policy: RedirectPolicy) {
+        self.redirect = Some(policy);
+    }
+}
+
+#[cfg_attr(not(feature = "non-send"), async_trait)]
+#[cfg_attr(feature = "non-send", async_trait(?Send))]
+impl RustifyClient for Client {
+    fn base(&self) -> &str {
+        self.base.as_str()
+    }
+
+    fn drain(&self) -> Option<&Drain> {
+        Some(&self.drain)
+    }
+
+    fn default_headers(&self) -> Option<&HeaderMap> {
+        Some(&self.headers)
+    }
+
+    fn default_query(&self) -> Option<&[(String, String)]> {
+        Some(&self.query)
+    }
+
+    fn url_join(&self) -> UrlJoin {
+        self.join
+    }
+
+    // TODO: remove the allow when the upstream clippy issue is fixed:
+    // <https://github.com/rust-lang/rust-clippy/issues/12281>
+    #[allow(clippy::blocks_in_conditions)]
+    #[cfg_attr(feature = "tracing", instrument(skip(self, req), err))]
+    async fn send(&self, req: Request<Bytes>) -> Result<Response<Bytes>, ClientError> {
+        crate::redirect::follow(self.redirect.as_ref(), req, |req| self.dispatch(req)).await
+    }
+}
+
+impl Client {
+    /// Converts the given [Request] and sends it using the backing
+    /// [isahc::HttpClient][1].
+    ///
+    /// [1]: https://docs.rs/isahc/latest/isahc/struct.HttpClient.html
+    async fn dispatch(&self, req: Request<Bytes>) -> Result<Response<Bytes>, ClientError> {
+        let url_err = req.uri().to_string();
+        let method_err = req.method().to_string();
+
+        // isahc uses an older version of the http crate
+        let (parts, body) = req.into_parts();
+        let mut request = http02::Request::builder()
+            .method(parts.method.as_str())
+            .uri(url_err.as_str());
+        for (name, value) in parts.headers.iter() {
+            request = request.header(name.as_str(), value.as_bytes());
+        }
+        let request = request
+            .body(Vec::from(body))
+            .map_err(|e| ClientError::RequestError {
+                source: e.into(),
+                url: url_err.clone(),
+                method: method_err.clone(),
+            })?;
+
+        let mut response = self
+            .http
+            .acquire()?
+            .send_async(request)
+            .await
+            .map_err(|e| ClientError::RequestError {
+                source: e.into(),
+                url: url_err,
+                method: method_err,
+            })?;
+
+        let mut http_resp = http::Response::builder().status(response.status().as_u16());
+        for (name, value) in response.headers().iter() {
+            http_resp = http_resp.header(name.as_str(), value.as_bytes());
+        }
+        http_resp
+            .body(
+                response
+                    .bytes()
+                    .await
+                    .map_err(|e| ClientError::ResponseError { source: e.into() })?
+                    .into(),
+            )
+            .map_err(|e| ClientError::ResponseError { source: e.into() })
+    }
+}
@@ -0,0 +1,474 @@
+//! Contains an implementation of [Client][crate::client::Client] which
+//! balances executions across a pool of base URLs.
+#[cfg(feature = "blocking")]
+use crate::blocking::client::Client as BlockingClient;
+use crate::{
+    auth::AuthProvider,
+    client::{ByteStream, Client, Drain, SuccessCodes},
+    endpoint::{client_defaults, Endpoint},
+    errors::ClientError,
+    http::UrlJoin,
+    metrics::MetricsObserver,
+    retry::RetryPolicy,
+};
+use async_trait::async_trait;
+use bytes::Bytes;
+use http::{HeaderMap, Request, Response, Uri};
+use std::{
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    time::Duration,
+};
+
+/// The strategy used by a [BalancedClient] for selecting a base URL.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Balance {
+    /// Cycles through each base URL in order.
+    RoundRobin,
+    /// Selects the base URL with the fewest requests in-flight.
+    LeastOutstanding,
+}
+
+/// Configures the health checks performed by a [BalancedClient].
+#[derive(Clone, Debug)]
+pub struct HealthCheck {
+    /// The relative path requested on each base URL.
+    pub path: String,
+    /// The interval between each round of health checks.
+    pub interval: Duration,
+    /// How long to wait for a health check response before marking the base
+    /// URL as unhealthy.
+    pub timeout: Duration,
+    /// The response codes which mark the base URL as healthy.
+    pub expected: SuccessCodes,
+}
+
+impl HealthCheck {
+    /// Returns a new [HealthCheck] which requests the given path at the given
+    /// interval with a timeout of five seconds, expecting any `2xx` response.
+    pub fn new(path: &str, interval: Duration) -> Self {
+        HealthCheck {
+            path: path.to_string(),
+            interval,
+            timeout: Duration::from_secs(5),
+            expected: SuccessCodes::new(vec![200..=299]),
+        }
+    }
+
+    /// Sets the response codes which mark the base URL as healthy, i.e.
+    /// `SuccessCodes::from_fn(|code| code < 500)`.
+    pub fn expect(mut self, codes: SuccessCodes) -> Self {
+        self.expected = codes;
+        self
+    }
+}
+
+/// A single base URL in the pool of a [BalancedClient].
+#[derive(Debug)]
+pub struct Backend {
+    base: String,
+    uri: Option<Uri>,
+    outstanding: AtomicUsize,
+    healthy: AtomicBool,
+}
+
+impl Backend {
+    fn new(base: &str) -> Self {
+        Backend {
+            base: base.to_string(),
+            uri: base.parse().ok(),
+            outstanding: AtomicUsize::new(0),
+            healthy: AtomicBool::new(true),
+        }
+    }
+
+    /// Returns the path and query of the given URI relative to this backend
+    /// if it's addressed to it. The scheme, host and port must match and the
+    /// path must start with the path of the base URL on a segment boundary.
+    fn relative<'a>(&self, uri: &'a Uri) -> Option<&'a str> {
+        let base = self.uri.as_ref()?;
+        let same_host = match (uri.host(), base.host()) {
+            (Some(a), Some(b)) => a.eq_ignore_ascii_case(b),
+            _ => false,
+        };
+        if uri.scheme() != base.scheme() || !same_host || port(uri) != port(base) {
+            return None;
+        }
+
+        let prefix = base.path().trim_end_matches('/');
+        let rest = uri.path_and_query()?.as_str().strip_prefix(prefix)?;
+        match rest.chars().next() {
+            None | Some('/') | Some('?') => Some(rest),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the last health check of this backend succeeded.
+    /// Backends are considered healthy until a health check fails.
+    pub fn is_healthy(&self) -> bool {
+        self.healthy.load(Ordering::SeqCst)
+    }
+
+    /// Marks this backend as healthy or unhealthy.
+    pub fn set_healthy(&self, healthy: bool) {
+        self.healthy.store(healthy, Ordering::SeqCst);
+    }
+
+    /// Returns the base URL of this backend.
+    pub fn base(&self) -> &str {
+        self.base.as_str()
+    }
+
+    /// Returns the number of requests currently in-flight to this backend.
+    pub fn outstanding(&self) -> usize {
+        self.outstanding.load(Ordering::SeqCst)
+    }
+}
+
+/// A client which distributes [Endpoint] executions across a pool of base
+/// URLs.
+///
+/// Endpoints are built against the first base URL, which is returned by
+/// [Client::base]. Each request addressed to it is redirected to a base URL
+/// selected using the configured [Balance] strategy when it's sent and is then
+/// sent using the wrapped client. The base URL of the wrapped client is
+/// ignored.
+///
+/// Base URLs which fail a health check are skipped until they pass again. If
+/// every base URL is unhealthy then all of them are used. Health checks can be
+/// run once with [BalancedClient::check_health] or periodically with
+/// [BalancedClient::monitor].
+///
+/// # Example
+/// ```
+/// use rustify::clients::{
+///     pool::{Balance, BalancedClient},
+///     reqwest::Client,
+/// };
+///
+/// let client = BalancedClient::new(
+///     Client::default("http://myapi.com"),
+///     &["http://node1.myapi.com", "http://node2.myapi.com"],
+///     Balance::RoundRobin,
+/// );
+/// ```
+pub struct BalancedClient<C> {
+    client: C,
+    backends: Vec<Backend>,
+    strategy: Balance,
+    next: AtomicUsize,
+}
+
+impl<C> BalancedClient<C> {
+    /// Returns a new [BalancedClient] which balances executions across the
+    /// given base URLs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no base URLs are given.
+    pub fn new(client: C, bases: &[&str], strategy: Balance) -> Self {
+        assert!(!bases.is_empty(), "at least one base URL is required");
+        BalancedClient {
+            client,
+            backends: bases.iter().map(|b| Backend::new(b)).collect(),
+            strategy,
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns a reference to the wrapped client.
+    pub fn inner(&self) -> &C {
+        &self.client
+    }
+
+    /// Returns the backends in the pool.
+    pub fn backends(&self) -> &[Backend] {
+        &self.backends
+    }
+
+    /// Selects the next backend according to the [Balance] strategy, skipping
+    /// any unhealthy backends.
+    fn select(&self) -> &Backend {
+        let mut candidates = self
+            .backends
+            .iter()
+            .filter(|b| b.is_healthy())
+            .collect::<Vec<&Backend>>();
+        if candidates.is_empty() {
+            candidates = self.backends.iter().collect();
+        }
+
+        let i = self.next.fetch_add(1, Ordering::SeqCst);
+        match self.strategy {
+            Balance::RoundRobin => candidates[i % candidates.len()],
+            Balance::LeastOutstanding => {
+                // Start from a rotating offset so ties are spread evenly
+                (0..candidates.len())
+                    .map(|j| candidates[(i + j) % candidates.len()])
+                    .min_by_key(|b| b.outstanding())
+                    .unwrap()
+            }
+        }
+    }
+
+    /// Returns the backend the given request is addressed to, if any.
+    fn backend_for(&self, req: &Request<Bytes>) -> Option<&Backend> {
+        self.backends
+            .iter()
+            .filter(|b| b.relative(req.uri()).is_some())
+            .max_by_key(|b| b.base.len())
+    }
+
+    /// Returns the backend the given request is sent to.
+    ///
+    /// Requests addressed to the base URL returned by [Client::base] are
+    /// redirected to a backend selected using the [Balance] strategy while any
+    /// other request is sent unchanged.
+    fn route(&self, req: &mut Request<Bytes>) -> Result<Option<&Backend>, ClientError> {
+        let rest = match self.backends[0].relative(req.uri()) {
+            Some(rest) => rest.to_string(),
+            None => return Ok(self.backend_for(req)),
+        };
+
+        let backend = self.select();
+        let uri = format!("{}{}", backend.base.trim_end_matches('/'), rest);
+        *req.uri_mut() = uri
+            .parse()
+            .map_err(|e| ClientError::UrlBuildError { source: e })?;
+        Ok(Some(backend))
+    }
+}
+
+/// Returns the port of the given URI, falling back to the default port of its
+/// scheme.
+fn port(uri: &Uri) -> Option<u16> {
+    uri.port_u16().or_else(|| match uri.scheme_str() {
+        Some("http") => Some(80),
+        Some("https") => Some(443),
+        _ => None,
+    })
+}
+
+impl<C: Client> BalancedClient<C> {
+    /// Performs a single round of health checks, marking each base URL as
+    /// healthy if it responds to the health check with one of the expected
+    /// response codes.
+    ///
+    /// Health checks are executed by the wrapped client, so they carry its
+    /// default headers, query parameters and credentials.
+    #[cfg_attr(feature = "tracing", instrument(skip(self)))]
+    pub async fn check_health(&self, check: &HealthCheck) {
+        for backend in self.backends.iter() {
+            let healthy = match self.health_request(backend.base(), check) {
+                Ok(req) => matches!(
+                    tokio::time::timeout(check.timeout, self.client.execute(req)).await,
+                    Ok(Ok(_))
+                ),
+                Err(_) => false,
+            };
+
+            if healthy != backend.is_healthy() {
+                info!(base = backend.base(), healthy, "Backend health changed");
+            }
+            backend.set_healthy(healthy);
+        }
+    }
+
+    /// Builds the request for the given [HealthCheck] of a base URL using the
+    /// defaults of the wrapped client.
+    fn health_request(
+        &self,
+        base: &str,
+        check: &HealthCheck,
+    ) -> Result<Request<Bytes>, ClientError> {
+        let defaults = client_defaults(&self.client);
+        let mut req = Request::new(Bytes::new());
+        *req.uri_mut() = crate::http::join_url(base, check.path.as_str(), None, defaults.join)?;
+        defaults.apply(base, &mut req)?;
+        req.extensions_mut().insert(check.expected.clone());
+        Ok(req)
+    }
+
+    /// Continuously performs health checks at the configured interval.
+    ///
+    /// The returned future never completes and is expected to be spawned onto
+    /// a runtime alongside a shared reference to the client:
+    ///
+    /// ```no_run
+    /// use std::{sync::Arc, time::Duration};
+    /// use rustify::clients::{
+    ///     pool::{Balance, BalancedClient, HealthCheck},
+    ///     reqwest::Client,
+    /// };
+    ///
+    /// # tokio_test::block_on(async {
+    /// let client = Arc::new(BalancedClient::new(
+    ///     Client::default("http://myapi.com"),
+    ///     &["http://node1.myapi.com", "http://node2.myapi.com"],
+    ///     Balance::RoundRobin,
+    /// ));
+    /// let monitored = client.clone();
+    /// # #[cfg(not(feature = "non-send"))]
+    /// tokio::spawn(async move {
+    ///     monitored
+    ///         .monitor(HealthCheck::new("health", Duration::from_secs(10)))
+    ///         .await
+    /// });
+    /// # })
+    /// ```
+    pub async fn monitor(&self, check: HealthCheck) {
+        let mut interval = tokio::time::interval(check.interval);
+        loop {
+            interval.tick().await;
+            self.check_health(&check).await;
+        }
+    }
+}
+
+/// Tracks a request as outstanding for the lifetime of the guard.
+struct Outstanding<'a>(Option<&'a Backend>);
+
+impl<'a> Outstanding<'a> {
+    fn new(backend: Option<&'a Backend>) -> Self {
+        if let Some(b) = backend {
+            b.outstanding.fetch_add(1, Ordering::SeqCst);
+        }
+        Outstanding(backend)
+    }
+}
+
+impl Drop for Outstanding<'_> {
+    fn drop(&mut self) {
+        if let Some(b) = self.0 {
+            b.outstanding.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "non-send"), async_trait)]
+#[cfg_attr(feature = "non-send", async_trait(?Send))]
+impl<C: Client> Client for BalancedClient<C> {
+    async fn send(&self, mut req: Request<Bytes>) -> Result<Response<Bytes>, ClientError> {
+        let _outstanding = Outstanding::new(self.route(&mut req)?);
+        self.client.send(req).await
+    }
+
+    async fn send_stream(
+        &self,
+        mut req: Request<Bytes>,
+    ) -> Result<Response<ByteStream>, ClientError> {
+        let _outstanding = Outstanding::new(self.route(&mut req)?);
+        self.client.send_stream(req).await
+    }
+
+    fn base(&self) -> &str {
+        self.backends[0].base()
+    }
+
+    fn drain(&self) -> Option<&Drain> {
+        self.client.drain()
+    }
+
+    fn auth(&self) -> Option<&dyn AuthProvider> {
+        self.client.auth()
+    }
+
+    fn metrics(&self) -> Option<&dyn MetricsObserver> {
+        self.client.metrics()
+    }
+
+    fn retry_policy(&self) -> Option<&RetryPolicy> {
+        self.client.retry_policy()
+    }
+
+    fn success_codes(&self) -> Option<&SuccessCodes> {
+        self.client.success_codes()
+    }
+
+    fn default_headers(&self) -> Option<&HeaderMap> {
+        self.client.default_headers()
+    }
+
+    fn default_query(&self) -> Option<&[(String, String)]> {
+        self.client.default_query()
+    }
+
+    fn url_join(&self) -> UrlJoin {
+        self.client.url_join()
+    }
+
+    fn mutate_request<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        req: &mut Request<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.client.mutate_request(endpoint, req)
+    }
+
+    fn mutate_response<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        resp: &mut Response<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.client.mutate_response(endpoint, resp)
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl<C: BlockingClient> BlockingClient for BalancedClient<C> {
+    fn send(&self, mut req: Request<Bytes>) -> Result<Response<Bytes>, ClientError> {
+        let _outstanding = Outstanding::new(self.route(&mut req)?);
+        self.client.send(req)
+    }
+
+    fn base(&self) -> &str {
+        self.backends[0].base()
+    }
+
+    fn drain(&self) -> Option<&Drain> {
+        self.client.drain()
+    }
+
+    fn auth(&self) -> Option<&dyn AuthProvider> {
+        self.client.auth()
+    }
+
+    fn metrics(&self) -> Option<&dyn MetricsObserver> {
+        self.client.metrics()
+    }
+
+    fn retry_policy(&self) -> Option<&RetryPolicy> {
+        self.client.retry_policy()
+    }
+
+    fn success_codes(&self) -> Option<&SuccessCodes> {
+        self.client.success_codes()
+    }
+
+    fn default_headers(&self) -> Option<&HeaderMap> {
+        self.client.default_headers()
+    }
+
+    fn default_query(&self) -> Option<&[(String, String)]> {
+        self.client.default_query()
+    }
+
+    fn url_join(&self) -> UrlJoin {
+        self.client.url_join()
+    }
+
+    fn mutate_request<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        req: &mut Request<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.client.mutate_request(endpoint, req)
+    }
+
+    fn mutate_response<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        resp: &mut Response<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.client.mutate_response(endpoint, resp)
+    }
+}
@@ -1,10 +1,20 @@
 //! Contains an implementation of [Client][crate::client::Client] being backed
 //! by the [reqwest](https://docs.rs/reqwest/) crate.
 
-use crate::{client::Client as RustifyClient, errors::ClientError};
+use crate::{
+    auth::AuthProvider,
+    client::{Backing, ByteStream, Client as RustifyClient, Drain},
+    config::{unsupported, Certificate, ClientConfig, HttpVersion, Identity},
+    errors::ClientError,
+    http::UrlJoin,
+    metrics::MetricsObserver,
+    redirect::{RedirectPolicy, Redirects},
+};
 use async_trait::async_trait;
-use http::{Request, Response};
-use std::convert::TryFrom;
+use bytes::Bytes;
+use futures_util::StreamExt;
+use http::{HeaderMap, Request, Response};
+use std::{convert::TryFrom, net::SocketAddr, path::Path, sync::Arc, time::Duration};
 
 /// A client based on the
 /// [reqwest::Client][1] which can be used for executing
@@ -32,73 +42,518 @@ use std::convert::TryFrom;
 /// # })
 /// ```
 ///
+/// The client tracks its in-flight executions and can be gracefully shut down
+/// with [shutdown][RustifyClient::shutdown], which releases the backing
+/// [reqwest::Client][1] and its connection pool once the client is drained.
+///
+/// Credentials can be attached to every request by setting an
+/// [AuthProvider] with [set_auth][Client::set_auth].
+///
+/// Redirects are followed by the backing [reqwest::Client][1], which only
+/// reports the final URL of a response. Clients created with
+/// [Client::with_redirect] or [Client::with_config] follow them according to
+/// their [RedirectPolicy] instead, which records the followed redirects on
+/// every response.
+///
 /// [1]: https://docs.rs/reqwest/latest/reqwest/struct.Client.html
+#[derive(Clone)]
 pub struct Client {
-    pub http: reqwest::Client,
+    http: Backing<reqwest::Client>,
     pub base: String,
+    drain: Drain,
+    pub auth: Option<Arc<dyn AuthProvider>>,
+    pub metrics: Option<Arc<dyn MetricsObserver>>,
+    pub redirect: Option<RedirectPolicy>,
+    pub headers: HeaderMap,
+    pub query: Vec<(String, String)>,
+    pub join: UrlJoin,
 }
 
 impl Client {
     /// Creates a new instance of [Client] using the provided parameters.
     pub fn new(base: &str, http: reqwest::Client) -> Self {
+        let drain = Drain::new();
         Client {
             base: base.to_string(),
-            http,
+            http: Backing::new(http, &drain),
+            drain,
+            auth: None,
+            metrics: None,
+            redirect: None,
+            headers: crate::config::default_headers(),
+            query: Vec::new(),
+            join: UrlJoin::default(),
         }
     }
 
+    /// Creates a new instance of [Client] using the provided parameters,
+    /// failing if the base URL is invalid.
+    pub fn try_new(base: &str, http: reqwest::Client) -> Result<Self, ClientError> {
+        crate::http::validate_base(base)?;
+        Ok(Client::new(base, http))
+    }
+
     /// Creates a new instance of [Client] with a default instance of
-    /// [reqwest::Client][1].
+    /// [reqwest::Client][1] which follows up to 10 redirects itself.
     ///
     /// [1]: https://docs.rs/reqwest/latest/reqwest/struct.Client.html
     pub fn default(base: &str) -> Self {
-        Client {
-            base: base.to_string(),
-            http: reqwest::Client::default(),
-        }
+        Client::new(base, reqwest::Client::default())
+    }
+
+    /// Creates a new instance of [Client] with a default instance of
+    /// [reqwest::Client][1] which follows up to 10 redirects itself, failing
+    /// if the base URL is invalid or the backing client fails to initialize.
+    ///
+    /// [1]: https://docs.rs/reqwest/latest/reqwest/struct.Client.html
+    pub fn try_default(base: &str) -> Result<Self, ClientError> {
+        crate::http::validate_base(base)?;
+        let http = reqwest::Client::builder()
+            .build()
+            .map_err(|e| ClientError::ClientBuildError { source: e.into() })?;
+        Ok(Client::new(base, http))
+    }
+
+    /// Creates a new instance of [Client] with a default instance of
+    /// [reqwest::Client][1] which follows redirects according to the given
+    /// [RedirectPolicy].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the backing [reqwest::Client][1] fails to initialize.
+    ///
+    /// [1]: https://docs.rs/reqwest/latest/reqwest/struct.Client.html
+    pub fn with_redirect(base: &str, policy: RedirectPolicy) -> Self {
+        let http = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .expect("failed to initialize reqwest client");
+        let mut client = Client::new(base, http);
+        client.redirect = Some(policy);
+        client
+    }
+
+    /// Creates a new instance of [Client] with a [reqwest::Client][1]
+    /// configured using the given [ClientConfig] which follows redirects
+    /// according to its [redirect][ClientConfig::redirect] policy. Fails if
+    /// the base URL is invalid.
+    ///
+    /// [1]: https://docs.rs/reqwest/latest/reqwest/struct.Client.html
+    pub fn with_config(base: &str, config: &ClientConfig) -> Result<Self, ClientError> {
+        crate::http::validate_base(base)?;
+        let builder = reqwest::Client::builder().redirect(reqwest::redirect::Policy::none());
+        let http = configure(builder, config)?
+            .build()
+            .map_err(|e| ClientError::ClientBuildError { source: e.into() })?;
+        let mut client = Client::new(base, http);
+        client.redirect = Some(config.redirect);
+        client.headers = config.headers()?;
+        client.query = config.default_query.clone();
+        client.join = config.url_join;
+        Ok(client)
+    }
+
+    /// Creates a new instance of [Client] for the common mutual TLS setup,
+    /// which trusts the PEM encoded CA certificate and presents the PEM
+    /// encoded client certificate and PKCS#8 private key read from the given
+    /// files.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use rustify::clients::reqwest::Client;
+    ///
+    /// let client = Client::with_mtls(
+    ///     "https://vault.internal:8200",
+    ///     "/etc/vault/ca.pem",
+    ///     "/etc/vault/client.pem",
+    ///     "/etc/vault/client-key.pem",
+    /// )
+    /// .unwrap();
+    /// ```
+    pub fn with_mtls(
+        base: &str,
+        ca_path: impl AsRef<Path>,
+        cert_path: impl AsRef<Path>,
+        key_path: impl AsRef<Path>,
+    ) -> Result<Self, ClientError> {
+        let config = ClientConfig::default()
+            .root_certificate(Certificate::from_pem_file(ca_path)?)
+            .identity(Identity::from_pem_files(cert_path, key_path)?);
+        Client::with_config(base, &config)
+    }
+
+    /// Sets the [AuthProvider] used for attaching credentials to every
+    /// request sent by this client.
+    pub fn set_auth(&mut self, auth: impl AuthProvider + 'static) {
+        self.auth = Some(Arc::new(auth));
+    }
+
+    /// Sets the [MetricsObserver] invoked with the metrics of every request
+    /// sent by this client.
+    pub fn set_metrics(&mut self, metrics: impl MetricsObserver + 'static) {
+        self.metrics = Some(Arc::new(metrics));
+    }
+
+    /// Returns the backing [reqwest::Client][1], or [None] once the client has been
+    /// shut down and its connection pool released.
+    ///
+    /// [1]: https://docs.rs/reqwest/latest/reqwest/struct.Client.html
+    pub fn http(&self) -> Option<reqwest::Client> {
+        self.http.get()
+    }
+
+    /// Returns a new instance of [Client] which sends requests to the given
+    /// base URL, sharing the backing [reqwest::Client][1], its connection pool and the
+    /// settings of this client.
+    ///
+    /// The returned client shares the [Drain] of this client, so shutting
+    /// down either of them shuts down both.
+    ///
+    /// # Example
+    /// ```
+    /// use rustify::clients::reqwest::Client;
+    ///
+    /// let client = Client::default("https://vault-0.internal:8200");
+    /// // Follow the cluster to its new leader
+    /// let leader = client.with_base("https://vault-1.internal:8200");
+    /// ```
+    ///
+    /// [1]: https://docs.rs/reqwest/latest/reqwest/struct.Client.html
+    pub fn with_base(&self, base: &str) -> Self {
+        let mut client = self.clone();
+        client.base = base.to_string();
+        client
+    }
+
+    /// Sets the headers added to every request sent by this client which
+    /// doesn't set them itself, replacing the
+    /// [DEFAULT_USER_AGENT][crate::config::DEFAULT_USER_AGENT] sent by
+    /// default.
+    pub fn set_default_headers(&mut self, headers: HeaderMap) {
+        self.headers = headers;
+    }
+
+    /// Sets the query parameters added to the URL of every request sent by
+    /// this client which doesn't set them itself.
+    pub fn set_default_query(&mut self, query: Vec<(String, String)>) {
+        self.query = query;
+    }
+
+    /// Sets the [UrlJoin] mode used for joining the path of every
+    /// [Endpoint][crate::endpoint::Endpoint] onto the base URL.
+    pub fn set_url_join(&mut self, join: UrlJoin) {
+        self.join = join;
+    }
+
+    /// Sets the [RedirectPolicy] used for following redirects.
+    ///
+    /// The backing [reqwest::Client][1] must not follow redirects itself, as
+    /// is the case for clients created with [Client::with_redirect] or
+    /// [Client::with_config] but not with [Client::default].
+    ///
+    /// [1]: https://docs.rs/reqwest/latest/reqwest/struct.Client.html
+    pub fn set_redirect(&mut self, policy: RedirectPolicy) {
+        self.redirect = Some(policy);
     }
 }
 
-#[async_trait]
+#[cfg_attr(not(feature = "non-send"), async_trait)]
+#[cfg_attr(feature = "non-send", async_trait(?Send))]
 impl RustifyClient for Client {
     fn base(&self) -> &str {
         self.base.as_str()
     }
 
+    fn drain(&self) -> Option<&Drain> {
+        Some(&self.drain)
+    }
+
+    fn auth(&self) -> Option<&dyn AuthProvider> {
+        self.auth.as_deref()
+    }
+
+    fn metrics(&self) -> Option<&dyn MetricsObserver> {
+        self.metrics.as_deref()
+    }
+
+    fn default_headers(&self) -> Option<&HeaderMap> {
+        Some(&self.headers)
+    }
+
+    fn default_query(&self) -> Option<&[(String, String)]> {
+        Some(&self.query)
+    }
+
+    fn url_join(&self) -> UrlJoin {
+        self.join
+    }
+
     // TODO: remove the allow when the upstream clippy issue is fixed:
     // <https://github.com/rust-lang/rust-clippy/issues/12281>
     #[allow(clippy::blocks_in_conditions)]
-    #[instrument(skip(self, req), err)]
-    async fn send(&self, req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, ClientError> {
+    #[cfg_attr(feature = "tracing", instrument(skip(self, req), err))]
+    async fn send(&self, req: Request<Bytes>) -> Result<Response<Bytes>, ClientError> {
+        crate::redirect::follow(self.redirect.as_ref(), req, |req| async move {
+            let response = self.dispatch(req).await?;
+            let http_resp = response_builder(&response);
+
+            http_resp
+                .body(
+                    response
+                        .bytes()
+                        .await
+                        .map_err(|e| ClientError::ResponseError { source: e.into() })?,
+                )
+                .map_err(|e| ClientError::ResponseError { source: e.into() })
+        })
+        .await
+    }
+
+    #[allow(clippy::blocks_in_conditions)]
+    #[cfg_attr(feature = "tracing", instrument(skip(self, req), err))]
+    async fn send_stream(&self, req: Request<Bytes>) -> Result<Response<ByteStream>, ClientError> {
+        crate::redirect::follow(self.redirect.as_ref(), req, |req| async move {
+            let response = self.dispatch(req).await?;
+            let http_resp = response_builder(&response);
+            let body = response
+                .bytes_stream()
+                .map(|c| c.map_err(|e| ClientError::ResponseError { source: e.into() }));
+
+            http_resp
+                .body(Box::pin(body) as ByteStream)
+                .map_err(|e| ClientError::ResponseError { source: e.into() })
+        })
+        .await
+    }
+}
+
+impl Client {
+    /// Converts the given [Request] and sends it using the backing
+    /// [reqwest::Client][1].
+    ///
+    /// [1]: https://docs.rs/reqwest/latest/reqwest/struct.Client.html
+    async fn dispatch(&self, req: Request<Bytes>) -> Result<reqwest::Response, ClientError> {
         let request = reqwest::Request::try_from(req)
             .map_err(|e| ClientError::ReqwestBuildError { source: e })?;
 
         let url_err = request.url().to_string();
         let method_err = request.method().to_string();
-        let response = self
-            .http
+        self.http
+            .acquire()?
             .execute(request)
             .await
             .map_err(|e| ClientError::RequestError {
                 source: e.into(),
                 url: url_err,
                 method: method_err,
-            })?;
+            })
+    }
+}
+
+/// Returns a [Response] builder with the status, headers and final URL of the
+/// given [reqwest::Response].
+fn response_builder(response: &reqwest::Response) -> http::response::Builder {
+    let mut http_resp = http::Response::builder().status(response.status().as_u16());
+    for v in response.headers().into_iter() {
+        http_resp = http_resp.header(v.0, v.1);
+    }
+    if let Ok(url) = response.url().as_str().parse() {
+        http_resp = http_resp.extension(Redirects {
+            url,
+            chain: Vec::new(),
+        });
+    }
+    http_resp
+}
+
+/// A reqwest client builder which a [ClientConfig] can be applied to, which
+/// allows sharing the configuration of the async and blocking clients.
+pub(crate) trait Builder: Sized {
+    fn timeout(self, timeout: Duration) -> Self;
+
+    fn connect_timeout(self, timeout: Duration) -> Self;
+
+    fn proxy(self, proxy: reqwest::Proxy) -> Self;
+
+    fn resolve_to_addrs(self, domain: &str, addrs: &[SocketAddr]) -> Self;
 
-        let status_code = response.status().as_u16();
-        let mut http_resp = http::Response::builder().status(status_code);
-        for v in response.headers().into_iter() {
-            http_resp = http_resp.header(v.0, v.1);
+    fn pool_idle_timeout(self, timeout: Duration) -> Self;
+
+    fn pool_max_idle_per_host(self, max: usize) -> Self;
+
+    fn tcp_keepalive(self, interval: Duration) -> Self;
+
+    fn http1_only(self) -> Self;
+
+    #[cfg(feature = "http2")]
+    fn http2_prior_knowledge(self) -> Self;
+
+    #[cfg(any(feature = "native-tls", feature = "rustls-tls"))]
+    fn add_root_certificate(self, cert: reqwest::Certificate) -> Self;
+
+    #[cfg(any(feature = "native-tls", feature = "rustls-tls"))]
+    fn identity(self, identity: reqwest::Identity) -> Self;
+
+    #[cfg(any(feature = "native-tls", feature = "rustls-tls"))]
+    fn min_tls_version(self, version: reqwest::tls::Version) -> Self;
+}
+
+impl Builder for reqwest::ClientBuilder {
+    fn timeout(self, timeout: Duration) -> Self {
+        self.timeout(timeout)
+    }
+
+    fn connect_timeout(self, timeout: Duration) -> Self {
+        self.connect_timeout(timeout)
+    }
+
+    fn proxy(self, proxy: reqwest::Proxy) -> Self {
+        self.proxy(proxy)
+    }
+
+    fn resolve_to_addrs(self, domain: &str, addrs: &[SocketAddr]) -> Self {
+        self.resolve_to_addrs(domain, addrs)
+    }
+
+    fn pool_idle_timeout(self, timeout: Duration) -> Self {
+        self.pool_idle_timeout(timeout)
+    }
+
+    fn pool_max_idle_per_host(self, max: usize) -> Self {
+        self.pool_max_idle_per_host(max)
+    }
+
+    fn tcp_keepalive(self, interval: Duration) -> Self {
+        self.tcp_keepalive(interval)
+    }
+
+    fn http1_only(self) -> Self {
+        self.http1_only()
+    }
+
+    #[cfg(feature = "http2")]
+    fn http2_prior_knowledge(self) -> Self {
+        self.http2_prior_knowledge()
+    }
+
+    #[cfg(any(feature = "native-tls", feature = "rustls-tls"))]
+    fn add_root_certificate(self, cert: reqwest::Certificate) -> Self {
+        self.add_root_certificate(cert)
+    }
+
+    #[cfg(any(feature = "native-tls", feature = "rustls-tls"))]
+    fn identity(self, identity: reqwest::Identity) -> Self {
+        self.identity(identity)
+    }
+
+    #[cfg(any(feature = "native-tls", feature = "rustls-tls"))]
+    fn min_tls_version(self, version: reqwest::tls::Version) -> Self {
+        self.min_tls_version(version)
+    }
+}
+
+/// Applies the given [ClientConfig] to the given builder.
+pub(crate) fn configure<B: Builder>(
+    mut builder: B,
+    config: &ClientConfig,
+) -> Result<B, ClientError> {
+    if let Some(timeout) = config.timeout {
+        builder = builder.timeout(timeout);
+    }
+    if let Some(timeout) = config.connect_timeout {
+        builder = builder.connect_timeout(timeout);
+    }
+    if let Some(url) = &config.proxy {
+        let proxy = reqwest::Proxy::all(url.as_str())
+            .map_err(|e| ClientError::ClientBuildError { source: e.into() })?;
+        builder = builder.proxy(proxy);
+    }
+    for (host, addrs) in config.resolve.iter() {
+        // The port is ignored in favor of the port of the request URL
+        let addrs: Vec<_> = addrs.iter().map(|a| SocketAddr::new(*a, 0)).collect();
+        builder = builder.resolve_to_addrs(host, &addrs);
+    }
+    if let Some(timeout) = config.pool_idle_timeout {
+        builder = builder.pool_idle_timeout(timeout);
+    }
+    if let Some(max) = config.pool_max_idle_per_host {
+        builder = builder.pool_max_idle_per_host(max);
+    }
+    if let Some(interval) = config.tcp_keepalive {
+        builder = builder.tcp_keepalive(interval);
+    }
+    match config.http_version {
+        HttpVersion::Auto => {}
+        HttpVersion::Http1Only => builder = builder.http1_only(),
+        #[cfg(feature = "http2")]
+        HttpVersion::Http2PriorKnowledge => builder = builder.http2_prior_knowledge(),
+        #[cfg(not(feature = "http2"))]
+        HttpVersion::Http2PriorKnowledge => {
+            return Err(ClientError::ClientBuildError {
+                source: anyhow::anyhow!("HTTP/2 prior knowledge requires the http2 feature"),
+            })
         }
+        HttpVersion::Http3 => return Err(unsupported("reqwest", "HTTP/3")),
+    }
 
-        http_resp
-            .body(
-                response
-                    .bytes()
-                    .await
-                    .map_err(|e| ClientError::ResponseError { source: e.into() })?
-                    .to_vec(),
-            )
-            .map_err(|e| ClientError::ResponseError { source: e.into() })
+    #[cfg(any(feature = "native-tls", feature = "rustls-tls"))]
+    {
+        for cert in config.root_certificates.iter() {
+            let cert = match cert {
+                Certificate::Pem(pem) => reqwest::Certificate::from_pem(pem),
+                Certificate::Der(der) => reqwest::Certificate::from_der(der),
+            }
+            .map_err(|e| ClientError::CertificateParseError {
+                source: e.into(),
+                name: "root certificate".to_string(),
+            })?;
+            builder = builder.add_root_certificate(cert);
+        }
+        if let Some(identity) = &config.identity {
+            builder = builder.identity(reqwest_identity(identity)?);
+        }
+        if let Some(version) = config.min_tls_version {
+            builder = builder.min_tls_version(match version {
+                crate::config::TlsVersion::Tls1_2 => reqwest::tls::Version::TLS_1_2,
+                crate::config::TlsVersion::Tls1_3 => reqwest::tls::Version::TLS_1_3,
+            });
+        }
+    }
+
+    #[cfg(not(any(feature = "native-tls", feature = "rustls-tls")))]
+    if !config.root_certificates.is_empty()
+        || config.identity.is_some()
+        || config.min_tls_version.is_some()
+    {
+        return Err(ClientError::ClientBuildError {
+            source: anyhow::anyhow!("TLS settings require the native-tls or rustls-tls feature"),
+        });
     }
+    Ok(builder)
+}
+
+/// Converts the given [Identity] into a [reqwest::Identity].
+#[cfg(any(feature = "native-tls", feature = "rustls-tls"))]
+fn reqwest_identity(identity: &Identity) -> Result<reqwest::Identity, ClientError> {
+    let identity = match identity {
+        #[cfg(feature = "native-tls")]
+        Identity::Pem { cert, key } => reqwest::Identity::from_pkcs8_pem(cert, key),
+        #[cfg(not(feature = "native-tls"))]
+        Identity::Pem { cert, key } => {
+            reqwest::Identity::from_pem(&[key.as_slice(), b"\n", cert.as_slice()].concat())
+        }
+        #[cfg(feature = "native-tls")]
+        Identity::Pkcs12 { der, password } => reqwest::Identity::from_pkcs12_der(der, password),
+        #[cfg(not(feature = "native-tls"))]
+        Identity::Pkcs12 { .. } => {
+            return Err(ClientError::CertificateParseError {
+                source: anyhow::anyhow!("PKCS#12 identities require the native-tls feature"),
+                name: "client identity".to_string(),
+            })
+        }
+    };
+    identity.map_err(|e| ClientError::CertificateParseError {
+        source: e.into(),
+        name: "client identity".to_string(),
+    })
 }
@@ -0,0 +1,452 @@
+//! Contains the [ClientConfig] used for configuring the backing HTTP clients
+//! of the built-in clients.
+use crate::{errors::ClientError, http::UrlJoin, redirect::RedirectPolicy};
+#[cfg(any(
+    feature = "attohttpc",
+    feature = "hyper",
+    feature = "isahc",
+    feature = "reqwest"
+))]
+use http::header::USER_AGENT;
+use http::{header::AUTHORIZATION, HeaderMap, HeaderName, HeaderValue};
+use std::{collections::HashMap, net::IpAddr, path::Path, time::Duration};
+
+/// The `User-Agent` sent by the built-in clients unless configured otherwise.
+pub const DEFAULT_USER_AGENT: &str = concat!("rustify/", env!("CARGO_PKG_VERSION"));
+
+/// A certificate used for verifying servers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Certificate {
+    /// A PEM encoded certificate.
+    Pem(Vec<u8>),
+    /// A DER encoded certificate.
+    Der(Vec<u8>),
+}
+
+impl Certificate {
+    /// Reads a PEM encoded certificate from the given file.
+    pub fn from_pem_file(path: impl AsRef<Path>) -> Result<Self, ClientError> {
+        read(path.as_ref()).map(Certificate::Pem)
+    }
+}
+
+/// The identity presented to servers which require client authentication.
+#[derive(Clone, PartialEq, Eq)]
+pub enum Identity {
+    /// A PEM encoded certificate chain and its PKCS#8 private key.
+    Pem { cert: Vec<u8>, key: Vec<u8> },
+    /// A DER encoded PKCS#12 archive and its password.
+    ///
+    /// Requires the `native-tls` feature.
+    Pkcs12 { der: Vec<u8>, password: String },
+}
+
+impl Identity {
+    /// Reads a PEM encoded certificate chain and PKCS#8 private key from the
+    /// given files.
+    pub fn from_pem_files(
+        cert_path: impl AsRef<Path>,
+        key_path: impl AsRef<Path>,
+    ) -> Result<Self, ClientError> {
+        Ok(Identity::Pem {
+            cert: read(cert_path.as_ref())?,
+            key: read(key_path.as_ref())?,
+        })
+    }
+
+    /// Reads a DER encoded PKCS#12 archive from the given file.
+    pub fn from_pkcs12_file(path: impl AsRef<Path>, password: &str) -> Result<Self, ClientError> {
+        Ok(Identity::Pkcs12 {
+            der: read(path.as_ref())?,
+            password: password.to_string(),
+        })
+    }
+}
+
+impl std::fmt::Debug for Identity {
+    /// Omits the private key material.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Identity::Pem { .. } => f.write_str("Identity::Pem"),
+            Identity::Pkcs12 { .. } => f.write_str("Identity::Pkcs12"),
+        }
+    }
+}
+
+/// A version of the TLS protocol.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TlsVersion {
+    /// TLS 1.2
+    Tls1_2,
+    /// TLS 1.3
+    ///
+    /// Requiring TLS 1.3 is not supported by the `native-tls` backend on all
+    /// platforms, in which case building the client fails.
+    Tls1_3,
+}
+
+/// The version of HTTP used for sending requests.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HttpVersion {
+    /// Negotiates the version with the server, using HTTP/2 over TLS when
+    /// the server supports it and the `http2` feature is enabled.
+    #[default]
+    Auto,
+    /// Only uses HTTP/1.1.
+    Http1Only,
+    /// Uses HTTP/2 without negotiating it first, which is required for
+    /// servers only supporting HTTP/2 over cleartext (h2c).
+    ///
+    /// Requires the `http2` feature.
+    Http2PriorKnowledge,
+    /// Uses HTTP/3 over QUIC.
+    ///
+    /// Not supported by any of the built-in clients yet: `reqwest` only
+    /// provides HTTP/3 behind its unstable `reqwest_unstable` cfg flag, so
+    /// building a client with this version fails with a
+    /// [ClientError::ClientBuildError].
+    Http3,
+}
+
+/// Configures the backing HTTP client of a built-in client.
+///
+/// A [ClientConfig] is built by chaining its setters onto
+/// [ClientConfig::default] and is accepted by the `with_config` constructor
+/// of every built-in client. Backends which don't support a setting which is
+/// set fail to build with a [ClientError::ClientBuildError] rather than
+/// silently ignoring it.
+///
+/// # Example
+/// ```no_run
+/// use rustify::clients::reqwest::Client;
+/// use rustify::config::{Certificate, ClientConfig};
+/// use std::time::Duration;
+///
+/// let ca = std::fs::read("/etc/vault/ca.pem").unwrap();
+/// let config = ClientConfig::default()
+///     .root_certificate(Certificate::Pem(ca))
+///     .timeout(Duration::from_secs(30))
+///     .user_agent("my-sdk/1.0");
+/// let client = Client::with_config("https://vault.internal:8200", &config).unwrap();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ClientConfig {
+    /// The base URL read by [ClientConfig::from_env], which isn't used by the
+    /// clients themselves.
+    pub base_url: Option<String>,
+    /// The maximum amount of time a request may take to complete.
+    pub timeout: Option<Duration>,
+    /// The maximum amount of time establishing a connection may take.
+    pub connect_timeout: Option<Duration>,
+    /// The URL of the proxy every request is sent through.
+    pub proxy: Option<String>,
+    /// Headers sent with every request which doesn't set them itself.
+    pub default_headers: HeaderMap,
+    /// Query parameters added to the URL of every request which doesn't set
+    /// them itself.
+    pub default_query: Vec<(String, String)>,
+    /// How the path of every endpoint is joined onto the base URL.
+    pub url_join: UrlJoin,
+    /// The `User-Agent` sent with every request which doesn't set one itself,
+    /// replacing the [DEFAULT_USER_AGENT].
+    pub user_agent: Option<String>,
+    /// The products (i.e. `my-sdk/1.0`) appended to the [DEFAULT_USER_AGENT].
+    pub user_agent_products: Vec<String>,
+    /// The policy used for following redirects.
+    pub redirect: RedirectPolicy,
+    /// Certificates which are trusted in addition to the system roots.
+    pub root_certificates: Vec<Certificate>,
+    /// The identity presented to servers requesting client authentication.
+    pub identity: Option<Identity>,
+    /// The minimum version of TLS servers must support.
+    pub min_tls_version: Option<TlsVersion>,
+    /// The addresses hostnames are resolved to in place of querying DNS.
+    pub resolve: HashMap<String, Vec<IpAddr>>,
+    /// How long idle connections are kept open in the pool.
+    pub pool_idle_timeout: Option<Duration>,
+    /// The maximum number of idle connections kept open for each host.
+    pub pool_max_idle_per_host: Option<usize>,
+    /// The interval of the TCP keepalive probes sent on open connections.
+    pub tcp_keepalive: Option<Duration>,
+    /// The version of HTTP used for sending requests.
+    pub http_version: HttpVersion,
+}
+
+impl ClientConfig {
+    /// Reads a [ClientConfig] from the environment variables starting with
+    /// the given prefix, ignoring variables which are unset or empty:
+    ///
+    /// * `{prefix}_BASE_URL`: The [base_url][ClientConfig::base_url]
+    /// * `{prefix}_PROXY`: The URL of the proxy every request is sent through
+    /// * `{prefix}_TIMEOUT`: The request timeout in seconds (i.e. `2.5`)
+    /// * `{prefix}_CA_CERT`: The path of a PEM encoded root certificate
+    /// * `{prefix}_TOKEN`: A bearer token sent in the `Authorization` header
+    ///
+    /// Fails with a [ClientError::EnvParseError] if a variable holds an
+    /// invalid value.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use rustify::clients::reqwest::Client;
+    /// use rustify::config::ClientConfig;
+    ///
+    /// // Reads VAULT_BASE_URL, VAULT_TOKEN, etc.
+    /// let config = ClientConfig::from_env("VAULT").unwrap();
+    /// let base = config.base_url.as_deref().unwrap_or("http://127.0.0.1:8200");
+    /// let client = Client::with_config(base, &config).unwrap();
+    /// ```
+    pub fn from_env(prefix: &str) -> Result<Self, ClientError> {
+        let mut config = ClientConfig {
+            base_url: env(prefix, "BASE_URL")?,
+            proxy: env(prefix, "PROXY")?,
+            ..Default::default()
+        };
+        if let Some(timeout) = env(prefix, "TIMEOUT")? {
+            let timeout = timeout
+                .parse::<f64>()
+                .map_err(anyhow::Error::from)
+                .and_then(|t| Duration::try_from_secs_f64(t).map_err(anyhow::Error::from))
+                .map_err(|e| ClientError::EnvParseError {
+                    source: e,
+                    name: env_name(prefix, "TIMEOUT"),
+                })?;
+            config.timeout = Some(timeout);
+        }
+        if let Some(path) = env(prefix, "CA_CERT")? {
+            config = config.root_certificate(Certificate::from_pem_file(path)?);
+        }
+        if let Some(token) = env(prefix, "TOKEN")? {
+            let mut value = HeaderValue::from_str(&format!("Bearer {}", token)).map_err(|e| {
+                ClientError::EnvParseError {
+                    source: e.into(),
+                    name: env_name(prefix, "TOKEN"),
+                }
+            })?;
+            value.set_sensitive(true);
+            config.default_headers.insert(AUTHORIZATION, value);
+        }
+        Ok(config)
+    }
+
+    /// Fails requests which take longer than the given duration to complete.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Fails requests which take longer than the given duration to establish
+    /// a connection.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Sends every request through the proxy at the given URL.
+    pub fn proxy(mut self, url: &str) -> Self {
+        self.proxy = Some(url.to_string());
+        self
+    }
+
+    /// Sends the given header with every request which doesn't set it
+    /// itself. Adding the same header more than once sends all of its values.
+    pub fn default_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.default_headers.append(name, value);
+        self
+    }
+
+    /// Adds the given query parameter to the URL of every request which
+    /// doesn't set it itself (i.e. an `api-version`).
+    pub fn default_query_param(mut self, name: &str, value: &str) -> Self {
+        self.default_query
+            .push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Joins the path of every endpoint onto the base URL according to the
+    /// given [UrlJoin] mode.
+    pub fn url_join(mut self, join: UrlJoin) -> Self {
+        self.url_join = join;
+        self
+    }
+
+    /// Sends the given `User-Agent` with every request which doesn't set one
+    /// itself in place of the [DEFAULT_USER_AGENT].
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.user_agent = Some(user_agent.to_string());
+        self
+    }
+
+    /// Identifies the given product in the [DEFAULT_USER_AGENT], which is
+    /// then sent as `rustify/x.y (name/version)`.
+    ///
+    /// # Example
+    /// ```
+    /// use rustify::config::ClientConfig;
+    ///
+    /// let config = ClientConfig::default()
+    ///     .user_agent_product(env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+    /// ```
+    pub fn user_agent_product(mut self, name: &str, version: &str) -> Self {
+        self.user_agent_products
+            .push(format!("{}/{}", name, version));
+        self
+    }
+
+    /// Follows redirects according to the given [RedirectPolicy].
+    pub fn redirect(mut self, policy: RedirectPolicy) -> Self {
+        self.redirect = policy;
+        self
+    }
+
+    /// Trusts the given certificate in addition to the system roots.
+    pub fn root_certificate(mut self, cert: Certificate) -> Self {
+        self.root_certificates.push(cert);
+        self
+    }
+
+    /// Presents the given identity to servers requesting client
+    /// authentication.
+    pub fn identity(mut self, identity: Identity) -> Self {
+        self.identity = Some(identity);
+        self
+    }
+
+    /// Refuses connecting to servers which don't support at least the given
+    /// version of TLS.
+    pub fn min_tls_version(mut self, version: TlsVersion) -> Self {
+        self.min_tls_version = Some(version);
+        self
+    }
+
+    /// Resolves the given hostname to the given address in place of querying
+    /// DNS. Connections are made to the port of the request URL and TLS
+    /// verification and SNI still use the hostname, which makes this useful
+    /// for testing against staging hosts. Resolving a hostname to several
+    /// addresses tries each of them in order.
+    pub fn resolve(mut self, host: &str, addr: IpAddr) -> Self {
+        self.resolve
+            .entry(host.to_lowercase())
+            .or_default()
+            .push(addr);
+        self
+    }
+
+    /// Closes connections which have been idle in the pool for longer than
+    /// the given duration.
+    pub fn pool_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.pool_idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Keeps at most the given number of idle connections open for each
+    /// host.
+    pub fn pool_max_idle_per_host(mut self, max: usize) -> Self {
+        self.pool_max_idle_per_host = Some(max);
+        self
+    }
+
+    /// Sends TCP keepalive probes on open connections at the given interval.
+    pub fn tcp_keepalive(mut self, interval: Duration) -> Self {
+        self.tcp_keepalive = Some(interval);
+        self
+    }
+
+    /// Sends requests using the given version of HTTP.
+    pub fn http_version(mut self, version: HttpVersion) -> Self {
+        self.http_version = version;
+        self
+    }
+}
+
+impl ClientConfig {
+    /// Returns the headers sent with every request, which are the
+    /// [default_headers][ClientConfig::default_headers] along with the
+    /// `User-Agent`. A `User-Agent` in the default headers is only replaced
+    /// by an explicit [user_agent][ClientConfig::user_agent].
+    #[cfg(any(
+        feature = "attohttpc",
+        feature = "hyper",
+        feature = "isahc",
+        feature = "reqwest"
+    ))]
+    pub(crate) fn headers(&self) -> Result<HeaderMap, ClientError> {
+        let mut headers = self.default_headers.clone();
+        let user_agent = match &self.user_agent {
+            Some(user_agent) => user_agent.clone(),
+            None if headers.contains_key(USER_AGENT) => return Ok(headers),
+            None if self.user_agent_products.is_empty() => DEFAULT_USER_AGENT.to_string(),
+            None => format!(
+                "{} ({})",
+                DEFAULT_USER_AGENT,
+                self.user_agent_products.join("; ")
+            ),
+        };
+        let value =
+            HeaderValue::from_str(&user_agent).map_err(|e| ClientError::HeaderParseError {
+                source: e.into(),
+                name: USER_AGENT.to_string(),
+            })?;
+        headers.insert(USER_AGENT, value);
+        Ok(headers)
+    }
+}
+
+/// Returns the headers sent by the built-in clients by default, which only
+/// contain the [DEFAULT_USER_AGENT].
+#[cfg(any(
+    feature = "attohttpc",
+    feature = "hyper",
+    feature = "isahc",
+    feature = "reqwest"
+))]
+pub(crate) fn default_headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(USER_AGENT, HeaderValue::from_static(DEFAULT_USER_AGENT));
+    headers
+}
+
+/// Returns the name of the environment variable with the given prefix and
+/// suffix.
+fn env_name(prefix: &str, suffix: &str) -> String {
+    match prefix.trim_end_matches('_') {
+        "" => suffix.to_string(),
+        prefix => format!("{}_{}", prefix, suffix),
+    }
+}
+
+/// Returns the value of the environment variable with the given prefix and
+/// suffix, or [None] if it's unset or empty.
+fn env(prefix: &str, suffix: &str) -> Result<Option<String>, ClientError> {
+    let name = env_name(prefix, suffix);
+    match std::env::var(&name) {
+        Ok(value) if value.is_empty() => Ok(None),
+        Ok(value) => Ok(Some(value)),
+        Err(std::env::VarError::NotPresent) => Ok(None),
+        Err(e) => Err(ClientError::EnvParseError {
+            source: e.into(),
+            name,
+        }),
+    }
+}
+
+/// Returns the error for a setting which isn't supported by the given
+/// backend.
+#[cfg(any(
+    feature = "attohttpc",
+    feature = "hyper",
+    feature = "isahc",
+    feature = "reqwest"
+))]
+pub(crate) fn unsupported(backend: &str, setting: &str) -> ClientError {
+    ClientError::ClientBuildError {
+        source: anyhow::anyhow!("{} is not supported by the {} client", setting, backend),
+    }
+}
+
+/// Reads the certificate material stored in the given file.
+fn read(path: &Path) -> Result<Vec<u8>, ClientError> {
+    std::fs::read(path).map_err(|e| ClientError::CertificateReadError {
+        source: e,
+        path: path.display().to_string(),
+    })
+}
@@ -0,0 +1,148 @@
+//! Contains [MiddleWare] for correlating requests with server-side logs.
+use crate::{
+    endpoint::{Endpoint, MiddleWare},
+    errors::ClientError,
+};
+use bytes::Bytes;
+use http::{header::HeaderName, HeaderMap, HeaderValue, Request, Response};
+
+/// The header used for sending and receiving request IDs by default.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// The request ID echoed back by the server.
+///
+/// This is added to the extensions of the [Response] by [RequestId] and can be
+/// read from an [EndpointResult][crate::endpoint::EndpointResult] with
+/// [request_id][crate::endpoint::EndpointResult::request_id].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ServerRequestId(pub String);
+
+/// Where the request IDs sent by [RequestId] come from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RequestIdSource {
+    /// A random UUID is generated for every request.
+    Generate,
+    /// The ID of the current [tracing] span is used, falling back to a random
+    /// UUID when there's no current span or the `tracing` feature is disabled.
+    Span,
+}
+
+/// A [MiddleWare] which attaches a request ID to every outgoing request and
+/// records the ID echoed back by the server for log correlation.
+///
+/// Requests which already carry the header are left untouched. The ID in the
+/// response header is added to the extensions of the [Response] as a
+/// [ServerRequestId]. Unsuccessful responses never reach the middleware, so
+/// [Client::execute][crate::client::Client::execute] records the ID of those in
+/// the `request_id` field of its span instead when the default header is used.
+///
+/// # Example
+/// ```
+/// use rustify::client::Client as _;
+/// use rustify::clients::reqwest::Client;
+/// use rustify::correlation::RequestId;
+/// use rustify::Endpoint;
+/// use rustify_derive::Endpoint;
+///
+/// #[derive(Endpoint)]
+/// #[endpoint(path = "my/endpoint")]
+/// struct MyEndpoint {}
+///
+/// # tokio_test::block_on(async {
+/// let client = Client::default("http://myapi.com").with_middleware(RequestId::new());
+/// let endpoint = MyEndpoint {};
+/// if let Ok(result) = endpoint.exec(&client).await {
+///     println!("Request ID: {:?}", result.request_id());
+/// }
+/// # })
+/// ```
+#[derive(Clone, Debug)]
+pub struct RequestId {
+    /// The header the request ID is sent in and read from.
+    pub header: HeaderName,
+    /// Where the request IDs come from.
+    pub source: RequestIdSource,
+}
+
+impl RequestId {
+    /// Returns a new [RequestId] which generates a random UUID for every
+    /// request and uses the `X-Request-Id` header.
+    pub fn new() -> Self {
+        RequestId {
+            header: HeaderName::from_static(REQUEST_ID_HEADER),
+            source: RequestIdSource::Generate,
+        }
+    }
+
+    /// Returns a new [RequestId] which uses the ID of the current [tracing]
+    /// span.
+    pub fn from_span() -> Self {
+        RequestId {
+            source: RequestIdSource::Span,
+            ..RequestId::new()
+        }
+    }
+
+    /// Uses the given header for sending and reading the request ID.
+    pub fn header(mut self, header: HeaderName) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// Returns a new request ID from the configured source.
+    fn generate(&self) -> String {
+        match self.source {
+            #[cfg(feature = "tracing")]
+            RequestIdSource::Span => tracing::Span::current()
+                .id()
+                .map(|id| format!("{:016x}", id.into_u64()))
+                .unwrap_or_else(crate::http::uuid_v4),
+            _ => crate::http::uuid_v4(),
+        }
+    }
+}
+
+impl Default for RequestId {
+    fn default() -> Self {
+        RequestId::new()
+    }
+}
+
+impl MiddleWare for RequestId {
+    fn request<E: Endpoint>(&self, _: &E, req: &mut Request<Bytes>) -> Result<(), ClientError> {
+        if req.headers().contains_key(&self.header) {
+            return Ok(());
+        }
+        let id = self.generate();
+        let value = HeaderValue::from_str(&id).map_err(|e| ClientError::HeaderParseError {
+            source: e.into(),
+            name: self.header.to_string(),
+        })?;
+        req.headers_mut().insert(self.header.clone(), value);
+        Ok(())
+    }
+
+    fn response<E: Endpoint>(&self, _: &E, resp: &mut Response<Bytes>) -> Result<(), ClientError> {
+        let id = resp
+            .headers()
+            .get(&self.header)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| ServerRequestId(v.to_string()));
+        if let Some(id) = id {
+            resp.extensions_mut().insert(id);
+        }
+        Ok(())
+    }
+}
+
+/// Records the request ID in the given headers, if any, in the `request_id`
+/// field of the current span.
+#[cfg(feature = "tracing")]
+pub(crate) fn record_request_id(headers: &HeaderMap) {
+    if let Some(id) = headers.get(REQUEST_ID_HEADER).and_then(|v| v.to_str().ok()) {
+        tracing::Span::current().record("request_id", id);
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn record_request_id(_: &HeaderMap) {}
@@ -0,0 +1,414 @@
+//! Contains a [Client] decorator for attaching CSRF tokens to mutating
+//! requests.
+#[cfg(feature = "blocking")]
+use crate::blocking::client::Client as BlockingClient;
+use crate::{
+    auth::AuthProvider,
+    client::{ByteStream, Client, Drain, SuccessCodes},
+    endpoint::{client_defaults, Endpoint, RequestDefaults},
+    errors::ClientError,
+    http::UrlJoin,
+    metrics::MetricsObserver,
+    retry::RetryPolicy,
+};
+use async_trait::async_trait;
+use bytes::Bytes;
+use http::{
+    header::{HeaderName, SET_COOKIE},
+    HeaderMap, Method, Request, Response, StatusCode,
+};
+use std::sync::RwLock;
+
+/// Describes where a [CsrfClient] finds the CSRF token in a response.
+#[derive(Clone, Debug)]
+pub enum CsrfSource {
+    /// The value of the cookie with the given name set by any response.
+    Cookie(String),
+    /// The value of the header with the given name in any response.
+    Header(String),
+    /// The string at the given JSON pointer (i.e. `/csrf/token`) in the body
+    /// of the bootstrap response.
+    Json(String),
+}
+
+/// A [Client] decorator which attaches a CSRF token as a header to every
+/// mutating request.
+///
+/// The token is extracted from responses according to its [CsrfSource]. When
+/// a mutating request is sent before a token is known, the `bootstrap`
+/// endpoint is fetched with a `GET` request to obtain one. If the server
+/// rejects a request with one of the `expired` statuses, the token is
+/// refreshed, either from the rejected response itself or by fetching the
+/// `bootstrap` endpoint again, and the request is resent once.
+///
+/// Servers using the double submit cookie pattern also expect the cookie to be
+/// sent back, so the wrapped client must persist cookies itself when using
+/// [CsrfSource::Cookie].
+///
+/// # Example
+/// ```
+/// use rustify::clients::reqwest::Client;
+/// use rustify::csrf::{CsrfClient, CsrfSource};
+///
+/// let mut client = CsrfClient::new(
+///     Client::default("http://myapi.com"),
+///     CsrfSource::Json("/token".to_string()),
+/// );
+/// client.bootstrap = Some("csrf".to_string());
+/// ```
+pub struct CsrfClient<C> {
+    client: C,
+    source: CsrfSource,
+    token: RwLock<Option<String>>,
+    /// The header the token is sent in.
+    pub header: HeaderName,
+    /// The path of the endpoint which is fetched to obtain a token.
+    pub bootstrap: Option<String>,
+    /// The statuses the server responds with when rejecting a token.
+    pub expired: Vec<StatusCode>,
+}
+
+impl<C> CsrfClient<C> {
+    /// Returns a new [CsrfClient] wrapping the given client which finds tokens
+    /// using the given [CsrfSource]. Tokens are sent in the `X-CSRF-Token`
+    /// header and considered expired when the server responds with a `403` or
+    /// `419` status.
+    pub fn new(client: C, source: CsrfSource) -> Self {
+        CsrfClient {
+            client,
+            source,
+            token: RwLock::new(None),
+            header: HeaderName::from_static("x-csrf-token"),
+            bootstrap: None,
+            expired: vec![StatusCode::FORBIDDEN, StatusCode::from_u16(419).unwrap()],
+        }
+    }
+
+    /// Returns a reference to the wrapped client.
+    pub fn inner(&self) -> &C {
+        &self.client
+    }
+
+    /// Returns the current token, if any.
+    pub fn token(&self) -> Option<String> {
+        self.token.read().unwrap().clone()
+    }
+
+    /// Stores the token found in the given response headers, if any.
+    fn capture(&self, headers: &HeaderMap) {
+        let token = match &self.source {
+            CsrfSource::Cookie(name) => cookie(headers, name),
+            CsrfSource::Header(name) => headers
+                .get(name.as_str())
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string()),
+            CsrfSource::Json(_) => None,
+        };
+        if let Some(token) = token {
+            *self.token.write().unwrap() = Some(token);
+        }
+    }
+
+    /// Stores the token found in the given bootstrap response, if any.
+    fn capture_bootstrap(&self, resp: &Response<Bytes>) {
+        match &self.source {
+            CsrfSource::Json(pointer) => {
+                let token = serde_json::from_slice::<serde_json::Value>(resp.body())
+                    .ok()
+                    .and_then(|v| {
+                        v.pointer(pointer)
+                            .and_then(|t| t.as_str())
+                            .map(String::from)
+                    });
+                if let Some(token) = token {
+                    *self.token.write().unwrap() = Some(token);
+                }
+            }
+            _ => self.capture(resp.headers()),
+        }
+    }
+
+    /// Returns the request used for fetching a token, if a `bootstrap`
+    /// endpoint is configured. The given [RequestDefaults] of the wrapped
+    /// client are applied just like they are to endpoint requests.
+    fn bootstrap_request(
+        &self,
+        base: &str,
+        defaults: &RequestDefaults,
+    ) -> Option<Result<Request<Bytes>, ClientError>> {
+        let path = self.bootstrap.as_ref()?;
+        Some(
+            crate::http::join_url(base, path, None, defaults.join).and_then(|uri| {
+                let mut req = Request::new(Bytes::new());
+                *req.uri_mut() = uri;
+                defaults.apply(base, &mut req)?;
+                Ok(req)
+            }),
+        )
+    }
+
+    /// Returns a copy of the given request carrying the current token.
+    fn with_token(&self, req: &Request<Bytes>) -> Result<Request<Bytes>, ClientError> {
+        let mut req = crate::http::clone_request(req);
+        if let Some(token) = self.token() {
+            let (name, value) = crate::http::build_header(self.header.as_str(), &token)?;
+            req.headers_mut().insert(name, value);
+        }
+        Ok(req)
+    }
+
+    /// Returns whether a request which received the given status should be
+    /// resent after the token it was sent with has been refreshed.
+    fn should_resend(&self, status: StatusCode, sent: &Option<String>) -> bool {
+        if !self.expired.contains(&status) {
+            return false;
+        }
+        debug!(status = status.as_u16(), "CSRF token was rejected");
+        let token = self.token();
+        token.is_some() && token != *sent
+    }
+}
+
+impl<C: Client> CsrfClient<C> {
+    /// Fetches a new token from the `bootstrap` endpoint, if configured.
+    async fn fetch(&self) -> Result<(), ClientError> {
+        let defaults = client_defaults(&self.client);
+        if let Some(req) = self.bootstrap_request(self.client.base(), &defaults) {
+            let mut req = req?;
+            if let Some(auth) = self.client.auth() {
+                auth.apply(&mut req).await?;
+            }
+            trace!("Fetching CSRF token");
+            let resp = self.client.send(req).await?;
+            self.capture_bootstrap(&resp);
+        }
+        Ok(())
+    }
+}
+
+#[cfg_attr(not(feature = "non-send"), async_trait)]
+#[cfg_attr(feature = "non-send", async_trait(?Send))]
+impl<C: Client> Client for CsrfClient<C> {
+    #[cfg_attr(feature = "tracing", instrument(skip(self, req), err))]
+    async fn send(&self, req: Request<Bytes>) -> Result<Response<Bytes>, ClientError> {
+        if !is_mutating(req.method()) {
+            let resp = self.client.send(req).await?;
+            self.capture(resp.headers());
+            return Ok(resp);
+        }
+
+        if self.token().is_none() {
+            self.fetch().await?;
+        }
+        let sent = self.token();
+        let resp = self.client.send(self.with_token(&req)?).await?;
+        self.capture(resp.headers());
+        if self.expired.contains(&resp.status()) && self.token() == sent {
+            self.fetch().await?;
+        }
+        if !self.should_resend(resp.status(), &sent) {
+            return Ok(resp);
+        }
+
+        let resp = self.client.send(self.with_token(&req)?).await?;
+        self.capture(resp.headers());
+        Ok(resp)
+    }
+
+    async fn send_stream(&self, req: Request<Bytes>) -> Result<Response<ByteStream>, ClientError> {
+        if !is_mutating(req.method()) {
+            let resp = self.client.send_stream(req).await?;
+            self.capture(resp.headers());
+            return Ok(resp);
+        }
+
+        if self.token().is_none() {
+            self.fetch().await?;
+        }
+        let sent = self.token();
+        let resp = self.client.send_stream(self.with_token(&req)?).await?;
+        self.capture(resp.headers());
+        if self.expired.contains(&resp.status()) && self.token() == sent {
+            self.fetch().await?;
+        }
+        if !self.should_resend(resp.status(), &sent) {
+            return Ok(resp);
+        }
+
+        let resp = self.client.send_stream(self.with_token(&req)?).await?;
+        self.capture(resp.headers());
+        Ok(resp)
+    }
+
+    fn base(&self) -> &str {
+        self.client.base()
+    }
+
+    fn drain(&self) -> Option<&Drain> {
+        self.client.drain()
+    }
+
+    fn auth(&self) -> Option<&dyn AuthProvider> {
+        self.client.auth()
+    }
+
+    fn metrics(&self) -> Option<&dyn MetricsObserver> {
+        self.client.metrics()
+    }
+
+    fn retry_policy(&self) -> Option<&RetryPolicy> {
+        self.client.retry_policy()
+    }
+
+    fn success_codes(&self) -> Option<&SuccessCodes> {
+        self.client.success_codes()
+    }
+
+    fn default_headers(&self) -> Option<&HeaderMap> {
+        self.client.default_headers()
+    }
+
+    fn default_query(&self) -> Option<&[(String, String)]> {
+        self.client.default_query()
+    }
+
+    fn url_join(&self) -> UrlJoin {
+        self.client.url_join()
+    }
+
+    fn mutate_request<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        req: &mut Request<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.client.mutate_request(endpoint, req)
+    }
+
+    fn mutate_response<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        resp: &mut Response<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.client.mutate_response(endpoint, resp)
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl<C: BlockingClient> CsrfClient<C> {
+    /// Fetches a new token from the `bootstrap` endpoint, if configured.
+    fn fetch_blocking(&self) -> Result<(), ClientError> {
+        let defaults = crate::endpoint::blocking_defaults(&self.client);
+        if let Some(req) = self.bootstrap_request(self.client.base(), &defaults) {
+            let mut req = req?;
+            if let Some(auth) = self.client.auth() {
+                crate::blocking::client::apply_auth(auth, &mut req)?;
+            }
+            trace!("Fetching CSRF token");
+            let resp = self.client.send(req)?;
+            self.capture_bootstrap(&resp);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl<C: BlockingClient> BlockingClient for CsrfClient<C> {
+    #[cfg_attr(feature = "tracing", instrument(skip(self, req), err))]
+    fn send(&self, req: Request<Bytes>) -> Result<Response<Bytes>, ClientError> {
+        if !is_mutating(req.method()) {
+            let resp = self.client.send(req)?;
+            self.capture(resp.headers());
+            return Ok(resp);
+        }
+
+        if self.token().is_none() {
+            self.fetch_blocking()?;
+        }
+        let sent = self.token();
+        let resp = self.client.send(self.with_token(&req)?)?;
+        self.capture(resp.headers());
+        if self.expired.contains(&resp.status()) && self.token() == sent {
+            self.fetch_blocking()?;
+        }
+        if !self.should_resend(resp.status(), &sent) {
+            return Ok(resp);
+        }
+
+        let resp = self.client.send(self.with_token(&req)?)?;
+        self.capture(resp.headers());
+        Ok(resp)
+    }
+
+    fn base(&self) -> &str {
+        self.client.base()
+    }
+
+    fn drain(&self) -> Option<&Drain> {
+        self.client.drain()
+    }
+
+    fn auth(&self) -> Option<&dyn AuthProvider> {
+        self.client.auth()
+    }
+
+    fn metrics(&self) -> Option<&dyn MetricsObserver> {
+        self.client.metrics()
+    }
+
+    fn retry_policy(&self) -> Option<&RetryPolicy> {
+        self.client.retry_policy()
+    }
+
+    fn success_codes(&self) -> Option<&SuccessCodes> {
+        self.client.success_codes()
+    }
+
+    fn default_headers(&self) -> Option<&HeaderMap> {
+        self.client.default_headers()
+    }
+
+    fn default_query(&self) -> Option<&[(String, String)]> {
+        self.client.default_query()
+    }
+
+    fn url_join(&self) -> UrlJoin {
+        self.client.url_join()
+    }
+
+    fn mutate_request<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        req: &mut Request<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.client.mutate_request(endpoint, req)
+    }
+
+    fn mutate_response<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        resp: &mut Response<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.client.mutate_response(endpoint, resp)
+    }
+}
+
+/// Returns whether requests with the given method must carry a token.
+fn is_mutating(method: &Method) -> bool {
+    !matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE
+    )
+}
+
+/// Returns the value of the cookie with the given name set by the given
+/// response headers, ignoring cookies which are being cleared.
+fn cookie(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get_all(SET_COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .filter_map(|v| v.split(';').next()?.split_once('='))
+        .filter(|(n, v)| n.trim() == name && !v.trim().is_empty())
+        .map(|(_, v)| v.trim().trim_matches('"').to_string())
+        .next_back()
+}
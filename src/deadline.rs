@@ -0,0 +1,136 @@
+//! Contains [MiddleWare] for propagating a caller's deadline to downstream
+//! services.
+use crate::{
+    endpoint::{Endpoint, MiddleWare},
+    errors::ClientError,
+};
+use bytes::Bytes;
+use http::{header::HeaderName, HeaderValue, Request, Response};
+use std::time::{Duration, Instant};
+
+/// The format used when writing the remaining time budget into a header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeadlineFormat {
+    /// The remaining time as a whole number of milliseconds (i.e. `1500`).
+    Millis,
+    /// The remaining time in the `grpc-timeout` format: at most eight digits
+    /// followed by a unit (i.e. `1500m` or `30S`).
+    Grpc,
+}
+
+/// A [MiddleWare] which injects the time remaining until a caller-supplied
+/// deadline into every request, allowing downstream services to honor the
+/// caller's time budget.
+///
+/// The remaining time is computed when the request is built, so each attempt
+/// of a retried request advertises a smaller budget. If the deadline has
+/// already passed the request is not sent and [ClientError::Timeout] is
+/// returned instead.
+///
+/// # Example
+/// ```
+/// use rustify::deadline::Deadline;
+/// use std::time::Duration;
+///
+/// // Sends `X-Request-Timeout: <remaining milliseconds>`
+/// let deadline = Deadline::after(Duration::from_secs(5));
+///
+/// // Sends `grpc-timeout: <remaining><unit>`
+/// let deadline = Deadline::after(Duration::from_secs(5)).grpc();
+/// ```
+#[derive(Clone, Debug)]
+pub struct Deadline {
+    /// The point in time by which the caller needs a response.
+    pub deadline: Instant,
+    /// The header the remaining time is written to.
+    pub header: HeaderName,
+    /// The format the remaining time is written in.
+    pub format: DeadlineFormat,
+}
+
+impl Deadline {
+    /// Returns a new [Deadline] for the given instant which writes the
+    /// remaining milliseconds to the `X-Request-Timeout` header.
+    pub fn new(deadline: Instant) -> Self {
+        Deadline {
+            deadline,
+            header: HeaderName::from_static("x-request-timeout"),
+            format: DeadlineFormat::Millis,
+        }
+    }
+
+    /// Returns a new [Deadline] which expires after the given duration.
+    pub fn after(budget: Duration) -> Self {
+        Deadline::new(Instant::now() + budget)
+    }
+
+    /// Writes the remaining time to the `grpc-timeout` header using the gRPC
+    /// timeout format.
+    pub fn grpc(mut self) -> Self {
+        self.header = HeaderName::from_static("grpc-timeout");
+        self.format = DeadlineFormat::Grpc;
+        self
+    }
+
+    /// Writes the remaining time to the given header.
+    pub fn header(mut self, header: HeaderName) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// Writes the remaining time using the given format.
+    pub fn format(mut self, format: DeadlineFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Returns the time remaining until the deadline, or [None] if it has
+    /// already passed.
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline
+            .checked_duration_since(Instant::now())
+            .filter(|d| !d.is_zero())
+    }
+}
+
+impl MiddleWare for Deadline {
+    fn request<E: Endpoint>(&self, _: &E, req: &mut Request<Bytes>) -> Result<(), ClientError> {
+        let remaining = self.remaining().ok_or(ClientError::Timeout {
+            timeout: Duration::ZERO,
+        })?;
+        let value = match self.format {
+            DeadlineFormat::Millis => remaining.as_millis().to_string(),
+            DeadlineFormat::Grpc => grpc_timeout(remaining),
+        };
+        req.headers_mut().insert(
+            self.header.clone(),
+            HeaderValue::from_str(&value).expect("timeout is a valid header value"),
+        );
+        Ok(())
+    }
+
+    fn response<E: Endpoint>(&self, _: &E, _: &mut Response<Bytes>) -> Result<(), ClientError> {
+        Ok(())
+    }
+}
+
+/// Formats the given duration as a `grpc-timeout` value using the most
+/// precise unit which fits within the eight digit limit.
+fn grpc_timeout(remaining: Duration) -> String {
+    let nanos = remaining.as_nanos();
+    let units = [
+        (1, 'n'),
+        (1_000, 'u'),
+        (1_000_000, 'm'),
+        (1_000_000_000, 'S'),
+        (60_000_000_000, 'M'),
+        (3_600_000_000_000, 'H'),
+    ];
+    for (scale, unit) in units.iter() {
+        let value = nanos / scale;
+        if value < 100_000_000 {
+            return format!("{}{}", value, unit);
+        }
+    }
+    "99999999H".to_string()
+}
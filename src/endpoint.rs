@@ -1,17 +1,26 @@
 //! Contains the [Endpoint] trait and supporting traits/functions.
 
-use std::marker::PhantomData;
+use std::{marker::PhantomData, time::Duration};
 
 #[cfg(feature = "blocking")]
 use crate::blocking::client::Client as BlockingClient;
 use crate::{
-    client::Client,
+    client::{hold, register, ByteStream, Client, RawErrorBody, Registered, SuccessCodes},
+    correlation::ServerRequestId,
     enums::{RequestMethod, RequestType, ResponseType},
-    errors::ClientError,
+    errors::{ClientError, EndpointError},
+    http::{ByteRange, Progress, UrlJoin},
+    marker::MaybeSendSync,
+    metrics::PathTemplate,
+    redirect::Redirects,
+    retry::RetryPolicy,
 };
 use async_trait::async_trait;
-use http::{Request, Response};
-use serde::de::DeserializeOwned;
+use bytes::Bytes;
+use http::{HeaderMap, HeaderName, HeaderValue, Request, Response, StatusCode};
+use serde::de::{value::StrDeserializer, DeserializeOwned, IntoDeserializer};
+use tokio::io::AsyncWrite;
+use tokio_util::sync::CancellationToken;
 
 /// Represents a generic wrapper that can be applied to [Endpoint] results.
 ///
@@ -21,16 +30,34 @@ use serde::de::DeserializeOwned;
 /// [EndpointResult::wrap] to automatically wrap the [Endpoint::Response] in the
 /// wrapper. The only requirement is that the [Wrapper::Value] must enclose
 /// the [Endpoint::Response].
-pub trait Wrapper: DeserializeOwned + Send + Sync {
+///
+/// Wrappers nested deep inside an envelope can set [Wrapper::POINTER] to a
+/// JSON pointer (i.e. `/data/items`) locating the value the wrapper is
+/// deserialized from, which avoids defining a struct for each level of the
+/// envelope.
+pub trait Wrapper: DeserializeOwned + MaybeSendSync {
     type Value;
+
+    /// A JSON pointer to the value within the response that this wrapper is
+    /// deserialized from. The entire response is used when [None].
+    const POINTER: Option<&'static str> = None;
+
+    /// A JSON pointer to the cursor of the next page within the response
+    /// (i.e. `/meta/next_cursor`). Used by
+    /// [CursorPaginator][crate::pagination::CursorPaginator] to request the
+    /// following pages.
+    const CURSOR: Option<&'static str> = None;
 }
 
 /// Represents an [Endpoint] that has had [MiddleWare] applied to it.
 ///
 /// This type wraps [Endpoint] by implementng it. The primary difference is
 /// when `exec` is called the request and response will potentially be mutated
-/// before processing. Only one [MiddleWare] can be applied to a single
-/// [Endpoint].
+/// before processing.
+///
+/// Multiple [MiddleWare] can be chained by calling `with_middleware` more than
+/// once. Requests are passed through each [MiddleWare] in the order they were
+/// applied while responses are passed through in the reverse order.
 pub struct MutatedEndpoint<'a, E: Endpoint, M: MiddleWare> {
     endpoint: E,
     middleware: &'a M,
@@ -46,11 +73,18 @@ impl<'a, E: Endpoint, M: MiddleWare> MutatedEndpoint<'a, E, M> {
     }
 }
 
-#[async_trait]
+#[cfg_attr(not(feature = "non-send"), async_trait)]
+#[cfg_attr(feature = "non-send", async_trait(?Send))]
 impl<E: Endpoint, M: MiddleWare> Endpoint for MutatedEndpoint<'_, E, M> {
     type Response = E::Response;
     const REQUEST_BODY_TYPE: RequestType = E::REQUEST_BODY_TYPE;
     const RESPONSE_BODY_TYPE: ResponseType = E::RESPONSE_BODY_TYPE;
+    const STRICT_PARSING: bool = E::STRICT_PARSING;
+    const PATH_TEMPLATE: Option<&'static str> = E::PATH_TEMPLATE;
+
+    fn name() -> &'static str {
+        E::name()
+    }
 
     fn path(&self) -> String {
         self.endpoint.path()
@@ -68,50 +102,349 @@ impl<E: Endpoint, M: MiddleWare> Endpoint for MutatedEndpoint<'_, E, M> {
         self.endpoint.body()
     }
 
-    #[instrument(skip(self), err)]
+    fn headers(&self) -> Result<Vec<(HeaderName, HeaderValue)>, ClientError> {
+        self.endpoint.headers()
+    }
+
+    fn sensitive(&self) -> Vec<String> {
+        self.endpoint.sensitive()
+    }
+
+    fn accept(&self) -> Option<&str> {
+        self.endpoint.accept()
+    }
+
+    fn success_codes(&self) -> Option<SuccessCodes> {
+        self.endpoint.success_codes()
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.endpoint.timeout()
+    }
+
+    fn retry_policy(&self) -> Option<RetryPolicy> {
+        self.endpoint.retry_policy()
+    }
+
+    #[cfg_attr(feature = "tracing", instrument(skip(self), err))]
     fn url(&self, base: &str) -> Result<http::Uri, ClientError> {
         self.endpoint.url(base)
     }
 
-    #[instrument(skip(self), err)]
-    fn request(&self, base: &str) -> Result<Request<Vec<u8>>, ClientError> {
-        let mut req = crate::http::build_request(
-            base,
-            &self.path(),
-            self.method(),
-            self.query()?,
-            self.body()?,
-        )?;
-
+    #[cfg_attr(feature = "tracing", instrument(skip(self, defaults), err))]
+    fn request_with(
+        &self,
+        base: &str,
+        defaults: &RequestDefaults,
+    ) -> Result<Request<Bytes>, ClientError> {
+        let mut req = self.endpoint.request_with(base, defaults)?;
         self.middleware.request(self, &mut req)?;
         Ok(req)
     }
 
-    // TODO: remove the allow when the upstream clippy issue is fixed:
-    // <https://github.com/rust-lang/rust-clippy/issues/12281>
+    fn mutate_response(&self, resp: &mut Response<Bytes>) -> Result<(), ClientError> {
+        self.middleware.response(self, resp)?;
+        self.endpoint.mutate_response(resp)
+    }
+}
+
+impl<E: ErrorEndpoint, M: MiddleWare> ErrorEndpoint for MutatedEndpoint<'_, E, M> {
+    type Error = E::Error;
+}
+
+/// Represents an [Endpoint] whose concrete type has been erased.
+///
+/// Endpoints of different types can be wrapped in a [DynEndpoint] so that
+/// they can be stored and executed together, for example when replaying a
+/// recorded sequence of API calls or when passing them to
+/// [exec_batch][crate::batch::exec_batch]. The request is built using the
+/// wrapped endpoint and the response is parsed into a [serde_json::Value]
+/// using the [ResponseType] of the wrapped endpoint.
+///
+/// # Example
+/// ```
+/// use rustify::clients::reqwest::Client;
+/// use rustify::endpoint::{DynEndpoint, Endpoint};
+/// use rustify_derive::Endpoint;
+///
+/// #[derive(Endpoint)]
+/// #[endpoint(path = "users")]
+/// struct ListUsers {}
+///
+/// #[derive(Endpoint)]
+/// #[endpoint(path = "groups")]
+/// struct ListGroups {}
+///
+/// let endpoints = vec![DynEndpoint::new(ListUsers {}), DynEndpoint::new(ListGroups {})];
+///
+/// # tokio_test::block_on(async {
+/// let client = Client::default("http://myapi.com");
+/// for endpoint in endpoints.iter() {
+///     let result = endpoint.exec(&client).await;
+/// }
+/// # })
+/// ```
+pub struct DynEndpoint {
+    endpoint: Box<dyn ErasedEndpoint>,
+}
+
+impl DynEndpoint {
+    /// Returns a new [DynEndpoint] wrapping the given endpoint.
+    pub fn new<E: Endpoint + 'static>(endpoint: E) -> Self {
+        DynEndpoint {
+            endpoint: Box::new(endpoint),
+        }
+    }
+
+    /// Returns the [ResponseType] of the wrapped endpoint.
+    pub fn response_type(&self) -> ResponseType {
+        self.endpoint.response_type()
+    }
+
+    /// Executes the wrapped endpoint using its [RetryPolicy], falling back to
+    /// the policy of the client.
+    async fn exec_result(
+        &self,
+        client: &impl Client,
+    ) -> Result<EndpointResult<serde_json::Value>, ClientError> {
+        let _guard = register(client.drain())?;
+        let client = &Registered(client);
+        let policy = self.endpoint.retry_policy();
+        let policy = policy.as_ref().or_else(|| client.retry_policy());
+        let resp = crate::retry::retry(policy, || exec(client, self, false)).await?;
+        Ok(self.result(resp))
+    }
+
+    /// Returns an [EndpointResult] for the given response using the settings
+    /// of the wrapped endpoint.
+    fn result(&self, resp: Response<Bytes>) -> EndpointResult<serde_json::Value> {
+        let result = EndpointResult::new(resp, self.endpoint.response_type());
+        match self.endpoint.strict() {
+            true => result.strict(),
+            false => result,
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "non-send"), async_trait)]
+#[cfg_attr(feature = "non-send", async_trait(?Send))]
+impl Endpoint for DynEndpoint {
+    type Response = serde_json::Value;
+    const REQUEST_BODY_TYPE: RequestType = RequestType::JSON;
+    const RESPONSE_BODY_TYPE: ResponseType = ResponseType::JSON;
+
+    fn path(&self) -> String {
+        self.endpoint.path()
+    }
+
+    fn method(&self) -> RequestMethod {
+        self.endpoint.method()
+    }
+
+    fn query(&self) -> Result<Option<String>, ClientError> {
+        self.endpoint.query()
+    }
+
+    fn body(&self) -> Result<Option<Vec<u8>>, ClientError> {
+        self.endpoint.body()
+    }
+
+    fn headers(&self) -> Result<Vec<(HeaderName, HeaderValue)>, ClientError> {
+        self.endpoint.headers()
+    }
+
+    fn sensitive(&self) -> Vec<String> {
+        self.endpoint.sensitive()
+    }
+
+    fn accept(&self) -> Option<&str> {
+        self.endpoint.accept()
+    }
+
+    fn success_codes(&self) -> Option<SuccessCodes> {
+        self.endpoint.success_codes()
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.endpoint.timeout()
+    }
+
+    fn retry_policy(&self) -> Option<RetryPolicy> {
+        self.endpoint.retry_policy()
+    }
+
+    #[cfg_attr(feature = "tracing", instrument(skip(self), err))]
+    fn url(&self, base: &str) -> Result<http::Uri, ClientError> {
+        self.endpoint.url(base)
+    }
+
+    #[cfg_attr(feature = "tracing", instrument(skip(self, defaults), err))]
+    fn request_with(
+        &self,
+        base: &str,
+        defaults: &RequestDefaults,
+    ) -> Result<Request<Bytes>, ClientError> {
+        self.endpoint.request_with(base, defaults)
+    }
+
+    fn mutate_response(&self, resp: &mut Response<Bytes>) -> Result<(), ClientError> {
+        self.endpoint.mutate_response(resp)
+    }
+
     #[allow(clippy::blocks_in_conditions)]
-    #[instrument(skip(self, client), err)]
+    #[cfg_attr(feature = "tracing", instrument(skip(self, client), err))]
     async fn exec(
         &self,
         client: &impl Client,
     ) -> Result<EndpointResult<Self::Response>, ClientError> {
         trace!("Executing endpoint");
 
-        let req = self.request(client.base())?;
-        let resp = exec_mut(client, self, req, self.middleware).await?;
-        Ok(EndpointResult::new(resp, Self::RESPONSE_BODY_TYPE))
+        match self.endpoint.timeout() {
+            Some(t) => self.exec_with_timeout(client, t).await,
+            None => self.exec_result(client).await,
+        }
+    }
+
+    #[allow(clippy::blocks_in_conditions)]
+    #[cfg_attr(feature = "tracing", instrument(skip(self, client, policy), err))]
+    async fn exec_with_retry(
+        &self,
+        client: &impl Client,
+        policy: &RetryPolicy,
+    ) -> Result<EndpointResult<Self::Response>, ClientError> {
+        trace!("Executing endpoint");
+
+        let _guard = register(client.drain())?;
+        let client = &Registered(client);
+        let resp = crate::retry::retry(Some(policy), || exec(client, self, false)).await?;
+        Ok(self.result(resp))
+    }
+
+    #[allow(clippy::blocks_in_conditions)]
+    #[cfg_attr(feature = "tracing", instrument(skip(self, client), err))]
+    async fn exec_with_timeout(
+        &self,
+        client: &impl Client,
+        timeout: Duration,
+    ) -> Result<EndpointResult<Self::Response>, ClientError> {
+        tokio::time::timeout(timeout, self.exec_result(client))
+            .await
+            .map_err(|_| ClientError::Timeout { timeout })?
     }
 
     #[cfg(feature = "blocking")]
+    #[cfg_attr(feature = "tracing", instrument(skip(self, client), err))]
     fn exec_block(
         &self,
         client: &impl BlockingClient,
     ) -> Result<EndpointResult<Self::Response>, ClientError> {
         trace!("Executing endpoint");
 
-        let req = self.request(client.base())?;
-        let resp = exec_block_mut(client, self, req, self.middleware)?;
-        Ok(EndpointResult::new(resp, Self::RESPONSE_BODY_TYPE))
+        match self.endpoint.timeout() {
+            Some(t) => self.exec_block_with_timeout(client, t),
+            None => exec_block_deadline(client, self, None, false).map(|r| self.result(r)),
+        }
+    }
+
+    #[cfg(feature = "blocking")]
+    #[cfg_attr(feature = "tracing", instrument(skip(self, client), err))]
+    fn exec_block_with_timeout(
+        &self,
+        client: &impl BlockingClient,
+        timeout: Duration,
+    ) -> Result<EndpointResult<Self::Response>, ClientError> {
+        trace!("Executing endpoint");
+
+        exec_block_deadline(client, self, Some(timeout), false).map(|r| self.result(r))
+    }
+}
+
+/// An object safe subset of [Endpoint] used by [DynEndpoint].
+trait ErasedEndpoint: MaybeSendSync {
+    fn path(&self) -> String;
+    fn method(&self) -> RequestMethod;
+    fn query(&self) -> Result<Option<String>, ClientError>;
+    fn body(&self) -> Result<Option<Vec<u8>>, ClientError>;
+    fn headers(&self) -> Result<Vec<(HeaderName, HeaderValue)>, ClientError>;
+    fn sensitive(&self) -> Vec<String>;
+    fn accept(&self) -> Option<&str>;
+    fn success_codes(&self) -> Option<SuccessCodes>;
+    fn timeout(&self) -> Option<Duration>;
+    fn retry_policy(&self) -> Option<RetryPolicy>;
+    fn url(&self, base: &str) -> Result<http::Uri, ClientError>;
+    fn request_with(
+        &self,
+        base: &str,
+        defaults: &RequestDefaults,
+    ) -> Result<Request<Bytes>, ClientError>;
+    fn mutate_response(&self, resp: &mut Response<Bytes>) -> Result<(), ClientError>;
+    fn response_type(&self) -> ResponseType;
+    fn strict(&self) -> bool;
+}
+
+impl<E: Endpoint> ErasedEndpoint for E {
+    fn path(&self) -> String {
+        Endpoint::path(self)
+    }
+
+    fn method(&self) -> RequestMethod {
+        Endpoint::method(self)
+    }
+
+    fn query(&self) -> Result<Option<String>, ClientError> {
+        Endpoint::query(self)
+    }
+
+    fn body(&self) -> Result<Option<Vec<u8>>, ClientError> {
+        Endpoint::body(self)
+    }
+
+    fn headers(&self) -> Result<Vec<(HeaderName, HeaderValue)>, ClientError> {
+        Endpoint::headers(self)
+    }
+
+    fn sensitive(&self) -> Vec<String> {
+        Endpoint::sensitive(self)
+    }
+
+    fn accept(&self) -> Option<&str> {
+        Endpoint::accept(self)
+    }
+
+    fn success_codes(&self) -> Option<SuccessCodes> {
+        Endpoint::success_codes(self)
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        Endpoint::timeout(self)
+    }
+
+    fn retry_policy(&self) -> Option<RetryPolicy> {
+        Endpoint::retry_policy(self)
+    }
+
+    fn url(&self, base: &str) -> Result<http::Uri, ClientError> {
+        Endpoint::url(self, base)
+    }
+
+    fn request_with(
+        &self,
+        base: &str,
+        defaults: &RequestDefaults,
+    ) -> Result<Request<Bytes>, ClientError> {
+        Endpoint::request_with(self, base, defaults)
+    }
+
+    fn mutate_response(&self, resp: &mut Response<Bytes>) -> Result<(), ClientError> {
+        Endpoint::mutate_response(self, resp)
+    }
+
+    fn response_type(&self) -> ResponseType {
+        E::RESPONSE_BODY_TYPE
+    }
+
+    fn strict(&self) -> bool {
+        E::STRICT_PARSING
     }
 }
 
@@ -132,7 +465,8 @@ impl<E: Endpoint, M: MiddleWare> Endpoint for MutatedEndpoint<'_, E, M> {
 /// serialized and sent to the remote server. Where and how each field appears
 /// in the final request is determined by how they are tagged with attributes.
 /// For example, fields with `#[endpoint(query)]` will show up as a query
-/// parameter and fields with `#[endpoint(body)]` will show up in the body in
+/// parameter, fields with `#[endpoint(header)]` will show up as a request
+/// header, and fields with `#[endpoint(body)]` will show up in the body in
 /// the format specified by [Endpoint::REQUEST_BODY_TYPE]. By default, if no
 /// fields are tagged with `#[endpoint(body)]` or `#[endpoint(raw)]` then any
 /// untagged fields are assumed to be tagged with `#[endpoint(body)]` (this
@@ -172,12 +506,13 @@ impl<E: Endpoint, M: MiddleWare> Endpoint for MutatedEndpoint<'_, E, M> {
 /// let result = endpoint.exec(&client).await;
 /// # })
 /// ```
-#[async_trait]
-pub trait Endpoint: Send + Sync + Sized {
+#[cfg_attr(not(feature = "non-send"), async_trait)]
+#[cfg_attr(feature = "non-send", async_trait(?Send))]
+pub trait Endpoint: MaybeSendSync + Sized {
     /// The type that the raw response from executing this endpoint will
     /// deserialized into. This type is passed on to the [EndpointResult] and is
     /// used to determine the type returned when the `parse()` method is called.
-    type Response: DeserializeOwned + Send + Sync;
+    type Response: DeserializeOwned + MaybeSendSync;
 
     /// The content type of the request body
     const REQUEST_BODY_TYPE: RequestType;
@@ -185,6 +520,29 @@ pub trait Endpoint: Send + Sync + Sized {
     /// The content type of the response body
     const RESPONSE_BODY_TYPE: ResponseType;
 
+    /// Whether responses are parsed using [EndpointResult::strict]. The
+    /// derive macro sets this when the `strict` parameter is passed (i.e.
+    /// `strict = "true"`).
+    const STRICT_PARSING: bool = false;
+
+    /// The relative URL path of this Endpoint before any interpolation (i.e.
+    /// `user/{name}`). This is attached to the extensions of the [Request] as a
+    /// [PathTemplate] so that metrics can group requests by endpoint rather
+    /// than by their fully interpolated path. The derive macro sets this from
+    /// the `path` parameter.
+    const PATH_TEMPLATE: Option<&'static str> = None;
+
+    /// The name of the operation this Endpoint performs (i.e. `CreateUser`),
+    /// which is recorded in the `endpoint` field of the spans created when
+    /// executing it. Defaults to the type name of the Endpoint.
+    ///
+    /// The derive macro overrides this method with the name of the struct, or
+    /// with the `operation_id` parameter when passed (i.e.
+    /// `operation_id = "users.create"`).
+    fn name() -> &'static str {
+        std::any::type_name::<Self>()
+    }
+
     /// The relative URL path that represents the location of this Endpoint.
     /// This is combined with the base URL from a
     /// [Client][crate::client::Client] instance to create the fully qualified
@@ -204,57 +562,580 @@ pub trait Endpoint: Send + Sync + Sized {
         Ok(None)
     }
 
+    /// Optional headers to add to the request.
+    ///
+    /// The returned headers are merged into the [Request] produced by
+    /// [Endpoint::request]. The derive macro implements this method for any
+    /// fields tagged with `#[endpoint(header)]` as well as any static headers
+    /// passed to the `headers` parameter.
+    ///
+    /// # Example
+    /// ```
+    /// use http::{HeaderName, HeaderValue};
+    /// use rustify::endpoint::Endpoint;
+    /// use rustify::enums::{RequestMethod, RequestType, ResponseType};
+    /// use rustify::errors::ClientError;
+    ///
+    /// struct MyEndpoint {
+    ///     token: String,
+    /// }
+    ///
+    /// impl Endpoint for MyEndpoint {
+    ///     type Response = ();
+    ///     const REQUEST_BODY_TYPE: RequestType = RequestType::JSON;
+    ///     const RESPONSE_BODY_TYPE: ResponseType = ResponseType::JSON;
+    ///
+    ///     fn path(&self) -> String {
+    ///         String::from("my/endpoint")
+    ///     }
+    ///
+    ///     fn method(&self) -> RequestMethod {
+    ///         RequestMethod::GET
+    ///     }
+    ///
+    ///     fn headers(&self) -> Result<Vec<(HeaderName, HeaderValue)>, ClientError> {
+    ///         Ok(vec![rustify::http::build_header("X-API-Token", &self.token)?])
+    ///     }
+    /// }
+    ///
+    /// let endpoint = MyEndpoint { token: "mytoken".to_string() };
+    /// let req = endpoint.request("http://myapi.com").unwrap();
+    /// assert_eq!(req.headers()["X-API-Token"], "mytoken");
+    /// ```
+    fn headers(&self) -> Result<Vec<(HeaderName, HeaderValue)>, ClientError> {
+        Ok(Vec::new())
+    }
+
+    /// The values of any fields which must not appear in logs or exports,
+    /// such as passwords and tokens.
+    ///
+    /// The values are attached to the extensions of the [Request] produced by
+    /// [Endpoint::request] as [Sensitive][crate::http::Sensitive] and are
+    /// replaced with [REDACTED][crate::http::REDACTED] wherever they appear in
+    /// the spans of [Client::execute], [Endpoint::to_curl] and request
+    /// snapshots. The derive macro implements this method for any fields
+    /// tagged with `#[endpoint(sensitive)]`.
+    fn sensitive(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// The value of the `Accept` header sent with the request.
+    ///
+    /// Defaults to the media type of [Endpoint::RESPONSE_BODY_TYPE]. The
+    /// derive macro overrides this method when the `accept` parameter is
+    /// passed (i.e. `accept = "application/vnd.github+json"`).
+    fn accept(&self) -> Option<&str> {
+        Self::RESPONSE_BODY_TYPE.accept()
+    }
+
+    /// The response codes which indicate a successful execution of this
+    /// endpoint.
+    ///
+    /// Defaults to [HTTP_SUCCESS_CODES][crate::client::HTTP_SUCCESS_CODES].
+    /// The derive macro overrides this method when the `success` parameter is
+    /// passed (i.e. `success = "200..=204, 404"`).
+    fn success_codes(&self) -> Option<SuccessCodes> {
+        None
+    }
+
+    /// The default timeout applied when executing this endpoint with `exec`
+    /// or `exec_block`. The timeout can be overridden by executing with
+    /// `exec_with_timeout` or `exec_block_with_timeout` instead.
+    ///
+    /// The derive macro overrides this method when the `timeout_ms` parameter
+    /// is passed (i.e. `timeout_ms = "5000"`).
+    fn timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    /// The [RetryPolicy] used when executing this endpoint, which takes
+    /// precedence over the policy of the client. The policy can be overridden
+    /// by executing with `exec_with_retry` instead.
+    ///
+    /// The derive macro overrides this method when the `retries` or
+    /// `retry_on` parameters are passed (i.e. `retries = "3"` and
+    /// `retry_on = "502, 503"`).
+    fn retry_policy(&self) -> Option<RetryPolicy> {
+        None
+    }
+
     /// Returns the full URL address of the endpoint using the base address.
-    #[instrument(skip(self), err)]
+    #[cfg_attr(feature = "tracing", instrument(skip(self), err))]
     fn url(&self, base: &str) -> Result<http::Uri, ClientError> {
         crate::http::build_url(base, &self.path(), self.query()?)
     }
 
     /// Returns a [Request] containing all data necessary to execute against
     /// this endpoint.
-    #[instrument(skip(self), err)]
-    fn request(&self, base: &str) -> Result<Request<Vec<u8>>, ClientError> {
-        crate::http::build_request(
+    ///
+    /// This is a shorthand for calling [Endpoint::request_with] without any
+    /// [RequestDefaults].
+    #[cfg_attr(feature = "tracing", instrument(skip(self), err))]
+    fn request(&self, base: &str) -> Result<Request<Bytes>, ClientError> {
+        self.request_with(base, &RequestDefaults::default())
+    }
+
+    /// Returns a [Request] containing all data necessary to execute against
+    /// this endpoint, applying the given [RequestDefaults] of the [Client]
+    /// executing it.
+    ///
+    /// When the request has a body, a `Content-Type` header matching
+    /// [Endpoint::REQUEST_BODY_TYPE] is added unless one was already
+    /// provided by [Endpoint::headers]. Likewise, an `Accept` header is added
+    /// using [Endpoint::accept]. Any [Endpoint::success_codes] are attached to
+    /// the extensions of the request, along with the [Endpoint::PATH_TEMPLATE]
+    /// and [Endpoint::sensitive] values if any are set. Credentials embedded
+    /// in the base URL are sent as a Basic `Authorization` header unless the
+    /// request sets one itself.
+    ///
+    /// Clients build requests using this method, so types wrapping another
+    /// [Endpoint] (i.e. to apply [MiddleWare]) should override it rather than
+    /// [Endpoint::request] in order to see the request as it's sent.
+    #[cfg_attr(feature = "tracing", instrument(skip(self, defaults), err))]
+    fn request_with(
+        &self,
+        base: &str,
+        defaults: &RequestDefaults,
+    ) -> Result<Request<Bytes>, ClientError> {
+        let body = self.body()?;
+        let mut headers = self.headers()?;
+        if let Some(ct) = Self::REQUEST_BODY_TYPE.content_type() {
+            let has_body = body.as_ref().map(|b| !b.is_empty()).unwrap_or(false);
+            if has_body && !headers.iter().any(|(k, _)| k == http::header::CONTENT_TYPE) {
+                headers.push((http::header::CONTENT_TYPE, HeaderValue::from_static(ct)));
+            }
+        }
+
+        if let Some(accept) = self.accept() {
+            if !headers.iter().any(|(k, _)| k == http::header::ACCEPT) {
+                headers.push(crate::http::build_header(
+                    http::header::ACCEPT.as_str(),
+                    &accept,
+                )?);
+            }
+        }
+
+        let mut req = crate::http::build_request(
             base,
             &self.path(),
+            defaults.join,
             self.method(),
             self.query()?,
-            self.body()?,
-        )
+            headers,
+            body,
+        )?;
+        defaults.apply(base, &mut req)?;
+        if let Some(codes) = self.success_codes() {
+            req.extensions_mut().insert(codes);
+        }
+        if let Some(template) = Self::PATH_TEMPLATE {
+            req.extensions_mut().insert(PathTemplate(template));
+        }
+        let sensitive = self.sensitive();
+        if !sensitive.is_empty() {
+            req.extensions_mut()
+                .insert(crate::http::Sensitive(sensitive));
+        }
+        Ok(req)
+    }
+
+    /// Renders the [Request] for this endpoint as a copy-pasteable `curl`
+    /// command using the given base address.
+    ///
+    /// This is useful for reproducing a request outside of Rust. Note that
+    /// any changes made by a [Client] or [MiddleWare] are not included and
+    /// [Endpoint::sensitive] values are redacted.
+    ///
+    /// # Example
+    /// ```
+    /// use rustify::Endpoint;
+    /// use rustify_derive::Endpoint;
+    ///
+    /// #[derive(Endpoint)]
+    /// #[endpoint(path = "users", method = "POST")]
+    /// struct CreateUser {
+    ///     name: String,
+    /// }
+    ///
+    /// let endpoint = CreateUser { name: "test".to_string() };
+    /// assert_eq!(
+    ///     endpoint.to_curl("http://myapi.com").unwrap(),
+    ///     "curl -X POST 'http://myapi.com/users' \\\n  \
+    ///      -H 'accept: application/json' \\\n  \
+    ///      --data-raw '{\"name\":\"test\"}'"
+    /// );
+    /// ```
+    fn to_curl(&self, base: &str) -> Result<String, ClientError> {
+        Ok(crate::http::to_curl(&self.request(base)?))
+    }
+
+    /// Modifies the [Response] received from executing this Endpoint before
+    /// it's returned as an [EndpointResult]. This is used for applying
+    /// [MiddleWare] and does nothing by default.
+    fn mutate_response(&self, _resp: &mut Response<Bytes>) -> Result<(), ClientError> {
+        Ok(())
     }
 
     /// Executes the Endpoint using the given [Client].
     // TODO: remove the allow when the upstream clippy issue is fixed:
     // <https://github.com/rust-lang/rust-clippy/issues/12281>
     #[allow(clippy::blocks_in_conditions)]
-    #[instrument(skip(self, client), err)]
+    #[cfg_attr(feature = "tracing", instrument(skip(self, client), fields(endpoint = Self::name()), err))]
     async fn exec(
         &self,
         client: &impl Client,
     ) -> Result<EndpointResult<Self::Response>, ClientError> {
         trace!("Executing endpoint");
 
-        let req = self.request(client.base())?;
-        let resp = exec(client, req).await?;
-        Ok(EndpointResult::new(resp, Self::RESPONSE_BODY_TYPE))
+        match self.timeout() {
+            Some(t) => self.exec_with_timeout(client, t).await,
+            None => exec_result(client, self, false).await,
+        }
+    }
+
+    /// Executes the Endpoint using the given [Client], aborting the execution
+    /// with [ClientError::CancelledError] as soon as the given
+    /// [CancellationToken] is cancelled.
+    ///
+    /// Any request which is in-flight when the token is cancelled is dropped,
+    /// closing its connection.
+    #[allow(clippy::blocks_in_conditions)]
+    #[cfg_attr(feature = "tracing", instrument(skip(self, client, token), fields(endpoint = Self::name()), err))]
+    async fn exec_with_cancel(
+        &self,
+        client: &impl Client,
+        token: &CancellationToken,
+    ) -> Result<EndpointResult<Self::Response>, ClientError> {
+        token
+            .run_until_cancelled(self.exec(client))
+            .await
+            .ok_or(ClientError::CancelledError)?
+    }
+
+    /// Executes the Endpoint using the given [Client], retrying failed
+    /// executions according to the given [RetryPolicy] instead of the policy
+    /// of the client.
+    ///
+    /// Each attempt rebuilds the request from this Endpoint, so any
+    /// [MiddleWare] is applied again on every attempt.
+    #[allow(clippy::blocks_in_conditions)]
+    #[cfg_attr(feature = "tracing", instrument(skip(self, client, policy), fields(endpoint = Self::name()), err))]
+    async fn exec_with_retry(
+        &self,
+        client: &impl Client,
+        policy: &RetryPolicy,
+    ) -> Result<EndpointResult<Self::Response>, ClientError> {
+        trace!("Executing endpoint");
+
+        let _guard = register(client.drain())?;
+        let client = &Registered(client);
+        let resp = crate::retry::retry(Some(policy), || exec(client, self, false)).await?;
+        Ok(endpoint_result::<Self>(resp))
+    }
+
+    /// Executes the Endpoint using the given [Client], failing with
+    /// [ClientError::Timeout] if the execution, including any retries, doesn't
+    /// complete within the given duration. The given duration takes
+    /// precedence over [Endpoint::timeout].
+    #[allow(clippy::blocks_in_conditions)]
+    #[cfg_attr(feature = "tracing", instrument(skip(self, client), fields(endpoint = Self::name()), err))]
+    async fn exec_with_timeout(
+        &self,
+        client: &impl Client,
+        timeout: Duration,
+    ) -> Result<EndpointResult<Self::Response>, ClientError> {
+        tokio::time::timeout(timeout, exec_result(client, self, false))
+            .await
+            .map_err(|_| ClientError::Timeout { timeout })?
+    }
+
+    /// Executes the Endpoint using the given [Client] and parses the
+    /// response into the final result type.
+    ///
+    /// This is a shorthand for calling [EndpointResult::parse] on the result
+    /// of [Endpoint::exec].
+    #[allow(clippy::blocks_in_conditions)]
+    #[cfg_attr(feature = "tracing", instrument(skip(self, client), fields(endpoint = Self::name()), err))]
+    async fn exec_parse(&self, client: &impl Client) -> Result<Self::Response, ClientError> {
+        self.exec(client).await?.parse()
+    }
+
+    /// Executes the Endpoint using the given [Client], returning [None] if
+    /// the server responds with `404 Not Found`.
+    ///
+    /// This is useful for endpoints where a missing resource is an expected
+    /// outcome rather than an error.
+    #[allow(clippy::blocks_in_conditions)]
+    #[cfg_attr(feature = "tracing", instrument(skip(self, client), fields(endpoint = Self::name()), err))]
+    async fn exec_opt(
+        &self,
+        client: &impl Client,
+    ) -> Result<Option<EndpointResult<Self::Response>>, ClientError> {
+        match self.exec(client).await {
+            Ok(r) => Ok(Some(r)),
+            Err(ClientError::ServerResponseError { code: 404, .. }) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Executes the Endpoint using the given [Client] and returns a
+    /// [Response] with a streaming body.
+    ///
+    /// Unlike [Endpoint::exec], the response body is not buffered into
+    /// memory which makes this suitable for large responses. Since the body
+    /// hasn't been read, response [MiddleWare] is not applied.
+    ///
+    /// # Example
+    /// ```
+    /// use futures_util::StreamExt;
+    /// use rustify::{test::{Mock, MockClient}, Endpoint};
+    /// use rustify_derive::Endpoint;
+    ///
+    /// #[derive(Endpoint)]
+    /// #[endpoint(path = "files/{self.name}")]
+    /// struct Download {
+    ///     #[endpoint(skip)]
+    ///     name: String,
+    /// }
+    ///
+    /// # tokio_test::block_on(async {
+    /// let client = MockClient::new("http://myapi.com");
+    /// client.mock(Mock::new(http::Method::GET, "/files/data.bin").respond(vec![1, 2, 3]));
+    ///
+    /// let endpoint = Download { name: "data.bin".to_string() };
+    /// let mut body = endpoint.exec_stream(&client).await.unwrap().into_body();
+    /// while let Some(chunk) = body.next().await {
+    ///     assert_eq!(chunk.unwrap().as_ref(), &[1, 2, 3]);
+    /// }
+    /// # })
+    /// ```
+    #[allow(clippy::blocks_in_conditions)]
+    #[cfg_attr(feature = "tracing", instrument(skip(self, client), fields(endpoint = Self::name()), err))]
+    async fn exec_stream(&self, client: &impl Client) -> Result<Response<ByteStream>, ClientError> {
+        trace!("Executing endpoint");
+
+        exec_stream_result(client, self, None).await
+    }
+
+    /// Executes the Endpoint using the given [Client], requesting only the
+    /// given [ByteRange] of the response body with a `Range` header.
+    ///
+    /// Servers which support range requests respond with
+    /// `206 Partial Content` while servers which don't will typically
+    /// return the entire body with `200 OK`. See [Endpoint::exec_stream] for
+    /// details on how the response is handled.
+    #[allow(clippy::blocks_in_conditions)]
+    #[cfg_attr(feature = "tracing", instrument(skip(self, client), fields(endpoint = Self::name()), err))]
+    async fn exec_range(
+        &self,
+        client: &impl Client,
+        range: ByteRange,
+    ) -> Result<Response<ByteStream>, ClientError> {
+        trace!("Executing endpoint");
+
+        exec_stream_result(client, self, Some(&range)).await
     }
 
-    fn with_middleware<M: MiddleWare>(self, middleware: &M) -> MutatedEndpoint<Self, M> {
+    /// Resumes an interrupted download by streaming the response body into
+    /// the writer starting from `offset`, the number of bytes which were
+    /// already written. Returns the total size of the downloaded body.
+    ///
+    /// The writer is expected to be positioned after the bytes which were
+    /// already written, i.e. a file opened in append mode. If the server
+    /// ignores the `Range` header and returns the entire body, the first
+    /// `offset` bytes are skipped. The `progress` callback is called after
+    /// every chunk is written and includes the bytes already written.
+    #[allow(clippy::blocks_in_conditions)]
+    #[cfg_attr(
+        feature = "tracing",
+        instrument(skip(self, client, writer, progress), fields(endpoint = Self::name()), err)
+    )]
+    async fn exec_resume<W, F>(
+        &self,
+        client: &impl Client,
+        writer: &mut W,
+        offset: u64,
+        mut progress: F,
+    ) -> Result<u64, ClientError>
+    where
+        W: AsyncWrite + Unpin + MaybeSendSync,
+        F: FnMut(Progress) + MaybeSendSync,
+    {
+        if offset == 0 {
+            return self.exec_download(client, writer, progress).await;
+        }
+
+        let resp = match self.exec_range(client, ByteRange::from(offset)).await {
+            Ok(r) => r,
+            // The requested range starts at or after the end of the body
+            Err(ClientError::ServerResponseError { code: 416, .. }) => return Ok(offset),
+            Err(e) => return Err(e),
+        };
+
+        let length = crate::http::content_length(resp.headers());
+        let (total, body) = match resp.status() {
+            StatusCode::PARTIAL_CONTENT => (
+                crate::http::content_range_total(resp.headers())
+                    .or_else(|| length.map(|l| l + offset)),
+                resp.into_body(),
+            ),
+            _ => (length, crate::http::skip_body(resp.into_body(), offset)),
+        };
+
+        let written = crate::http::write_body(body, writer, total, |p| {
+            progress(Progress {
+                written: p.written + offset,
+                total: p.total,
+            })
+        })
+        .await?;
+        Ok(written + offset)
+    }
+
+    /// Executes the Endpoint using the given [Client] and streams the
+    /// response body into the writer. Returns the number of bytes written.
+    ///
+    /// The `progress` callback is called after every chunk is written. See
+    /// [Endpoint::exec_stream] for details on how the response is handled.
+    #[allow(clippy::blocks_in_conditions)]
+    #[cfg_attr(
+        feature = "tracing",
+        instrument(skip(self, client, writer, progress), fields(endpoint = Self::name()), err)
+    )]
+    async fn exec_download<W, F>(
+        &self,
+        client: &impl Client,
+        writer: &mut W,
+        progress: F,
+    ) -> Result<u64, ClientError>
+    where
+        W: AsyncWrite + Unpin + MaybeSendSync,
+        F: FnMut(Progress) + MaybeSendSync,
+    {
+        let resp = self.exec_stream(client).await?;
+        let total = crate::http::content_length(resp.headers());
+        crate::http::write_body(resp.into_body(), writer, total, progress).await
+    }
+
+    fn with_middleware<M: MiddleWare>(self, middleware: &M) -> MutatedEndpoint<'_, Self, M> {
         MutatedEndpoint::new(self, middleware)
     }
 
     /// Executes the Endpoint using the given [Client].
     #[cfg(feature = "blocking")]
-    #[instrument(skip(self, client), err)]
+    #[cfg_attr(feature = "tracing", instrument(skip(self, client), fields(endpoint = Self::name()), err))]
     fn exec_block(
         &self,
         client: &impl BlockingClient,
     ) -> Result<EndpointResult<Self::Response>, ClientError> {
         trace!("Executing endpoint");
 
-        let req = self.request(client.base())?;
-        let resp = exec_block(client, req)?;
-        Ok(EndpointResult::new(resp, Self::RESPONSE_BODY_TYPE))
+        match self.timeout() {
+            Some(t) => self.exec_block_with_timeout(client, t),
+            None => exec_block_deadline(client, self, None, false).map(endpoint_result::<Self>),
+        }
+    }
+
+    /// Executes the Endpoint using the given [Client], failing with
+    /// [ClientError::Timeout] if the execution, including any retries, doesn't
+    /// complete within the given duration. The given duration takes
+    /// precedence over [Endpoint::timeout].
+    ///
+    /// The remaining time is attached to each request as a
+    /// [RequestTimeout][crate::client::RequestTimeout] which the blocking
+    /// clients use to abort requests that run past the deadline.
+    #[cfg(feature = "blocking")]
+    #[cfg_attr(feature = "tracing", instrument(skip(self, client), fields(endpoint = Self::name()), err))]
+    fn exec_block_with_timeout(
+        &self,
+        client: &impl BlockingClient,
+        timeout: Duration,
+    ) -> Result<EndpointResult<Self::Response>, ClientError> {
+        trace!("Executing endpoint");
+
+        exec_block_deadline(client, self, Some(timeout), false).map(endpoint_result::<Self>)
+    }
+}
+
+/// An [Endpoint] which parses the body of unsuccessful responses into a
+/// structured [ErrorEndpoint::Error].
+///
+/// This is kept separate from [Endpoint] because associated type defaults
+/// aren't available on stable Rust, so adding the type to [Endpoint] would
+/// require every existing implementation to declare it. The derive macro
+/// implements this trait for every endpoint, using the type passed with the
+/// `error` parameter (i.e. `error = "MyApiError"`) or [serde_json::Value] by
+/// default.
+///
+/// # Example
+/// ```
+/// use rustify::clients::reqwest::Client;
+/// use rustify::endpoint::ErrorEndpoint;
+/// use rustify::errors::EndpointError;
+/// use rustify_derive::Endpoint;
+/// use serde::Deserialize;
+///
+/// #[derive(Debug, Deserialize)]
+/// struct MyApiError {
+///     message: String,
+/// }
+///
+/// #[derive(Endpoint)]
+/// #[endpoint(path = "users", error = "MyApiError")]
+/// struct ListUsers {}
+///
+/// # tokio_test::block_on(async {
+/// let client = Client::default("http://myapi.com");
+/// let endpoint = ListUsers {};
+/// match endpoint.exec_typed(&client).await {
+///     Ok(result) => {}
+///     Err(EndpointError::Api { code, error }) => println!("{}: {}", code, error.message),
+///     Err(EndpointError::Client(e)) => {}
+/// }
+/// # })
+/// ```
+#[cfg_attr(not(feature = "non-send"), async_trait)]
+#[cfg_attr(feature = "non-send", async_trait(?Send))]
+pub trait ErrorEndpoint: Endpoint {
+    /// The type that the body of an unsuccessful response is deserialized
+    /// into using the deserializer determined by the [ResponseType].
+    type Error: DeserializeOwned + MaybeSendSync;
+
+    /// Executes the Endpoint using the given [Client], returning the body of
+    /// an unsuccessful response parsed into [ErrorEndpoint::Error] as an
+    /// [EndpointError::Api].
+    ///
+    /// If the body can't be parsed, a [ClientError::ApiError] holding the raw
+    /// body is returned instead.
+    #[allow(clippy::blocks_in_conditions)]
+    #[cfg_attr(feature = "tracing", instrument(skip(self, client), fields(endpoint = Self::name()), err))]
+    async fn exec_typed(
+        &self,
+        client: &impl Client,
+    ) -> Result<EndpointResult<Self::Response>, EndpointError<Self::Error>> {
+        trace!("Executing endpoint");
+
+        let result = match self.timeout() {
+            Some(timeout) => tokio::time::timeout(timeout, exec_result(client, self, true))
+                .await
+                .unwrap_or(Err(ClientError::Timeout { timeout })),
+            None => exec_result(client, self, true).await,
+        };
+        result.map_err(typed_error::<Self>)
+    }
+
+    /// The blocking variant of [ErrorEndpoint::exec_typed].
+    #[cfg(feature = "blocking")]
+    #[cfg_attr(feature = "tracing", instrument(skip(self, client), fields(endpoint = Self::name()), err))]
+    fn exec_block_typed(
+        &self,
+        client: &impl BlockingClient,
+    ) -> Result<EndpointResult<Self::Response>, EndpointError<Self::Error>> {
+        trace!("Executing endpoint");
+
+        exec_block_deadline(client, self, self.timeout(), true)
+            .map(endpoint_result::<Self>)
+            .map_err(typed_error::<Self>)
     }
 }
 
@@ -264,55 +1145,405 @@ pub trait Endpoint: Send + Sync + Sized {
 /// the actual HTTP [Response] and the final result type. The response can be
 /// parsed into the final result type by calling `parse()` or optionally
 /// wrapped by a [Wrapper] by calling `wrap()`.
-pub struct EndpointResult<T: DeserializeOwned + Send + Sync> {
-    pub response: Response<Vec<u8>>,
+///
+/// By default the response is parsed regardless of its `Content-Type`. Call
+/// [EndpointResult::verify_content_type] to instead fail with a descriptive
+/// error when the server responds with an unexpected type of content, such
+/// as an HTML error page from a proxy.
+pub struct EndpointResult<T: DeserializeOwned + MaybeSendSync> {
+    pub response: Response<Bytes>,
     pub ty: ResponseType,
+    strict: bool,
+    verify: bool,
     inner: PhantomData<T>,
 }
 
-impl<T: DeserializeOwned + Send + Sync> EndpointResult<T> {
+impl<T: DeserializeOwned + MaybeSendSync> EndpointResult<T> {
     /// Returns a new [EndpointResult].
-    pub fn new(response: Response<Vec<u8>>, ty: ResponseType) -> Self {
+    ///
+    /// Bodies which are a [`Vec<u8>`] are converted into [Bytes] without
+    /// copying.
+    pub fn new(response: Response<impl Into<Bytes>>, ty: ResponseType) -> Self {
         EndpointResult {
-            response,
+            response: response.map(Into::into),
             ty,
+            strict: false,
+            verify: false,
             inner: PhantomData,
         }
     }
 
+    /// Enables strict parsing, which causes `parse()` and `wrap()` to fail
+    /// if the response contains fields which aren't present in the type being
+    /// parsed. This is useful for detecting changes to an API's schema.
+    ///
+    /// Only JSON, MessagePack and YAML responses are checked.
+    pub fn strict(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+
+    /// Enables verifying the `Content-Type` of the response against the
+    /// [ResponseType] before it's parsed by `parse()` or `wrap()`.
+    ///
+    /// Responses without a `Content-Type` header are always parsed.
+    pub fn verify_content_type(mut self) -> Self {
+        self.verify = true;
+        self
+    }
+
     /// Parses the response into the final result type.
-    #[instrument(skip(self), err)]
+    #[cfg_attr(feature = "tracing", instrument(skip(self), err))]
     pub fn parse(&self) -> Result<T, ClientError> {
-        match self.ty {
-            ResponseType::JSON => serde_json::from_slice(self.response.body()).map_err(|e| {
-                ClientError::ResponseParseError {
-                    source: e.into(),
-                    content: String::from_utf8(self.response.body().to_vec()).ok(),
-                }
-            }),
-        }
+        self.deserialize()
     }
 
-    /// Returns the raw response body from the HTTP [Response].
+    /// Parses the raw response body using the given function.
+    ///
+    /// This allows plugging in custom or lenient parsing for a single call
+    /// without defining a new [ResponseType]. Errors returned by the function
+    /// are wrapped in a [ClientError::ResponseParseError].
+    pub fn parse_with<R, E, F>(&self, f: F) -> Result<R, ClientError>
+    where
+        F: FnOnce(&[u8]) -> Result<R, E>,
+        E: Into<anyhow::Error>,
+    {
+        f(self.raw_ref()).map_err(|e| ClientError::ResponseParseError {
+            source: e.into(),
+            content: String::from_utf8(self.raw()).ok(),
+        })
+    }
+
+    /// Parses the response into a [serde_json::Value] regardless of the final
+    /// result type.
+    ///
+    /// This is useful for inspecting or logging unexpected responses. The
+    /// deserializer determined by the [ResponseType] is still used, so
+    /// non-JSON responses are converted into their JSON representation.
+    #[cfg_attr(feature = "tracing", instrument(skip(self), err))]
+    pub fn parse_value(&self) -> Result<serde_json::Value, ClientError> {
+        self.deserialize()
+    }
+
+    /// Parses only the field at the given dot-separated path of the response
+    /// (i.e. `data.user.name`) into the given type. Array elements are
+    /// selected using their index as a path segment (i.e. `items.0.name`).
+    #[cfg_attr(feature = "tracing", instrument(skip(self), err))]
+    pub fn parse_field<F: DeserializeOwned>(&self, path: &str) -> Result<F, ClientError> {
+        let pointer = path
+            .split('.')
+            .map(|s| format!("/{}", s.replace('~', "~0").replace('/', "~1")))
+            .collect::<String>();
+        self.deserialize_at(&pointer)
+    }
+
+    /// Returns the status code of the HTTP [Response].
+    pub fn status(&self) -> StatusCode {
+        self.response.status()
+    }
+
+    /// Returns the headers of the HTTP [Response].
+    pub fn headers(&self) -> &HeaderMap {
+        self.response.headers()
+    }
+
+    /// Returns the request ID echoed back by the server, if it was recorded by
+    /// the [RequestId][crate::correlation::RequestId] middleware.
+    pub fn request_id(&self) -> Option<&str> {
+        self.response
+            .extensions()
+            .get::<ServerRequestId>()
+            .map(|id| id.0.as_str())
+    }
+
+    /// Returns the URL the HTTP [Response] was received from after following
+    /// any redirects, if reported by the client.
+    pub fn url(&self) -> Option<&http::Uri> {
+        self.response
+            .extensions()
+            .get::<Redirects>()
+            .map(|r| &r.url)
+    }
+
+    /// Returns the URLs which responded with a redirect that was followed
+    /// before receiving the HTTP [Response], in order.
+    pub fn redirects(&self) -> &[http::Uri] {
+        self.response
+            .extensions()
+            .get::<Redirects>()
+            .map(|r| r.chain.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Returns the value of the `Content-Type` header of the HTTP [Response],
+    /// if present and valid.
+    pub fn content_type(&self) -> Option<&str> {
+        self.response
+            .headers()
+            .get(http::header::CONTENT_TYPE)?
+            .to_str()
+            .ok()
+    }
+
+    /// Returns the value of the `Content-Length` header of the HTTP
+    /// [Response], if present and valid.
+    pub fn content_length(&self) -> Option<u64> {
+        crate::http::content_length(self.response.headers())
+    }
+
+    /// Returns a copy of the raw response body from the HTTP [Response].
+    ///
+    /// Prefer [EndpointResult::raw_ref] or [EndpointResult::into_raw] for
+    /// large responses to avoid duplicating the body in memory.
     pub fn raw(&self) -> Vec<u8> {
-        self.response.body().clone()
+        self.response.body().to_vec()
+    }
+
+    /// Returns a reference to the raw response body from the HTTP [Response].
+    pub fn raw_ref(&self) -> &[u8] {
+        self.response.body()
+    }
+
+    /// Consumes the result and returns the raw response body from the HTTP
+    /// [Response]. The body is only copied if it's shared elsewhere.
+    pub fn into_raw(self) -> Vec<u8> {
+        Vec::from(self.into_body())
+    }
+
+    /// Consumes the result and returns the parts and body of the HTTP
+    /// [Response].
+    pub fn into_parts(self) -> (http::response::Parts, Bytes) {
+        self.response.into_parts()
+    }
+
+    /// Consumes the result and returns the body of the HTTP [Response]
+    /// without copying it.
+    pub fn into_body(self) -> Bytes {
+        self.response.into_body()
+    }
+
+    /// Writes the response body into the writer, calling `progress` after
+    /// every chunk is written. Returns the number of bytes written.
+    pub async fn stream_to<W, F>(&self, writer: &mut W, progress: F) -> Result<u64, ClientError>
+    where
+        W: AsyncWrite + Unpin + ?Sized,
+        F: FnMut(Progress),
+    {
+        let body = crate::http::stream_body(self.response.body().clone());
+        let total = Some(self.raw_ref().len() as u64);
+        crate::http::write_body(body, writer, total, progress).await
+    }
+
+    /// Returns the response body from the HTTP [Response] as a UTF-8 string.
+    pub fn text(&self) -> Result<String, ClientError> {
+        String::from_utf8(self.raw()).map_err(|e| ClientError::ResponseConversionError {
+            source: e.into(),
+            content: self.raw(),
+        })
     }
 
     /// Parses the response into the final result type and then wraps it in the
     /// given [Wrapper].
-    #[instrument(skip(self), err)]
+    #[cfg_attr(feature = "tracing", instrument(skip(self), err))]
     pub fn wrap<W>(&self) -> Result<W, ClientError>
     where
         W: Wrapper<Value = T>,
     {
-        match self.ty {
-            ResponseType::JSON => serde_json::from_slice(self.response.body()).map_err(|e| {
-                ClientError::ResponseParseError {
+        match W::POINTER {
+            Some(pointer) => self.deserialize_at(pointer),
+            None => self.deserialize(),
+        }
+    }
+
+    /// Deserializes the response body into the given type using the
+    /// deserializer determined by the [ResponseType].
+    ///
+    /// The unit type is returned without reading the response body, which
+    /// allows endpoints without a response to ignore whatever the server
+    /// returns.
+    fn deserialize<D: DeserializeOwned>(&self) -> Result<D, ClientError> {
+        if let Ok(unit) = D::deserialize(UnitDeserializer) {
+            return Ok(unit);
+        }
+        if self.verify {
+            self.check_content_type()?;
+        }
+        deserialize(self.response.body(), &self.ty, self.strict)
+    }
+
+    /// Deserializes the value found at the given JSON pointer within the
+    /// response body into the given type.
+    ///
+    /// The body is first deserialized into a [serde_json::Value] using the
+    /// deserializer determined by the [ResponseType], so any response type
+    /// which can represent a JSON document is supported.
+    fn deserialize_at<D: DeserializeOwned>(&self, pointer: &str) -> Result<D, ClientError> {
+        let mut value: serde_json::Value = self.deserialize()?;
+        let value = value
+            .pointer_mut(pointer)
+            .map(|v| v.take())
+            .ok_or_else(|| ClientError::ResponseParseError {
+                source: anyhow::anyhow!("No value found at {}", pointer),
+                content: String::from_utf8(self.raw()).ok(),
+            })?;
+        let content = value.to_string();
+        deserialize_from(value, self.strict).map_err(|e| ClientError::ResponseParseError {
+            source: e,
+            content: Some(content),
+        })
+    }
+
+    /// Returns an error if the `Content-Type` of the response doesn't match
+    /// the [ResponseType].
+    fn check_content_type(&self) -> Result<(), ClientError> {
+        let content_type = match self.response.headers().get(http::header::CONTENT_TYPE) {
+            Some(v) => String::from_utf8_lossy(v.as_bytes()).to_string(),
+            None => return Ok(()),
+        };
+        if self.ty.matches(&content_type) {
+            return Ok(());
+        }
+        Err(ClientError::ResponseContentTypeError {
+            expected: self.ty.clone(),
+            content_type,
+            content: String::from_utf8(self.response.body().to_vec()).ok(),
+        })
+    }
+}
+
+/// Deserializes a response body into the given type using the deserializer
+/// determined by the [ResponseType].
+///
+/// JSON, MessagePack and YAML responses are deserialized using
+/// [serde_path_to_error] so that parse errors include the path to the value
+/// which failed (i.e. `data.users[3].created_at`). When `strict` is set, these
+/// responses also fail to parse if they contain fields which aren't present in
+/// the given type.
+fn deserialize<D: DeserializeOwned>(
+    body: &[u8],
+    ty: &ResponseType,
+    strict: bool,
+) -> Result<D, ClientError> {
+    let result: Result<D, anyhow::Error> = match ty {
+        #[cfg(feature = "cbor")]
+        ResponseType::CBOR => ciborium::from_reader(body).map_err(|e| e.into()),
+        ResponseType::JSON => {
+            let mut de = serde_json::Deserializer::from_slice(body);
+            deserialize_from(&mut de, strict)
+                .and_then(|v| de.end().map(|_| v).map_err(|e| e.into()))
+        }
+        #[cfg(feature = "msgpack")]
+        ResponseType::MsgPack => deserialize_from(&mut rmp_serde::Deserializer::new(body), strict),
+        ResponseType::Raw => D::deserialize(RawDeserializer(body.to_vec())).map_err(|e| e.into()),
+        ResponseType::Text => {
+            let text = String::from_utf8(body.to_vec()).map_err(|e| {
+                ClientError::ResponseConversionError {
                     source: e.into(),
-                    content: String::from_utf8(self.response.body().to_vec()).ok(),
+                    content: body.to_vec(),
                 }
-            }),
+            })?;
+            return parse_text(&text).map_err(|e| ClientError::ResponseParseError {
+                source: e,
+                content: Some(text),
+            });
         }
+        #[cfg(feature = "yaml")]
+        ResponseType::YAML => deserialize_from(serde_yaml::Deserializer::from_slice(body), strict),
+    };
+    result.map_err(|e| ClientError::ResponseParseError {
+        source: e,
+        content: String::from_utf8(body.to_vec()).ok(),
+    })
+}
+
+/// Deserializes the given type from the deserializer while tracking the path
+/// to the value being deserialized. When `strict` is set, an error is returned
+/// if any fields were ignored while deserializing.
+fn deserialize_from<'de, De, D>(de: De, strict: bool) -> Result<D, anyhow::Error>
+where
+    De: serde::Deserializer<'de>,
+    De::Error: std::error::Error + Send + Sync + 'static,
+    D: serde::Deserialize<'de>,
+{
+    if !strict {
+        return serde_path_to_error::deserialize(de).map_err(|e| e.into());
+    }
+
+    let mut unknown = Vec::new();
+    let value = serde_path_to_error::deserialize(serde_ignored::Deserializer::new(
+        de,
+        &mut |p: serde_ignored::Path| unknown.push(p.to_string()),
+    ))?;
+    if !unknown.is_empty() {
+        return Err(anyhow::anyhow!("Unknown fields: {}", unknown.join(", ")));
+    }
+    Ok(value)
+}
+
+/// Parses a plain text response into the given type.
+///
+/// The text is first deserialized as a string, which supports [String] and
+/// any type which deserializes from one. If that fails, the trimmed text is
+/// parsed as a JSON scalar to support numbers and booleans.
+fn parse_text<D: DeserializeOwned>(text: &str) -> Result<D, anyhow::Error> {
+    let de: StrDeserializer<'_, serde::de::value::Error> = text.into_deserializer();
+    D::deserialize(de).or_else(|e| serde_json::from_str(text.trim()).map_err(|_| e.into()))
+}
+
+/// A deserializer which hands a raw response body to the type being
+/// deserialized, either as a sequence of bytes or a byte buffer.
+struct RawDeserializer(Vec<u8>);
+
+impl<'de> serde::Deserializer<'de> for RawDeserializer {
+    type Error = serde::de::value::Error;
+
+    fn deserialize_any<V: serde::de::Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_byte_buf(self.0)
+    }
+
+    fn deserialize_seq<V: serde::de::Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_seq(serde::de::value::SeqDeserializer::new(self.0.into_iter()))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+/// A deserializer which only succeeds in deserializing the unit type. Any
+/// other type, including types which accept a unit value such as [Option],
+/// fails to deserialize.
+struct UnitDeserializer;
+
+impl<'de> serde::Deserializer<'de> for UnitDeserializer {
+    type Error = serde::de::value::Error;
+
+    fn deserialize_any<V: serde::de::Visitor<'de>>(
+        self,
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        Err(serde::de::Error::custom("expected the unit type"))
+    }
+
+    fn deserialize_unit<V: serde::de::Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
     }
 }
 
@@ -321,12 +1552,12 @@ impl<T: DeserializeOwned + Send + Sync> EndpointResult<T> {
 /// Types implementing this trait that do not desire to implement both methods
 /// should instead return `OK(())` to bypass any processing of the [Request] or
 /// [Response].
-pub trait MiddleWare: Sync + Send {
+pub trait MiddleWare: MaybeSendSync {
     /// Modifies a [Request] from an [Endpoint] before it's executed.
     fn request<E: Endpoint>(
         &self,
         endpoint: &E,
-        req: &mut Request<Vec<u8>>,
+        req: &mut Request<Bytes>,
     ) -> Result<(), ClientError>;
 
     /// Modifies a [Response] from an [Endpoint] before being returned as an
@@ -334,44 +1565,211 @@ pub trait MiddleWare: Sync + Send {
     fn response<E: Endpoint>(
         &self,
         endpoint: &E,
-        resp: &mut Response<Vec<u8>>,
+        resp: &mut Response<Bytes>,
     ) -> Result<(), ClientError>;
 }
 
-async fn exec(
-    client: &impl Client,
-    req: Request<Vec<u8>>,
-) -> Result<Response<Vec<u8>>, ClientError> {
-    client.execute(req).await
+/// The settings of a [Client] which are applied while building the [Request]
+/// of an [Endpoint] with [Endpoint::request_with], before any [MiddleWare]
+/// of the endpoint sees it.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RequestDefaults<'a> {
+    /// Headers added to the request unless the endpoint sets them itself.
+    pub headers: Option<&'a HeaderMap>,
+    /// Query parameters added to the URL unless the endpoint sets them itself.
+    pub query: Option<&'a [(String, String)]>,
+    /// How the path of the endpoint is joined onto the base URL.
+    pub join: UrlJoin,
 }
 
-async fn exec_mut(
+impl RequestDefaults<'_> {
+    /// Adds the default headers and query parameters to the given [Request]
+    /// along with any credentials embedded in the given base URL.
+    pub fn apply(&self, base: &str, req: &mut Request<Bytes>) -> Result<(), ClientError> {
+        if let Some(headers) = self.headers {
+            crate::http::merge_headers(req.headers_mut(), headers);
+        }
+        if !req.headers().contains_key(http::header::AUTHORIZATION) {
+            if let Some(value) = crate::auth::url_credentials(base)? {
+                req.headers_mut().insert(http::header::AUTHORIZATION, value);
+            }
+        }
+        if let Some(query) = self.query.filter(|q| !q.is_empty()) {
+            *req.uri_mut() = crate::http::merge_query(req.uri(), query)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the [RequestDefaults] of the given [Client].
+pub(crate) fn client_defaults(client: &impl Client) -> RequestDefaults<'_> {
+    RequestDefaults {
+        headers: client.default_headers(),
+        query: client.default_query(),
+        join: client.url_join(),
+    }
+}
+
+/// The blocking variant of [client_defaults].
+#[cfg(feature = "blocking")]
+pub(crate) fn blocking_defaults(client: &impl BlockingClient) -> RequestDefaults<'_> {
+    RequestDefaults {
+        headers: client.default_headers(),
+        query: client.default_query(),
+        join: client.url_join(),
+    }
+}
+
+/// Builds and executes a single request for the given [Endpoint], applying
+/// any client-level and endpoint-level [MiddleWare]. When `raw_errors` is set,
+/// unsuccessful responses are returned as a [ClientError::ApiError].
+async fn exec<E: Endpoint>(
     client: &impl Client,
-    endpoint: &impl Endpoint,
-    req: Request<Vec<u8>>,
-    middle: &impl MiddleWare,
-) -> Result<Response<Vec<u8>>, ClientError> {
+    endpoint: &E,
+    raw_errors: bool,
+) -> Result<Response<Bytes>, ClientError> {
+    let mut req = endpoint.request_with(client.base(), &client_defaults(client))?;
+    if raw_errors {
+        req.extensions_mut().insert(RawErrorBody);
+    }
+    client.mutate_request(endpoint, &mut req)?;
     let mut resp = client.execute(req).await?;
-    middle.response(endpoint, &mut resp)?;
+    client.mutate_response(endpoint, &mut resp)?;
+    endpoint.mutate_response(&mut resp)?;
     Ok(resp)
 }
 
-#[cfg(feature = "blocking")]
-fn exec_block(
-    client: &impl BlockingClient,
-    req: Request<Vec<u8>>,
-) -> Result<Response<Vec<u8>>, ClientError> {
-    client.execute(req)
+/// Executes the given [Endpoint] using its [RetryPolicy], falling back to the
+/// policy of the client, and returns the result.
+async fn exec_result<E: Endpoint>(
+    client: &impl Client,
+    endpoint: &E,
+    raw_errors: bool,
+) -> Result<EndpointResult<E::Response>, ClientError> {
+    let _guard = register(client.drain())?;
+    let client = &Registered(client);
+    let policy = endpoint.retry_policy();
+    let policy = policy.as_ref().or_else(|| client.retry_policy());
+    let resp = crate::retry::retry(policy, || exec(client, endpoint, raw_errors)).await?;
+    Ok(endpoint_result::<E>(resp))
+}
+
+/// Returns an [EndpointResult] for the given response using the settings of
+/// the given [Endpoint].
+fn endpoint_result<E: Endpoint>(resp: Response<Bytes>) -> EndpointResult<E::Response> {
+    let result = EndpointResult::new(resp, E::RESPONSE_BODY_TYPE);
+    match E::STRICT_PARSING {
+        true => result.strict(),
+        false => result,
+    }
+}
+
+/// The streaming variant of [exec_result], which holds the registration of
+/// the execution with the [Drain][crate::client::Drain] of the client until
+/// the returned body has been consumed or dropped.
+async fn exec_stream_result<E: Endpoint>(
+    client: &impl Client,
+    endpoint: &E,
+    range: Option<&ByteRange>,
+) -> Result<Response<ByteStream>, ClientError> {
+    let guard = register(client.drain())?;
+    let client = &Registered(client);
+    let policy = endpoint.retry_policy();
+    let policy = policy.as_ref().or_else(|| client.retry_policy());
+    let resp = crate::retry::retry(policy, || exec_stream(client, endpoint, range)).await?;
+    Ok(match guard {
+        Some(g) => resp.map(|body| hold(body, g)),
+        None => resp,
+    })
+}
+
+/// The streaming variant of [exec].
+async fn exec_stream<E: Endpoint>(
+    client: &impl Client,
+    endpoint: &E,
+    range: Option<&ByteRange>,
+) -> Result<Response<ByteStream>, ClientError> {
+    let mut req = endpoint.request_with(client.base(), &client_defaults(client))?;
+    if let Some(r) = range {
+        req.headers_mut().insert(http::header::RANGE, r.to_header());
+    }
+    client.mutate_request(endpoint, &mut req)?;
+    client.execute_stream(req).await
 }
 
+/// The blocking variant of [exec].
 #[cfg(feature = "blocking")]
-fn exec_block_mut(
+fn exec_block<E: Endpoint>(
     client: &impl BlockingClient,
-    endpoint: &impl Endpoint,
-    req: Request<Vec<u8>>,
-    middle: &impl MiddleWare,
-) -> Result<Response<Vec<u8>>, ClientError> {
+    endpoint: &E,
+    timeout: Option<Duration>,
+    raw_errors: bool,
+) -> Result<Response<Bytes>, ClientError> {
+    let mut req = endpoint.request_with(client.base(), &blocking_defaults(client))?;
+    if let Some(t) = timeout {
+        req.extensions_mut()
+            .insert(crate::client::RequestTimeout(t));
+    }
+    if raw_errors {
+        req.extensions_mut().insert(RawErrorBody);
+    }
+    client.mutate_request(endpoint, &mut req)?;
     let mut resp = client.execute(req)?;
-    middle.response(endpoint, &mut resp)?;
+    client.mutate_response(endpoint, &mut resp)?;
+    endpoint.mutate_response(&mut resp)?;
     Ok(resp)
 }
+
+/// Executes the given [Endpoint] using its [RetryPolicy], falling back to the
+/// policy of the blocking client, failing with [ClientError::Timeout] if the execution doesn't
+/// complete within the given timeout.
+#[cfg(feature = "blocking")]
+fn exec_block_deadline<E: Endpoint>(
+    client: &impl BlockingClient,
+    endpoint: &E,
+    timeout: Option<Duration>,
+    raw_errors: bool,
+) -> Result<Response<Bytes>, ClientError> {
+    let _guard = register(client.drain())?;
+    let client = &Registered(client);
+    let policy = endpoint.retry_policy();
+    let policy = policy.as_ref().or_else(|| client.retry_policy());
+    let timeout = match timeout {
+        Some(t) => t,
+        None => {
+            return crate::retry::retry_block(policy, || {
+                exec_block(client, endpoint, None, raw_errors)
+            })
+        }
+    };
+
+    let deadline = std::time::Instant::now() + timeout;
+    crate::retry::retry_block(policy, || {
+        let remaining = deadline
+            .checked_duration_since(std::time::Instant::now())
+            .filter(|d| !d.is_zero())
+            .ok_or(ClientError::Timeout { timeout })?;
+        exec_block(client, endpoint, Some(remaining), raw_errors)
+    })
+    .map_err(|e| match e {
+        ClientError::RequestError { .. } if std::time::Instant::now() >= deadline => {
+            ClientError::Timeout { timeout }
+        }
+        e => e,
+    })
+}
+
+/// Parses the body of a [ClientError::ApiError] into the
+/// [ErrorEndpoint::Error] of the given endpoint. The error is returned
+/// unchanged if the body can't be parsed.
+fn typed_error<E: ErrorEndpoint>(err: ClientError) -> EndpointError<E::Error> {
+    match err {
+        ClientError::ApiError { code, body } => {
+            match deserialize(&body, &E::RESPONSE_BODY_TYPE, false) {
+                Ok(error) => EndpointError::Api { code, error },
+                Err(_) => EndpointError::Client(ClientError::ApiError { code, body }),
+            }
+        }
+        e => EndpointError::Client(e),
+    }
+}
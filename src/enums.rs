@@ -36,11 +36,101 @@ impl Into<http::Method> for RequestMethod {
 /// Represents the type of a HTTP request body
 #[derive(Clone, Debug)]
 pub enum RequestType {
+    /// The body is serialized as CBOR
+    #[cfg(feature = "cbor")]
+    CBOR,
+    /// The body is serialized as `application/x-www-form-urlencoded`
+    Form,
     JSON,
+    /// The body is serialized as MessagePack
+    #[cfg(feature = "msgpack")]
+    MsgPack,
+    /// The body is serialized as YAML
+    #[cfg(feature = "yaml")]
+    YAML,
+}
+
+impl RequestType {
+    /// Returns the value of the `Content-Type` header sent with request bodies
+    /// of this type. JSON bodies are sent without a `Content-Type` header.
+    pub fn content_type(&self) -> Option<&'static str> {
+        match self {
+            #[cfg(feature = "cbor")]
+            RequestType::CBOR => Some("application/cbor"),
+            RequestType::Form => Some("application/x-www-form-urlencoded"),
+            RequestType::JSON => None,
+            #[cfg(feature = "msgpack")]
+            RequestType::MsgPack => Some("application/msgpack"),
+            #[cfg(feature = "yaml")]
+            RequestType::YAML => Some("application/yaml"),
+        }
+    }
 }
 
 /// Represents the type of a HTTP response body
 #[derive(Clone, Debug)]
 pub enum ResponseType {
+    /// The body is deserialized from CBOR
+    #[cfg(feature = "cbor")]
+    CBOR,
     JSON,
+    /// The body is deserialized from MessagePack
+    #[cfg(feature = "msgpack")]
+    MsgPack,
+    /// The body is returned as is. The response must be a [Vec<u8>] or a type
+    /// which deserializes from a byte buffer.
+    Raw,
+    /// The body is parsed from plain text. The response must be a [String] or
+    /// a type which deserializes from a string, number or boolean.
+    Text,
+    /// The body is deserialized from YAML
+    #[cfg(feature = "yaml")]
+    YAML,
+}
+
+impl ResponseType {
+    /// Returns the value of the `Accept` header sent with requests expecting
+    /// a response of this type. Raw responses are requested without an
+    /// `Accept` header.
+    pub fn accept(&self) -> Option<&'static str> {
+        match self {
+            #[cfg(feature = "cbor")]
+            ResponseType::CBOR => Some("application/cbor"),
+            ResponseType::JSON => Some("application/json"),
+            #[cfg(feature = "msgpack")]
+            ResponseType::MsgPack => Some("application/msgpack"),
+            ResponseType::Raw => None,
+            ResponseType::Text => Some("text/plain"),
+            #[cfg(feature = "yaml")]
+            ResponseType::YAML => Some("application/yaml"),
+        }
+    }
+
+    /// Returns whether a response with the given `Content-Type` header value
+    /// can be parsed as this type.
+    ///
+    /// Parameters such as `charset` are ignored and structured syntax suffixes
+    /// are supported (i.e. `application/problem+json` matches JSON). Raw
+    /// responses match any content type.
+    pub fn matches(&self, content_type: &str) -> bool {
+        let essence = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+        let (kind, subtype) = essence.split_once('/').unwrap_or((essence.as_str(), ""));
+        let suffix = subtype.rsplit_once('+').map(|(_, s)| s);
+        match self {
+            #[cfg(feature = "cbor")]
+            ResponseType::CBOR => subtype == "cbor" || suffix == Some("cbor"),
+            ResponseType::JSON => subtype == "json" || suffix == Some("json"),
+            #[cfg(feature = "msgpack")]
+            ResponseType::MsgPack => matches!(subtype, "msgpack" | "x-msgpack"),
+            ResponseType::Raw => true,
+            ResponseType::Text => kind == "text",
+            #[cfg(feature = "yaml")]
+            ResponseType::YAML => matches!(subtype, "yaml" | "x-yaml") || suffix == Some("yaml"),
+        }
+    }
 }
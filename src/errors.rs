@@ -1,17 +1,40 @@
 //! Contains the common error enum used across this crate
+use std::time::Duration;
+
+use bytes::Bytes;
+
 use thiserror::Error;
 
-use crate::enums::RequestMethod;
+use crate::enums::{RequestMethod, ResponseType};
 
 /// The general error type returned by this crate
 #[derive(Error, Debug)]
 pub enum ClientError {
+    #[error("Server returned error")]
+    ApiError { code: u16, body: Bytes },
+    #[error("Invalid base URL {url}: {reason}")]
+    BaseUrlError { url: String, reason: String },
+    #[error("Execution was cancelled")]
+    CancelledError,
+    #[error("Error parsing {name}")]
+    CertificateParseError { source: anyhow::Error, name: String },
+    #[error("Error reading certificate material from: {path}")]
+    CertificateReadError {
+        source: std::io::Error,
+        path: String,
+    },
+    #[error("Error building HTTP client")]
+    ClientBuildError { source: anyhow::Error },
     #[error("Error parsing endpoint into data")]
     DataParseError { source: anyhow::Error },
     #[error("Error building endpoint request")]
     EndpointBuildError { source: anyhow::Error },
     #[error("An error occurred in processing the request")]
     GenericError { source: anyhow::Error },
+    #[error("Invalid value for environment variable: {name}")]
+    EnvParseError { source: anyhow::Error, name: String },
+    #[error("Error building header: {name}")]
+    HeaderParseError { source: anyhow::Error, name: String },
     #[error("Error sending HTTP request")]
     RequestError {
         source: anyhow::Error,
@@ -24,6 +47,7 @@ pub enum ClientError {
         method: RequestMethod,
         url: String,
     },
+    #[cfg(feature = "reqwest")]
     #[error("Error building request for Reqwest crate")]
     ReqwestBuildError { source: reqwest::Error },
     #[error("Error retrieving HTTP response")]
@@ -33,13 +57,27 @@ pub enum ClientError {
         source: anyhow::Error,
         content: Vec<u8>,
     },
+    #[error("Error writing response body")]
+    ResponseWriteError { source: std::io::Error },
+    #[error("Expected a {expected:?} response but received content type: {content_type}")]
+    ResponseContentTypeError {
+        expected: ResponseType,
+        content_type: String,
+        content: Option<String>,
+    },
     #[error("Error parsing HTTP response")]
     ResponseParseError {
         source: anyhow::Error,
         content: Option<String>,
     },
+    #[error("Client has been shut down")]
+    ShutdownError,
+    #[error("Timed out waiting for {in_flight} in-flight request(s) to finish")]
+    ShutdownTimeoutError { in_flight: usize },
     #[error("Server returned error")]
     ServerResponseError { code: u16, content: Option<String> },
+    #[error("Execution timed out after {timeout:?}")]
+    Timeout { timeout: Duration },
     #[error("Error building URL")]
     UrlBuildError { source: http::uri::InvalidUri },
     #[error("Error serializing URL query parameters")]
@@ -47,3 +85,14 @@ pub enum ClientError {
     #[error("Error parsing URL")]
     UrlParseError { source: url::ParseError },
 }
+
+/// The error returned by executing an
+/// [ErrorEndpoint][crate::endpoint::ErrorEndpoint], which holds the body of
+/// unsuccessful responses parsed into the error type of the endpoint.
+#[derive(Error, Debug)]
+pub enum EndpointError<E> {
+    #[error("Server returned error")]
+    Api { code: u16, error: E },
+    #[error(transparent)]
+    Client(#[from] ClientError),
+}
@@ -1,18 +1,32 @@
 //! Contains helper functions for working with HTTP requests and responses.
 
 use crate::{
+    client::ByteStream,
     enums::{RequestMethod, RequestType},
     errors::ClientError,
 };
-use http::{Request, Uri};
+use bytes::{Bytes, BytesMut};
+use futures_util::StreamExt;
+use http::{HeaderName, HeaderValue, Request, Uri};
 use serde::Serialize;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
 use url::Url;
 
 /// Builds a request body by serializing an object using a serializer determined
 /// by the [RequestType].
-#[instrument(skip(object), err)]
+#[cfg_attr(feature = "tracing", instrument(skip(object), err))]
 pub fn build_body(object: &impl Serialize, ty: RequestType) -> Result<Vec<u8>, ClientError> {
     match ty {
+        #[cfg(feature = "cbor")]
+        RequestType::CBOR => {
+            let mut data = Vec::new();
+            ciborium::into_writer(object, &mut data)
+                .map_err(|e| ClientError::DataParseError { source: e.into() })?;
+            Ok(data)
+        }
+        RequestType::Form => serde_urlencoded::to_string(object)
+            .map(|s| s.into_bytes())
+            .map_err(|e| ClientError::DataParseError { source: e.into() }),
         RequestType::JSON => {
             let parse_data = serde_json::to_string(object)
                 .map_err(|e| ClientError::DataParseError { source: e.into() })?;
@@ -22,34 +36,67 @@ pub fn build_body(object: &impl Serialize, ty: RequestType) -> Result<Vec<u8>, C
                 _ => parse_data.as_bytes().to_vec(),
             })
         }
+        #[cfg(feature = "msgpack")]
+        RequestType::MsgPack => rmp_serde::to_vec_named(object)
+            .map_err(|e| ClientError::DataParseError { source: e.into() }),
+        #[cfg(feature = "yaml")]
+        RequestType::YAML => serde_yaml::to_string(object)
+            .map(|s| s.into_bytes())
+            .map_err(|e| ClientError::DataParseError { source: e.into() }),
     }
 }
 
 /// Builds a query string by serializing an object.
-#[instrument(skip(object), err)]
+#[cfg_attr(feature = "tracing", instrument(skip(object), err))]
 pub fn build_query(object: &impl Serialize) -> Result<String, ClientError> {
     serde_urlencoded::to_string(object)
         .map_err(|e| ClientError::UrlQueryParseError { source: e.into() })
 }
 
-/// Builds a [Request] using the given [Endpoint][crate::Endpoint] and base URL.
-#[instrument(skip(query, data), err)]
+/// Builds a header name/value pair from the given name and value.
+#[cfg_attr(feature = "tracing", instrument(skip(value), err))]
+pub fn build_header(
+    name: &str,
+    value: &impl ToString,
+) -> Result<(HeaderName, HeaderValue), ClientError> {
+    let header_name =
+        HeaderName::from_bytes(name.as_bytes()).map_err(|e| ClientError::HeaderParseError {
+            source: e.into(),
+            name: name.to_string(),
+        })?;
+    let header_value = HeaderValue::from_str(value.to_string().as_str()).map_err(|e| {
+        ClientError::HeaderParseError {
+            source: e.into(),
+            name: name.to_string(),
+        }
+    })?;
+    Ok((header_name, header_value))
+}
+
+/// Builds a [Request] using the given [Endpoint][crate::Endpoint] and base URL,
+/// joining the path onto the base URL according to the given [UrlJoin] mode.
+#[cfg_attr(feature = "tracing", instrument(skip(query, headers, data), err))]
 pub fn build_request(
     base: &str,
     path: &str,
+    join: UrlJoin,
     method: RequestMethod,
     query: Option<String>,
+    headers: Vec<(HeaderName, HeaderValue)>,
     data: Option<Vec<u8>>,
-) -> Result<Request<Vec<u8>>, ClientError> {
+) -> Result<Request<Bytes>, ClientError> {
     trace!("Building endpoint request");
-    let uri = build_url(base, path, query)?;
+    let uri = join_url(base, path, query, join)?;
 
     let method_err = method.clone();
     let uri_err = uri.to_string();
-    Request::builder()
-        .uri(uri)
-        .method(method)
-        .body(data.unwrap_or_default())
+    let mut builder = Request::builder().uri(uri).method(method);
+    for (name, value) in headers {
+        builder = builder.header(name, value);
+    }
+
+    builder
+        .body(Bytes::from(data.unwrap_or_default()))
         .map_err(|e| ClientError::RequestBuildError {
             source: e,
             method: method_err,
@@ -57,12 +104,78 @@ pub fn build_request(
         })
 }
 
+/// How the path of an [Endpoint][crate::endpoint::Endpoint] is joined onto
+/// the base URL of a client.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UrlJoin {
+    /// Appends each segment of the path to the path of the base URL as-is,
+    /// which is how [build_url] has always joined them. Slashes where the
+    /// paths meet are kept (i.e. `http://host/v1/` and `users` become
+    /// `http://host/v1//users`).
+    #[default]
+    Lenient,
+    /// Appends the segments of the path to the path of the base URL,
+    /// collapsing the slashes where they meet (i.e. `http://host/v1/` and
+    /// `/users` become `http://host/v1/users`).
+    Normalized,
+    /// Resolves the path as a relative reference against the base URL as
+    /// described in [RFC 3986](https://www.rfc-editor.org/rfc/rfc3986#section-5.2).
+    /// The last segment of a base path without a trailing slash is replaced
+    /// and paths with a leading slash replace the base path entirely (i.e.
+    /// `http://host/v1` and `users` become `http://host/users`, while
+    /// `http://host/v1/` and `users` become `http://host/v1/users`).
+    Strict,
+}
+
 /// Combines the given base URL, relative path, and optional query parameters
-/// into a single [Uri].
-#[instrument(skip(query), err)]
+/// into a single [Uri] using [UrlJoin::Lenient].
+///
+/// Credentials embedded in the base URL are removed, as they're sent in the
+/// `Authorization` header instead.
+#[cfg_attr(feature = "tracing", instrument(skip(base, query), err))]
 pub fn build_url(base: &str, path: &str, query: Option<String>) -> Result<Uri, ClientError> {
+    join_url(base, path, query, UrlJoin::Lenient)
+}
+
+/// Combines the given base URL, relative path, and optional query parameters
+/// into a single [Uri] using the given [UrlJoin] mode.
+///
+/// Credentials embedded in the base URL are removed, as they're sent in the
+/// `Authorization` header instead.
+#[cfg_attr(feature = "tracing", instrument(skip(base, query), err))]
+pub fn join_url(
+    base: &str,
+    path: &str,
+    query: Option<String>,
+    join: UrlJoin,
+) -> Result<Uri, ClientError> {
     let mut url = Url::parse(base).map_err(|e| ClientError::UrlParseError { source: e })?;
-    url.path_segments_mut().unwrap().extend(path.split('/'));
+    let cannot_be_base = || ClientError::BaseUrlError {
+        url: base.to_string(),
+        reason: "cannot be a base".to_string(),
+    };
+    match join {
+        UrlJoin::Lenient => {
+            url.path_segments_mut()
+                .map_err(|_| cannot_be_base())?
+                .extend(path.split('/'));
+        }
+        UrlJoin::Normalized => {
+            url.path_segments_mut()
+                .map_err(|_| cannot_be_base())?
+                .pop_if_empty()
+                .extend(path.trim_start_matches('/').split('/'));
+        }
+        UrlJoin::Strict => {
+            url = url
+                .join(path)
+                .map_err(|e| ClientError::UrlParseError { source: e })?;
+        }
+    }
+    if url.has_authority() {
+        url.set_username("").ok();
+        url.set_password(None).ok();
+    }
     if let Some(q) = query {
         url.set_query(Some(q.as_str()));
     }
@@ -71,3 +184,386 @@ pub fn build_url(base: &str, path: &str, query: Option<String>) -> Result<Uri, C
         .parse::<Uri>()
         .map_err(|e| ClientError::UrlBuildError { source: e })
 }
+
+/// Parses and validates the given base URL, failing if it can't be used for
+/// qualifying the paths of [Endpoints][crate::endpoint::Endpoint].
+///
+/// Valid base URLs use the `http` or `https` scheme and contain a host.
+pub fn validate_base(base: &str) -> Result<Url, ClientError> {
+    let url = Url::parse(base).map_err(|e| ClientError::UrlParseError { source: e })?;
+    let reason = if !matches!(url.scheme(), "http" | "https") {
+        Some(format!("unsupported scheme: {}", url.scheme()))
+    } else if !url.has_host() {
+        Some("missing host".to_string())
+    } else {
+        None
+    };
+    match reason {
+        Some(reason) => Err(ClientError::BaseUrlError {
+            url: base.to_string(),
+            reason,
+        }),
+        None => Ok(url),
+    }
+}
+
+/// Adds the given default query parameters to the given [Uri], skipping any
+/// parameter which is already set.
+pub fn merge_query(uri: &Uri, defaults: &[(String, String)]) -> Result<Uri, ClientError> {
+    let mut url =
+        Url::parse(&uri.to_string()).map_err(|e| ClientError::UrlParseError { source: e })?;
+    let existing: Vec<String> = url.query_pairs().map(|(k, _)| k.into_owned()).collect();
+    let missing: Vec<_> = defaults
+        .iter()
+        .filter(|(k, _)| !existing.contains(k))
+        .collect();
+    if missing.is_empty() {
+        return Ok(uri.clone());
+    }
+
+    url.query_pairs_mut().extend_pairs(missing);
+    url.to_string()
+        .parse::<Uri>()
+        .map_err(|e| ClientError::UrlBuildError { source: e })
+}
+
+/// Returns a copy of the given [Request].
+///
+/// This is useful for resending a request since [Request] does not implement
+/// [Clone]. Request extensions are not copied.
+pub fn clone_request(req: &Request<Bytes>) -> Request<Bytes> {
+    let mut clone = Request::new(req.body().clone());
+    *clone.method_mut() = req.method().clone();
+    *clone.uri_mut() = req.uri().clone();
+    *clone.version_mut() = req.version();
+    *clone.headers_mut() = req.headers().clone();
+    clone
+}
+
+/// Returns a [ByteStream] which yields the given body as a single chunk.
+pub fn stream_body(body: impl Into<Bytes>) -> ByteStream {
+    let body = body.into();
+    Box::pin(futures_util::stream::once(async move { Ok(body) }))
+}
+
+/// Reads the given [ByteStream] to completion and returns its contents.
+pub async fn collect_body(mut stream: ByteStream) -> Result<Bytes, ClientError> {
+    let mut body = BytesMut::new();
+    while let Some(chunk) = stream.next().await {
+        body.extend_from_slice(&chunk?);
+    }
+    Ok(body.freeze())
+}
+
+/// The progress of writing a response body.
+#[derive(Clone, Copy, Debug)]
+pub struct Progress {
+    /// The number of bytes written so far.
+    pub written: u64,
+    /// The total size of the body, if known from the `Content-Length` header.
+    pub total: Option<u64>,
+}
+
+/// Writes the given [ByteStream] to the writer, calling `progress` after each
+/// chunk is written. Returns the total number of bytes written.
+pub async fn write_body<W, F>(
+    mut stream: ByteStream,
+    writer: &mut W,
+    total: Option<u64>,
+    mut progress: F,
+) -> Result<u64, ClientError>
+where
+    W: AsyncWrite + Unpin + ?Sized,
+    F: FnMut(Progress),
+{
+    let mut written = 0;
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        writer
+            .write_all(&chunk)
+            .await
+            .map_err(|e| ClientError::ResponseWriteError { source: e })?;
+        written += chunk.len() as u64;
+        progress(Progress { written, total });
+    }
+    writer
+        .flush()
+        .await
+        .map_err(|e| ClientError::ResponseWriteError { source: e })?;
+    Ok(written)
+}
+
+/// A range of bytes requested from a response body using the `Range` header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ByteRange {
+    /// The offset of the first byte in the range.
+    pub offset: u64,
+    /// The number of bytes in the range. When [None], the range extends to
+    /// the end of the body.
+    pub length: Option<u64>,
+}
+
+impl ByteRange {
+    /// Returns a new [ByteRange] of `length` bytes starting at `offset`.
+    pub fn new(offset: u64, length: u64) -> Self {
+        ByteRange {
+            offset,
+            length: Some(length),
+        }
+    }
+
+    /// Returns the value of the `Range` header for this range.
+    pub fn to_header(&self) -> HeaderValue {
+        let value = match self.length {
+            Some(0) | None => format!("bytes={}-", self.offset),
+            Some(l) => format!("bytes={}-{}", self.offset, self.offset + l - 1),
+        };
+        HeaderValue::from_str(&value).unwrap()
+    }
+}
+
+impl From<u64> for ByteRange {
+    /// Returns a [ByteRange] starting at the given offset and extending to
+    /// the end of the body.
+    fn from(offset: u64) -> Self {
+        ByteRange {
+            offset,
+            length: None,
+        }
+    }
+}
+
+/// Returns a [ByteStream] which skips the first `n` bytes of the given
+/// stream.
+pub fn skip_body(stream: ByteStream, n: u64) -> ByteStream {
+    let mut remaining = n;
+    Box::pin(stream.map(move |chunk| {
+        chunk.map(|b| {
+            let skip = remaining.min(b.len() as u64) as usize;
+            remaining -= skip as u64;
+            b.slice(skip..)
+        })
+    }))
+}
+
+/// Returns the total size of the body from the `Content-Range` header in
+/// the given headers (i.e. `bytes 100-199/200`).
+pub(crate) fn content_range_total(headers: &http::HeaderMap) -> Option<u64> {
+    headers
+        .get(http::header::CONTENT_RANGE)?
+        .to_str()
+        .ok()?
+        .rsplit('/')
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// Returns a new random (version 4) UUID in its hyphenated form.
+pub(crate) fn uuid_v4() -> String {
+    let bits = fastrand::u128(..) & !(0xf000 << 64) & !(0xc << 60) | (0x4000 << 64) | (0x8 << 60);
+    let hex = format!("{:032x}", bits);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// Returns the value of the `Content-Length` header in the given headers.
+pub(crate) fn content_length(headers: &http::HeaderMap) -> Option<u64> {
+    headers
+        .get(http::header::CONTENT_LENGTH)?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()
+}
+
+/// Adds the given default headers to the given headers, skipping any header
+/// which is already set.
+pub(crate) fn merge_headers(headers: &mut http::HeaderMap, defaults: &http::HeaderMap) {
+    for name in defaults.keys() {
+        if !headers.contains_key(name) {
+            for value in defaults.get_all(name) {
+                headers.append(name, value.clone());
+            }
+        }
+    }
+}
+
+/// The value of the `Accept-Encoding` header sent by clients which decompress
+/// responses using [decompress].
+#[cfg(feature = "decompression")]
+pub const ACCEPT_ENCODING: &str = "gzip, deflate, br, zstd";
+
+/// Decompresses the body of the given [Response] according to its
+/// `Content-Encoding` header.
+///
+/// Supports the `gzip`, `deflate`, `br` and `zstd` encodings, including
+/// multiple encodings applied in sequence. Once decompressed, the
+/// `Content-Encoding` and `Content-Length` headers are removed. Responses
+/// using an unsupported encoding are left untouched.
+#[cfg(feature = "decompression")]
+pub fn decompress(resp: &mut http::Response<Bytes>) -> Result<(), ClientError> {
+    use std::io::Read;
+
+    let encodings: Vec<String> = match resp.headers().get(http::header::CONTENT_ENCODING) {
+        Some(v) => v
+            .to_str()
+            .unwrap_or_default()
+            .split(',')
+            .map(|e| e.trim().to_ascii_lowercase())
+            .filter(|e| !e.is_empty() && e != "identity")
+            .collect(),
+        None => return Ok(()),
+    };
+    if encodings
+        .iter()
+        .any(|e| !matches!(e.as_str(), "gzip" | "x-gzip" | "deflate" | "br" | "zstd"))
+    {
+        return Ok(());
+    }
+
+    // Encodings are listed in the order they were applied
+    let mut body = std::mem::take(resp.body_mut());
+    for encoding in encodings.iter().rev() {
+        let mut out = Vec::new();
+        let result = match encoding.as_str() {
+            "gzip" | "x-gzip" => flate2::read::MultiGzDecoder::new(body.as_ref())
+                .read_to_end(&mut out)
+                .map(|_| ()),
+            "deflate" => flate2::read::ZlibDecoder::new(body.as_ref())
+                .read_to_end(&mut out)
+                .map(|_| ()),
+            "br" => brotli::Decompressor::new(body.as_ref(), 4096)
+                .read_to_end(&mut out)
+                .map(|_| ()),
+            _ => zstd::stream::copy_decode(body.as_ref(), &mut out),
+        };
+        result.map_err(|e| ClientError::ResponseError { source: e.into() })?;
+        body = out.into();
+    }
+
+    *resp.body_mut() = body;
+    resp.headers_mut().remove(http::header::CONTENT_ENCODING);
+    resp.headers_mut().remove(http::header::CONTENT_LENGTH);
+    Ok(())
+}
+
+/// The text which replaces sensitive values in logs and exports.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// The values of the fields marked as sensitive on the
+/// [Endpoint][crate::endpoint::Endpoint] a [Request] was built from.
+///
+/// This is added to the extensions of the request by
+/// [Endpoint::request][crate::endpoint::Endpoint::request] and used for
+/// masking the values wherever the crate logs or exports a request.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Sensitive(pub Vec<String>);
+
+impl Sensitive {
+    /// Replaces every occurrence of a sensitive value in the given text,
+    /// including its URL and JSON encoded forms, with [REDACTED].
+    pub fn redact(&self, text: &str) -> String {
+        let mut forms: Vec<String> = self
+            .0
+            .iter()
+            .filter(|v| !v.is_empty())
+            .flat_map(|v| {
+                let form: String = url::form_urlencoded::byte_serialize(v.as_bytes()).collect();
+                let json = serde_json::to_string(v).unwrap_or_default();
+                vec![
+                    v.clone(),
+                    form.replace('+', "%20"),
+                    form,
+                    json.trim_matches('"').to_string(),
+                ]
+            })
+            .collect();
+        forms.sort_by_key(|f| std::cmp::Reverse(f.len()));
+        forms.dedup();
+        forms.iter().fold(text.to_string(), |text, f| {
+            text.replace(f.as_str(), REDACTED)
+        })
+    }
+}
+
+/// Returns the string forms of the given value which are masked when it's
+/// marked as sensitive. Strings are returned as is, while the items of
+/// sequences, maps and options are visited recursively.
+///
+/// Numbers and booleans are skipped, as masking them would also mask every
+/// unrelated occurrence of the same digits or word in the output. Sensitive
+/// numbers should be stored as strings instead.
+pub fn sensitive_values(value: &impl Serialize) -> Vec<String> {
+    fn collect(value: serde_json::Value, out: &mut Vec<String>) {
+        match value {
+            serde_json::Value::String(s) => out.push(s),
+            serde_json::Value::Array(a) => a.into_iter().for_each(|v| collect(v, out)),
+            serde_json::Value::Object(o) => o.into_iter().for_each(|(_, v)| collect(v, out)),
+            _ => {}
+        }
+    }
+
+    let mut out = Vec::new();
+    if let Ok(v) = serde_json::to_value(value) {
+        collect(v, &mut out);
+    }
+    out
+}
+
+/// Masks any [Sensitive] values of the given [Request] in the given text.
+pub(crate) fn redact(req: &Request<Bytes>, text: &str) -> String {
+    match req.extensions().get::<Sensitive>() {
+        Some(s) => s.redact(text),
+        None => text.to_string(),
+    }
+}
+
+/// Returns the URI of the given [Request] with any [Sensitive] values masked.
+pub(crate) fn redacted_uri(req: &Request<Bytes>) -> String {
+    redact(req, &req.uri().to_string())
+}
+
+/// Returns the value of the given header of a [Request] with any [Sensitive]
+/// values masked. Header values marked as sensitive are masked entirely.
+pub(crate) fn redacted_header(req: &Request<Bytes>, value: &HeaderValue) -> String {
+    match value.is_sensitive() {
+        true => REDACTED.to_string(),
+        false => redact(req, &String::from_utf8_lossy(value.as_bytes())),
+    }
+}
+
+/// Renders the given [Request] as a copy-pasteable `curl` command.
+///
+/// The method, URL, headers and body of the request are included. Bodies
+/// which aren't valid UTF-8 are rendered lossily. Any [Sensitive] values and
+/// header values marked as sensitive are replaced with [REDACTED].
+pub fn to_curl(req: &Request<Bytes>) -> String {
+    let mut cmd = format!(
+        "curl -X {} {}",
+        req.method(),
+        shell_quote(&redacted_uri(req))
+    );
+    for (name, value) in req.headers() {
+        let header = format!("{}: {}", name, redacted_header(req, value));
+        cmd.push_str(&format!(" \\\n  -H {}", shell_quote(&header)));
+    }
+    if !req.body().is_empty() {
+        cmd.push_str(&format!(
+            " \\\n  --data-raw {}",
+            shell_quote(&redact(req, &String::from_utf8_lossy(req.body())))
+        ));
+    }
+    cmd
+}
+
+/// Quotes a string for use as a single argument in a POSIX shell.
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
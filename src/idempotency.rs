@@ -0,0 +1,90 @@
+//! Contains [MiddleWare] for attaching idempotency keys to requests.
+use crate::{
+    endpoint::{Endpoint, MiddleWare},
+    errors::ClientError,
+};
+use bytes::Bytes;
+use http::{header::HeaderName, HeaderValue, Request, Response};
+
+/// A [MiddleWare] which attaches an `Idempotency-Key` header to requests,
+/// allowing servers to safely deduplicate retried requests.
+///
+/// A key identifies a single logical operation, so a new [IdempotencyKey]
+/// should be created for each operation. Every attempt made while executing an
+/// [Endpoint] with the same instance, including retries made by
+/// [exec_with_retry][Endpoint::exec_with_retry], sends the same key. Requests
+/// which already carry the header are left untouched.
+///
+/// # Example
+/// ```
+/// use rustify::clients::reqwest::Client;
+/// use rustify::idempotency::IdempotencyKey;
+/// use rustify::retry::RetryPolicy;
+/// use rustify::{Endpoint, MiddleWare};
+/// use rustify_derive::Endpoint;
+///
+/// #[derive(Endpoint)]
+/// #[endpoint(path = "charges", method = "POST")]
+/// struct CreateCharge {}
+///
+/// # tokio_test::block_on(async {
+/// let client = Client::default("http://myapi.com");
+/// let key = IdempotencyKey::new();
+/// let result = CreateCharge {}
+///     .with_middleware(&key)
+///     .exec_with_retry(&client, &RetryPolicy::default())
+///     .await;
+/// # })
+/// ```
+#[derive(Clone, Debug)]
+pub struct IdempotencyKey {
+    /// The header the key is written to.
+    pub header: HeaderName,
+    key: String,
+}
+
+impl IdempotencyKey {
+    /// Returns a new [IdempotencyKey] with a random UUID as its key.
+    pub fn new() -> Self {
+        IdempotencyKey::from_key(&crate::http::uuid_v4())
+    }
+
+    /// Returns a new [IdempotencyKey] with the given key, which is useful for
+    /// resuming an operation across process restarts.
+    pub fn from_key(key: &str) -> Self {
+        IdempotencyKey {
+            header: HeaderName::from_static("idempotency-key"),
+            key: key.to_string(),
+        }
+    }
+
+    /// Returns the key sent with requests.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+}
+
+impl Default for IdempotencyKey {
+    fn default() -> Self {
+        IdempotencyKey::new()
+    }
+}
+
+impl MiddleWare for IdempotencyKey {
+    fn request<E: Endpoint>(&self, _: &E, req: &mut Request<Bytes>) -> Result<(), ClientError> {
+        if req.headers().contains_key(&self.header) {
+            return Ok(());
+        }
+        let value =
+            HeaderValue::from_str(&self.key).map_err(|e| ClientError::HeaderParseError {
+                source: e.into(),
+                name: self.header.to_string(),
+            })?;
+        req.headers_mut().insert(self.header.clone(), value);
+        Ok(())
+    }
+
+    fn response<E: Endpoint>(&self, _: &E, _: &mut Response<Bytes>) -> Result<(), ClientError> {
+        Ok(())
+    }
+}
@@ -141,6 +141,40 @@
 //! # });
 //! ```
 //!
+//! ### Headers
+//!
+//! ```rust
+//! use rustify::{Client, Endpoint};
+//! use rustify_derive::Endpoint;
+//!
+//! // Defines an API endpoint which sends a static `X-API-Version` header along
+//! // with a per-request `X-API-Token` header taken from the struct. Header names
+//! // default to the field name with underscores replaced by dashes. Endpoints
+//! // implemented by hand can instead override `Endpoint::headers()`.
+//! // An `Accept` header matching the response type is always sent unless
+//! // overridden with `accept = "..."`. Fields tagged with `sensitive` are
+//! // masked in tracing spans, `to_curl()` output and request snapshots.
+//! #[derive(Endpoint)]
+//! #[endpoint(path = "test/path", headers("X-API-Version: 2"))]
+//! struct Test {
+//!     #[endpoint(header, sensitive)]
+//!     pub x_api_token: String,
+//!     #[endpoint(header = "X-Request-Tag")] // Overrides the header name
+//!     pub tag: Option<String>, // Note: the header is skipped when this field is None
+//! }
+//!
+//! # tokio_test::block_on(async {
+//! let endpoint = Test {
+//!     x_api_token: "mytoken".to_string(),
+//!     tag: None,
+//! };
+//! let client = Client::default("http://api.com");
+//! let result = endpoint.exec(&client).await; // Sends GET request with the X-API-Version and X-API-Token headers
+//!
+//! // assert!(result.is_ok());
+//! # });
+//! ```
+//!
 //! ### Responses
 //!
 //! ```should_panic
@@ -173,6 +207,7 @@
 //! // assert!(result.is_ok());
 //!
 //! let response = result.unwrap().parse().unwrap(); // Returns the parsed `TestResponse`
+//! // Alternatively, `endpoint.exec_parse(&client)` executes and parses in one call
 //! // dbg!(response.success);
 //! # });
 //! ```
@@ -194,13 +229,63 @@
 //! The following features are available for this crate:
 //!
 //! * `blocking`: Enables the blocking variants of `Client`s as well as the blocking
-//!    `exec()` functions in `Endpoint`s.
+//!   `exec()` functions in `Endpoint`s.
+//! * `attohttpc`: Enables the blocking `Client` backed by the `attohttpc` crate.
+//! * `attohttpc-tls`: Enables HTTPS support for the `attohttpc` backed `Client`.
+//! * `cbor`: Enables CBOR request and response bodies.
+//! * `cloud`: Enables the `auth::cloud` token sources for the instance metadata
+//!   endpoints of GCP, Azure and AWS (enables `oauth2`).
+//! * `decompression`: Enables decompressing `gzip`, `deflate`, `br` and `zstd`
+//!   encoded responses in `http::decompress()` and the `hyper` backed `Client`.
+//! * `hmac`: Enables the `signing::HmacSigner` middleware for signing requests
+//!   with a shared secret.
+//! * `http2`: Enables HTTP/2 support for the `reqwest` backed `Client`s.
+//! * `httpmock`: Enables helpers for matching `Endpoint`s with `httpmock` (enables
+//!   `test-util`).
+//! * `hyper`: Enables the `Client` backed by the `hyper` crate.
+//! * `hyper-tls`: Enables HTTPS support for the `hyper` backed `Client`.
+//! * `isahc`: Enables the `Client` backed by the `isahc` crate (requires libcurl).
+//! * `jwt`: Enables the `auth::jwt::JwtAuth` provider for authenticating with
+//!   self-signed JSON Web Tokens.
+//! * `msgpack`: Enables MessagePack request and response bodies.
+//! * `native-tls`: Enables HTTPS support for the `reqwest` backed `Client`s
+//!   using the platform's TLS library (enabled by default).
+//! * `non-send`: Removes the `Send` and `Sync` requirements from `Client`s and
+//!   `Endpoint`s, allowing their use on single-threaded runtimes.
+//! * `oauth2`: Enables the `auth::oauth2::ClientCredentials` provider for the
+//!   OAuth2 client credentials flow.
+//! * `prometheus`: Enables the `metrics::prometheus::InstrumentedClient`
+//!   decorator for exporting request metrics in the Prometheus text format.
+//! * `propagation`: Enables the `propagation::TracePropagation` middleware for
+//!   injecting W3C Trace Context or B3 headers.
+//! * `rustls-tls`: Enables HTTPS support for the `reqwest` backed `Client`s
+//!   using `rustls`.
+//! * `test-util`: Enables the `test` module containing the `MockClient`,
+//!   `RecordingClient` and other helpers for testing `Endpoint`s.
+//! * `tracing`: Enables the `tracing` spans and events emitted by `Client`s and
+//!   `Endpoint`s (enabled by default).
+//! * `yaml`: Enables YAML request and response bodies.
 //!
 //! ## Error Handling
 //!
 //! All errors generated by this crate are wrapped in the `ClientError` enum
 //! provided by the crate.
 //!
+//! Endpoints can parse the body of unsuccessful responses into a structured
+//! type by passing `error = "MyApiError"` to the derive macro and executing
+//! them with `ErrorEndpoint::exec_typed()`. The parsed error is returned as
+//! an `EndpointError::Api` holding a `MyApiError`, which defaults to a
+//! `serde_json::Value` when no type is given.
+//!
+//! ## Logging
+//!
+//! All logging is done through the [tracing](https://docs.rs/tracing) crate.
+//! `Client::execute()` runs in a span carrying the `method`, `uri` and
+//! `request_id` of the request and emits a `sending_request` event with the
+//! `method`, `uri` and `body_len` and a `response_received` event with the
+//! `status` and `response_len`. Consumers using the `log` crate instead of a `tracing`
+//! subscriber receive the same events as log records.
+//!
 //! ## Testing
 //!
 //! See the the [tests](tests) directory for tests. Run tests with `cargo test`.
@@ -218,23 +303,47 @@
 //!
 //! [1]: https://github.com/jmgilman/rustify/issues
 
+#[cfg(feature = "tracing")]
 #[macro_use]
 extern crate tracing;
 
+#[cfg(not(feature = "tracing"))]
+#[macro_use]
+mod macros;
+
+pub mod auth;
+pub mod batch;
 #[cfg(feature = "blocking")]
 pub mod blocking;
+pub mod cache;
 pub mod client;
 pub mod clients;
+pub mod config;
+pub mod correlation;
+pub mod csrf;
+pub mod deadline;
 pub mod endpoint;
 pub mod enums;
 pub mod errors;
 pub mod http;
+pub mod idempotency;
+pub mod marker;
+pub mod metrics;
+pub mod pagination;
+#[cfg(feature = "propagation")]
+pub mod propagation;
+pub mod ratelimit;
+pub mod redirect;
+pub mod retry;
+#[cfg(feature = "hmac")]
+pub mod signing;
+#[cfg(feature = "test-util")]
+pub mod test;
 
 #[doc(hidden)]
 #[path = "private/mod.rs"]
 pub mod __private;
 
-pub use crate::{
-    clients::reqwest::Client,
-    endpoint::{Endpoint, MiddleWare, Wrapper},
-};
+#[cfg(feature = "reqwest")]
+pub use crate::clients::reqwest::Client;
+pub use crate::endpoint::{Endpoint, MiddleWare, Wrapper};
@@ -0,0 +1,14 @@
+//! Contains no-op replacements for the [tracing](https://docs.rs/tracing)
+//! macros which are used when the `tracing` feature is disabled.
+
+macro_rules! trace {
+    ($($arg:tt)*) => {};
+}
+
+macro_rules! debug {
+    ($($arg:tt)*) => {};
+}
+
+macro_rules! info {
+    ($($arg:tt)*) => {};
+}
@@ -0,0 +1,25 @@
+//! Contains marker traits used for relaxing thread safety requirements.
+
+/// Requires implementors to be [Send] and [Sync].
+///
+/// When the `non-send` feature is enabled this trait is implemented for all
+/// types, allowing [Endpoints][crate::endpoint::Endpoint] and
+/// [Clients][crate::client::Client] to be used on single-threaded runtimes
+/// which do not require futures to be [Send].
+#[cfg(not(feature = "non-send"))]
+pub trait MaybeSendSync: Send + Sync {}
+
+#[cfg(not(feature = "non-send"))]
+impl<T: Send + Sync> MaybeSendSync for T {}
+
+/// Requires implementors to be [Send] and [Sync].
+///
+/// When the `non-send` feature is enabled this trait is implemented for all
+/// types, allowing [Endpoints][crate::endpoint::Endpoint] and
+/// [Clients][crate::client::Client] to be used on single-threaded runtimes
+/// which do not require futures to be [Send].
+#[cfg(feature = "non-send")]
+pub trait MaybeSendSync {}
+
+#[cfg(feature = "non-send")]
+impl<T> MaybeSendSync for T {}
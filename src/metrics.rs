@@ -0,0 +1,67 @@
+//! Contains the [MetricsObserver] trait for recording metrics about the
+//! requests sent by a [Client][crate::client::Client].
+use crate::marker::MaybeSendSync;
+use http::Method;
+use std::time::Duration;
+
+#[cfg(feature = "prometheus")]
+pub mod prometheus;
+
+/// The [Endpoint::PATH_TEMPLATE][crate::endpoint::Endpoint::PATH_TEMPLATE] of
+/// the endpoint a [Request][http::Request] was built from.
+///
+/// This is added to the extensions of the request by
+/// [Endpoint::request][crate::endpoint::Endpoint::request].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PathTemplate(pub &'static str);
+
+/// The metrics recorded for a single request sent by a
+/// [Client][crate::client::Client].
+#[derive(Clone, Debug)]
+pub struct RequestMetrics<'a> {
+    /// The method of the request.
+    pub method: &'a Method,
+    /// The path of the request URL.
+    pub path: &'a str,
+    /// The path template of the endpoint the request was built from, if any.
+    pub template: Option<&'a str>,
+    /// The status code of the response, or [None] if no response was
+    /// received.
+    pub status: Option<u16>,
+    /// The time taken from sending the request to receiving the response.
+    pub duration: Duration,
+    /// The size of the request body in bytes.
+    pub request_size: usize,
+    /// The size of the response body in bytes, or [None] if no response was
+    /// received or its body is streamed.
+    pub response_size: Option<usize>,
+}
+
+/// Represents a sink for [RequestMetrics] which is invoked by
+/// [Client::execute][crate::client::Client::execute] after every request.
+///
+/// Implementations are called on the hot path of every request and should
+/// avoid blocking, typically by updating counters and histograms in place.
+///
+/// # Example
+/// ```
+/// use rustify::clients::reqwest::Client;
+/// use rustify::metrics::{MetricsObserver, RequestMetrics};
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// #[derive(Default)]
+/// struct Counter(AtomicUsize);
+///
+/// impl MetricsObserver for Counter {
+///     fn observe(&self, _: &RequestMetrics) {
+///         self.0.fetch_add(1, Ordering::Relaxed);
+///     }
+/// }
+///
+/// let mut client = Client::default("http://myapi.com");
+/// client.set_metrics(Counter::default());
+/// ```
+pub trait MetricsObserver: MaybeSendSync {
+    /// Records the metrics of a completed request.
+    fn observe(&self, metrics: &RequestMetrics);
+}
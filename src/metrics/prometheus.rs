@@ -0,0 +1,328 @@
+//! Contains a [Client] decorator which exports request metrics in the
+//! Prometheus text exposition format.
+use crate::{
+    auth::AuthProvider,
+    client::{ByteStream, Client, Drain, SuccessCodes},
+    endpoint::Endpoint,
+    errors::ClientError,
+    http::UrlJoin,
+    metrics::{MetricsObserver, RequestMetrics},
+    retry::RetryPolicy,
+};
+use async_trait::async_trait;
+use bytes::Bytes;
+use http::{HeaderMap, Request, Response};
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{Arc, Mutex},
+};
+
+/// The default latency buckets in seconds, matching the defaults of the
+/// Prometheus client libraries.
+pub const DEFAULT_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// The latency histogram of a single method and path.
+#[derive(Default)]
+struct Histogram {
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+#[derive(Default)]
+struct State {
+    requests: BTreeMap<(String, String, String), u64>,
+    errors: BTreeMap<(String, String), u64>,
+    latency: BTreeMap<(String, String), Histogram>,
+}
+
+/// A [MetricsObserver] which aggregates request metrics into Prometheus
+/// counters and histograms.
+///
+/// The following metrics are recorded, each labeled by `method` and `path`:
+///
+/// * `<namespace>_requests_total`: a counter of all requests, additionally
+///   labeled by the response `status` (or `none` if no response was
+///   received).
+/// * `<namespace>_request_errors_total`: a counter of requests which received
+///   no response or a `4xx`/`5xx` response.
+/// * `<namespace>_request_duration_seconds`: a histogram of request latency.
+///
+/// The `path` label uses the
+/// [Endpoint::PATH_TEMPLATE][crate::endpoint::Endpoint::PATH_TEMPLATE] of the
+/// request when available to avoid creating a series per interpolated path.
+/// The metrics are exported with [PrometheusMetrics::render], typically from
+/// the `/metrics` handler of the consuming service.
+pub struct PrometheusMetrics {
+    namespace: String,
+    buckets: Vec<f64>,
+    state: Mutex<State>,
+}
+
+impl PrometheusMetrics {
+    /// Returns a new [PrometheusMetrics] using the `rustify` namespace and the
+    /// [DEFAULT_BUCKETS].
+    pub fn new() -> Self {
+        PrometheusMetrics {
+            namespace: "rustify".to_string(),
+            buckets: DEFAULT_BUCKETS.to_vec(),
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// Prefixes the names of all metrics with the given namespace.
+    pub fn namespace(mut self, namespace: &str) -> Self {
+        self.namespace = namespace.to_string();
+        self
+    }
+
+    /// Uses the given upper bounds in seconds for the latency buckets.
+    pub fn buckets(mut self, mut buckets: Vec<f64>) -> Self {
+        buckets.sort_by(|a, b| a.total_cmp(b));
+        self.buckets = buckets;
+        self
+    }
+
+    /// Returns all recorded metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let state = self.state.lock().unwrap();
+        let ns = &self.namespace;
+        let mut out = String::new();
+
+        let _ = writeln!(
+            out,
+            "# HELP {}_requests_total The total number of requests sent.",
+            ns
+        );
+        let _ = writeln!(out, "# TYPE {}_requests_total counter", ns);
+        for ((method, path, status), n) in state.requests.iter() {
+            let _ = writeln!(
+                out,
+                "{}_requests_total{{method=\"{}\",path=\"{}\",status=\"{}\"}} {}",
+                ns,
+                escape(method),
+                escape(path),
+                status,
+                n
+            );
+        }
+
+        let _ = writeln!(
+            out,
+            "# HELP {}_request_errors_total The total number of requests which failed.",
+            ns
+        );
+        let _ = writeln!(out, "# TYPE {}_request_errors_total counter", ns);
+        for ((method, path), n) in state.errors.iter() {
+            let _ = writeln!(
+                out,
+                "{}_request_errors_total{{{}}} {}",
+                ns,
+                labels(method, path),
+                n
+            );
+        }
+
+        let _ = writeln!(
+            out,
+            "# HELP {}_request_duration_seconds The latency of requests in seconds.",
+            ns
+        );
+        let _ = writeln!(out, "# TYPE {}_request_duration_seconds histogram", ns);
+        for ((method, path), h) in state.latency.iter() {
+            let labels = labels(method, path);
+            let mut cumulative = 0;
+            for (bound, n) in self.buckets.iter().zip(h.counts.iter()) {
+                cumulative += n;
+                let _ = writeln!(
+                    out,
+                    "{}_request_duration_seconds_bucket{{{},le=\"{}\"}} {}",
+                    ns, labels, bound, cumulative
+                );
+            }
+            let _ = writeln!(
+                out,
+                "{}_request_duration_seconds_bucket{{{},le=\"+Inf\"}} {}",
+                ns, labels, h.count
+            );
+            let _ = writeln!(
+                out,
+                "{}_request_duration_seconds_sum{{{}}} {}",
+                ns, labels, h.sum
+            );
+            let _ = writeln!(
+                out,
+                "{}_request_duration_seconds_count{{{}}} {}",
+                ns, labels, h.count
+            );
+        }
+        out
+    }
+}
+
+impl Default for PrometheusMetrics {
+    fn default() -> Self {
+        PrometheusMetrics::new()
+    }
+}
+
+impl MetricsObserver for PrometheusMetrics {
+    fn observe(&self, metrics: &RequestMetrics) {
+        let method = metrics.method.to_string();
+        let path = match metrics.template {
+            Some(t) => format!("/{}", t.trim_start_matches('/')),
+            None => metrics.path.to_string(),
+        };
+        let status = metrics
+            .status
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| "none".to_string());
+        let seconds = metrics.duration.as_secs_f64();
+
+        let mut state = self.state.lock().unwrap();
+        *state
+            .requests
+            .entry((method.clone(), path.clone(), status))
+            .or_default() += 1;
+        if metrics.status.is_none_or(|s| s >= 400) {
+            *state
+                .errors
+                .entry((method.clone(), path.clone()))
+                .or_default() += 1;
+        }
+
+        let h = state.latency.entry((method, path)).or_default();
+        if h.counts.is_empty() {
+            h.counts = vec![0; self.buckets.len()];
+        }
+        if let Some(i) = self.buckets.iter().position(|b| seconds <= *b) {
+            h.counts[i] += 1;
+        }
+        h.sum += seconds;
+        h.count += 1;
+    }
+}
+
+/// A [Client] decorator which records the metrics of every request in a
+/// [PrometheusMetrics].
+///
+/// The metrics of this client take precedence over any [MetricsObserver] set
+/// on the wrapped client. A single [PrometheusMetrics] can be shared between
+/// several clients using [InstrumentedClient::with_metrics].
+///
+/// # Example
+/// ```
+/// use rustify::clients::reqwest::Client;
+/// use rustify::metrics::prometheus::InstrumentedClient;
+///
+/// let client = InstrumentedClient::new(Client::default("http://myapi.com"));
+/// println!("{}", client.prometheus().render());
+/// ```
+pub struct InstrumentedClient<C> {
+    client: C,
+    metrics: Arc<PrometheusMetrics>,
+}
+
+impl<C> InstrumentedClient<C> {
+    /// Returns a new [InstrumentedClient] wrapping the given client which
+    /// records metrics in a new [PrometheusMetrics].
+    pub fn new(client: C) -> Self {
+        InstrumentedClient::with_metrics(client, Arc::new(PrometheusMetrics::new()))
+    }
+
+    /// Returns a new [InstrumentedClient] wrapping the given client which
+    /// records metrics in the given [PrometheusMetrics].
+    pub fn with_metrics(client: C, metrics: Arc<PrometheusMetrics>) -> Self {
+        InstrumentedClient { client, metrics }
+    }
+
+    /// Returns the [PrometheusMetrics] the metrics are recorded in.
+    pub fn prometheus(&self) -> &Arc<PrometheusMetrics> {
+        &self.metrics
+    }
+
+    /// Returns a reference to the wrapped client.
+    pub fn inner(&self) -> &C {
+        &self.client
+    }
+}
+
+#[cfg_attr(not(feature = "non-send"), async_trait)]
+#[cfg_attr(feature = "non-send", async_trait(?Send))]
+impl<C: Client> Client for InstrumentedClient<C> {
+    async fn send(&self, req: Request<Bytes>) -> Result<Response<Bytes>, ClientError> {
+        self.client.send(req).await
+    }
+
+    async fn send_stream(&self, req: Request<Bytes>) -> Result<Response<ByteStream>, ClientError> {
+        self.client.send_stream(req).await
+    }
+
+    fn base(&self) -> &str {
+        self.client.base()
+    }
+
+    fn drain(&self) -> Option<&Drain> {
+        self.client.drain()
+    }
+
+    fn auth(&self) -> Option<&dyn AuthProvider> {
+        self.client.auth()
+    }
+
+    fn metrics(&self) -> Option<&dyn MetricsObserver> {
+        Some(self.metrics.as_ref())
+    }
+
+    fn retry_policy(&self) -> Option<&RetryPolicy> {
+        self.client.retry_policy()
+    }
+
+    fn success_codes(&self) -> Option<&SuccessCodes> {
+        self.client.success_codes()
+    }
+
+    fn default_headers(&self) -> Option<&HeaderMap> {
+        self.client.default_headers()
+    }
+
+    fn default_query(&self) -> Option<&[(String, String)]> {
+        self.client.default_query()
+    }
+
+    fn url_join(&self) -> UrlJoin {
+        self.client.url_join()
+    }
+
+    fn mutate_request<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        req: &mut Request<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.client.mutate_request(endpoint, req)
+    }
+
+    fn mutate_response<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        resp: &mut Response<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.client.mutate_response(endpoint, resp)
+    }
+}
+
+/// Returns the `method` and `path` labels of a series.
+fn labels(method: &str, path: &str) -> String {
+    format!("method=\"{}\",path=\"{}\"", escape(method), escape(path))
+}
+
+/// Escapes the given label value.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
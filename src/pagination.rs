@@ -0,0 +1,556 @@
+//! Contains the [PaginatedEndpoint] trait for executing endpoints which return
+//! their results across multiple pages.
+use crate::{
+    client::{register, Client, Registered, SuccessCodes},
+    endpoint::{Endpoint, EndpointResult, RequestDefaults, Wrapper},
+    enums::{RequestMethod, RequestType, ResponseType},
+    errors::ClientError,
+    marker::MaybeSendSync,
+    retry::RetryPolicy,
+};
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_core::Stream;
+use futures_util::{stream::try_unfold, TryStreamExt};
+use http::{HeaderMap, HeaderName, HeaderValue, Request, Response, Uri};
+use std::{marker::PhantomData, pin::Pin, sync::Arc, time::Duration};
+use url::Url;
+
+/// A stream of parsed pages returned by [PaginatedEndpoint::exec_pages].
+#[cfg(not(feature = "non-send"))]
+pub type PageStream<'a, T> = Pin<Box<dyn Stream<Item = Result<T, ClientError>> + Send + 'a>>;
+
+/// A stream of parsed pages returned by [PaginatedEndpoint::exec_pages].
+#[cfg(feature = "non-send")]
+pub type PageStream<'a, T> = Pin<Box<dyn Stream<Item = Result<T, ClientError>> + 'a>>;
+
+/// Limits the number of pages requested and items collected by
+/// [PaginatedEndpoint::exec_all].
+///
+/// By default, no limits are applied and every page is requested.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PageLimit {
+    /// The maximum number of pages to request.
+    pub max_pages: Option<usize>,
+    /// The maximum number of items to collect. Items past the limit from the
+    /// last requested page are discarded.
+    pub max_items: Option<usize>,
+}
+
+impl PageLimit {
+    /// Returns a new [PageLimit] with the given maximum number of pages.
+    pub fn new(max_pages: usize) -> Self {
+        PageLimit {
+            max_pages: Some(max_pages),
+            ..Default::default()
+        }
+    }
+}
+
+/// Represents an [Endpoint] whose results are split across multiple pages.
+///
+/// Implementations determine how the request for the next page is produced
+/// from the response of the current page, typically by copying the endpoint
+/// and updating a page number, offset or cursor field. The pages can then be
+/// consumed as a [Stream] using [PaginatedEndpoint::exec_pages] rather than
+/// writing the request loop by hand.
+///
+/// # Example
+/// ```
+/// use futures_util::TryStreamExt;
+/// use rustify::clients::reqwest::Client;
+/// use rustify::endpoint::{Endpoint, EndpointResult};
+/// use rustify::errors::ClientError;
+/// use rustify::pagination::PaginatedEndpoint;
+/// use rustify_derive::Endpoint;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Users {
+///     page: u64,
+///     total_pages: u64,
+///     data: Vec<String>,
+/// }
+///
+/// #[derive(Endpoint)]
+/// #[endpoint(path = "users", response = "Users")]
+/// struct ListUsers {
+///     #[endpoint(query)]
+///     page: u64,
+/// }
+///
+/// impl PaginatedEndpoint for ListUsers {
+///     fn next_page(
+///         &self,
+///         result: &EndpointResult<Users>,
+///     ) -> Result<Option<Self>, ClientError> {
+///         let users = result.parse()?;
+///         Ok((users.page < users.total_pages).then(|| ListUsers { page: users.page + 1 }))
+///     }
+/// }
+///
+/// # tokio_test::block_on(async {
+/// let client = Client::default("http://myapi.com");
+/// let pages: Result<Vec<Users>, ClientError> =
+///     ListUsers { page: 1 }.exec_pages(&client).try_collect().await;
+/// # })
+/// ```
+#[cfg_attr(not(feature = "non-send"), async_trait)]
+#[cfg_attr(feature = "non-send", async_trait(?Send))]
+pub trait PaginatedEndpoint: Endpoint {
+    /// Returns the endpoint which requests the page following the given
+    /// result, or [None] if the result is the last page.
+    fn next_page(
+        &self,
+        result: &EndpointResult<Self::Response>,
+    ) -> Result<Option<Self>, ClientError>;
+
+    /// Executes the Endpoint and every following page using the given
+    /// [Client], returning a [Stream] of the parsed pages.
+    ///
+    /// Pages are requested lazily as the stream is polled. The stream ends
+    /// after the last page or after yielding the first error.
+    fn exec_pages<'a, C: Client>(self, client: &'a C) -> PageStream<'a, Self::Response>
+    where
+        Self: 'a,
+    {
+        // The execution is registered with the drain of the client when the
+        // first page is requested and stays registered until the last one
+        Box::pin(try_unfold(
+            (Some(self), None),
+            move |(endpoint, guard)| async move {
+                let endpoint = match endpoint {
+                    Some(e) => e,
+                    None => return Ok(None),
+                };
+                let guard = match guard {
+                    Some(g) => g,
+                    None => register(client.drain())?,
+                };
+                let result = endpoint.exec(&Registered(client)).await?;
+                let next = endpoint.next_page(&result)?;
+                let guard = next.is_some().then_some(guard);
+                Ok(Some((result.parse()?, (next, guard))))
+            },
+        ))
+    }
+
+    /// Executes the Endpoint and every following page using the given
+    /// [Client], concatenating the items of each page into a single [Vec].
+    ///
+    /// Pages are requested until the last page is reached or until the given
+    /// [PageLimit] is exceeded, in which case the items collected so far are
+    /// returned.
+    async fn exec_all<C, T>(self, client: &C, limit: PageLimit) -> Result<Vec<T>, ClientError>
+    where
+        C: Client,
+        Self::Response: IntoIterator<Item = T>,
+        T: MaybeSendSync,
+    {
+        let mut pages = self.exec_pages(client);
+        let mut items = Vec::new();
+        let mut count = 0;
+        while limit.max_pages.is_none_or(|max| count < max) {
+            let page = match pages.try_next().await? {
+                Some(p) => p,
+                None => break,
+            };
+            count += 1;
+            items.extend(page);
+
+            if let Some(max) = limit.max_items {
+                if items.len() >= max {
+                    items.truncate(max);
+                    break;
+                }
+            }
+        }
+        Ok(items)
+    }
+}
+
+/// A [PaginatedEndpoint] which follows the `Link` header (RFC 5988) of each
+/// response to request the next page.
+///
+/// APIs such as GitHub's advertise the location of the next page with a
+/// `Link: <https://api.example.com/items?page=2>; rel="next"` response header.
+/// The wrapped endpoint is used to request the first page and every following
+/// page is requested from the advertised URL, keeping the method, headers and
+/// body of the wrapped endpoint. Pages are yielded until a response no longer
+/// contains a `next` link. Relative links are resolved against the base URL
+/// of the [Client].
+///
+/// # Example
+/// ```
+/// use futures_util::TryStreamExt;
+/// use rustify::clients::reqwest::Client;
+/// use rustify::errors::ClientError;
+/// use rustify::pagination::{LinkPaginator, PaginatedEndpoint};
+/// use rustify_derive::Endpoint;
+///
+/// #[derive(Endpoint)]
+/// #[endpoint(path = "repos", response = "Vec<String>")]
+/// struct ListRepos {}
+///
+/// # tokio_test::block_on(async {
+/// let client = Client::default("http://myapi.com");
+/// let pages: Result<Vec<Vec<String>>, ClientError> = LinkPaginator::new(ListRepos {})
+///     .exec_pages(&client)
+///     .try_collect()
+///     .await;
+/// # })
+/// ```
+pub struct LinkPaginator<E: Endpoint> {
+    endpoint: Arc<E>,
+    next: Option<String>,
+}
+
+impl<E: Endpoint> LinkPaginator<E> {
+    /// Returns a new [LinkPaginator] which requests the first page using the
+    /// given endpoint.
+    pub fn new(endpoint: E) -> Self {
+        LinkPaginator {
+            endpoint: Arc::new(endpoint),
+            next: None,
+        }
+    }
+
+    /// Returns a reference to the wrapped endpoint.
+    pub fn inner(&self) -> &E {
+        &self.endpoint
+    }
+
+    /// Returns the URL of the page requested by this paginator, resolving
+    /// relative links against the given base URL.
+    fn next_url(&self, base: &str, next: &str) -> Result<Uri, ClientError> {
+        let url = Url::parse(base)
+            .and_then(|b| b.join(next))
+            .map_err(|e| ClientError::UrlParseError { source: e })?;
+        url.as_str()
+            .parse::<Uri>()
+            .map_err(|e| ClientError::UrlBuildError { source: e })
+    }
+}
+
+#[cfg_attr(not(feature = "non-send"), async_trait)]
+#[cfg_attr(feature = "non-send", async_trait(?Send))]
+impl<E: Endpoint> Endpoint for LinkPaginator<E> {
+    type Response = E::Response;
+    const REQUEST_BODY_TYPE: RequestType = E::REQUEST_BODY_TYPE;
+    const RESPONSE_BODY_TYPE: ResponseType = E::RESPONSE_BODY_TYPE;
+    const STRICT_PARSING: bool = E::STRICT_PARSING;
+    const PATH_TEMPLATE: Option<&'static str> = E::PATH_TEMPLATE;
+
+    fn name() -> &'static str {
+        E::name()
+    }
+
+    fn path(&self) -> String {
+        self.endpoint.path()
+    }
+
+    fn method(&self) -> RequestMethod {
+        self.endpoint.method()
+    }
+
+    fn query(&self) -> Result<Option<String>, ClientError> {
+        self.endpoint.query()
+    }
+
+    fn body(&self) -> Result<Option<Vec<u8>>, ClientError> {
+        self.endpoint.body()
+    }
+
+    fn headers(&self) -> Result<Vec<(HeaderName, HeaderValue)>, ClientError> {
+        self.endpoint.headers()
+    }
+
+    fn sensitive(&self) -> Vec<String> {
+        self.endpoint.sensitive()
+    }
+
+    fn accept(&self) -> Option<&str> {
+        self.endpoint.accept()
+    }
+
+    fn success_codes(&self) -> Option<SuccessCodes> {
+        self.endpoint.success_codes()
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.endpoint.timeout()
+    }
+
+    fn retry_policy(&self) -> Option<RetryPolicy> {
+        self.endpoint.retry_policy()
+    }
+
+    #[cfg_attr(feature = "tracing", instrument(skip(self), err))]
+    fn url(&self, base: &str) -> Result<Uri, ClientError> {
+        match &self.next {
+            Some(next) => self.next_url(base, next),
+            None => self.endpoint.url(base),
+        }
+    }
+
+    #[cfg_attr(feature = "tracing", instrument(skip(self, defaults), err))]
+    fn request_with(
+        &self,
+        base: &str,
+        defaults: &RequestDefaults,
+    ) -> Result<Request<Bytes>, ClientError> {
+        let mut req = self.endpoint.request_with(base, defaults)?;
+        if let Some(next) = &self.next {
+            *req.uri_mut() = self.next_url(base, next)?;
+            if let Some(query) = defaults.query.filter(|q| !q.is_empty()) {
+                *req.uri_mut() = crate::http::merge_query(req.uri(), query)?;
+            }
+        }
+        Ok(req)
+    }
+
+    fn mutate_response(&self, resp: &mut Response<Bytes>) -> Result<(), ClientError> {
+        self.endpoint.mutate_response(resp)
+    }
+}
+
+impl<E: Endpoint> PaginatedEndpoint for LinkPaginator<E> {
+    fn next_page(
+        &self,
+        result: &EndpointResult<Self::Response>,
+    ) -> Result<Option<Self>, ClientError> {
+        Ok(next_link(result.headers()).map(|next| LinkPaginator {
+            endpoint: self.endpoint.clone(),
+            next: Some(next),
+        }))
+    }
+}
+
+/// Represents an [Endpoint] which requests a specific page using a cursor
+/// returned by the previous page.
+///
+/// The derive macro implements this trait when a field is tagged with
+/// `#[endpoint(query, cursor)]`, in which case the cursor is assigned to the
+/// field using [Into] (i.e. a field of type `Option<String>`).
+pub trait CursorEndpoint: Endpoint {
+    /// Sets the cursor of the page requested by this endpoint.
+    fn set_cursor(&mut self, cursor: String);
+}
+
+/// A [PaginatedEndpoint] which feeds the cursor advertised by a [Wrapper]
+/// back into the wrapped [CursorEndpoint] to request the next page.
+///
+/// Each page is parsed from the response as the [Wrapper] and the cursor of
+/// the next page is read from the location given by [Wrapper::CURSOR]. Pages
+/// are yielded until the cursor is missing, `null` or an empty string.
+///
+/// # Example
+/// ```
+/// use futures_util::TryStreamExt;
+/// use rustify::clients::reqwest::Client;
+/// use rustify::endpoint::Wrapper;
+/// use rustify::errors::ClientError;
+/// use rustify::pagination::{CursorPaginator, PaginatedEndpoint};
+/// use rustify_derive::Endpoint;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Page<T> {
+///     items: T,
+///     next_cursor: Option<String>,
+/// }
+///
+/// impl<T: serde::de::DeserializeOwned + Send + Sync> Wrapper for Page<T> {
+///     type Value = T;
+///     const CURSOR: Option<&'static str> = Some("/next_cursor");
+/// }
+///
+/// #[derive(Clone, Endpoint)]
+/// #[endpoint(path = "users", response = "Vec<String>")]
+/// struct ListUsers {
+///     #[endpoint(query, cursor)]
+///     cursor: Option<String>,
+/// }
+///
+/// # tokio_test::block_on(async {
+/// let client = Client::default("http://myapi.com");
+/// let pages: Result<Vec<Page<Vec<String>>>, ClientError> =
+///     CursorPaginator::<_, Page<_>>::new(ListUsers { cursor: None })
+///         .exec_pages(&client)
+///         .try_collect()
+///         .await;
+/// # })
+/// ```
+pub struct CursorPaginator<E, W> {
+    endpoint: E,
+    wrapper: PhantomData<fn() -> W>,
+}
+
+impl<E, W> CursorPaginator<E, W>
+where
+    E: CursorEndpoint + Clone,
+    W: Wrapper<Value = E::Response>,
+{
+    /// Returns a new [CursorPaginator] which requests the first page using
+    /// the given endpoint.
+    pub fn new(endpoint: E) -> Self {
+        CursorPaginator {
+            endpoint,
+            wrapper: PhantomData,
+        }
+    }
+
+    /// Returns a reference to the wrapped endpoint.
+    pub fn inner(&self) -> &E {
+        &self.endpoint
+    }
+}
+
+#[cfg_attr(not(feature = "non-send"), async_trait)]
+#[cfg_attr(feature = "non-send", async_trait(?Send))]
+impl<E, W> Endpoint for CursorPaginator<E, W>
+where
+    E: CursorEndpoint + Clone,
+    W: Wrapper<Value = E::Response>,
+{
+    type Response = W;
+    const REQUEST_BODY_TYPE: RequestType = E::REQUEST_BODY_TYPE;
+    const RESPONSE_BODY_TYPE: ResponseType = E::RESPONSE_BODY_TYPE;
+    const STRICT_PARSING: bool = E::STRICT_PARSING;
+    const PATH_TEMPLATE: Option<&'static str> = E::PATH_TEMPLATE;
+
+    fn name() -> &'static str {
+        E::name()
+    }
+
+    fn path(&self) -> String {
+        self.endpoint.path()
+    }
+
+    fn method(&self) -> RequestMethod {
+        self.endpoint.method()
+    }
+
+    fn query(&self) -> Result<Option<String>, ClientError> {
+        self.endpoint.query()
+    }
+
+    fn body(&self) -> Result<Option<Vec<u8>>, ClientError> {
+        self.endpoint.body()
+    }
+
+    fn headers(&self) -> Result<Vec<(HeaderName, HeaderValue)>, ClientError> {
+        self.endpoint.headers()
+    }
+
+    fn sensitive(&self) -> Vec<String> {
+        self.endpoint.sensitive()
+    }
+
+    fn accept(&self) -> Option<&str> {
+        self.endpoint.accept()
+    }
+
+    fn success_codes(&self) -> Option<SuccessCodes> {
+        self.endpoint.success_codes()
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.endpoint.timeout()
+    }
+
+    fn retry_policy(&self) -> Option<RetryPolicy> {
+        self.endpoint.retry_policy()
+    }
+
+    #[cfg_attr(feature = "tracing", instrument(skip(self), err))]
+    fn url(&self, base: &str) -> Result<Uri, ClientError> {
+        self.endpoint.url(base)
+    }
+
+    #[cfg_attr(feature = "tracing", instrument(skip(self, defaults), err))]
+    fn request_with(
+        &self,
+        base: &str,
+        defaults: &RequestDefaults,
+    ) -> Result<Request<Bytes>, ClientError> {
+        self.endpoint.request_with(base, defaults)
+    }
+
+    fn mutate_response(&self, resp: &mut Response<Bytes>) -> Result<(), ClientError> {
+        self.endpoint.mutate_response(resp)
+    }
+}
+
+impl<E, W> PaginatedEndpoint for CursorPaginator<E, W>
+where
+    E: CursorEndpoint + Clone,
+    W: Wrapper<Value = E::Response>,
+{
+    fn next_page(
+        &self,
+        result: &EndpointResult<Self::Response>,
+    ) -> Result<Option<Self>, ClientError> {
+        let pointer = match W::CURSOR {
+            Some(p) => p,
+            None => return Ok(None),
+        };
+        let cursor = match result.parse_value()?.pointer(pointer) {
+            Some(serde_json::Value::String(s)) if !s.is_empty() => s.clone(),
+            Some(serde_json::Value::Number(n)) => n.to_string(),
+            _ => return Ok(None),
+        };
+
+        let mut endpoint = self.endpoint.clone();
+        endpoint.set_cursor(cursor);
+        Ok(Some(CursorPaginator::new(endpoint)))
+    }
+}
+
+/// Returns the target of the `Link` header entry with a relation type of
+/// `next` from the given headers, if present.
+pub fn next_link(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get_all(http::header::LINK)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(parse_links)
+        .find(|(_, rel)| {
+            rel.split_whitespace()
+                .any(|r| r.eq_ignore_ascii_case("next"))
+        })
+        .map(|(target, _)| target)
+}
+
+/// Parses the value of a `Link` header into pairs of targets and relation
+/// types (i.e. `<https://api.example.com/items?page=2>; rel="next"`).
+fn parse_links(value: &str) -> Vec<(String, String)> {
+    let mut links = Vec::new();
+    let mut rest = value;
+    while let Some(start) = rest.find('<') {
+        let end = match rest[start..].find('>') {
+            Some(e) => start + e,
+            None => break,
+        };
+        let params = &rest[end + 1..];
+        let params_end = params.find('<').unwrap_or(params.len());
+        let rel = params[..params_end]
+            .split(';')
+            .filter_map(|p| {
+                let (k, v) = p.split_once('=')?;
+                k.trim().eq_ignore_ascii_case("rel").then(|| {
+                    v.trim()
+                        .trim_end_matches(',')
+                        .trim()
+                        .trim_matches('"')
+                        .to_string()
+                })
+            })
+            .next()
+            .unwrap_or_default();
+
+        links.push((rest[start + 1..end].to_string(), rel));
+        rest = &params[params_end..];
+    }
+    links
+}
@@ -1 +1,3 @@
+pub use ::http;
 pub use serde;
+pub use serde_json;
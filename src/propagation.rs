@@ -0,0 +1,209 @@
+//! Contains [MiddleWare] for propagating distributed tracing context to
+//! downstream services.
+use crate::{
+    endpoint::{Endpoint, MiddleWare},
+    errors::ClientError,
+};
+use bytes::Bytes;
+use http::{header::HeaderName, HeaderValue, Request, Response};
+use std::sync::Arc;
+
+/// The header carrying the trace and parent span IDs in the W3C Trace Context
+/// format.
+pub const TRACEPARENT: &str = "traceparent";
+
+/// The header carrying vendor-specific trace state in the W3C Trace Context
+/// format.
+pub const TRACESTATE: &str = "tracestate";
+
+/// The header carrying the trace context in the single header B3 format.
+pub const B3: &str = "b3";
+
+/// The format used when injecting the trace context into requests.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PropagationFormat {
+    /// The W3C Trace Context `traceparent` and `tracestate` headers.
+    TraceContext,
+    /// The single `b3` header used by Zipkin.
+    B3Single,
+    /// The multiple `X-B3-*` headers used by Zipkin.
+    B3Multi,
+}
+
+/// The context of the span a request is sent from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceContext {
+    /// The 128-bit ID of the trace.
+    pub trace_id: u128,
+    /// The 64-bit ID of the span the request is sent from.
+    pub span_id: u64,
+    /// Whether the trace is being recorded.
+    pub sampled: bool,
+    /// The vendor-specific trace state, if any.
+    pub state: Option<String>,
+}
+
+impl TraceContext {
+    /// Returns a new sampled [TraceContext] which starts a new trace.
+    pub fn root() -> Self {
+        TraceContext {
+            trace_id: fastrand::u128(1..),
+            span_id: fastrand::u64(1..),
+            sampled: true,
+            state: None,
+        }
+    }
+
+    /// Parses the given `traceparent` and `tracestate` header values, returning
+    /// [None] if the `traceparent` is invalid.
+    pub fn parse(traceparent: &str, tracestate: Option<&str>) -> Option<Self> {
+        let parts: Vec<&str> = traceparent.trim().split('-').collect();
+        match parts.as_slice() {
+            [version, trace, span, flags]
+                if version.len() == 2
+                    && *version != "ff"
+                    && trace.len() == 32
+                    && span.len() == 16
+                    && flags.len() == 2 =>
+            {
+                let trace_id = u128::from_str_radix(trace, 16).ok().filter(|t| *t != 0)?;
+                let span_id = u64::from_str_radix(span, 16).ok().filter(|s| *s != 0)?;
+                let flags = u8::from_str_radix(flags, 16).ok()?;
+                Some(TraceContext {
+                    trace_id,
+                    span_id,
+                    sampled: flags & 1 == 1,
+                    state: tracestate.map(|s| s.to_string()),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the `traceparent` header value for this context.
+    pub fn traceparent(&self) -> String {
+        format!(
+            "00-{:032x}-{:016x}-{:02x}",
+            self.trace_id, self.span_id, self.sampled as u8
+        )
+    }
+
+    /// Returns the single `b3` header value for this context.
+    pub fn b3(&self) -> String {
+        format!(
+            "{:032x}-{:016x}-{}",
+            self.trace_id, self.span_id, self.sampled as u8
+        )
+    }
+}
+
+/// A [MiddleWare] which injects the trace context into every request so that
+/// it participates in the caller's distributed trace.
+///
+/// The W3C `traceparent` and `tracestate` headers are used by default, while
+/// infrastructure standardized on Zipkin can select one of the B3 formats with
+/// [format][TracePropagation::format]. The context is read from a source function each time a request is sent,
+/// which is normally bridged to the active OpenTelemetry context of the
+/// current [tracing] span. When the source returns [None] a new trace is
+/// started for the request. Requests which already carry a trace context
+/// header of the selected format are left untouched.
+///
+/// # Example
+/// ```ignore
+/// use opentelemetry::trace::TraceContextExt;
+/// use rustify::propagation::{TraceContext, TracePropagation};
+/// use tracing_opentelemetry::OpenTelemetrySpanExt;
+///
+/// let propagation = TracePropagation::new(|| {
+///     let context = tracing::Span::current().context();
+///     let span = context.span();
+///     let span = span.span_context();
+///     span.is_valid().then(|| TraceContext {
+///         trace_id: u128::from_be_bytes(span.trace_id().to_bytes()),
+///         span_id: u64::from_be_bytes(span.span_id().to_bytes()),
+///         sampled: span.is_sampled(),
+///         state: Some(span.trace_state().header()).filter(|s| !s.is_empty()),
+///     })
+/// });
+/// ```
+#[derive(Clone)]
+pub struct TracePropagation {
+    source: Arc<dyn Fn() -> Option<TraceContext> + Send + Sync>,
+    format: PropagationFormat,
+}
+
+impl TracePropagation {
+    /// Returns a new [TracePropagation] which reads the context from the given
+    /// function.
+    pub fn new<F>(source: F) -> Self
+    where
+        F: Fn() -> Option<TraceContext> + Send + Sync + 'static,
+    {
+        TracePropagation {
+            source: Arc::new(source),
+            format: PropagationFormat::TraceContext,
+        }
+    }
+
+    /// Injects the trace context using the given format.
+    pub fn format(mut self, format: PropagationFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Returns a new [TracePropagation] which starts a new trace for every
+    /// request.
+    pub fn root() -> Self {
+        TracePropagation::new(|| None)
+    }
+
+    /// Returns the context to propagate for the next request.
+    pub fn context(&self) -> TraceContext {
+        (self.source)().unwrap_or_else(TraceContext::root)
+    }
+}
+
+impl MiddleWare for TracePropagation {
+    fn request<E: Endpoint>(&self, _: &E, req: &mut Request<Bytes>) -> Result<(), ClientError> {
+        let existing = match self.format {
+            PropagationFormat::TraceContext => TRACEPARENT,
+            PropagationFormat::B3Single => B3,
+            PropagationFormat::B3Multi => "x-b3-traceid",
+        };
+        if req.headers().contains_key(existing) {
+            return Ok(());
+        }
+
+        let context = self.context();
+        match self.format {
+            PropagationFormat::TraceContext => {
+                insert(req, TRACEPARENT, &context.traceparent())?;
+                if let Some(state) = &context.state {
+                    insert(req, TRACESTATE, state)?;
+                }
+            }
+            PropagationFormat::B3Single => insert(req, B3, &context.b3())?,
+            PropagationFormat::B3Multi => {
+                insert(req, "x-b3-traceid", &format!("{:032x}", context.trace_id))?;
+                insert(req, "x-b3-spanid", &format!("{:016x}", context.span_id))?;
+                insert(req, "x-b3-sampled", if context.sampled { "1" } else { "0" })?;
+            }
+        }
+        Ok(())
+    }
+
+    fn response<E: Endpoint>(&self, _: &E, _: &mut Response<Bytes>) -> Result<(), ClientError> {
+        Ok(())
+    }
+}
+
+/// Inserts the given header into the given [Request].
+fn insert(req: &mut Request<Bytes>, name: &'static str, value: &str) -> Result<(), ClientError> {
+    let value = HeaderValue::from_str(value).map_err(|e| ClientError::HeaderParseError {
+        source: e.into(),
+        name: name.to_string(),
+    })?;
+    req.headers_mut()
+        .insert(HeaderName::from_static(name), value);
+    Ok(())
+}
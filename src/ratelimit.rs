@@ -0,0 +1,601 @@
+//! Contains [Client] decorators for dealing with rate and concurrency limited
+//! APIs.
+#[cfg(feature = "blocking")]
+use crate::blocking::client::Client as BlockingClient;
+use crate::{
+    auth::AuthProvider,
+    client::{hold, ByteStream, Client, Drain, SuccessCodes},
+    endpoint::Endpoint,
+    errors::ClientError,
+    http::UrlJoin,
+    metrics::MetricsObserver,
+    retry::RetryPolicy,
+};
+use async_trait::async_trait;
+use bytes::Bytes;
+use http::{HeaderMap, Request, Response, StatusCode};
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::Semaphore;
+
+/// A [Client] decorator which honors rate limit responses from the server.
+///
+/// When the server responds with `429 Too Many Requests`, the request is
+/// resent after waiting for the delay advertised by the `Retry-After` or
+/// `X-RateLimit-Reset` response headers. If neither header is present, or they
+/// cannot be parsed, the `default_delay` is used instead. Delays are capped at
+/// `max_delay` and the request is resent at most `max_retries` times before the
+/// `429` response is returned.
+///
+/// # Example
+/// ```
+/// use rustify::clients::reqwest::Client;
+/// use rustify::ratelimit::ThrottleClient;
+///
+/// let client = ThrottleClient::new(Client::default("http://myapi.com"));
+/// ```
+pub struct ThrottleClient<C> {
+    client: C,
+    /// The maximum number of times a single request is resent.
+    pub max_retries: u32,
+    /// The delay used when the server doesn't advertise one.
+    pub default_delay: Duration,
+    /// The upper bound on the delay between attempts.
+    pub max_delay: Duration,
+}
+
+impl<C> ThrottleClient<C> {
+    /// Returns a new [ThrottleClient] wrapping the given client using a
+    /// default of five retries, a one second default delay and a maximum delay
+    /// of one minute.
+    pub fn new(client: C) -> Self {
+        ThrottleClient {
+            client,
+            max_retries: 5,
+            default_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+        }
+    }
+
+    /// Returns a reference to the wrapped client.
+    pub fn inner(&self) -> &C {
+        &self.client
+    }
+
+    /// Returns the delay to wait before resending a request which received
+    /// the given rate limited response.
+    fn delay(&self, headers: &HeaderMap) -> Duration {
+        retry_after(headers)
+            .unwrap_or(self.default_delay)
+            .min(self.max_delay)
+    }
+}
+
+#[cfg_attr(not(feature = "non-send"), async_trait)]
+#[cfg_attr(feature = "non-send", async_trait(?Send))]
+impl<C: Client> Client for ThrottleClient<C> {
+    #[cfg_attr(feature = "tracing", instrument(skip(self, req), err))]
+    async fn send(&self, req: Request<Bytes>) -> Result<Response<Bytes>, ClientError> {
+        let mut retries = 0;
+        loop {
+            let resp = self.client.send(crate::http::clone_request(&req)).await?;
+            if resp.status() != StatusCode::TOO_MANY_REQUESTS || retries >= self.max_retries {
+                return Ok(resp);
+            }
+
+            retries += 1;
+            let delay = self.delay(resp.headers());
+            debug!(retries, delay_ms = delay.as_millis() as u64, "Rate limited");
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    async fn send_stream(&self, req: Request<Bytes>) -> Result<Response<ByteStream>, ClientError> {
+        let mut retries = 0;
+        loop {
+            let resp = self
+                .client
+                .send_stream(crate::http::clone_request(&req))
+                .await?;
+            if resp.status() != StatusCode::TOO_MANY_REQUESTS || retries >= self.max_retries {
+                return Ok(resp);
+            }
+
+            retries += 1;
+            let delay = self.delay(resp.headers());
+            debug!(retries, delay_ms = delay.as_millis() as u64, "Rate limited");
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    fn base(&self) -> &str {
+        self.client.base()
+    }
+
+    fn drain(&self) -> Option<&Drain> {
+        self.client.drain()
+    }
+
+    fn auth(&self) -> Option<&dyn AuthProvider> {
+        self.client.auth()
+    }
+
+    fn metrics(&self) -> Option<&dyn MetricsObserver> {
+        self.client.metrics()
+    }
+
+    fn retry_policy(&self) -> Option<&RetryPolicy> {
+        self.client.retry_policy()
+    }
+
+    fn success_codes(&self) -> Option<&SuccessCodes> {
+        self.client.success_codes()
+    }
+
+    fn default_headers(&self) -> Option<&HeaderMap> {
+        self.client.default_headers()
+    }
+
+    fn default_query(&self) -> Option<&[(String, String)]> {
+        self.client.default_query()
+    }
+
+    fn url_join(&self) -> UrlJoin {
+        self.client.url_join()
+    }
+
+    fn mutate_request<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        req: &mut Request<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.client.mutate_request(endpoint, req)
+    }
+
+    fn mutate_response<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        resp: &mut Response<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.client.mutate_response(endpoint, resp)
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl<C: BlockingClient> BlockingClient for ThrottleClient<C> {
+    #[cfg_attr(feature = "tracing", instrument(skip(self, req), err))]
+    fn send(&self, req: Request<Bytes>) -> Result<Response<Bytes>, ClientError> {
+        let mut retries = 0;
+        loop {
+            let resp = self.client.send(crate::http::clone_request(&req))?;
+            if resp.status() != StatusCode::TOO_MANY_REQUESTS || retries >= self.max_retries {
+                return Ok(resp);
+            }
+
+            retries += 1;
+            let delay = self.delay(resp.headers());
+            debug!(retries, delay_ms = delay.as_millis() as u64, "Rate limited");
+            std::thread::sleep(delay);
+        }
+    }
+
+    fn base(&self) -> &str {
+        self.client.base()
+    }
+
+    fn drain(&self) -> Option<&Drain> {
+        self.client.drain()
+    }
+
+    fn auth(&self) -> Option<&dyn AuthProvider> {
+        self.client.auth()
+    }
+
+    fn metrics(&self) -> Option<&dyn MetricsObserver> {
+        self.client.metrics()
+    }
+
+    fn retry_policy(&self) -> Option<&RetryPolicy> {
+        self.client.retry_policy()
+    }
+
+    fn success_codes(&self) -> Option<&SuccessCodes> {
+        self.client.success_codes()
+    }
+
+    fn default_headers(&self) -> Option<&HeaderMap> {
+        self.client.default_headers()
+    }
+
+    fn default_query(&self) -> Option<&[(String, String)]> {
+        self.client.default_query()
+    }
+
+    fn url_join(&self) -> UrlJoin {
+        self.client.url_join()
+    }
+
+    fn mutate_request<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        req: &mut Request<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.client.mutate_request(endpoint, req)
+    }
+
+    fn mutate_response<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        resp: &mut Response<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.client.mutate_response(endpoint, resp)
+    }
+}
+
+/// A [Client] decorator which limits the rate at which requests are sent using
+/// a token bucket.
+///
+/// The bucket holds up to `burst` tokens and is refilled at a rate of
+/// `requests_per_second`. Each request consumes a single token and requests
+/// made while the bucket is empty wait until a token becomes available.
+///
+/// # Example
+/// ```
+/// use rustify::clients::reqwest::Client;
+/// use rustify::ratelimit::RateLimitedClient;
+///
+/// // Allows an average of 10 requests per second with bursts of up to 20
+/// let client = RateLimitedClient::new(Client::default("http://myapi.com"), 10.0, 20);
+/// ```
+pub struct RateLimitedClient<C> {
+    client: C,
+    bucket: Mutex<TokenBucket>,
+}
+
+struct TokenBucket {
+    rate: f64,
+    capacity: f64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl TokenBucket {
+    /// Attempts to take a token from the bucket, returning how long to wait
+    /// before trying again if the bucket is empty.
+    fn take(&mut self) -> Option<Duration> {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.last = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            None
+        } else {
+            Some(Duration::from_secs_f64((1.0 - self.tokens) / self.rate))
+        }
+    }
+}
+
+impl<C> RateLimitedClient<C> {
+    /// Returns a new [RateLimitedClient] wrapping the given client which
+    /// allows an average of `requests_per_second` with bursts of up to
+    /// `burst` requests. The bucket starts full.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `requests_per_second` is not positive.
+    pub fn new(client: C, requests_per_second: f64, burst: u32) -> Self {
+        assert!(
+            requests_per_second > 0.0,
+            "requests_per_second must be positive"
+        );
+        let capacity = f64::from(burst.max(1));
+        RateLimitedClient {
+            client,
+            bucket: Mutex::new(TokenBucket {
+                rate: requests_per_second,
+                capacity,
+                tokens: capacity,
+                last: Instant::now(),
+            }),
+        }
+    }
+
+    /// Returns a reference to the wrapped client.
+    pub fn inner(&self) -> &C {
+        &self.client
+    }
+
+    fn take(&self) -> Option<Duration> {
+        self.bucket.lock().unwrap().take()
+    }
+}
+
+#[cfg_attr(not(feature = "non-send"), async_trait)]
+#[cfg_attr(feature = "non-send", async_trait(?Send))]
+impl<C: Client> Client for RateLimitedClient<C> {
+    async fn send(&self, req: Request<Bytes>) -> Result<Response<Bytes>, ClientError> {
+        while let Some(wait) = self.take() {
+            trace!(wait_ms = wait.as_millis() as u64, "Waiting for rate limit");
+            tokio::time::sleep(wait).await;
+        }
+        self.client.send(req).await
+    }
+
+    async fn send_stream(&self, req: Request<Bytes>) -> Result<Response<ByteStream>, ClientError> {
+        while let Some(wait) = self.take() {
+            trace!(wait_ms = wait.as_millis() as u64, "Waiting for rate limit");
+            tokio::time::sleep(wait).await;
+        }
+        self.client.send_stream(req).await
+    }
+
+    fn base(&self) -> &str {
+        self.client.base()
+    }
+
+    fn drain(&self) -> Option<&Drain> {
+        self.client.drain()
+    }
+
+    fn auth(&self) -> Option<&dyn AuthProvider> {
+        self.client.auth()
+    }
+
+    fn metrics(&self) -> Option<&dyn MetricsObserver> {
+        self.client.metrics()
+    }
+
+    fn retry_policy(&self) -> Option<&RetryPolicy> {
+        self.client.retry_policy()
+    }
+
+    fn success_codes(&self) -> Option<&SuccessCodes> {
+        self.client.success_codes()
+    }
+
+    fn default_headers(&self) -> Option<&HeaderMap> {
+        self.client.default_headers()
+    }
+
+    fn default_query(&self) -> Option<&[(String, String)]> {
+        self.client.default_query()
+    }
+
+    fn url_join(&self) -> UrlJoin {
+        self.client.url_join()
+    }
+
+    fn mutate_request<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        req: &mut Request<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.client.mutate_request(endpoint, req)
+    }
+
+    fn mutate_response<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        resp: &mut Response<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.client.mutate_response(endpoint, resp)
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl<C: BlockingClient> BlockingClient for RateLimitedClient<C> {
+    fn send(&self, req: Request<Bytes>) -> Result<Response<Bytes>, ClientError> {
+        while let Some(wait) = self.take() {
+            trace!(wait_ms = wait.as_millis() as u64, "Waiting for rate limit");
+            std::thread::sleep(wait);
+        }
+        self.client.send(req)
+    }
+
+    fn base(&self) -> &str {
+        self.client.base()
+    }
+
+    fn drain(&self) -> Option<&Drain> {
+        self.client.drain()
+    }
+
+    fn auth(&self) -> Option<&dyn AuthProvider> {
+        self.client.auth()
+    }
+
+    fn metrics(&self) -> Option<&dyn MetricsObserver> {
+        self.client.metrics()
+    }
+
+    fn retry_policy(&self) -> Option<&RetryPolicy> {
+        self.client.retry_policy()
+    }
+
+    fn success_codes(&self) -> Option<&SuccessCodes> {
+        self.client.success_codes()
+    }
+
+    fn default_headers(&self) -> Option<&HeaderMap> {
+        self.client.default_headers()
+    }
+
+    fn default_query(&self) -> Option<&[(String, String)]> {
+        self.client.default_query()
+    }
+
+    fn url_join(&self) -> UrlJoin {
+        self.client.url_join()
+    }
+
+    fn mutate_request<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        req: &mut Request<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.client.mutate_request(endpoint, req)
+    }
+
+    fn mutate_response<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        resp: &mut Response<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.client.mutate_response(endpoint, resp)
+    }
+}
+
+/// A [Client] decorator which limits the number of requests in-flight at once.
+///
+/// Requests made while the limit has been reached wait until a previous
+/// request completes. This is useful when executing many endpoints
+/// concurrently (i.e. with `futures::future::join_all`) against a server which
+/// caps the number of concurrent connections.
+///
+/// # Example
+/// ```
+/// use rustify::clients::reqwest::Client;
+/// use rustify::ratelimit::ConcurrencyLimitedClient;
+///
+/// // Allows at most 4 requests to be in-flight at once
+/// let client = ConcurrencyLimitedClient::new(Client::default("http://myapi.com"), 4);
+/// ```
+pub struct ConcurrencyLimitedClient<C> {
+    client: C,
+    semaphore: Arc<Semaphore>,
+}
+
+impl<C> ConcurrencyLimitedClient<C> {
+    /// Returns a new [ConcurrencyLimitedClient] wrapping the given client which
+    /// allows at most `limit` requests to be in-flight at once.
+    pub fn new(client: C, limit: usize) -> Self {
+        ConcurrencyLimitedClient {
+            client,
+            semaphore: Arc::new(Semaphore::new(limit.max(1))),
+        }
+    }
+
+    /// Returns a reference to the wrapped client.
+    pub fn inner(&self) -> &C {
+        &self.client
+    }
+
+    /// Returns the number of requests which may currently be sent without
+    /// waiting.
+    pub fn available(&self) -> usize {
+        self.semaphore.available_permits()
+    }
+}
+
+#[cfg_attr(not(feature = "non-send"), async_trait)]
+#[cfg_attr(feature = "non-send", async_trait(?Send))]
+impl<C: Client> Client for ConcurrencyLimitedClient<C> {
+    async fn send(&self, req: Request<Bytes>) -> Result<Response<Bytes>, ClientError> {
+        let _permit = self
+            .semaphore
+            .acquire()
+            .await
+            .map_err(|e| ClientError::GenericError { source: e.into() })?;
+        self.client.send(req).await
+    }
+
+    async fn send_stream(&self, req: Request<Bytes>) -> Result<Response<ByteStream>, ClientError> {
+        // The permit is held until the body has been consumed or dropped
+        let permit = self
+            .semaphore
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|e| ClientError::GenericError { source: e.into() })?;
+        let resp = self.client.send_stream(req).await?;
+        Ok(resp.map(|body| hold(body, permit)))
+    }
+
+    fn base(&self) -> &str {
+        self.client.base()
+    }
+
+    fn drain(&self) -> Option<&Drain> {
+        self.client.drain()
+    }
+
+    fn auth(&self) -> Option<&dyn AuthProvider> {
+        self.client.auth()
+    }
+
+    fn metrics(&self) -> Option<&dyn MetricsObserver> {
+        self.client.metrics()
+    }
+
+    fn retry_policy(&self) -> Option<&RetryPolicy> {
+        self.client.retry_policy()
+    }
+
+    fn success_codes(&self) -> Option<&SuccessCodes> {
+        self.client.success_codes()
+    }
+
+    fn default_headers(&self) -> Option<&HeaderMap> {
+        self.client.default_headers()
+    }
+
+    fn default_query(&self) -> Option<&[(String, String)]> {
+        self.client.default_query()
+    }
+
+    fn url_join(&self) -> UrlJoin {
+        self.client.url_join()
+    }
+
+    fn mutate_request<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        req: &mut Request<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.client.mutate_request(endpoint, req)
+    }
+
+    fn mutate_response<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        resp: &mut Response<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.client.mutate_response(endpoint, resp)
+    }
+}
+
+/// Returns the delay advertised by the `Retry-After` or `X-RateLimit-Reset`
+/// headers, if any.
+///
+/// `Retry-After` may contain either a number of seconds or an HTTP date.
+/// `X-RateLimit-Reset` may contain either a number of seconds or a UNIX
+/// timestamp in seconds.
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let now = SystemTime::now();
+    if let Some(value) = headers.get(http::header::RETRY_AFTER) {
+        let value = value.to_str().ok()?.trim();
+        return match value.parse::<u64>() {
+            Ok(secs) => Some(Duration::from_secs(secs)),
+            Err(_) => httpdate::parse_http_date(value)
+                .ok()
+                .map(|t| t.duration_since(now).unwrap_or_default()),
+        };
+    }
+
+    let value = headers.get("X-RateLimit-Reset")?.to_str().ok()?;
+    let secs = value.trim().parse::<u64>().ok()?;
+    let epoch = now.duration_since(UNIX_EPOCH).ok()?.as_secs();
+
+    // Values larger than the current time are assumed to be timestamps
+    if secs > epoch {
+        Some(Duration::from_secs(secs - epoch))
+    } else if secs > 1_000_000_000 {
+        Some(Duration::ZERO)
+    } else {
+        Some(Duration::from_secs(secs))
+    }
+}
@@ -0,0 +1,240 @@
+//! Contains the [RedirectPolicy] used by the built-in clients for following
+//! redirects and the [Redirects] metadata they attach to responses.
+use crate::client::RequestTimeout;
+#[cfg(any(
+    feature = "attohttpc",
+    feature = "hyper",
+    feature = "isahc",
+    feature = "reqwest"
+))]
+use crate::errors::ClientError;
+use bytes::Bytes;
+use http::{header, Method, Request, Response, StatusCode, Uri};
+#[cfg(any(feature = "hyper", feature = "isahc", feature = "reqwest"))]
+use std::future::Future;
+use url::Url;
+
+/// Controls which redirect responses are followed by the built-in clients.
+///
+/// Redirects responding with `303 See Other`, or `301` and `302` responding to
+/// a `POST` request, are followed with a `GET` request without a body, while
+/// all others resend the original request. Credentials are removed from
+/// requests redirected to another origin. Once the maximum number of
+/// redirects is reached, the last redirect response is returned as-is.
+///
+/// # Example
+/// ```
+/// use rustify::clients::reqwest::Client;
+/// use rustify::redirect::RedirectPolicy;
+///
+/// let client = Client::with_redirect("http://myapi.com", RedirectPolicy::SameOrigin(5));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RedirectPolicy {
+    /// Never follows redirects.
+    None,
+    /// Follows up to the given number of redirects.
+    Limited(usize),
+    /// Follows up to the given number of redirects which stay on the origin of
+    /// the original request.
+    SameOrigin(usize),
+}
+
+impl Default for RedirectPolicy {
+    /// Follows up to 10 redirects.
+    fn default() -> Self {
+        RedirectPolicy::Limited(10)
+    }
+}
+
+impl RedirectPolicy {
+    /// Returns the request which follows the given response to the given
+    /// request, or [None] if the response should not be followed.
+    ///
+    /// The `hops` are the number of redirects which were already followed.
+    pub fn next<B>(
+        &self,
+        req: &Request<Bytes>,
+        resp: &Response<B>,
+        hops: usize,
+    ) -> Option<Request<Bytes>> {
+        let max = match self {
+            RedirectPolicy::None => return None,
+            RedirectPolicy::Limited(n) | RedirectPolicy::SameOrigin(n) => *n,
+        };
+        let status = resp.status();
+        let redirect = matches!(status.as_u16(), 301 | 302 | 303 | 307 | 308);
+        if !redirect || hops >= max {
+            return None;
+        }
+
+        let location = resp.headers().get(header::LOCATION)?.to_str().ok()?;
+        let url = Url::parse(&req.uri().to_string())
+            .ok()?
+            .join(location)
+            .ok()?;
+        let uri = url.as_str().parse::<Uri>().ok()?;
+        let same_origin = is_same_origin(req.uri(), &uri);
+        if !same_origin && matches!(self, RedirectPolicy::SameOrigin(_)) {
+            return None;
+        }
+
+        let mut next = clone_request(req);
+        *next.uri_mut() = uri;
+        let to_get = status == StatusCode::SEE_OTHER && req.method() != Method::HEAD
+            || status.as_u16() <= 302 && req.method() == Method::POST;
+        if to_get {
+            *next.method_mut() = Method::GET;
+            *next.body_mut() = Bytes::new();
+            for name in [
+                header::CONTENT_TYPE,
+                header::CONTENT_LENGTH,
+                header::CONTENT_ENCODING,
+                header::TRANSFER_ENCODING,
+            ]
+            .iter()
+            {
+                next.headers_mut().remove(name);
+            }
+        }
+        if !same_origin {
+            for name in [
+                header::AUTHORIZATION,
+                header::COOKIE,
+                header::PROXY_AUTHORIZATION,
+            ]
+            .iter()
+            {
+                next.headers_mut().remove(name);
+            }
+        }
+        Some(next)
+    }
+}
+
+/// A response extension describing the redirects which were followed to
+/// receive a response.
+///
+/// The built-in clients attach it to every response, which makes it available
+/// through [EndpointResult::url][crate::endpoint::EndpointResult::url] and
+/// [EndpointResult::redirects][crate::endpoint::EndpointResult::redirects].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Redirects {
+    /// The URL the response was received from.
+    pub url: Uri,
+    /// The URLs which responded with a followed redirect, in order.
+    pub chain: Vec<Uri>,
+}
+
+/// Sends the given request using the given function, following redirects
+/// according to the given policy and attaching [Redirects] to the response.
+///
+/// A [Redirects] extension which is already attached by the function is used
+/// for determining the final URL, which allows clients whose backing
+/// implementation follows redirects by itself to report it.
+#[cfg(any(feature = "hyper", feature = "isahc", feature = "reqwest"))]
+pub(crate) async fn follow<B, F, Fut>(
+    policy: Option<&RedirectPolicy>,
+    mut req: Request<Bytes>,
+    send: F,
+) -> Result<Response<B>, ClientError>
+where
+    F: Fn(Request<Bytes>) -> Fut,
+    Fut: Future<Output = Result<Response<B>, ClientError>>,
+{
+    let policy = match policy {
+        Some(p) if *p != RedirectPolicy::None => p,
+        _ => {
+            let uri = req.uri().clone();
+            let resp = send(req).await?;
+            return Ok(finish(resp, uri, Vec::new()));
+        }
+    };
+
+    let mut chain = Vec::new();
+    loop {
+        let resp = send(clone_request(&req)).await?;
+        match policy.next(&req, &resp, chain.len()) {
+            Some(next) => {
+                debug!(location = %next.uri(), "Following redirect");
+                chain.push(req.uri().clone());
+                req = next;
+            }
+            None => return Ok(finish(resp, req.uri().clone(), chain)),
+        }
+    }
+}
+
+/// The blocking variant of [follow].
+#[cfg(all(feature = "blocking", any(feature = "attohttpc", feature = "reqwest")))]
+pub(crate) fn follow_blocking<B, F>(
+    policy: Option<&RedirectPolicy>,
+    mut req: Request<Bytes>,
+    send: F,
+) -> Result<Response<B>, ClientError>
+where
+    F: Fn(Request<Bytes>) -> Result<Response<B>, ClientError>,
+{
+    let policy = match policy {
+        Some(p) if *p != RedirectPolicy::None => p,
+        _ => {
+            let uri = req.uri().clone();
+            let resp = send(req)?;
+            return Ok(finish(resp, uri, Vec::new()));
+        }
+    };
+
+    let mut chain = Vec::new();
+    loop {
+        let resp = send(clone_request(&req))?;
+        match policy.next(&req, &resp, chain.len()) {
+            Some(next) => {
+                debug!(location = %next.uri(), "Following redirect");
+                chain.push(req.uri().clone());
+                req = next;
+            }
+            None => return Ok(finish(resp, req.uri().clone(), chain)),
+        }
+    }
+}
+
+/// Attaches the [Redirects] of the given response.
+#[cfg(any(
+    feature = "attohttpc",
+    feature = "hyper",
+    feature = "isahc",
+    feature = "reqwest"
+))]
+fn finish<B>(mut resp: Response<B>, uri: Uri, chain: Vec<Uri>) -> Response<B> {
+    let url = match resp.extensions_mut().remove::<Redirects>() {
+        Some(r) => r.url,
+        None => uri,
+    };
+    resp.extensions_mut().insert(Redirects { url, chain });
+    resp
+}
+
+/// Returns a copy of the given request which keeps its [RequestTimeout].
+fn clone_request(req: &Request<Bytes>) -> Request<Bytes> {
+    let mut clone = crate::http::clone_request(req);
+    if let Some(t) = req.extensions().get::<RequestTimeout>() {
+        clone.extensions_mut().insert(*t);
+    }
+    clone
+}
+
+/// Returns whether the given URIs share the same scheme, host and port.
+fn is_same_origin(a: &Uri, b: &Uri) -> bool {
+    a.scheme() == b.scheme()
+        && a.host() == b.host()
+        && a.port_u16().or_else(|| default_port(a)) == b.port_u16().or_else(|| default_port(b))
+}
+
+/// Returns the default port of the scheme of the given URI.
+fn default_port(uri: &Uri) -> Option<u16> {
+    match uri.scheme_str() {
+        Some("http") => Some(80),
+        Some("https") => Some(443),
+        _ => None,
+    }
+}
@@ -0,0 +1,312 @@
+//! Contains the [RetryPolicy] type and [RetryClient] decorator for retrying
+//! failed [Endpoint][crate::endpoint::Endpoint] executions.
+#[cfg(feature = "blocking")]
+use crate::blocking::client::Client as BlockingClient;
+use crate::{
+    auth::AuthProvider,
+    client::{ByteStream, Client, Drain, StatusCodes, SuccessCodes},
+    endpoint::Endpoint,
+    errors::ClientError,
+    http::UrlJoin,
+    metrics::MetricsObserver,
+};
+use async_trait::async_trait;
+use bytes::Bytes;
+use http::{HeaderMap, Request, Response};
+use std::{future::Future, time::Duration};
+
+/// Configures how failed executions are retried.
+///
+/// By default, executions which fail due to a connection error or a server
+/// returning a 5xx status code are retried up to three times in total. The
+/// status codes which are retried can be changed with `retryable_codes`. The
+/// delay between attempts grows exponentially starting at `initial_backoff`
+/// and is capped at `max_backoff`. When `jitter` is enabled, a random delay
+/// between zero and the computed backoff is used instead in order to avoid
+/// many clients retrying in lockstep.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    /// The maximum number of attempts, including the initial attempt.
+    pub max_attempts: u32,
+    /// The delay before the first retry.
+    pub initial_backoff: Duration,
+    /// The upper bound on the delay between attempts.
+    pub max_backoff: Duration,
+    /// The factor the delay is multiplied by after each attempt.
+    pub multiplier: f64,
+    /// Whether to randomize the delay between attempts.
+    pub jitter: bool,
+    /// The response status codes which are retried.
+    pub retryable_codes: StatusCodes,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            multiplier: 2.0,
+            jitter: true,
+            retryable_codes: StatusCodes::new(vec![500..=599]),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Returns a new [RetryPolicy] with the given maximum number of attempts
+    /// and default backoff settings.
+    pub fn new(max_attempts: u32) -> Self {
+        RetryPolicy {
+            max_attempts,
+            ..Default::default()
+        }
+    }
+
+    /// Returns whether the given error should be retried.
+    pub fn is_retryable(&self, error: &ClientError) -> bool {
+        match error {
+            ClientError::RequestError { .. } => true,
+            ClientError::ApiError { code, .. } | ClientError::ServerResponseError { code, .. } => {
+                self.retryable_codes.contains(*code)
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the delay to wait before making the given attempt, where the
+    /// first retry is attempt two.
+    ///
+    /// The delay is capped at `max_backoff` before it's converted into a
+    /// [Duration], so large attempts never overflow. Delays which are
+    /// negative or NaN due to the `multiplier` are treated as no delay.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exp = attempt.saturating_sub(2).min(i32::MAX as u32) as i32;
+        let secs = self.initial_backoff.as_secs_f64() * self.multiplier.powi(exp);
+        let secs = match secs.is_nan() {
+            true => 0.0,
+            false => secs.clamp(0.0, self.max_backoff.as_secs_f64()),
+        };
+        let delay = Duration::try_from_secs_f64(secs).unwrap_or(self.max_backoff);
+        match self.jitter {
+            true => delay.mul_f64(fastrand::f64()),
+            false => delay,
+        }
+    }
+}
+
+/// A [Client] decorator which retries failed executions according to a
+/// [RetryPolicy].
+///
+/// Each attempt rebuilds the request from the
+/// [Endpoint][crate::endpoint::Endpoint] being executed, so any
+/// [MiddleWare][crate::endpoint::MiddleWare] is applied again on every
+/// attempt.
+///
+/// # Example
+/// ```
+/// use rustify::clients::reqwest::Client;
+/// use rustify::retry::{RetryClient, RetryPolicy};
+///
+/// let client = RetryClient::new(Client::default("http://myapi.com"), RetryPolicy::new(5));
+/// ```
+pub struct RetryClient<C> {
+    client: C,
+    policy: RetryPolicy,
+}
+
+impl<C> RetryClient<C> {
+    /// Returns a new [RetryClient] wrapping the given client.
+    pub fn new(client: C, policy: RetryPolicy) -> Self {
+        RetryClient { client, policy }
+    }
+
+    /// Returns a reference to the wrapped client.
+    pub fn inner(&self) -> &C {
+        &self.client
+    }
+}
+
+#[cfg_attr(not(feature = "non-send"), async_trait)]
+#[cfg_attr(feature = "non-send", async_trait(?Send))]
+impl<C: Client> Client for RetryClient<C> {
+    async fn send(&self, req: Request<Bytes>) -> Result<Response<Bytes>, ClientError> {
+        self.client.send(req).await
+    }
+
+    async fn send_stream(&self, req: Request<Bytes>) -> Result<Response<ByteStream>, ClientError> {
+        self.client.send_stream(req).await
+    }
+
+    fn base(&self) -> &str {
+        self.client.base()
+    }
+
+    fn drain(&self) -> Option<&Drain> {
+        self.client.drain()
+    }
+
+    fn auth(&self) -> Option<&dyn AuthProvider> {
+        self.client.auth()
+    }
+
+    fn metrics(&self) -> Option<&dyn MetricsObserver> {
+        self.client.metrics()
+    }
+
+    fn retry_policy(&self) -> Option<&RetryPolicy> {
+        Some(&self.policy)
+    }
+
+    fn success_codes(&self) -> Option<&SuccessCodes> {
+        self.client.success_codes()
+    }
+
+    fn default_headers(&self) -> Option<&HeaderMap> {
+        self.client.default_headers()
+    }
+
+    fn default_query(&self) -> Option<&[(String, String)]> {
+        self.client.default_query()
+    }
+
+    fn url_join(&self) -> UrlJoin {
+        self.client.url_join()
+    }
+
+    fn mutate_request<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        req: &mut Request<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.client.mutate_request(endpoint, req)
+    }
+
+    fn mutate_response<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        resp: &mut Response<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.client.mutate_response(endpoint, resp)
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl<C: BlockingClient> BlockingClient for RetryClient<C> {
+    fn send(&self, req: Request<Bytes>) -> Result<Response<Bytes>, ClientError> {
+        self.client.send(req)
+    }
+
+    fn base(&self) -> &str {
+        self.client.base()
+    }
+
+    fn drain(&self) -> Option<&Drain> {
+        self.client.drain()
+    }
+
+    fn auth(&self) -> Option<&dyn AuthProvider> {
+        self.client.auth()
+    }
+
+    fn metrics(&self) -> Option<&dyn MetricsObserver> {
+        self.client.metrics()
+    }
+
+    fn retry_policy(&self) -> Option<&RetryPolicy> {
+        Some(&self.policy)
+    }
+
+    fn success_codes(&self) -> Option<&SuccessCodes> {
+        self.client.success_codes()
+    }
+
+    fn default_headers(&self) -> Option<&HeaderMap> {
+        self.client.default_headers()
+    }
+
+    fn default_query(&self) -> Option<&[(String, String)]> {
+        self.client.default_query()
+    }
+
+    fn url_join(&self) -> UrlJoin {
+        self.client.url_join()
+    }
+
+    fn mutate_request<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        req: &mut Request<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.client.mutate_request(endpoint, req)
+    }
+
+    fn mutate_response<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        resp: &mut Response<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.client.mutate_response(endpoint, resp)
+    }
+}
+
+/// Repeatedly calls the given function until it succeeds, returns an error
+/// which is not retryable, or the [RetryPolicy] is exhausted.
+pub(crate) async fn retry<T, F, Fut>(
+    policy: Option<&RetryPolicy>,
+    mut f: F,
+) -> Result<T, ClientError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, ClientError>>,
+{
+    let mut attempt = 1;
+    loop {
+        match f().await {
+            Err(e) if should_retry(policy, attempt, &e) => {
+                attempt += 1;
+                let delay = policy.unwrap().backoff(attempt);
+                debug!(
+                    attempt,
+                    delay_ms = delay.as_millis() as u64,
+                    "Retrying request: {}",
+                    e
+                );
+                tokio::time::sleep(delay).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// The blocking variant of [retry].
+#[cfg(feature = "blocking")]
+pub(crate) fn retry_block<T, F>(policy: Option<&RetryPolicy>, mut f: F) -> Result<T, ClientError>
+where
+    F: FnMut() -> Result<T, ClientError>,
+{
+    let mut attempt = 1;
+    loop {
+        match f() {
+            Err(e) if should_retry(policy, attempt, &e) => {
+                attempt += 1;
+                let delay = policy.unwrap().backoff(attempt);
+                debug!(
+                    attempt,
+                    delay_ms = delay.as_millis() as u64,
+                    "Retrying request: {}",
+                    e
+                );
+                std::thread::sleep(delay);
+            }
+            result => return result,
+        }
+    }
+}
+
+fn should_retry(policy: Option<&RetryPolicy>, attempt: u32, error: &ClientError) -> bool {
+    match policy {
+        Some(p) => attempt < p.max_attempts && p.is_retryable(error),
+        None => false,
+    }
+}
@@ -0,0 +1,239 @@
+//! Contains [MiddleWare] for signing requests with a shared secret.
+use crate::{
+    endpoint::{Endpoint, MiddleWare},
+    errors::ClientError,
+    marker::MaybeSendSync,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bytes::Bytes;
+use http::{header::HeaderName, HeaderValue, Request, Response};
+use ring::{digest, hmac};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The hash function used for computing signatures.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HmacAlgorithm {
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl HmacAlgorithm {
+    fn hmac(&self) -> hmac::Algorithm {
+        match self {
+            HmacAlgorithm::Sha256 => hmac::HMAC_SHA256,
+            HmacAlgorithm::Sha384 => hmac::HMAC_SHA384,
+            HmacAlgorithm::Sha512 => hmac::HMAC_SHA512,
+        }
+    }
+}
+
+/// The encoding used when writing a signature into a header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SignatureEncoding {
+    Base64,
+    Hex,
+}
+
+impl SignatureEncoding {
+    fn encode(&self, data: &[u8]) -> String {
+        match self {
+            SignatureEncoding::Base64 => STANDARD.encode(data),
+            SignatureEncoding::Hex => hex(data),
+        }
+    }
+}
+
+/// A component of a request which is included in the signed message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Component {
+    /// The request method (i.e. `POST`).
+    Method,
+    /// The request path (i.e. `/v1/users`).
+    Path,
+    /// The request query string, without the leading `?`.
+    Query,
+    /// The signing timestamp as seconds since the Unix epoch.
+    Timestamp,
+    /// The hex encoded SHA-256 hash of the request body.
+    BodyHash,
+    /// The value of the given request header, or an empty string if it's not
+    /// present.
+    Header(HeaderName),
+}
+
+/// Produces the message which is signed for a [Request].
+///
+/// This is implemented for [Canonical] as well as any closure taking the
+/// request and the signing timestamp, allowing vendor specific formats to be
+/// supported.
+pub trait Canonicalize: MaybeSendSync {
+    /// Returns the message to sign for the given [Request].
+    fn canonicalize(&self, req: &Request<Bytes>, timestamp: u64) -> Vec<u8>;
+}
+
+impl<F> Canonicalize for F
+where
+    F: Fn(&Request<Bytes>, u64) -> Vec<u8> + MaybeSendSync,
+{
+    fn canonicalize(&self, req: &Request<Bytes>, timestamp: u64) -> Vec<u8> {
+        self(req, timestamp)
+    }
+}
+
+/// A [Canonicalize] implementation which joins a list of [Component] with a
+/// separator.
+#[derive(Clone, Debug)]
+pub struct Canonical {
+    pub components: Vec<Component>,
+    pub separator: String,
+}
+
+impl Canonical {
+    /// Returns a new [Canonical] which joins the given components with
+    /// newlines.
+    pub fn new(components: Vec<Component>) -> Self {
+        Canonical {
+            components,
+            separator: "\n".to_string(),
+        }
+    }
+}
+
+impl Default for Canonical {
+    /// Signs the method, path, timestamp and body hash of a request.
+    fn default() -> Self {
+        Canonical::new(vec![
+            Component::Method,
+            Component::Path,
+            Component::Timestamp,
+            Component::BodyHash,
+        ])
+    }
+}
+
+impl Canonicalize for Canonical {
+    fn canonicalize(&self, req: &Request<Bytes>, timestamp: u64) -> Vec<u8> {
+        self.components
+            .iter()
+            .map(|c| match c {
+                Component::Method => req.method().to_string(),
+                Component::Path => req.uri().path().to_string(),
+                Component::Query => req.uri().query().unwrap_or_default().to_string(),
+                Component::Timestamp => timestamp.to_string(),
+                Component::BodyHash => hex(digest::digest(&digest::SHA256, req.body()).as_ref()),
+                Component::Header(h) => req
+                    .headers()
+                    .get(h)
+                    .and_then(|v| v.to_str().ok())
+                    .unwrap_or_default()
+                    .to_string(),
+            })
+            .collect::<Vec<String>>()
+            .join(&self.separator)
+            .into_bytes()
+    }
+}
+
+/// A [MiddleWare] which signs every request using an HMAC computed over
+/// configurable components of the request.
+///
+/// The message to sign is produced by a [Canonicalize] implementation, which
+/// defaults to [Canonical::default]. The signature is written to the
+/// `X-Signature` header and the signing timestamp to the `X-Timestamp` header,
+/// both of which can be changed to match the format expected by the server.
+///
+/// # Example
+/// ```
+/// use rustify::signing::{Canonical, Component, HmacSigner};
+///
+/// let signer = HmacSigner::new(b"mysecret").canonical(Canonical::new(vec![
+///     Component::Timestamp,
+///     Component::Method,
+///     Component::Path,
+///     Component::BodyHash,
+/// ]));
+/// ```
+pub struct HmacSigner {
+    key: hmac::Key,
+    algorithm: HmacAlgorithm,
+    canonical: Box<dyn Canonicalize>,
+    /// The header the signature is written to.
+    pub signature_header: HeaderName,
+    /// The header the signing timestamp is written to, if any.
+    pub timestamp_header: Option<HeaderName>,
+    /// The encoding used for the signature.
+    pub encoding: SignatureEncoding,
+}
+
+impl HmacSigner {
+    /// Returns a new [HmacSigner] which signs requests with the given secret
+    /// using HMAC-SHA256.
+    pub fn new(secret: &[u8]) -> Self {
+        HmacSigner::with_algorithm(secret, HmacAlgorithm::Sha256)
+    }
+
+    /// Returns a new [HmacSigner] which signs requests with the given secret
+    /// and algorithm.
+    pub fn with_algorithm(secret: &[u8], algorithm: HmacAlgorithm) -> Self {
+        HmacSigner {
+            key: hmac::Key::new(algorithm.hmac(), secret),
+            algorithm,
+            canonical: Box::new(Canonical::default()),
+            signature_header: HeaderName::from_static("x-signature"),
+            timestamp_header: Some(HeaderName::from_static("x-timestamp")),
+            encoding: SignatureEncoding::Hex,
+        }
+    }
+
+    /// Produces the signed message using the given [Canonicalize].
+    pub fn canonical(mut self, canonical: impl Canonicalize + 'static) -> Self {
+        self.canonical = Box::new(canonical);
+        self
+    }
+
+    /// Returns the [HmacAlgorithm] used for signing.
+    pub fn algorithm(&self) -> HmacAlgorithm {
+        self.algorithm
+    }
+
+    /// Returns the encoded signature of the given [Request] at the given
+    /// timestamp.
+    pub fn sign(&self, req: &Request<Bytes>, timestamp: u64) -> String {
+        let message = self.canonical.canonicalize(req, timestamp);
+        self.encoding
+            .encode(hmac::sign(&self.key, &message).as_ref())
+    }
+}
+
+impl MiddleWare for HmacSigner {
+    fn request<E: Endpoint>(&self, _: &E, req: &mut Request<Bytes>) -> Result<(), ClientError> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        if let Some(h) = &self.timestamp_header {
+            req.headers_mut()
+                .insert(h.clone(), HeaderValue::from(timestamp));
+        }
+
+        let signature = self.sign(req, timestamp);
+        let value =
+            HeaderValue::from_str(&signature).map_err(|e| ClientError::HeaderParseError {
+                source: e.into(),
+                name: self.signature_header.to_string(),
+            })?;
+        req.headers_mut()
+            .insert(self.signature_header.clone(), value);
+        Ok(())
+    }
+
+    fn response<E: Endpoint>(&self, _: &E, _: &mut Response<Bytes>) -> Result<(), ClientError> {
+        Ok(())
+    }
+}
+
+/// Returns the lowercase hex encoding of the given bytes.
+fn hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,763 @@
+//! Contains helpers for testing [Endpoints][crate::endpoint::Endpoint] without
+//! sending requests over the network.
+#[cfg(feature = "blocking")]
+use crate::blocking::client::Client as BlockingClient;
+use crate::{
+    auth::AuthProvider,
+    client::{ByteStream, Client, Drain, SuccessCodes},
+    endpoint::Endpoint,
+    errors::ClientError,
+    http::UrlJoin,
+    metrics::MetricsObserver,
+    retry::RetryPolicy,
+};
+use async_trait::async_trait;
+use bytes::Bytes;
+use http::{HeaderMap, HeaderName, HeaderValue, Method, Request, Response};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+pub mod chaos;
+#[cfg(feature = "httpmock")]
+pub mod httpmock;
+pub mod snapshot;
+
+/// A [Client] which responds to requests with programmable canned responses.
+///
+/// Responses are registered as [Mocks][Mock] which are matched against the
+/// method, path, query and body of each incoming request. The first matching
+/// [Mock] is used to build the response while requests which don't match any
+/// [Mock] fail with a [ClientError::RequestError]. Every request sent through
+/// the client is recorded and can be inspected with
+/// [requests][MockClient::requests].
+///
+/// A [Mock] can also declare how many times it expects to be matched using
+/// [times][Mock::times]. Calling [verify][MockClient::verify] at the end of a
+/// test panics if any of these expectations weren't met.
+///
+/// # Example
+/// ```
+/// use http::Method;
+/// use rustify::{test::{Mock, MockClient}, Endpoint};
+/// use rustify_derive::Endpoint;
+/// use serde::Deserialize;
+/// use serde_json::json;
+///
+/// #[derive(Endpoint)]
+/// #[endpoint(path = "users/{self.id}", response = "User")]
+/// struct GetUser {
+///     #[endpoint(skip)]
+///     id: u64,
+/// }
+///
+/// #[derive(Deserialize)]
+/// struct User {
+///     name: String,
+/// }
+///
+/// # tokio_test::block_on(async {
+/// let client = MockClient::new("http://myapi.com");
+/// client.mock(Mock::new(Method::GET, "/users/1").json(&json!({"name": "test"})));
+///
+/// let user = GetUser { id: 1 }.exec(&client).await.unwrap().parse().unwrap();
+/// assert_eq!(user.name, "test");
+/// assert_eq!(client.requests().len(), 1);
+/// # })
+/// ```
+///
+/// ```should_panic
+/// use http::Method;
+/// use rustify::test::{Mock, MockClient};
+/// use serde_json::json;
+///
+/// let client = MockClient::new("http://myapi.com");
+/// client.mock(
+///     Mock::new(Method::POST, "/v1/users")
+///         .json_body(&json!({"name": "test"}))
+///         .times(2),
+/// );
+///
+/// // Panics as the mock was never matched
+/// client.verify();
+/// ```
+pub struct MockClient {
+    pub base: String,
+    mocks: Mutex<Vec<Mock>>,
+    requests: Mutex<Vec<Request<Bytes>>>,
+}
+
+impl MockClient {
+    /// Creates a new instance of [MockClient] with no registered [Mocks][Mock].
+    pub fn new(base: &str) -> Self {
+        MockClient {
+            base: base.to_string(),
+            mocks: Mutex::new(Vec::new()),
+            requests: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Registers the given [Mock] with this client.
+    pub fn mock(&self, mock: Mock) {
+        self.mocks.lock().unwrap().push(mock);
+    }
+
+    /// Returns a copy of every request received by this client in the order
+    /// they were received.
+    pub fn requests(&self) -> Vec<Request<Bytes>> {
+        self.requests
+            .lock()
+            .unwrap()
+            .iter()
+            .map(crate::http::clone_request)
+            .collect()
+    }
+
+    /// Removes all registered [Mocks][Mock] and recorded requests.
+    pub fn reset(&self) {
+        self.mocks.lock().unwrap().clear();
+        self.requests.lock().unwrap().clear();
+    }
+
+    /// Verifies that every [Mock] configured with [times][Mock::times] was
+    /// matched the expected number of times.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any expectations weren't met. When the unmet [Mock] matches
+    /// on a body, the message includes a diff between the expected body and
+    /// the bodies of received requests with the same method and path.
+    pub fn verify(&self) {
+        let failures: Vec<String> = {
+            let requests = self.requests.lock().unwrap();
+            self.mocks
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|m| m.times.map(|t| t != m.hits).unwrap_or(false))
+                .map(|m| m.report(&requests))
+                .collect()
+        };
+
+        if !failures.is_empty() {
+            panic!("Mock expectations were not met:\n\n{}", failures.join("\n"));
+        }
+    }
+
+    fn respond(&self, req: Request<Bytes>) -> Result<Response<Bytes>, ClientError> {
+        let response = self
+            .mocks
+            .lock()
+            .unwrap()
+            .iter_mut()
+            .find(|m| m.matches(&req))
+            .map(|m| {
+                m.hits += 1;
+                m.response()
+            });
+        let url = req.uri().to_string();
+        let method = req.method().to_string();
+        self.requests.lock().unwrap().push(req);
+
+        match response {
+            Some(r) => r,
+            None => Err(ClientError::RequestError {
+                source: anyhow::anyhow!("No mock matched the request"),
+                url,
+                method,
+            }),
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "non-send"), async_trait)]
+#[cfg_attr(feature = "non-send", async_trait(?Send))]
+impl Client for MockClient {
+    fn base(&self) -> &str {
+        self.base.as_str()
+    }
+
+    async fn send(&self, req: Request<Bytes>) -> Result<Response<Bytes>, ClientError> {
+        self.respond(req)
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl BlockingClient for MockClient {
+    fn base(&self) -> &str {
+        self.base.as_str()
+    }
+
+    fn send(&self, req: Request<Bytes>) -> Result<Response<Bytes>, ClientError> {
+        self.respond(req)
+    }
+}
+
+/// A canned response returned by a [MockClient] for matching requests.
+///
+/// A [Mock] always matches on the request method and path. It can optionally
+/// be configured to also match on the query parameters and body of the
+/// request. By default it responds with an empty `200 OK` response.
+#[derive(Clone, Debug)]
+pub struct Mock {
+    method: Method,
+    path: String,
+    query: Option<Vec<(String, String)>>,
+    body: Option<Body>,
+    status: u16,
+    headers: HeaderMap,
+    response: Bytes,
+    times: Option<usize>,
+    hits: usize,
+}
+
+/// The request body a [Mock] matches against.
+#[derive(Clone, Debug)]
+enum Body {
+    Json(Value),
+    Raw(Vec<u8>),
+}
+
+impl Mock {
+    /// Creates a new [Mock] which matches requests with the given method and
+    /// path.
+    pub fn new(method: Method, path: &str) -> Self {
+        Mock {
+            method,
+            path: normalize_path(path),
+            query: None,
+            body: None,
+            status: 200,
+            headers: HeaderMap::new(),
+            response: Bytes::new(),
+            times: None,
+            hits: 0,
+        }
+    }
+
+    /// Only matches requests with the given query parameters. The order of
+    /// the parameters is ignored.
+    pub fn query<T: Serialize>(mut self, query: &T) -> Self {
+        let query = serde_urlencoded::to_string(query).unwrap_or_default();
+        self.query = Some(parse_query(&query));
+        self
+    }
+
+    /// Only matches requests with a body equal to the given bytes.
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = Some(Body::Raw(body.into()));
+        self
+    }
+
+    /// Only matches requests with a JSON body equal to the given value.
+    pub fn json_body<T: Serialize>(mut self, body: &T) -> Self {
+        self.body = Some(Body::Json(
+            serde_json::to_value(body).unwrap_or(Value::Null),
+        ));
+        self
+    }
+
+    /// Expects this [Mock] to be matched exactly the given number of times.
+    /// The expectation is checked by [MockClient::verify].
+    pub fn times(mut self, times: usize) -> Self {
+        self.times = Some(times);
+        self
+    }
+
+    /// Sets the status code of the response.
+    pub fn status(mut self, status: u16) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Adds a header to the response. Invalid headers are ignored.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        if let (Ok(n), Ok(v)) = (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(value),
+        ) {
+            self.headers.append(n, v);
+        }
+        self
+    }
+
+    /// Sets the raw body of the response.
+    pub fn respond(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.response = Bytes::from(body.into());
+        self
+    }
+
+    /// Sets the body of the response to the given value serialized as JSON.
+    pub fn json<T: Serialize>(mut self, body: &T) -> Self {
+        self.response = serde_json::to_vec(body).unwrap_or_default().into();
+        self.header("Content-Type", "application/json")
+    }
+
+    /// Returns whether the given request matches this [Mock].
+    pub fn matches(&self, req: &Request<Bytes>) -> bool {
+        if req.method() != self.method || normalize_path(req.uri().path()) != self.path {
+            return false;
+        }
+
+        if let Some(query) = &self.query {
+            if parse_query(req.uri().query().unwrap_or_default()) != *query {
+                return false;
+            }
+        }
+
+        match &self.body {
+            Some(Body::Raw(b)) => req.body() == b,
+            Some(Body::Json(v)) => serde_json::from_slice::<Value>(req.body())
+                .map(|b| b == *v)
+                .unwrap_or(false),
+            None => true,
+        }
+    }
+
+    /// Returns a description of why this [Mock] didn't meet its expectation.
+    fn report(&self, requests: &[Request<Bytes>]) -> String {
+        let mut report = format!(
+            "{} {} expected {} request(s) but received {}\n",
+            self.method,
+            self.path,
+            self.times.unwrap_or_default(),
+            self.hits
+        );
+
+        let expected = match &self.body {
+            Some(b) => b.render(),
+            None => return report,
+        };
+        let candidates = requests
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| {
+                r.method() == self.method && normalize_path(r.uri().path()) == self.path
+            })
+            .filter(|(_, r)| !self.matches(r));
+        for (i, req) in candidates {
+            report.push_str(&format!(
+                "  request #{} body (- expected, + actual):\n",
+                i + 1
+            ));
+            report.push_str(&diff(&expected, &render_body(req.body())));
+        }
+        report
+    }
+
+    fn response(&self) -> Result<Response<Bytes>, ClientError> {
+        let mut resp = Response::builder().status(self.status);
+        for (k, v) in self.headers.iter() {
+            resp = resp.header(k, v);
+        }
+        resp.body(self.response.clone())
+            .map_err(|e| ClientError::ResponseError { source: e.into() })
+    }
+}
+
+impl Body {
+    fn render(&self) -> String {
+        match self {
+            Body::Json(v) => serde_json::to_string_pretty(v).unwrap_or_default(),
+            Body::Raw(b) => render_body(b),
+        }
+    }
+}
+
+/// Renders a request body for display, pretty printing it if it's JSON.
+fn render_body(body: &[u8]) -> String {
+    match serde_json::from_slice::<Value>(body) {
+        Ok(v) => serde_json::to_string_pretty(&v).unwrap_or_default(),
+        Err(_) => String::from_utf8_lossy(body).into_owned(),
+    }
+}
+
+/// Returns a line based diff between two strings using their longest common
+/// subsequence.
+fn diff(expected: &str, actual: &str) -> String {
+    let a: Vec<&str> = expected.lines().collect();
+    let b: Vec<&str> = actual.lines().collect();
+
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut out = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            out.push_str(&format!("      {}\n", a[i]));
+            i += 1;
+            j += 1;
+        } else if i < a.len() && (j == b.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            out.push_str(&format!("    - {}\n", a[i]));
+            i += 1;
+        } else {
+            out.push_str(&format!("    + {}\n", b[j]));
+            j += 1;
+        }
+    }
+    out
+}
+
+fn normalize_path(path: &str) -> String {
+    format!("/{}", path.trim_matches('/'))
+}
+
+fn parse_query(query: &str) -> Vec<(String, String)> {
+    let mut pairs: Vec<(String, String)> = url::form_urlencoded::parse(query.as_bytes())
+        .into_owned()
+        .collect();
+    pairs.sort();
+    pairs
+}
+
+/// A [Client] which records requests and responses to a cassette file and
+/// replays them on later runs.
+///
+/// When the cassette file doesn't exist, requests are sent using the wrapped
+/// client and every request/response pair is written to the cassette. Once
+/// the cassette exists, requests are instead answered with the recorded
+/// responses without touching the network. Delete the cassette to record it
+/// again.
+///
+/// Recorded requests are matched on their method, URI and body. Request
+/// headers are never written to the cassette to avoid persisting credentials.
+/// Streamed responses are buffered while recording so they can be written to
+/// the cassette.
+///
+/// # Example
+/// ```no_run
+/// use rustify::clients::reqwest::Client;
+/// use rustify::test::RecordingClient;
+///
+/// let client =
+///     RecordingClient::new(Client::default("http://myapi.com"), "tests/cassettes/users.json")
+///         .unwrap();
+/// ```
+pub struct RecordingClient<C> {
+    client: C,
+    path: PathBuf,
+    replaying: bool,
+    interactions: Mutex<Vec<Interaction>>,
+    played: Mutex<Vec<bool>>,
+}
+
+impl<C> RecordingClient<C> {
+    /// Returns a new [RecordingClient] wrapping the given client which uses
+    /// the cassette at the given path. The cassette is loaded if it exists.
+    pub fn new(client: C, path: impl AsRef<Path>) -> Result<Self, ClientError> {
+        let path = path.as_ref().to_path_buf();
+        let (replaying, interactions) = if path.exists() {
+            let content =
+                std::fs::read(&path).map_err(|e| ClientError::GenericError { source: e.into() })?;
+            let interactions: Vec<Interaction> = serde_json::from_slice(&content)
+                .map_err(|e| ClientError::GenericError { source: e.into() })?;
+            (true, interactions)
+        } else {
+            (false, Vec::new())
+        };
+
+        Ok(RecordingClient {
+            client,
+            path,
+            replaying,
+            played: Mutex::new(vec![false; interactions.len()]),
+            interactions: Mutex::new(interactions),
+        })
+    }
+
+    /// Returns a reference to the wrapped client.
+    pub fn inner(&self) -> &C {
+        &self.client
+    }
+
+    /// Returns whether responses are being replayed from an existing
+    /// cassette rather than recorded.
+    pub fn is_replaying(&self) -> bool {
+        self.replaying
+    }
+
+    /// Returns the recorded response for the given request, preferring
+    /// interactions which haven't been replayed yet.
+    fn replay(&self, req: &Request<Bytes>) -> Result<Response<Bytes>, ClientError> {
+        let interactions = self.interactions.lock().unwrap();
+        let mut played = self.played.lock().unwrap();
+        let matching: Vec<usize> = interactions
+            .iter()
+            .enumerate()
+            .filter(|(_, i)| i.request.matches(req))
+            .map(|(n, _)| n)
+            .collect();
+
+        let index = matching
+            .iter()
+            .find(|n| !played[**n])
+            .or_else(|| matching.last())
+            .copied()
+            .ok_or_else(|| ClientError::RequestError {
+                source: anyhow::anyhow!("No recorded interaction matched the request"),
+                url: req.uri().to_string(),
+                method: req.method().to_string(),
+            })?;
+        played[index] = true;
+        interactions[index].response.to_response()
+    }
+
+    /// Appends the given request/response pair to the cassette.
+    fn record(&self, req: RecordedRequest, resp: &Response<Bytes>) -> Result<(), ClientError> {
+        let mut interactions = self.interactions.lock().unwrap();
+        interactions.push(Interaction {
+            request: req,
+            response: RecordedResponse::from_response(resp),
+        });
+
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| ClientError::GenericError { source: e.into() })?;
+        }
+        let content = serde_json::to_vec_pretty(&*interactions)
+            .map_err(|e| ClientError::GenericError { source: e.into() })?;
+        std::fs::write(&self.path, content)
+            .map_err(|e| ClientError::GenericError { source: e.into() })
+    }
+}
+
+#[cfg_attr(not(feature = "non-send"), async_trait)]
+#[cfg_attr(feature = "non-send", async_trait(?Send))]
+impl<C: Client> Client for RecordingClient<C> {
+    async fn send(&self, req: Request<Bytes>) -> Result<Response<Bytes>, ClientError> {
+        if self.replaying {
+            return self.replay(&req);
+        }
+
+        let recorded = RecordedRequest::from_request(&req);
+        let resp = self.client.send(req).await?;
+        self.record(recorded, &resp)?;
+        Ok(resp)
+    }
+
+    async fn send_stream(&self, req: Request<Bytes>) -> Result<Response<ByteStream>, ClientError> {
+        if self.replaying {
+            return Ok(self.replay(&req)?.map(crate::http::stream_body));
+        }
+
+        let recorded = RecordedRequest::from_request(&req);
+        let (parts, body) = self.client.send_stream(req).await?.into_parts();
+        let resp = Response::from_parts(parts, crate::http::collect_body(body).await?);
+        self.record(recorded, &resp)?;
+        Ok(resp.map(crate::http::stream_body))
+    }
+
+    fn base(&self) -> &str {
+        self.client.base()
+    }
+
+    fn drain(&self) -> Option<&Drain> {
+        self.client.drain()
+    }
+
+    fn auth(&self) -> Option<&dyn AuthProvider> {
+        self.client.auth()
+    }
+
+    fn metrics(&self) -> Option<&dyn MetricsObserver> {
+        self.client.metrics()
+    }
+
+    fn retry_policy(&self) -> Option<&RetryPolicy> {
+        self.client.retry_policy()
+    }
+
+    fn success_codes(&self) -> Option<&SuccessCodes> {
+        self.client.success_codes()
+    }
+
+    fn default_headers(&self) -> Option<&HeaderMap> {
+        self.client.default_headers()
+    }
+
+    fn default_query(&self) -> Option<&[(String, String)]> {
+        self.client.default_query()
+    }
+
+    fn url_join(&self) -> UrlJoin {
+        self.client.url_join()
+    }
+
+    fn mutate_request<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        req: &mut Request<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.client.mutate_request(endpoint, req)
+    }
+
+    fn mutate_response<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        resp: &mut Response<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.client.mutate_response(endpoint, resp)
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl<C: BlockingClient> BlockingClient for RecordingClient<C> {
+    fn send(&self, req: Request<Bytes>) -> Result<Response<Bytes>, ClientError> {
+        if self.replaying {
+            return self.replay(&req);
+        }
+
+        let recorded = RecordedRequest::from_request(&req);
+        let resp = self.client.send(req)?;
+        self.record(recorded, &resp)?;
+        Ok(resp)
+    }
+
+    fn base(&self) -> &str {
+        self.client.base()
+    }
+
+    fn drain(&self) -> Option<&Drain> {
+        self.client.drain()
+    }
+
+    fn auth(&self) -> Option<&dyn AuthProvider> {
+        self.client.auth()
+    }
+
+    fn metrics(&self) -> Option<&dyn MetricsObserver> {
+        self.client.metrics()
+    }
+
+    fn retry_policy(&self) -> Option<&RetryPolicy> {
+        self.client.retry_policy()
+    }
+
+    fn success_codes(&self) -> Option<&SuccessCodes> {
+        self.client.success_codes()
+    }
+
+    fn default_headers(&self) -> Option<&HeaderMap> {
+        self.client.default_headers()
+    }
+
+    fn default_query(&self) -> Option<&[(String, String)]> {
+        self.client.default_query()
+    }
+
+    fn url_join(&self) -> UrlJoin {
+        self.client.url_join()
+    }
+
+    fn mutate_request<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        req: &mut Request<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.client.mutate_request(endpoint, req)
+    }
+
+    fn mutate_response<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        resp: &mut Response<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.client.mutate_response(endpoint, resp)
+    }
+}
+
+/// A single request/response pair stored in a cassette.
+#[derive(Debug, Deserialize, Serialize)]
+struct Interaction {
+    request: RecordedRequest,
+    response: RecordedResponse,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct RecordedRequest {
+    method: String,
+    uri: String,
+    body: RecordedBody,
+}
+
+impl RecordedRequest {
+    fn from_request(req: &Request<Bytes>) -> Self {
+        RecordedRequest {
+            method: req.method().to_string(),
+            uri: req.uri().to_string(),
+            body: RecordedBody::new(req.body()),
+        }
+    }
+
+    fn matches(&self, req: &Request<Bytes>) -> bool {
+        self.method == req.method().as_str()
+            && self.uri == req.uri().to_string()
+            && self.body.as_bytes() == req.body().as_ref()
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct RecordedResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: RecordedBody,
+}
+
+impl RecordedResponse {
+    fn from_response(resp: &Response<Bytes>) -> Self {
+        RecordedResponse {
+            status: resp.status().as_u16(),
+            headers: resp
+                .headers()
+                .iter()
+                .filter_map(|(k, v)| Some((k.to_string(), v.to_str().ok()?.to_string())))
+                .collect(),
+            body: RecordedBody::new(resp.body()),
+        }
+    }
+
+    fn to_response(&self) -> Result<Response<Bytes>, ClientError> {
+        let mut resp = Response::builder().status(self.status);
+        for (k, v) in self.headers.iter() {
+            resp = resp.header(k, v);
+        }
+        resp.body(Bytes::copy_from_slice(self.body.as_bytes()))
+            .map_err(|e| ClientError::ResponseError { source: e.into() })
+    }
+}
+
+/// A recorded body which is stored as text when it's valid UTF-8.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+enum RecordedBody {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+impl RecordedBody {
+    fn new(body: &[u8]) -> Self {
+        match std::str::from_utf8(body) {
+            Ok(s) => RecordedBody::Text(s.to_string()),
+            Err(_) => RecordedBody::Binary(body.to_vec()),
+        }
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        match self {
+            RecordedBody::Text(s) => s.as_bytes(),
+            RecordedBody::Binary(b) => b,
+        }
+    }
+}
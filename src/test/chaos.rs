@@ -0,0 +1,280 @@
+//! Contains the [ChaosClient] decorator for injecting faults into requests.
+#[cfg(feature = "blocking")]
+use crate::blocking::client::Client as BlockingClient;
+use crate::{
+    auth::AuthProvider,
+    client::{ByteStream, Client, Drain, SuccessCodes},
+    endpoint::Endpoint,
+    errors::ClientError,
+    http::UrlJoin,
+    metrics::MetricsObserver,
+    retry::RetryPolicy,
+};
+use async_trait::async_trait;
+use bytes::Bytes;
+use http::{HeaderMap, Request, Response};
+use std::{sync::Mutex, time::Duration};
+
+/// Configures which faults are injected by a [ChaosClient].
+///
+/// Each rate is the probability, between `0.0` and `1.0`, that the fault is
+/// injected into a given request. By default no faults are injected. When a
+/// `seed` is provided, the same sequence of faults is injected on every run.
+#[derive(Clone, Debug)]
+pub struct ChaosPolicy {
+    /// The delay added to requests selected for latency injection.
+    pub latency: Duration,
+    /// The probability of delaying a request by `latency`.
+    pub latency_rate: f64,
+    /// The probability of failing a request with a connection error.
+    pub failure_rate: f64,
+    /// The probability of responding to a request with `error_status`.
+    pub error_rate: f64,
+    /// The status code returned for requests selected for error injection.
+    pub error_status: u16,
+    /// The seed for the random number generator.
+    pub seed: Option<u64>,
+}
+
+impl Default for ChaosPolicy {
+    fn default() -> Self {
+        ChaosPolicy {
+            latency: Duration::from_millis(100),
+            latency_rate: 0.0,
+            failure_rate: 0.0,
+            error_rate: 0.0,
+            error_status: 503,
+            seed: None,
+        }
+    }
+}
+
+/// A fault selected by a [ChaosClient] for a single request.
+enum Fault {
+    Failure,
+    Error(u16),
+}
+
+/// A [Client] decorator which probabilistically injects latency, connection
+/// failures and error responses into requests according to a [ChaosPolicy].
+///
+/// Injected connection failures are returned as a
+/// [ClientError::RequestError] without sending the request while injected
+/// error responses contain an empty body. This is useful for testing how
+/// code built on rustify handles failures, including any configured
+/// [RetryPolicy].
+///
+/// # Example
+/// ```
+/// use rustify::clients::reqwest::Client;
+/// use rustify::test::chaos::{ChaosClient, ChaosPolicy};
+///
+/// let client = ChaosClient::new(
+///     Client::default("http://myapi.com"),
+///     ChaosPolicy {
+///         failure_rate: 0.1,
+///         error_rate: 0.1,
+///         seed: Some(42),
+///         ..Default::default()
+///     },
+/// );
+/// ```
+pub struct ChaosClient<C> {
+    client: C,
+    policy: ChaosPolicy,
+    rng: Mutex<fastrand::Rng>,
+}
+
+impl<C> ChaosClient<C> {
+    /// Returns a new [ChaosClient] wrapping the given client.
+    pub fn new(client: C, policy: ChaosPolicy) -> Self {
+        let rng = match policy.seed {
+            Some(s) => fastrand::Rng::with_seed(s),
+            None => fastrand::Rng::new(),
+        };
+        ChaosClient {
+            client,
+            policy,
+            rng: Mutex::new(rng),
+        }
+    }
+
+    /// Returns a reference to the wrapped client.
+    pub fn inner(&self) -> &C {
+        &self.client
+    }
+
+    /// Rolls for each fault, returning the delay to inject along with the
+    /// fault to inject, if any.
+    fn roll(&self) -> (Option<Duration>, Option<Fault>) {
+        let mut rng = self.rng.lock().unwrap();
+        let latency = (rng.f64() < self.policy.latency_rate).then_some(self.policy.latency);
+        let fault = if rng.f64() < self.policy.failure_rate {
+            Some(Fault::Failure)
+        } else if rng.f64() < self.policy.error_rate {
+            Some(Fault::Error(self.policy.error_status))
+        } else {
+            None
+        };
+        (latency, fault)
+    }
+}
+
+/// Returns the result of injecting the given fault into the request.
+fn inject(fault: Fault, req: &Request<Bytes>) -> Result<Response<Bytes>, ClientError> {
+    match fault {
+        Fault::Failure => Err(ClientError::RequestError {
+            source: anyhow::anyhow!("Injected connection failure"),
+            url: req.uri().to_string(),
+            method: req.method().to_string(),
+        }),
+        Fault::Error(status) => Response::builder()
+            .status(status)
+            .body(Bytes::new())
+            .map_err(|e| ClientError::ResponseError { source: e.into() }),
+    }
+}
+
+#[cfg_attr(not(feature = "non-send"), async_trait)]
+#[cfg_attr(feature = "non-send", async_trait(?Send))]
+impl<C: Client> Client for ChaosClient<C> {
+    async fn send(&self, req: Request<Bytes>) -> Result<Response<Bytes>, ClientError> {
+        let (latency, fault) = self.roll();
+        if let Some(l) = latency {
+            tokio::time::sleep(l).await;
+        }
+        match fault {
+            Some(f) => inject(f, &req),
+            None => self.client.send(req).await,
+        }
+    }
+
+    async fn send_stream(&self, req: Request<Bytes>) -> Result<Response<ByteStream>, ClientError> {
+        let (latency, fault) = self.roll();
+        if let Some(l) = latency {
+            tokio::time::sleep(l).await;
+        }
+        match fault {
+            Some(f) => inject(f, &req).map(|r| r.map(crate::http::stream_body)),
+            None => self.client.send_stream(req).await,
+        }
+    }
+
+    fn base(&self) -> &str {
+        self.client.base()
+    }
+
+    fn drain(&self) -> Option<&Drain> {
+        self.client.drain()
+    }
+
+    fn auth(&self) -> Option<&dyn AuthProvider> {
+        self.client.auth()
+    }
+
+    fn metrics(&self) -> Option<&dyn MetricsObserver> {
+        self.client.metrics()
+    }
+
+    fn retry_policy(&self) -> Option<&RetryPolicy> {
+        self.client.retry_policy()
+    }
+
+    fn success_codes(&self) -> Option<&SuccessCodes> {
+        self.client.success_codes()
+    }
+
+    fn default_headers(&self) -> Option<&HeaderMap> {
+        self.client.default_headers()
+    }
+
+    fn default_query(&self) -> Option<&[(String, String)]> {
+        self.client.default_query()
+    }
+
+    fn url_join(&self) -> UrlJoin {
+        self.client.url_join()
+    }
+
+    fn mutate_request<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        req: &mut Request<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.client.mutate_request(endpoint, req)
+    }
+
+    fn mutate_response<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        resp: &mut Response<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.client.mutate_response(endpoint, resp)
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl<C: BlockingClient> BlockingClient for ChaosClient<C> {
+    fn send(&self, req: Request<Bytes>) -> Result<Response<Bytes>, ClientError> {
+        let (latency, fault) = self.roll();
+        if let Some(l) = latency {
+            std::thread::sleep(l);
+        }
+        match fault {
+            Some(f) => inject(f, &req),
+            None => self.client.send(req),
+        }
+    }
+
+    fn base(&self) -> &str {
+        self.client.base()
+    }
+
+    fn drain(&self) -> Option<&Drain> {
+        self.client.drain()
+    }
+
+    fn auth(&self) -> Option<&dyn AuthProvider> {
+        self.client.auth()
+    }
+
+    fn metrics(&self) -> Option<&dyn MetricsObserver> {
+        self.client.metrics()
+    }
+
+    fn retry_policy(&self) -> Option<&RetryPolicy> {
+        self.client.retry_policy()
+    }
+
+    fn success_codes(&self) -> Option<&SuccessCodes> {
+        self.client.success_codes()
+    }
+
+    fn default_headers(&self) -> Option<&HeaderMap> {
+        self.client.default_headers()
+    }
+
+    fn default_query(&self) -> Option<&[(String, String)]> {
+        self.client.default_query()
+    }
+
+    fn url_join(&self) -> UrlJoin {
+        self.client.url_join()
+    }
+
+    fn mutate_request<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        req: &mut Request<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.client.mutate_request(endpoint, req)
+    }
+
+    fn mutate_response<E: Endpoint>(
+        &self,
+        endpoint: &E,
+        resp: &mut Response<Bytes>,
+    ) -> Result<(), ClientError> {
+        self.client.mutate_response(endpoint, resp)
+    }
+}
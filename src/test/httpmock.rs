@@ -0,0 +1,68 @@
+//! Contains helpers for matching the requests generated by
+//! [Endpoints][crate::endpoint::Endpoint] with [httpmock](https://docs.rs/httpmock/).
+//!
+//! # Example
+//! ```
+//! use httpmock::MockServer;
+//! use rustify::{clients::reqwest::Client, test::httpmock::MatchesEndpoint, Endpoint};
+//! use rustify_derive::Endpoint;
+//!
+//! #[derive(Endpoint)]
+//! #[endpoint(path = "users", method = "POST")]
+//! struct CreateUser {
+//!     name: String,
+//! }
+//!
+//! # tokio_test::block_on(async {
+//! let server = MockServer::start();
+//! let endpoint = CreateUser { name: "test".to_string() };
+//! let mock = server.mock(|when, then| {
+//!     when.matches_endpoint(&endpoint);
+//!     then.status(200);
+//! });
+//!
+//! let client = Client::default(server.base_url().as_str());
+//! endpoint.exec(&client).await.unwrap();
+//! mock.assert();
+//! # })
+//! ```
+use crate::endpoint::Endpoint;
+use httpmock::When;
+
+/// Extends [When] with matchers derived from an [Endpoint].
+pub trait MatchesEndpoint {
+    /// Only matches requests with the method, path, query parameters,
+    /// headers and body produced by the given [Endpoint]. JSON bodies are
+    /// compared semantically rather than byte for byte.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the [Request][http::Request] for the [Endpoint] couldn't be
+    /// built.
+    fn matches_endpoint<E: Endpoint>(self, endpoint: &E) -> Self;
+}
+
+impl MatchesEndpoint for When {
+    fn matches_endpoint<E: Endpoint>(self, endpoint: &E) -> Self {
+        let req = endpoint
+            .request("http://localhost")
+            .unwrap_or_else(|e| panic!("Failed building request: {}", e));
+
+        let mut when = self.method(req.method().as_str()).path(req.uri().path());
+        for (k, v) in url::form_urlencoded::parse(req.uri().query().unwrap_or_default().as_bytes())
+        {
+            when = when.query_param(k, v);
+        }
+        for (k, v) in req.headers() {
+            when = when.header(k.as_str(), String::from_utf8_lossy(v.as_bytes()));
+        }
+
+        if req.body().is_empty() {
+            return when;
+        }
+        match serde_json::from_slice::<serde_json::Value>(req.body()) {
+            Ok(v) => when.json_body(v),
+            Err(_) => when.body(String::from_utf8_lossy(req.body())),
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! Contains helpers for comparing the requests generated by
+//! [Endpoints][crate::endpoint::Endpoint] against stored snapshots.
+//!
+//! Requests are rendered into a canonical text form containing the method,
+//! URI, headers (sorted by name) and body (pretty printed when it's JSON).
+//! Snapshots are written on first use and compared on every use afterwards.
+//! Set the `RUSTIFY_UPDATE_SNAPSHOTS` environment variable to overwrite
+//! existing snapshots with the current output.
+//!
+//! # Example
+//! ```no_run
+//! use rustify::test::snapshot;
+//! use rustify_derive::Endpoint;
+//!
+//! #[derive(Endpoint)]
+//! #[endpoint(path = "users", method = "POST")]
+//! struct CreateUser {
+//!     name: String,
+//! }
+//!
+//! let endpoint = CreateUser { name: "test".to_string() };
+//! snapshot::assert_endpoint(&endpoint, "http://myapi.com", "tests/snapshots/create_user.txt");
+//! ```
+use super::{diff, render_body};
+use crate::{
+    endpoint::Endpoint,
+    http::{redact, redacted_header, redacted_uri},
+};
+use bytes::Bytes;
+use http::Request;
+use std::path::Path;
+
+/// The environment variable which causes snapshots to be overwritten.
+pub const UPDATE_ENV: &str = "RUSTIFY_UPDATE_SNAPSHOTS";
+
+/// Renders the given [Request] into its canonical text form.
+pub fn render(req: &Request<Bytes>) -> String {
+    let mut out = format!("{} {}\n", req.method(), redacted_uri(req));
+
+    let mut headers: Vec<(String, String)> = req
+        .headers()
+        .iter()
+        .map(|(k, v)| (k.to_string(), redacted_header(req, v)))
+        .collect();
+    headers.sort();
+    for (k, v) in headers {
+        out.push_str(&format!("{}: {}\n", k, v));
+    }
+
+    if !req.body().is_empty() {
+        out.push('\n');
+        out.push_str(&redact(req, &render_body(req.body())));
+        out.push('\n');
+    }
+    out
+}
+
+/// Compares the rendered form of the given [Request] against the snapshot
+/// stored at the given path.
+///
+/// # Panics
+///
+/// Panics with a diff if the snapshot exists and doesn't match, or if the
+/// snapshot couldn't be read or written.
+pub fn assert_request(req: &Request<Bytes>, path: impl AsRef<Path>) {
+    let path = path.as_ref();
+    let actual = render(req);
+
+    if !path.exists() || std::env::var_os(UPDATE_ENV).is_some() {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).unwrap_or_else(|e| {
+                panic!(
+                    "Failed creating snapshot directory {}: {}",
+                    parent.display(),
+                    e
+                )
+            });
+        }
+        std::fs::write(path, &actual)
+            .unwrap_or_else(|e| panic!("Failed writing snapshot {}: {}", path.display(), e));
+        return;
+    }
+
+    let expected = std::fs::read_to_string(path)
+        .unwrap_or_else(|e| panic!("Failed reading snapshot {}: {}", path.display(), e));
+    if expected != actual {
+        panic!(
+            "Request does not match snapshot {} (- snapshot, + actual):\n{}\nSet {} to update it.",
+            path.display(),
+            diff(&expected, &actual),
+            UPDATE_ENV
+        );
+    }
+}
+
+/// Builds the [Request] for the given [Endpoint] using the base address and
+/// compares it against the snapshot stored at the given path.
+///
+/// # Panics
+///
+/// Panics if the request couldn't be built or doesn't match the snapshot.
+/// See [assert_request] for details.
+pub fn assert_endpoint<E: Endpoint>(endpoint: &E, base: &str, path: impl AsRef<Path>) {
+    let req = endpoint
+        .request(base)
+        .unwrap_or_else(|e| panic!("Failed building request: {}", e));
+    assert_request(&req, path);
+}
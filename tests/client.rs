@@ -0,0 +1,2975 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use common::{Middle, StreamOnly, TestResponse, TestServer};
+use httpmock::prelude::*;
+use rustify::{
+    auth::{AuthProvider, BearerAuth},
+    cache::{disk::DiskStore, CachingClient, MemoryStore},
+    client::{Client, StatusCodes, SuccessClient, SuccessCodes},
+    clients::{
+        pool::{Balance, BalancedClient, HealthCheck},
+        reqwest::Client as Reqwest,
+    },
+    config::{Certificate, ClientConfig, HttpVersion, Identity, TlsVersion},
+    correlation::RequestId,
+    csrf::{CsrfClient, CsrfSource},
+    endpoint::{Endpoint, MiddleWare},
+    errors::ClientError,
+    http::UrlJoin,
+    idempotency::IdempotencyKey,
+    metrics::{MetricsObserver, RequestMetrics},
+    ratelimit::{retry_after, ConcurrencyLimitedClient, RateLimitedClient, ThrottleClient},
+    redirect::RedirectPolicy,
+    retry::{RetryClient, RetryPolicy},
+    test::{
+        chaos::{ChaosClient, ChaosPolicy},
+        Mock, MockClient, RecordingClient,
+    },
+};
+use rustify_derive::Endpoint;
+use serde_json::json;
+use test_log::test;
+
+#[test(tokio::test)]
+async fn test_client_middleware() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "TestResponse")]
+    struct Test {}
+
+    let t = TestServer::default();
+    let client = Reqwest::default(t.server.base_url().as_str()).with_middleware(Middle {});
+    let m = t.server.mock(|when, then| {
+        when.method(GET)
+            .path("/test/path")
+            .header("X-API-Token", "mytoken");
+        then.status(200).json_body(json!({"result": {"age": 30}}));
+    });
+    let r = Test {}.exec(&client).await;
+
+    m.assert();
+    assert!(r.is_ok());
+    assert_eq!(r.unwrap().parse().unwrap().age, 30);
+}
+
+#[test(tokio::test)]
+async fn test_shutdown() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path")]
+    struct Test {}
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(200).delay(Duration::from_millis(200));
+    });
+
+    let (r, s) = tokio::join!(Test {}.exec(&t.client), async {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        t.client.shutdown(Duration::from_secs(5)).await
+    });
+
+    m.assert();
+    assert!(r.is_ok());
+    assert!(s.is_ok());
+    assert_eq!(t.client.drain().unwrap().in_flight(), 0);
+    assert!(t.client.http().is_none());
+
+    let r = Test {}.exec(&t.client).await;
+    assert!(matches!(r, Err(ClientError::ShutdownError)));
+}
+
+#[test(tokio::test)]
+async fn test_shutdown_retry() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path")]
+    struct Test {}
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(500);
+    });
+
+    // The shutdown starts while waiting to retry and waits for the retry
+    let policy = RetryPolicy {
+        max_attempts: 2,
+        initial_backoff: Duration::from_millis(200),
+        jitter: false,
+        ..Default::default()
+    };
+    let client = RetryClient::new(t.client, policy);
+    let (r, s) = tokio::join!(Test {}.exec(&client), async {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        client.shutdown(Duration::from_secs(5)).await
+    });
+
+    m.assert_hits(2);
+    assert!(matches!(
+        r,
+        Err(ClientError::ServerResponseError { code: 500, .. })
+    ));
+    assert!(s.is_ok());
+    assert!(client.inner().http().is_none());
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn test_shutdown_blocking() {
+    use common::TestServerBlocking;
+    use rustify::blocking::client::Client as BlockingClient;
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path")]
+    struct Test {}
+
+    let t = TestServerBlocking::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(200).delay(Duration::from_millis(200));
+    });
+
+    let (r, s) = std::thread::scope(|scope| {
+        let r = scope.spawn(|| Test {}.exec_block(&t.client));
+        std::thread::sleep(Duration::from_millis(50));
+        let s = t.client.shutdown(Duration::from_secs(5));
+        (r.join().unwrap(), s)
+    });
+
+    m.assert();
+    assert!(r.is_ok());
+    assert!(s.is_ok());
+    assert!(t.client.http().is_none());
+
+    let r = Test {}.exec_block(&t.client);
+    assert!(matches!(r, Err(ClientError::ShutdownError)));
+}
+
+#[test(tokio::test)]
+async fn test_shutdown_timeout() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path")]
+    struct Test {}
+
+    let t = TestServer::default();
+    t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(200).delay(Duration::from_millis(500));
+    });
+
+    let (_, s) = tokio::join!(Test {}.exec(&t.client), async {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        t.client.shutdown(Duration::from_millis(10)).await
+    });
+
+    assert!(matches!(
+        s,
+        Err(ClientError::ShutdownTimeoutError { in_flight: 1 })
+    ));
+    assert!(t.client.http().is_none());
+}
+
+#[test(tokio::test)]
+async fn test_retry() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path")]
+    struct Test {}
+
+    let t = TestServer::default();
+    let policy = RetryPolicy {
+        initial_backoff: Duration::from_millis(1),
+        ..RetryPolicy::new(3)
+    };
+    let client = RetryClient::new(t.client, policy);
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(503);
+    });
+    let r = Test {}.exec(&client).await;
+
+    m.assert_hits(3);
+    assert!(matches!(
+        r,
+        Err(ClientError::ServerResponseError { code: 503, .. })
+    ));
+}
+
+#[test(tokio::test)]
+async fn test_endpoint_retry() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", retries = "2", retry_on = "502, 503")]
+    struct Test {}
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/other", retries = "1")]
+    struct Other {}
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(503);
+    });
+    let r = Test {}.exec(&t.client).await;
+
+    m.assert_hits(3);
+    assert!(matches!(
+        r,
+        Err(ClientError::ServerResponseError { code: 503, .. })
+    ));
+
+    let policy = Test {}.retry_policy().unwrap();
+    assert_eq!(policy.max_attempts, 3);
+    assert!(!policy.retryable_codes.contains(500));
+    let policy = Other {}.retry_policy().unwrap();
+    assert_eq!(policy.max_attempts, 2);
+    assert!(policy.retryable_codes.contains(500));
+}
+
+#[test(tokio::test)]
+async fn test_retry_not_retryable() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path")]
+    struct Test {}
+
+    let t = TestServer::default();
+    let client = RetryClient::new(t.client, RetryPolicy::new(3));
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(404);
+    });
+    let r = Test {}.exec(&client).await;
+
+    m.assert_hits(1);
+    assert!(r.is_err());
+}
+
+#[test]
+fn test_retry_backoff_overflow() {
+    let policy = RetryPolicy {
+        max_attempts: u32::MAX,
+        jitter: false,
+        ..Default::default()
+    };
+    assert_eq!(policy.backoff(2), Duration::from_millis(100));
+    assert_eq!(policy.backoff(62), policy.max_backoff);
+    assert_eq!(policy.backoff(u32::MAX), policy.max_backoff);
+
+    for multiplier in [-2.0, f64::NAN, f64::INFINITY] {
+        let policy = RetryPolicy {
+            multiplier,
+            ..policy.clone()
+        };
+        assert!(policy.backoff(3) <= policy.max_backoff);
+        assert!(policy.backoff(1000) <= policy.max_backoff);
+    }
+}
+
+#[test(tokio::test)]
+async fn test_success_client() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path")]
+    struct Test {}
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", success = "200")]
+    struct Strict {}
+
+    let t = TestServer::default();
+    let client = SuccessClient::new(t.client, SuccessCodes::from_fn(|code| code < 500));
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(409);
+    });
+    let r = Test {}.exec(&client).await;
+    assert_eq!(r.unwrap().response.status(), 409);
+
+    // Endpoint success codes take precedence over the client
+    let r = Strict {}.exec(&client).await;
+    assert!(matches!(
+        r,
+        Err(ClientError::ServerResponseError { code: 409, .. })
+    ));
+    m.assert_hits(2);
+}
+
+#[test(tokio::test)]
+async fn test_throttle() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path")]
+    struct Test {}
+
+    let t = TestServer::default();
+    let mut client = ThrottleClient::new(t.client);
+    client.max_retries = 2;
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(429).header("Retry-After", "0");
+    });
+    let r = Test {}.exec(&client).await;
+
+    m.assert_hits(3);
+    assert!(matches!(
+        r,
+        Err(ClientError::ServerResponseError { code: 429, .. })
+    ));
+}
+
+#[test]
+fn test_retry_after() {
+    let mut headers = http::HeaderMap::new();
+    assert_eq!(retry_after(&headers), None);
+
+    headers.insert("X-RateLimit-Reset", "5".parse().unwrap());
+    assert_eq!(retry_after(&headers), Some(Duration::from_secs(5)));
+
+    headers.insert("Retry-After", "10".parse().unwrap());
+    assert_eq!(retry_after(&headers), Some(Duration::from_secs(10)));
+
+    headers.insert(
+        "Retry-After",
+        "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap(),
+    );
+    assert_eq!(retry_after(&headers), Some(Duration::ZERO));
+}
+
+#[test(tokio::test)]
+async fn test_rate_limit() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path")]
+    struct Test {}
+
+    let t = TestServer::default();
+    let client = RateLimitedClient::new(t.client, 20.0, 2);
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(200);
+    });
+
+    // The first two requests use the burst and the last two must wait
+    let start = Instant::now();
+    for _ in 0..4 {
+        assert!(Test {}.exec(&client).await.is_ok());
+    }
+
+    m.assert_hits(4);
+    assert!(start.elapsed() >= Duration::from_millis(90));
+}
+
+#[test(tokio::test)]
+async fn test_concurrency_limit() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path")]
+    struct Test {}
+
+    let t = TestServer::default();
+    let client = ConcurrencyLimitedClient::new(t.client, 2);
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(200).delay(Duration::from_millis(100));
+    });
+
+    // Four requests with a limit of two should take at least two round trips
+    let start = Instant::now();
+    let endpoints = (0..4).map(|_| Test {}).collect::<Vec<_>>();
+    let results = futures::future::join_all(endpoints.iter().map(|e| e.exec(&client))).await;
+
+    m.assert_hits(4);
+    assert!(results.iter().all(|r| r.is_ok()));
+    assert!(start.elapsed() >= Duration::from_millis(200));
+    assert_eq!(client.available(), 2);
+}
+
+#[test(tokio::test)]
+async fn test_concurrency_limit_stream() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path")]
+    struct Test {}
+
+    let t = TestServer::default();
+    let client = ConcurrencyLimitedClient::new(t.client, 2);
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(200).body("streamed");
+    });
+
+    // The permit is held until the streamed body has been read
+    let resp = Test {}.exec_stream(&client).await.unwrap();
+    assert_eq!(client.available(), 1);
+    let body = rustify::http::collect_body(resp.into_body()).await.unwrap();
+    assert_eq!(body, Bytes::from("streamed"));
+    assert_eq!(client.available(), 2);
+
+    let resp = Test {}.exec_stream(&client).await.unwrap();
+    assert_eq!(client.available(), 1);
+    drop(resp);
+    assert_eq!(client.available(), 2);
+    m.assert_hits(2);
+}
+
+#[test(tokio::test)]
+async fn test_balanced() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path")]
+    struct Test {}
+
+    let t1 = TestServer::default();
+    let t2 = TestServer::default();
+    let m1 = t1.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(200);
+    });
+    let m2 = t2.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(200);
+    });
+    let client = BalancedClient::new(
+        t1.client,
+        &[t1.server.base_url().as_str(), t2.server.base_url().as_str()],
+        Balance::RoundRobin,
+    );
+    for _ in 0..4 {
+        // Reading the base URL doesn't advance the selection
+        assert_eq!(client.base(), t1.server.base_url());
+        assert!(Test {}.exec(&client).await.is_ok());
+    }
+
+    m1.assert_hits(2);
+    m2.assert_hits(2);
+    assert!(client.backends().iter().all(|b| b.outstanding() == 0));
+}
+
+#[test(tokio::test)]
+async fn test_balanced_backend_match() {
+    let t1 = TestServer::default();
+    let t2 = TestServer::default();
+    let m = t1.server.mock(|when, then| {
+        when.method(GET).path("/apiv2/test");
+        then.status(200).delay(Duration::from_millis(300));
+    });
+    let client = BalancedClient::new(
+        t1.client,
+        &[
+            t2.server.url("/api").as_str(),
+            t1.server.url("/api").as_str(),
+        ],
+        Balance::RoundRobin,
+    );
+
+    // The path only shares a prefix with the base URL of the second backend
+    let req = http::Request::get(t1.server.url("/apiv2/test"))
+        .body(Bytes::new())
+        .unwrap();
+    let (resp, _) = tokio::join!(client.send(req), async {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(client.backends().iter().all(|b| b.outstanding() == 0));
+    });
+
+    m.assert();
+    assert!(resp.is_ok());
+}
+
+#[test(tokio::test)]
+async fn test_balanced_least_outstanding() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path")]
+    struct Test {}
+
+    let t1 = TestServer::default();
+    let t2 = TestServer::default();
+    let m1 = t1.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(200).delay(Duration::from_millis(300));
+    });
+    let m2 = t2.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(200);
+    });
+    let client = BalancedClient::new(
+        t1.client,
+        &[t1.server.base_url().as_str(), t2.server.base_url().as_str()],
+        Balance::LeastOutstanding,
+    );
+
+    // While the slow backend is busy all other requests go to the fast one
+    let (slow, _) = tokio::join!(Test {}.exec(&client), async {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        for _ in 0..3 {
+            Test {}.exec(&client).await.unwrap();
+        }
+    });
+
+    assert!(slow.is_ok());
+    m1.assert_hits(1);
+    m2.assert_hits(3);
+}
+
+#[test(tokio::test)]
+async fn test_balanced_health() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path")]
+    struct Test {}
+
+    let t1 = TestServer::default();
+    let t2 = TestServer::default();
+    t1.server.mock(|when, then| {
+        when.method(GET).path("/health");
+        then.status(503);
+    });
+    t2.server.mock(|when, then| {
+        when.method(GET).path("/health");
+        then.status(200);
+    });
+    let m1 = t1.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(200);
+    });
+    let m2 = t2.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(200);
+    });
+    let client = BalancedClient::new(
+        t1.client,
+        &[t1.server.base_url().as_str(), t2.server.base_url().as_str()],
+        Balance::RoundRobin,
+    );
+    client
+        .check_health(&HealthCheck::new("health", Duration::from_secs(10)))
+        .await;
+    for _ in 0..4 {
+        assert!(Test {}.exec(&client).await.is_ok());
+    }
+
+    assert!(!client.backends()[0].is_healthy());
+    assert!(client.backends()[1].is_healthy());
+    m1.assert_hits(0);
+    m2.assert_hits(4);
+}
+
+#[test(tokio::test)]
+async fn test_balanced_health_check() {
+    let t1 = TestServer::default();
+    let t2 = TestServer::default();
+    let m1 = t1.server.mock(|when, then| {
+        when.method(GET)
+            .path("/health")
+            .query_param("tenant", "acme")
+            .header("X-Api-Version", "2");
+        then.status(200);
+    });
+    let m2 = t2.server.mock(|when, then| {
+        when.method(GET).path("/health");
+        then.status(429);
+    });
+
+    let config = ClientConfig::default()
+        .default_header(
+            http::header::HeaderName::from_static("x-api-version"),
+            http::HeaderValue::from_static("2"),
+        )
+        .default_query_param("tenant", "acme");
+    let client = BalancedClient::new(
+        Reqwest::with_config(t1.server.base_url().as_str(), &config).unwrap(),
+        &[t1.server.base_url().as_str(), t2.server.base_url().as_str()],
+        Balance::RoundRobin,
+    );
+
+    let check = HealthCheck::new("health", Duration::from_secs(10));
+    client.check_health(&check).await;
+    assert!(client.backends()[0].is_healthy());
+    assert!(!client.backends()[1].is_healthy());
+
+    let check = check.expect(SuccessCodes::from_fn(|code| code < 500));
+    client.check_health(&check).await;
+    assert!(client.backends()[0].is_healthy());
+    assert!(client.backends()[1].is_healthy());
+    m1.assert_hits(2);
+    m2.assert_hits(2);
+}
+
+#[test(tokio::test)]
+async fn test_mock_client() {
+    #[derive(Endpoint)]
+    #[endpoint(
+        path = "test/path/{self.name}",
+        method = "POST",
+        response = "TestResponse"
+    )]
+    struct Test {
+        #[endpoint(skip)]
+        name: String,
+        #[endpoint(query)]
+        page: u64,
+        age: u64,
+    }
+
+    let client = MockClient::new("http://myapi.com");
+    client.mock(
+        Mock::new(http::Method::POST, "/test/path/test")
+            .query(&[("page", "1")])
+            .json_body(&json!({"age": 30}))
+            .json(&json!({"age": 30})),
+    );
+    client.mock(Mock::new(http::Method::POST, "/test/path/test").status(500));
+
+    let r = Test {
+        name: "test".to_string(),
+        page: 1,
+        age: 30,
+    }
+    .exec(&client)
+    .await;
+    assert_eq!(r.unwrap().parse().unwrap().age, 30);
+
+    let r = Test {
+        name: "test".to_string(),
+        page: 2,
+        age: 30,
+    }
+    .exec(&client)
+    .await;
+    assert!(matches!(
+        r,
+        Err(ClientError::ServerResponseError { code: 500, .. })
+    ));
+
+    let r = Test {
+        name: "other".to_string(),
+        page: 1,
+        age: 30,
+    }
+    .exec(&client)
+    .await;
+    assert!(matches!(r, Err(ClientError::RequestError { .. })));
+
+    let requests = client.requests();
+    assert_eq!(requests.len(), 3);
+    assert_eq!(requests[0].uri(), "http://myapi.com/test/path/test?page=1");
+}
+
+#[test(tokio::test)]
+async fn test_mock_client_verify() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "v1/users", method = "POST")]
+    struct Test {
+        name: String,
+    }
+
+    let client = MockClient::new("http://myapi.com");
+    client.mock(
+        Mock::new(http::Method::POST, "/v1/users")
+            .json_body(&json!({"name": "test"}))
+            .times(2),
+    );
+    client.mock(Mock::new(http::Method::POST, "/v1/users").status(400));
+
+    for name in ["test", "other"] {
+        let _ = Test {
+            name: name.to_string(),
+        }
+        .exec(&client)
+        .await;
+    }
+
+    let err = std::panic::catch_unwind(|| client.verify()).unwrap_err();
+    let msg = err.downcast_ref::<String>().unwrap();
+    assert!(msg.contains("POST /v1/users expected 2 request(s) but received 1"));
+    assert!(msg.contains(r#"-   "name": "test""#));
+    assert!(msg.contains(r#"+   "name": "other""#));
+
+    let _ = Test {
+        name: "test".to_string(),
+    }
+    .exec(&client)
+    .await;
+    client.verify();
+}
+
+#[test(tokio::test)]
+async fn test_recording_client() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", method = "POST", response = "TestResponse")]
+    struct Test {
+        age: u64,
+    }
+
+    let path = std::env::temp_dir().join(format!("rustify-cassette-{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(POST)
+            .path("/test/path")
+            .json_body(json!({"age": 30}));
+        then.status(200).json_body(json!({"age": 30}));
+    });
+
+    let client =
+        RecordingClient::new(Reqwest::default(t.server.base_url().as_str()), &path).unwrap();
+    assert!(!client.is_replaying());
+    let r = Test { age: 30 }.exec(&client).await;
+    assert_eq!(r.unwrap().parse().unwrap().age, 30);
+    assert!(path.exists());
+
+    let client =
+        RecordingClient::new(Reqwest::default(t.server.base_url().as_str()), &path).unwrap();
+    assert!(client.is_replaying());
+    let r = Test { age: 30 }.exec(&client).await;
+    assert_eq!(r.unwrap().parse().unwrap().age, 30);
+    let r = Test { age: 31 }.exec(&client).await;
+    assert!(matches!(r, Err(ClientError::RequestError { .. })));
+
+    m.assert_hits(1);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test(tokio::test)]
+async fn test_recording_client_stream() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path")]
+    struct Test {}
+
+    let path = std::env::temp_dir().join(format!(
+        "rustify-cassette-stream-{}.json",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+
+    let mock = MockClient::new("http://myapi.com");
+    mock.mock(Mock::new(http::Method::GET, "/test/path").respond(b"streamed".to_vec()));
+    let client = RecordingClient::new(StreamOnly(mock), &path).unwrap();
+    let resp = Test {}.exec_stream(&client).await.unwrap();
+    let body = rustify::http::collect_body(resp.into_body()).await.unwrap();
+    assert_eq!(body.as_ref(), b"streamed");
+    assert_eq!(client.inner().0.requests().len(), 1);
+
+    // The recorded response is replayed as a stream
+    let client =
+        RecordingClient::new(StreamOnly(MockClient::new("http://myapi.com")), &path).unwrap();
+    assert!(client.is_replaying());
+    let resp = Test {}.exec_stream(&client).await.unwrap();
+    let body = rustify::http::collect_body(resp.into_body()).await.unwrap();
+    assert_eq!(body.as_ref(), b"streamed");
+    assert!(client.inner().0.requests().is_empty());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test(tokio::test)]
+async fn test_chaos() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path")]
+    struct Test {}
+
+    let mock = || {
+        let client = MockClient::new("http://myapi.com");
+        client.mock(Mock::new(http::Method::GET, "/test/path"));
+        client
+    };
+
+    let client = ChaosClient::new(
+        mock(),
+        ChaosPolicy {
+            failure_rate: 1.0,
+            ..Default::default()
+        },
+    );
+    let r = Test {}.exec(&client).await;
+    assert!(matches!(r, Err(ClientError::RequestError { .. })));
+    assert!(client.inner().requests().is_empty());
+
+    let client = ChaosClient::new(
+        mock(),
+        ChaosPolicy {
+            error_rate: 1.0,
+            latency_rate: 1.0,
+            latency: Duration::from_millis(50),
+            ..Default::default()
+        },
+    );
+    let start = Instant::now();
+    let r = Test {}.exec(&client).await;
+    assert!(start.elapsed() >= Duration::from_millis(50));
+    assert!(matches!(
+        r,
+        Err(ClientError::ServerResponseError { code: 503, .. })
+    ));
+
+    let policy = ChaosPolicy {
+        failure_rate: 0.5,
+        seed: Some(42),
+        ..Default::default()
+    };
+    let mut outcomes = Vec::new();
+    for _ in 0..2 {
+        let client = ChaosClient::new(mock(), policy.clone());
+        let mut results = Vec::new();
+        for _ in 0..20 {
+            results.push(Test {}.exec(&client).await.is_ok());
+        }
+        outcomes.push(results);
+    }
+    assert_eq!(outcomes[0], outcomes[1]);
+    assert!(outcomes[0].contains(&true) && outcomes[0].contains(&false));
+}
+
+#[test(tokio::test)]
+async fn test_chaos_stream() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path")]
+    struct Test {}
+
+    let mock = || {
+        let client = MockClient::new("http://myapi.com");
+        client.mock(Mock::new(http::Method::GET, "/test/path").respond(b"streamed".to_vec()));
+        StreamOnly(client)
+    };
+
+    let client = ChaosClient::new(mock(), ChaosPolicy::default());
+    let resp = Test {}.exec_stream(&client).await.unwrap();
+    let body = rustify::http::collect_body(resp.into_body()).await.unwrap();
+    assert_eq!(body.as_ref(), b"streamed");
+
+    let client = ChaosClient::new(
+        mock(),
+        ChaosPolicy {
+            error_rate: 1.0,
+            ..Default::default()
+        },
+    );
+    let r = Test {}.exec_stream(&client).await;
+    assert!(matches!(
+        r,
+        Err(ClientError::ServerResponseError { code: 503, .. })
+    ));
+    assert!(client.inner().0.requests().is_empty());
+}
+
+#[cfg(feature = "hyper")]
+#[test(tokio::test)]
+async fn test_hyper() {
+    use rustify::clients::hyper::Client as Hyper;
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", method = "POST", response = "TestResponse")]
+    struct Test {
+        name: String,
+    }
+
+    let t = TestServer::default();
+    let client = Hyper::default(t.server.base_url().as_str());
+    let m = t.server.mock(|when, then| {
+        when.method(POST)
+            .path("/test/path")
+            .json_body(json!({ "name": "test" }));
+        then.status(200)
+            .header("X-Test", "test")
+            .json_body(json!({"age": 30}));
+    });
+    let r = Test {
+        name: "test".to_string(),
+    }
+    .exec(&client)
+    .await
+    .unwrap();
+
+    let resp = Test {
+        name: "test".to_string(),
+    }
+    .exec_stream(&client)
+    .await
+    .unwrap();
+    let body = rustify::http::collect_body(resp.into_body()).await.unwrap();
+
+    m.assert_hits(2);
+    assert_eq!(r.response.headers()["X-Test"], "test");
+    assert_eq!(r.parse().unwrap().age, 30);
+    assert_eq!(body.as_ref(), br#"{"age":30}"#);
+}
+
+#[cfg(feature = "attohttpc")]
+#[test]
+fn test_attohttpc() {
+    use rustify::blocking::clients::attohttpc::Client as Attohttpc;
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", method = "POST", response = "TestResponse")]
+    struct Test {
+        #[endpoint(header)]
+        x_api_token: String,
+        name: String,
+    }
+
+    let server = MockServer::start();
+    let client = Attohttpc::default(server.base_url().as_str());
+    let m = server.mock(|when, then| {
+        when.method(POST)
+            .path("/test/path")
+            .header("X-API-Token", "mytoken")
+            .json_body(json!({ "name": "test" }));
+        then.status(200)
+            .header("X-Test", "test")
+            .json_body(json!({"age": 30}));
+    });
+    let r = Test {
+        x_api_token: "mytoken".to_string(),
+        name: "test".to_string(),
+    }
+    .exec_block(&client)
+    .unwrap();
+
+    m.assert();
+    assert_eq!(r.response.headers()["X-Test"], "test");
+    assert_eq!(r.parse().unwrap().age, 30);
+}
+
+#[cfg(feature = "isahc")]
+#[test(tokio::test)]
+async fn test_isahc() {
+    use rustify::clients::isahc::Client as Isahc;
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", method = "POST", response = "TestResponse")]
+    struct Test {
+        #[endpoint(header)]
+        x_api_token: String,
+        name: String,
+    }
+
+    let t = TestServer::default();
+    let client = Isahc::default(t.server.base_url().as_str());
+    let m = t.server.mock(|when, then| {
+        when.method(POST)
+            .path("/test/path")
+            .header("X-API-Token", "mytoken")
+            .json_body(json!({ "name": "test" }));
+        then.status(200)
+            .header("X-Test", "test")
+            .json_body(json!({"age": 30}));
+    });
+    let r = Test {
+        x_api_token: "mytoken".to_string(),
+        name: "test".to_string(),
+    }
+    .exec(&client)
+    .await
+    .unwrap();
+
+    m.assert();
+    assert_eq!(r.response.headers()["X-Test"], "test");
+    assert_eq!(r.parse().unwrap().age, 30);
+}
+
+#[cfg(feature = "non-send")]
+#[test(tokio::test)]
+async fn test_non_send() {
+    use std::rc::Rc;
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path/{self.name}", response = "TestResponse")]
+    struct Test {
+        #[endpoint(skip)]
+        name: Rc<String>,
+    }
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path/test");
+        then.status(200).json_body(json!({"age": 30}));
+    });
+    let r = Test {
+        name: Rc::new("test".to_string()),
+    }
+    .exec(&t.client)
+    .await;
+
+    m.assert();
+    assert_eq!(r.unwrap().parse().unwrap().age, 30);
+}
+
+#[cfg(feature = "decompression")]
+#[test]
+fn test_decompress() {
+    use std::io::Write;
+
+    let data = br#"{"age": 30}"#.to_vec();
+    let gzip = {
+        let mut e = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        e.write_all(&data).unwrap();
+        e.finish().unwrap()
+    };
+    let brotli = {
+        let mut out = Vec::new();
+        let mut e = brotli::CompressorWriter::new(&mut out, 4096, 5, 22);
+        e.write_all(&data).unwrap();
+        drop(e);
+        out
+    };
+    let gzip_zstd = zstd::encode_all(gzip.as_slice(), 0).unwrap();
+
+    for (encoding, body) in [("gzip", gzip), ("br", brotli), ("gzip, zstd", gzip_zstd)] {
+        let mut resp = http::Response::builder()
+            .header("Content-Encoding", encoding)
+            .header("Content-Length", body.len())
+            .body(body.into())
+            .unwrap();
+        rustify::http::decompress(&mut resp).unwrap();
+
+        assert_eq!(resp.body(), &data);
+        assert!(resp.headers().get("Content-Encoding").is_none());
+        assert!(resp.headers().get("Content-Length").is_none());
+    }
+
+    let mut resp = http::Response::builder()
+        .header("Content-Encoding", "compress")
+        .body(data.clone().into())
+        .unwrap();
+    rustify::http::decompress(&mut resp).unwrap();
+    assert_eq!(resp.body(), &data);
+    assert_eq!(resp.headers()["Content-Encoding"], "compress");
+}
+
+#[test(tokio::test)]
+async fn test_exec_with_retry() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path")]
+    struct Test {}
+
+    let t = TestServer::default();
+    let policy = RetryPolicy {
+        initial_backoff: Duration::from_millis(1),
+        retryable_codes: StatusCodes::new(vec![429..=429]),
+        ..RetryPolicy::new(4)
+    };
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(429);
+    });
+    let r = Test {}.exec_with_retry(&t.client, &policy).await;
+
+    m.assert_hits(4);
+    assert!(matches!(
+        r,
+        Err(ClientError::ServerResponseError { code: 429, .. })
+    ));
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(503);
+    });
+    let r = Test {}.exec_with_retry(&t.client, &policy).await;
+
+    m.assert_hits(1);
+    assert!(r.is_err());
+}
+
+#[test(tokio::test)]
+async fn test_exec_with_timeout() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "TestResponse")]
+    struct Test {}
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(200)
+            .delay(Duration::from_millis(500))
+            .json_body(json!({"age": 30}));
+    });
+
+    let r = Test {}
+        .exec_with_timeout(&t.client, Duration::from_millis(50))
+        .await;
+    assert!(matches!(r, Err(ClientError::Timeout { .. })));
+
+    let r = Test {}
+        .exec_with_timeout(&t.client, Duration::from_secs(5))
+        .await;
+    assert_eq!(r.unwrap().parse().unwrap().age, 30);
+    m.assert_hits(2);
+}
+
+#[test(tokio::test)]
+async fn test_endpoint_timeout() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "TestResponse", timeout_ms = "50")]
+    struct Test {}
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(200)
+            .delay(Duration::from_millis(500))
+            .json_body(json!({"age": 30}));
+    });
+
+    assert_eq!(Test {}.timeout(), Some(Duration::from_millis(50)));
+    let r = Test {}.exec(&t.client).await;
+    assert!(matches!(r, Err(ClientError::Timeout { .. })));
+
+    let r = Test {}
+        .exec_with_timeout(&t.client, Duration::from_secs(5))
+        .await;
+    assert_eq!(r.unwrap().parse().unwrap().age, 30);
+    m.assert_hits(2);
+}
+
+#[test(tokio::test)]
+async fn test_exec_with_cancel() {
+    use tokio_util::sync::CancellationToken;
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "TestResponse")]
+    struct Test {}
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(200)
+            .delay(Duration::from_millis(500))
+            .json_body(json!({"age": 30}));
+    });
+
+    let token = CancellationToken::new();
+    let child = token.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        child.cancel();
+    });
+    let r = Test {}.exec_with_cancel(&t.client, &token).await;
+    assert!(matches!(r, Err(ClientError::CancelledError)));
+
+    let r = Test {}
+        .exec_with_cancel(&t.client, &CancellationToken::new())
+        .await;
+    assert_eq!(r.unwrap().parse().unwrap().age, 30);
+    m.assert_hits(2);
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn test_exec_block_with_timeout() {
+    use common::TestServerBlocking;
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "TestResponse")]
+    struct Test {}
+
+    let t = TestServerBlocking::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(200)
+            .delay(Duration::from_millis(500))
+            .json_body(json!({"age": 30}));
+    });
+
+    let r = Test {}.exec_block_with_timeout(&t.client, Duration::from_millis(50));
+    assert!(matches!(r, Err(ClientError::Timeout { .. })));
+
+    let r = Test {}.exec_block_with_timeout(&t.client, Duration::from_secs(5));
+    assert_eq!(r.unwrap().parse().unwrap().age, 30);
+    m.assert_hits(2);
+}
+
+#[test(tokio::test)]
+async fn test_auth_provider() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "TestResponse")]
+    struct Test {}
+
+    struct Token {}
+
+    #[cfg_attr(not(feature = "non-send"), async_trait)]
+    #[cfg_attr(feature = "non-send", async_trait(?Send))]
+    impl AuthProvider for Token {
+        async fn apply(&self, req: &mut http::Request<Bytes>) -> Result<(), ClientError> {
+            req.headers_mut().insert(
+                "Authorization",
+                http::HeaderValue::from_static("Bearer mytoken"),
+            );
+            Ok(())
+        }
+    }
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET)
+            .path("/test/path")
+            .header("Authorization", "Bearer mytoken");
+        then.status(200).json_body(json!({"age": 30}));
+    });
+
+    let mut client = Reqwest::default(t.server.base_url().as_str());
+    client.set_auth(Token {});
+    let r = Test {}.exec(&client).await;
+    assert_eq!(r.unwrap().parse().unwrap().age, 30);
+
+    let client = ThrottleClient::new(client);
+    assert!(client.auth().is_some());
+    let r = Test {}.exec(&client).await;
+    assert!(r.is_ok());
+    m.assert_hits(2);
+
+    assert!(Test {}.exec(&t.client).await.is_err());
+}
+
+#[test(tokio::test)]
+async fn test_bearer_auth() {
+    use std::sync::{Arc, RwLock};
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "TestResponse")]
+    struct Test {}
+
+    let t = TestServer::default();
+    let old = t.server.mock(|when, then| {
+        when.method(GET)
+            .path("/test/path")
+            .header("Authorization", "Bearer oldtoken");
+        then.status(200).json_body(json!({"age": 30}));
+    });
+    let new = t.server.mock(|when, then| {
+        when.method(GET)
+            .path("/test/path")
+            .header("Authorization", "Bearer newtoken");
+        then.status(200).json_body(json!({"age": 31}));
+    });
+
+    let token = Arc::new(RwLock::new("oldtoken".to_string()));
+    let mut client = Reqwest::default(t.server.base_url().as_str());
+    client.set_auth(BearerAuth::shared(token.clone()));
+    let r = Test {}.exec(&client).await;
+    assert_eq!(r.unwrap().parse().unwrap().age, 30);
+
+    *token.write().unwrap() = "newtoken".to_string();
+    let r = Test {}.exec(&client).await;
+    assert_eq!(r.unwrap().parse().unwrap().age, 31);
+
+    let mut client = Reqwest::default(t.server.base_url().as_str());
+    client.set_auth(BearerAuth::from_fn(|| "newtoken".to_string()));
+    let r = Test {}.exec(&client).await;
+    assert_eq!(r.unwrap().parse().unwrap().age, 31);
+
+    old.assert_hits(1);
+    new.assert_hits(2);
+    assert_eq!(BearerAuth::new("mytoken").token(), "mytoken");
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn test_auth_provider_blocking() {
+    use rustify::blocking::client::Client as BlockingClient;
+    use rustify::blocking::clients::reqwest::Client as ReqwestBlocking;
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "TestResponse")]
+    struct Test {}
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/wrapped", response = "TestResponse")]
+    struct Wrapped {}
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET)
+            .path("/test/path")
+            .header("Authorization", "Bearer mytoken");
+        then.status(200).json_body(json!({"age": 30}));
+    });
+    let wrapped = t.server.mock(|when, then| {
+        when.method(GET)
+            .path("/test/wrapped")
+            .header("Authorization", "Bearer mytoken")
+            .header("X-API-Token", "mytoken");
+        then.status(200).json_body(json!({"result": {"age": 30}}));
+    });
+
+    let mut client = ReqwestBlocking::default(t.server.base_url().as_str());
+    client.set_auth(BearerAuth::new("mytoken"));
+    let r = Test {}.exec_block(&client);
+    assert_eq!(r.unwrap().parse().unwrap().age, 30);
+
+    let client = ThrottleClient::new(client.with_middleware(Middle {}));
+    assert!(BlockingClient::auth(&client).is_some());
+    let r = Wrapped {}.exec_block(&client);
+    assert_eq!(r.unwrap().parse().unwrap().age, 30);
+    m.assert();
+    wrapped.assert();
+
+    let client = ReqwestBlocking::default(t.server.base_url().as_str());
+    assert!(Test {}.exec_block(&client).is_err());
+}
+
+#[cfg(feature = "oauth2")]
+#[test(tokio::test)]
+async fn test_oauth2_client_credentials() {
+    use rustify::auth::oauth2::ClientCredentials;
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "TestResponse")]
+    struct Test {}
+
+    let t = TestServer::default();
+    let token = t.server.mock(|when, then| {
+        when.method(POST)
+            .path("/oauth/token")
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .x_www_form_urlencoded_tuple("grant_type", "client_credentials")
+            .x_www_form_urlencoded_tuple("client_id", "myclient")
+            .x_www_form_urlencoded_tuple("client_secret", "mysecret")
+            .x_www_form_urlencoded_tuple("scope", "read write");
+        then.status(200).json_body(json!({
+            "access_token": "mytoken",
+            "token_type": "Bearer",
+            "expires_in": 3600,
+        }));
+    });
+    let m = t.server.mock(|when, then| {
+        when.method(GET)
+            .path("/test/path")
+            .header("Authorization", "Bearer mytoken");
+        then.status(200).json_body(json!({"age": 30}));
+    });
+
+    let auth = ClientCredentials::new(
+        Reqwest::default(t.server.base_url().as_str()),
+        t.server.url("/oauth/token").as_str(),
+        "myclient",
+        "mysecret",
+    )
+    .scopes(&["read", "write"]);
+    let mut client = Reqwest::default(t.server.base_url().as_str());
+    client.set_auth(auth);
+
+    for _ in 0..2 {
+        let r = Test {}.exec(&client).await;
+        assert_eq!(r.unwrap().parse().unwrap().age, 30);
+    }
+    token.assert_hits(1);
+    m.assert_hits(2);
+
+    // Tokens expiring within the refresh window are fetched again
+    let mut auth = ClientCredentials::new(
+        Reqwest::default(t.server.base_url().as_str()),
+        t.server.url("/oauth/token").as_str(),
+        "myclient",
+        "mysecret",
+    )
+    .scopes(&["read", "write"]);
+    auth.refresh_before = Duration::from_secs(7200);
+    assert_eq!(auth.token().await.unwrap(), "mytoken");
+    assert_eq!(auth.token().await.unwrap(), "mytoken");
+    token.assert_hits(3);
+}
+
+#[cfg(feature = "jwt")]
+#[test(tokio::test)]
+async fn test_jwt_auth() {
+    use base64::{
+        engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+        Engine,
+    };
+    use ring::{
+        rand::SystemRandom,
+        signature::{
+            EcdsaKeyPair, KeyPair, RsaKeyPair, UnparsedPublicKey, ECDSA_P256_SHA256_FIXED,
+            ECDSA_P256_SHA256_FIXED_SIGNING, RSA_PKCS1_2048_8192_SHA256,
+        },
+    };
+    use rustify::auth::jwt::{JwtAlgorithm, JwtAuth};
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "TestResponse")]
+    struct Test {}
+
+    fn der(pem: &str) -> Vec<u8> {
+        let body: String = pem.lines().filter(|l| !l.starts_with("-----")).collect();
+        STANDARD.decode(body).unwrap()
+    }
+
+    fn verify(token: &str, alg: &str, key: UnparsedPublicKey<Vec<u8>>) -> serde_json::Value {
+        let parts: Vec<&str> = token.split('.').collect();
+        assert_eq!(parts.len(), 3);
+        let header: serde_json::Value =
+            serde_json::from_slice(&URL_SAFE_NO_PAD.decode(parts[0]).unwrap()).unwrap();
+        assert_eq!(header["alg"], alg);
+        let signature = URL_SAFE_NO_PAD.decode(parts[2]).unwrap();
+        key.verify(format!("{}.{}", parts[0], parts[1]).as_bytes(), &signature)
+            .unwrap();
+        serde_json::from_slice(&URL_SAFE_NO_PAD.decode(parts[1]).unwrap()).unwrap()
+    }
+
+    // RS256 using a PKCS#1 key
+    let rsa = include_str!("fixtures/rsa.pem");
+    let mut auth = JwtAuth::from_pem(JwtAlgorithm::RS256, rsa, "123456").unwrap();
+    auth.audience = Some("https://myapi.com".to_string());
+    let token = auth.token().unwrap();
+    assert_eq!(auth.token().unwrap(), token);
+
+    let public = RsaKeyPair::from_der(&der(rsa)).unwrap();
+    let public = public.public_key().as_ref().to_vec();
+    let claims = verify(
+        &token,
+        "RS256",
+        UnparsedPublicKey::new(&RSA_PKCS1_2048_8192_SHA256, public),
+    );
+    assert_eq!(claims["iss"], "123456");
+    assert_eq!(claims["aud"], "https://myapi.com");
+    assert_eq!(
+        claims["exp"].as_u64().unwrap() - claims["iat"].as_u64().unwrap(),
+        360
+    );
+
+    // ES256 using a PKCS#8 key
+    let ec = include_str!("fixtures/ec.pem");
+    let auth = JwtAuth::from_pem(JwtAlgorithm::ES256, ec, "me").unwrap();
+    let token = auth.token().unwrap();
+
+    let public = EcdsaKeyPair::from_pkcs8(
+        &ECDSA_P256_SHA256_FIXED_SIGNING,
+        &der(ec),
+        &SystemRandom::new(),
+    )
+    .unwrap();
+    let public = public.public_key().as_ref().to_vec();
+    let claims = verify(
+        &token,
+        "ES256",
+        UnparsedPublicKey::new(&ECDSA_P256_SHA256_FIXED, public),
+    );
+    assert_eq!(claims["iss"], "me");
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET)
+            .path("/test/path")
+            .header("Authorization", format!("Bearer {}", token));
+        then.status(200).json_body(json!({"age": 30}));
+    });
+    let mut client = Reqwest::default(t.server.base_url().as_str());
+    client.set_auth(auth);
+    let r = Test {}.exec(&client).await;
+    m.assert();
+    assert_eq!(r.unwrap().parse().unwrap().age, 30);
+
+    assert!(JwtAuth::from_pem(JwtAlgorithm::ES256, rsa, "me").is_err());
+}
+
+#[cfg(feature = "cloud")]
+#[test(tokio::test)]
+async fn test_managed_identity() {
+    use rustify::auth::cloud::{aws_credentials, ManagedIdentity};
+
+    let t = TestServer::default();
+    let gcp = t.server.mock(|when, then| {
+        when.method(GET)
+            .path("/computeMetadata/v1/instance/service-accounts/default/token")
+            .query_param("scopes", "a,b")
+            .header("Metadata-Flavor", "Google");
+        then.status(200).json_body(json!({
+            "access_token": "gcptoken",
+            "expires_in": 3599,
+            "token_type": "Bearer",
+        }));
+    });
+    let azure = t.server.mock(|when, then| {
+        when.method(GET)
+            .path("/metadata/identity/oauth2/token")
+            .query_param("api-version", "2018-02-01")
+            .query_param("resource", "https://management.azure.com/")
+            .header("Metadata", "true");
+        then.status(200).json_body(json!({
+            "access_token": "azuretoken",
+            "expires_in": "3599",
+            "token_type": "Bearer",
+        }));
+    });
+
+    let auth = ManagedIdentity::gcp(Reqwest::default(t.server.base_url().as_str()), &["a", "b"]);
+    assert_eq!(auth.token().await.unwrap(), "gcptoken");
+    assert_eq!(auth.token().await.unwrap(), "gcptoken");
+    gcp.assert_hits(1);
+
+    let auth = ManagedIdentity::azure(
+        Reqwest::default(t.server.base_url().as_str()),
+        "https://management.azure.com/",
+        None,
+    );
+    assert_eq!(auth.token().await.unwrap(), "azuretoken");
+    assert_eq!(auth.token().await.unwrap(), "azuretoken");
+    azure.assert_hits(1);
+
+    let session = t.server.mock(|when, then| {
+        when.method(PUT)
+            .path("/latest/api/token")
+            .header_exists("X-aws-ec2-metadata-token-ttl-seconds");
+        then.status(200).body("session");
+    });
+    let roles = t.server.mock(|when, then| {
+        when.method(GET)
+            .path("/latest/meta-data/iam/security-credentials/")
+            .header("X-aws-ec2-metadata-token", "session");
+        then.status(200).body("myrole");
+    });
+    let creds = t.server.mock(|when, then| {
+        when.method(GET)
+            .path("/latest/meta-data/iam/security-credentials/myrole")
+            .header("X-aws-ec2-metadata-token", "session");
+        then.status(200).json_body(json!({
+            "Code": "Success",
+            "AccessKeyId": "AKID",
+            "SecretAccessKey": "secret",
+            "Token": "token",
+            "Expiration": "2026-01-01T00:00:00Z",
+        }));
+    });
+    let r = aws_credentials(&Reqwest::default(t.server.base_url().as_str()))
+        .await
+        .unwrap();
+    session.assert();
+    roles.assert();
+    creds.assert();
+    assert_eq!(r.access_key_id, "AKID");
+    assert_eq!(r.secret_access_key, "secret");
+    assert_eq!(r.session_token.as_deref(), Some("token"));
+}
+
+#[test(tokio::test)]
+async fn test_idempotency_key() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", method = "POST")]
+    struct Test {}
+
+    let key = IdempotencyKey::new();
+    let parts: Vec<usize> = key.key().split('-').map(|p| p.len()).collect();
+    assert_eq!(parts, vec![8, 4, 4, 4, 12]);
+    assert_eq!(&key.key()[14..15], "4");
+    assert_ne!(key.key(), IdempotencyKey::new().key());
+
+    let t = TestServer::default();
+    let policy = RetryPolicy {
+        initial_backoff: Duration::from_millis(1),
+        ..RetryPolicy::new(3)
+    };
+    let m = t.server.mock(|when, then| {
+        when.method(POST)
+            .path("/test/path")
+            .header("Idempotency-Key", key.key());
+        then.status(503);
+    });
+    let r = Test {}
+        .with_middleware(&key)
+        .exec_with_retry(&t.client, &policy)
+        .await;
+    m.assert_hits(3);
+    assert!(r.is_err());
+}
+
+#[test(tokio::test)]
+async fn test_request_id() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "TestResponse")]
+    struct Test {}
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET)
+            .path("/test/path")
+            .header_exists("X-Request-Id");
+        then.status(200)
+            .header("X-Request-Id", "server-id")
+            .json_body(json!({"age": 30}));
+    });
+    let client = Reqwest::default(t.server.base_url().as_str()).with_middleware(RequestId::new());
+    let r = Test {}.exec(&client).await.unwrap();
+    m.assert();
+    assert_eq!(r.request_id(), Some("server-id"));
+
+    // Existing request IDs are kept
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "TestResponse")]
+    struct WithId {
+        #[endpoint(header = "X-Request-Id")]
+        id: String,
+    }
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET)
+            .path("/test/path")
+            .header("X-Request-Id", "caller-id");
+        then.status(200).json_body(json!({"age": 30}));
+    });
+    let r = WithId {
+        id: "caller-id".to_string(),
+    }
+    .with_middleware(&RequestId::from_span())
+    .exec(&t.client)
+    .await;
+    m.assert();
+    assert_eq!(r.unwrap().request_id(), None);
+}
+
+#[test(tokio::test)]
+async fn test_metrics_observer() {
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Endpoint)]
+    #[endpoint(
+        path = "test/path/{self.name}",
+        method = "POST",
+        response = "TestResponse"
+    )]
+    struct Test {
+        #[endpoint(skip)]
+        name: String,
+        kind: String,
+    }
+
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+
+    impl MetricsObserver for Recorder {
+        fn observe(&self, metrics: &RequestMetrics) {
+            self.0.lock().unwrap().push(format!(
+                "{} {} {:?} {} {:?}",
+                metrics.method,
+                metrics.path,
+                metrics.status,
+                metrics.request_size,
+                metrics.response_size
+            ));
+        }
+    }
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(POST).path("/test/path/ok");
+        then.status(200).body("{\"age\": 30}");
+    });
+    let e = t.server.mock(|when, then| {
+        when.method(POST).path("/test/path/err");
+        then.status(500);
+    });
+
+    let recorder = Recorder::default();
+    let mut client = Reqwest::default(t.server.base_url().as_str());
+    client.set_metrics(recorder.clone());
+    let client = ThrottleClient::new(client);
+    assert!(client.metrics().is_some());
+
+    let ok = Test {
+        name: "ok".to_string(),
+        kind: "a".to_string(),
+    };
+    assert!(ok.exec(&client).await.is_ok());
+    let err = Test {
+        name: "err".to_string(),
+        kind: "a".to_string(),
+    };
+    assert!(err.exec(&client).await.is_err());
+    m.assert();
+    e.assert();
+
+    let recorded = recorder.0.lock().unwrap();
+    assert_eq!(
+        *recorded,
+        vec![
+            "POST /test/path/ok Some(200) 12 Some(11)",
+            "POST /test/path/err Some(500) 12 Some(0)",
+        ]
+    );
+    drop(recorded);
+
+    let client = Reqwest::default("http://127.0.0.1:1");
+    assert!(client.metrics().is_none());
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn test_metrics_observer_blocking() {
+    use rustify::blocking::client::Client as BlockingClient;
+    use rustify::blocking::clients::reqwest::Client as ReqwestBlocking;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path/{self.name}", response = "TestResponse")]
+    struct Test {
+        #[endpoint(skip)]
+        name: String,
+    }
+
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+
+    impl MetricsObserver for Recorder {
+        fn observe(&self, metrics: &RequestMetrics) {
+            self.0.lock().unwrap().push(format!(
+                "{} {} {:?} {:?} {:?}",
+                metrics.method,
+                metrics.path,
+                metrics.template,
+                metrics.status,
+                metrics.response_size
+            ));
+        }
+    }
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path/ok");
+        then.status(200).body("{\"age\": 30}");
+    });
+    let e = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path/err");
+        then.status(500);
+    });
+
+    let recorder = Recorder::default();
+    let mut client = ReqwestBlocking::default(t.server.base_url().as_str());
+    client.set_metrics(recorder.clone());
+    let client = ThrottleClient::new(client);
+    assert!(BlockingClient::metrics(&client).is_some());
+
+    let ok = Test {
+        name: "ok".to_string(),
+    };
+    assert!(ok.exec_block(&client).is_ok());
+    let err = Test {
+        name: "err".to_string(),
+    };
+    assert!(err.exec_block(&client).is_err());
+    m.assert();
+    e.assert();
+
+    let recorded = recorder.0.lock().unwrap();
+    assert_eq!(
+        *recorded,
+        vec![
+            "GET /test/path/ok Some(\"test/path/{name}\") Some(200) Some(11)",
+            "GET /test/path/err Some(\"test/path/{name}\") Some(500) Some(0)",
+        ]
+    );
+}
+
+#[cfg(feature = "prometheus")]
+#[test(tokio::test)]
+async fn test_instrumented_client() {
+    use rustify::metrics::prometheus::{InstrumentedClient, PrometheusMetrics};
+    use std::sync::Arc;
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "users/{self.name}", response = "TestResponse")]
+    struct Test {
+        #[endpoint(skip)]
+        name: String,
+    }
+
+    let t = TestServer::default();
+    t.server.mock(|when, then| {
+        when.method(GET).path("/users/a");
+        then.status(200).json_body(json!({"age": 30}));
+    });
+    t.server.mock(|when, then| {
+        when.method(GET).path("/users/b");
+        then.status(404);
+    });
+
+    let metrics = Arc::new(PrometheusMetrics::new().buckets(vec![60.0]));
+    let client = InstrumentedClient::with_metrics(
+        Reqwest::default(t.server.base_url().as_str()),
+        metrics.clone(),
+    );
+    for name in ["a", "a", "b"] {
+        let _ = Test {
+            name: name.to_string(),
+        }
+        .exec(&client)
+        .await;
+    }
+    let _ = Test {
+        name: "a".to_string(),
+    }
+    .exec(&InstrumentedClient::with_metrics(
+        Reqwest::default("http://127.0.0.1:1"),
+        metrics.clone(),
+    ))
+    .await;
+
+    let out = metrics.render();
+    let labels = "method=\"GET\",path=\"/users/{name}\"";
+    for line in [
+        format!("rustify_requests_total{{{},status=\"200\"}} 2", labels),
+        format!("rustify_requests_total{{{},status=\"404\"}} 1", labels),
+        format!("rustify_requests_total{{{},status=\"none\"}} 1", labels),
+        format!("rustify_request_errors_total{{{}}} 2", labels),
+        format!(
+            "rustify_request_duration_seconds_bucket{{{},le=\"60\"}} 4",
+            labels
+        ),
+        format!(
+            "rustify_request_duration_seconds_bucket{{{},le=\"+Inf\"}} 4",
+            labels
+        ),
+        format!("rustify_request_duration_seconds_count{{{}}} 4", labels),
+    ] {
+        assert!(out.contains(&line), "missing {} in:\n{}", line, out);
+    }
+    assert!(out.contains("# TYPE rustify_request_duration_seconds histogram"));
+}
+
+#[test(tokio::test)]
+async fn test_caching_client() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "TestResponse")]
+    struct Test {}
+
+    let t = TestServer::default();
+    let revalidated = t.server.mock(|when, then| {
+        when.method(GET)
+            .path("/test/path")
+            .header("If-None-Match", "\"v1\"");
+        then.status(304).header("X-Version", "2");
+    });
+    let fresh = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(200)
+            .header("ETag", "\"v1\"")
+            .header("X-Version", "1")
+            .json_body(json!({"age": 30}));
+    });
+
+    let client = CachingClient::new(Reqwest::default(t.server.base_url().as_str()));
+    let r = Test {}.exec(&client).await.unwrap();
+    assert_eq!(r.parse().unwrap().age, 30);
+    let url = format!("{}/test/path", t.server.base_url());
+    assert!(client.get(&http::Method::GET, &url).is_some());
+
+    let r = Test {}.exec(&client).await.unwrap();
+    assert_eq!(r.status(), 200);
+    assert_eq!(r.headers()["X-Version"], "2");
+    assert_eq!(r.parse().unwrap().age, 30);
+    fresh.assert_hits(1);
+    revalidated.assert_hits(1);
+
+    client.invalidate(&url);
+    assert!(Test {}.exec(&client).await.is_ok());
+    fresh.assert_hits(2);
+
+    let uncached = t.server.mock(|when, then| {
+        when.method(GET).path("/other");
+        then.status(200)
+            .header("ETag", "\"v1\"")
+            .header("Cache-Control", "private, no-store")
+            .json_body(json!({"age": 30}));
+    });
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "other", response = "TestResponse")]
+    struct Other {}
+
+    assert!(Other {}.exec(&client).await.is_ok());
+    assert!(Other {}.exec(&client).await.is_ok());
+    uncached.assert_hits(2);
+    assert!(client
+        .get(
+            &http::Method::GET,
+            &format!("{}/other", t.server.base_url())
+        )
+        .is_none());
+}
+
+#[test(tokio::test)]
+async fn test_caching_client_max_age() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/{self.id}", response = "TestResponse")]
+    struct Test {
+        #[endpoint(skip)]
+        id: u64,
+    }
+
+    let t = TestServer::default();
+    let fresh = t.server.mock(|when, then| {
+        when.method(GET).path("/test/1");
+        then.status(200)
+            .header("Cache-Control", "public, max-age=60")
+            .json_body(json!({"age": 30}));
+    });
+    let stale = t.server.mock(|when, then| {
+        when.method(GET).path("/test/2");
+        then.status(200)
+            .header("Cache-Control", "max-age=60")
+            .header("Age", "60")
+            .json_body(json!({"age": 30}));
+    });
+    let others: Vec<_> = vec!["/test/3", "/test/4"]
+        .into_iter()
+        .map(|path| {
+            t.server.mock(|when, then| {
+                when.method(GET).path(path);
+                then.status(200)
+                    .header("Cache-Control", "max-age=60")
+                    .json_body(json!({"age": 30}));
+            })
+        })
+        .collect();
+
+    let client = CachingClient::with_store(
+        Reqwest::default(t.server.base_url().as_str()),
+        MemoryStore::new(2),
+    );
+    for id in [1, 1, 2, 2, 1] {
+        let r = Test { id }.exec(&client).await.unwrap();
+        assert_eq!(r.parse().unwrap().age, 30);
+    }
+    fresh.assert_hits(1);
+    stale.assert_hits(2);
+    assert_eq!(client.store().len(), 1);
+
+    // The least recently used response is evicted
+    for id in [3, 1, 4, 1] {
+        Test { id }.exec(&client).await.unwrap();
+    }
+    fresh.assert_hits(1);
+    others.iter().for_each(|m| m.assert_hits(1));
+    assert_eq!(client.store().len(), 2);
+    let url = format!("{}/test/3", t.server.base_url());
+    assert!(client.get(&http::Method::GET, &url).is_none());
+
+    // Requests with no-cache always reach the server
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/1", headers("Cache-Control: no-cache"))]
+    struct NoCache {}
+
+    NoCache {}.exec(&client).await.unwrap();
+    fresh.assert_hits(2);
+}
+
+#[test(tokio::test)]
+async fn test_caching_client_vary() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "TestResponse")]
+    struct Test {
+        #[endpoint(header)]
+        x_user: String,
+    }
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "other", response = "TestResponse")]
+    struct Other {}
+
+    let t = TestServer::default();
+    let users: Vec<_> = vec![("a", 30), ("b", 40)]
+        .into_iter()
+        .map(|(user, age)| {
+            t.server.mock(|when, then| {
+                when.method(GET).path("/test/path").header("X-User", user);
+                then.status(200)
+                    .header("Cache-Control", "max-age=60")
+                    .header("Vary", "Accept-Encoding, X-User")
+                    .json_body(json!({ "age": age }));
+            })
+        })
+        .collect();
+    let any = t.server.mock(|when, then| {
+        when.method(GET).path("/other");
+        then.status(200)
+            .header("Cache-Control", "max-age=60")
+            .header("Vary", "*")
+            .json_body(json!({"age": 30}));
+    });
+
+    let client = CachingClient::new(Reqwest::default(t.server.base_url().as_str()));
+
+    // Responses are only served to requests with the same varying headers
+    for (user, age) in [("a", 30), ("a", 30), ("b", 40), ("b", 40)] {
+        let r = Test {
+            x_user: user.to_string(),
+        }
+        .exec(&client)
+        .await
+        .unwrap();
+        assert_eq!(r.parse().unwrap().age, age);
+    }
+    users[0].assert_hits(1);
+    users[1].assert_hits(1);
+
+    let url = format!("{}/test/path", t.server.base_url());
+    let cached = client.get(&http::Method::GET, &url).unwrap();
+    assert_eq!(cached.vary["x-user"], "b");
+
+    // Responses varying on every header are never cached
+    Other {}.exec(&client).await.unwrap();
+    Other {}.exec(&client).await.unwrap();
+    any.assert_hits(2);
+    let url = format!("{}/other", t.server.base_url());
+    assert!(client.get(&http::Method::GET, &url).is_none());
+}
+
+#[test(tokio::test)]
+async fn test_caching_client_disk_store() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "TestResponse")]
+    struct Test {}
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(200)
+            .header("Cache-Control", "max-age=60")
+            .header("Vary", "Accept")
+            .json_body(json!({"age": 30}));
+    });
+
+    let dir = std::env::temp_dir().join(format!("rustify-cache-{}", std::process::id()));
+    let url = format!("{}/test/path", t.server.base_url());
+    let new_client = || {
+        CachingClient::with_store(
+            Reqwest::default(t.server.base_url().as_str()),
+            DiskStore::new(&dir),
+        )
+    };
+
+    let r = Test {}.exec(&new_client()).await.unwrap();
+    assert_eq!(r.parse().unwrap().age, 30);
+
+    // A new client serves the response persisted by the previous one
+    let client = new_client();
+    let r = Test {}.exec(&client).await.unwrap();
+    assert_eq!(r.parse().unwrap().age, 30);
+    m.assert_hits(1);
+
+    let cached = client.get(&http::Method::GET, &url).unwrap();
+    assert_eq!(cached.status, 200);
+    assert_eq!(cached.headers["cache-control"], "max-age=60");
+    assert_eq!(cached.vary["accept"], "application/json");
+    assert!(cached.is_fresh());
+
+    client.invalidate(&url);
+    assert!(client.get(&http::Method::GET, &url).is_none());
+    Test {}.exec(&client).await.unwrap();
+    m.assert_hits(2);
+
+    client.clear();
+    assert!(client.get(&http::Method::GET, &url).is_none());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test(tokio::test)]
+async fn test_csrf_client() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", method = "POST")]
+    struct Create {}
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path")]
+    struct Get {}
+
+    let t = TestServer::default();
+    let bootstrap = t.server.mock(|when, then| {
+        when.method(GET).path("/csrf");
+        then.status(200)
+            .json_body(json!({"csrf": {"token": "one"}}));
+    });
+    let accepted = t.server.mock(|when, then| {
+        when.method(POST)
+            .path("/test/path")
+            .header("X-CSRF-Token", "one");
+        then.status(200);
+    });
+    let get = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(200);
+    });
+
+    let mut client = CsrfClient::new(
+        Reqwest::default(t.server.base_url().as_str()),
+        CsrfSource::Json("/csrf/token".to_string()),
+    );
+    client.bootstrap = Some("csrf".to_string());
+
+    // Safe requests don't need a token
+    Get {}.exec(&client).await.unwrap();
+    get.assert_hits(1);
+    bootstrap.assert_hits(0);
+
+    // The token is fetched once and reused
+    Create {}.exec(&client).await.unwrap();
+    Create {}.exec(&client).await.unwrap();
+    bootstrap.assert_hits(1);
+    accepted.assert_hits(2);
+    assert_eq!(client.token(), Some("one".to_string()));
+}
+
+#[test(tokio::test)]
+async fn test_csrf_client_expired() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", method = "POST")]
+    struct Create {}
+
+    let t = TestServer::default();
+    let bootstrap = t.server.mock(|when, then| {
+        when.method(GET).path("/csrf");
+        then.status(200)
+            .header("Set-Cookie", "csrftoken=one; Path=/");
+    });
+    let accepted = t.server.mock(|when, then| {
+        when.method(POST)
+            .path("/test/path")
+            .header("X-CSRFToken", "two");
+        then.status(200);
+    });
+    let rejected = t.server.mock(|when, then| {
+        when.method(POST).path("/test/path");
+        then.status(403)
+            .header("Set-Cookie", "csrftoken=two; Path=/");
+    });
+
+    let mut client = CsrfClient::new(
+        Reqwest::default(t.server.base_url().as_str()),
+        CsrfSource::Cookie("csrftoken".to_string()),
+    );
+    client.header = http::header::HeaderName::from_static("x-csrftoken");
+    client.bootstrap = Some("csrf".to_string());
+
+    // The rejected request is resent with the refreshed token
+    Create {}.exec(&client).await.unwrap();
+    bootstrap.assert_hits(1);
+    rejected.assert_hits(1);
+    accepted.assert_hits(1);
+    assert_eq!(client.token(), Some("two".to_string()));
+}
+
+#[test(tokio::test)]
+async fn test_csrf_client_defaults() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", method = "POST")]
+    struct Create {}
+
+    let t = TestServer::default();
+    let bootstrap = t.server.mock(|when, then| {
+        when.method(GET)
+            .path("/csrf")
+            .query_param("tenant", "acme")
+            .header("X-Api-Version", "2")
+            // user:p@ss
+            .header("Authorization", "Basic dXNlcjpwQHNz");
+        then.status(200).json_body(json!({"token": "one"}));
+    });
+    let accepted = t.server.mock(|when, then| {
+        when.method(POST)
+            .path("/test/path")
+            .header("X-CSRF-Token", "one");
+        then.status(200);
+    });
+
+    let config = ClientConfig::default()
+        .default_header(
+            http::header::HeaderName::from_static("x-api-version"),
+            http::HeaderValue::from_static("2"),
+        )
+        .default_query_param("tenant", "acme");
+    let base = format!("http://user:p%40ss@{}", t.server.address());
+    let mut client = CsrfClient::new(
+        Reqwest::with_config(&base, &config).unwrap(),
+        CsrfSource::Json("/token".to_string()),
+    );
+    client.bootstrap = Some("csrf".to_string());
+
+    Create {}.exec(&client).await.unwrap();
+    bootstrap.assert();
+    accepted.assert();
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn test_csrf_client_blocking() {
+    use rustify::blocking::clients::reqwest::Client as ReqwestBlocking;
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", method = "POST")]
+    struct Create {}
+
+    let server = MockServer::start();
+    let bootstrap = server.mock(|when, then| {
+        when.method(GET)
+            .path("/csrf")
+            // user:p@ss
+            .header("Authorization", "Basic dXNlcjpwQHNz");
+        then.status(200).json_body(json!({"token": "one"}));
+    });
+    let accepted = server.mock(|when, then| {
+        when.method(POST)
+            .path("/test/path")
+            .header("X-CSRF-Token", "one");
+        then.status(200);
+    });
+
+    let base = format!("http://user:p%40ss@{}", server.address());
+    let mut client = CsrfClient::new(
+        ReqwestBlocking::default(&base),
+        CsrfSource::Json("/token".to_string()),
+    );
+    client.bootstrap = Some("csrf".to_string());
+
+    Create {}.exec_block(&client).unwrap();
+    bootstrap.assert();
+    accepted.assert();
+}
+
+#[test(tokio::test)]
+async fn test_redirect_policy() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "start", method = "POST", response = "TestResponse")]
+    struct Test {}
+
+    let t = TestServer::default();
+    let other = MockServer::start();
+    let start = t.server.mock(|when, then| {
+        when.method(POST).path("/start");
+        then.status(303).header("Location", "/middle");
+    });
+    let middle = t.server.mock(|when, then| {
+        when.method(GET).path("/middle");
+        then.status(307)
+            .header("Location", format!("{}/end", other.base_url()));
+    });
+    let end = other.mock(|when, then| {
+        when.method(GET).path("/end");
+        then.status(200).json_body(json!({"age": 30}));
+    });
+
+    // The default client leaves the redirects to reqwest
+    let r = Test {}.exec(&t.client).await.unwrap();
+    assert_eq!(r.parse().unwrap().age, 30);
+    assert_eq!(
+        r.url().unwrap().to_string(),
+        format!("{}/end", other.base_url())
+    );
+    assert!(r.redirects().is_empty());
+    end.assert_hits(1);
+
+    // The redirects are followed by the client and recorded
+    let client = Reqwest::with_redirect(&t.server.base_url(), RedirectPolicy::default());
+    let r = Test {}.exec(&client).await.unwrap();
+    assert_eq!(r.parse().unwrap().age, 30);
+    assert_eq!(
+        r.url().unwrap().to_string(),
+        format!("{}/end", other.base_url())
+    );
+    let chain: Vec<String> = r.redirects().iter().map(|u| u.to_string()).collect();
+    assert_eq!(
+        chain,
+        vec![
+            format!("{}/start", t.server.base_url()),
+            format!("{}/middle", t.server.base_url()),
+        ]
+    );
+    end.assert_hits(2);
+
+    // Redirects to another origin aren't followed
+    let client = Reqwest::with_redirect(&t.server.base_url(), RedirectPolicy::SameOrigin(5));
+    let r = Test {}.exec(&client).await;
+    assert!(matches!(
+        r,
+        Err(ClientError::ServerResponseError { code: 307, .. })
+    ));
+    end.assert_hits(2);
+
+    // Redirects aren't followed beyond the limit
+    let client = Reqwest::with_redirect(&t.server.base_url(), RedirectPolicy::Limited(1));
+    let r = Test {}.exec(&client).await;
+    assert!(matches!(
+        r,
+        Err(ClientError::ServerResponseError { code: 307, .. })
+    ));
+
+    let client = Reqwest::with_redirect(&t.server.base_url(), RedirectPolicy::None);
+    let r = Test {}.exec(&client).await;
+    assert!(matches!(
+        r,
+        Err(ClientError::ServerResponseError { code: 303, .. })
+    ));
+    start.assert_hits(5);
+    middle.assert_hits(4);
+    end.assert_hits(2);
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn test_redirect_policy_blocking() {
+    use common::TestServerBlocking;
+    use rustify::blocking::clients::reqwest::Client as ReqwestBlocking;
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "start", response = "TestResponse")]
+    struct Test {}
+
+    let t = TestServerBlocking::default();
+    t.server.mock(|when, then| {
+        when.method(GET).path("/start");
+        then.status(301).header("Location", "/end");
+    });
+    t.server.mock(|when, then| {
+        when.method(GET).path("/end");
+        then.status(200).json_body(json!({"age": 30}));
+    });
+
+    let r = Test {}.exec_block(&t.client).unwrap();
+    assert_eq!(r.parse().unwrap().age, 30);
+    assert_eq!(
+        r.url().unwrap().to_string(),
+        format!("{}/end", t.server.base_url())
+    );
+    assert!(r.redirects().is_empty());
+
+    let client = ReqwestBlocking::with_redirect(&t.server.base_url(), RedirectPolicy::default());
+    let r = Test {}.exec_block(&client).unwrap();
+    assert_eq!(r.parse().unwrap().age, 30);
+    assert_eq!(
+        r.url().unwrap().to_string(),
+        format!("{}/end", t.server.base_url())
+    );
+    assert_eq!(r.redirects().len(), 1);
+}
+
+#[test(tokio::test)]
+async fn test_client_config() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "TestResponse")]
+    struct Test {}
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(200).json_body(json!({"age": 30}));
+    });
+
+    let cert = include_bytes!("fixtures/client.pem").to_vec();
+    let key = include_bytes!("fixtures/client-key.pem").to_vec();
+    let config = ClientConfig::default()
+        .root_certificate(Certificate::Pem(cert.clone()))
+        .identity(Identity::Pem { cert, key });
+    let client = Reqwest::with_config(&t.server.base_url(), &config).unwrap();
+    let r = Test {}.exec(&client).await.unwrap();
+    assert_eq!(r.parse().unwrap().age, 30);
+    m.assert_hits(1);
+
+    let config = ClientConfig::default().identity(Identity::Pkcs12 {
+        der: include_bytes!("fixtures/client.p12").to_vec(),
+        password: "rustify".to_string(),
+    });
+    assert!(Reqwest::with_config(&t.server.base_url(), &config).is_ok());
+
+    let config = ClientConfig::default().root_certificate(Certificate::Der(b"invalid".to_vec()));
+    let r = Reqwest::with_config(&t.server.base_url(), &config);
+    assert!(matches!(r, Err(ClientError::CertificateParseError { .. })));
+}
+
+#[test]
+fn test_client_with_mtls() {
+    let fixture = |name: &str| format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name);
+
+    let r = Reqwest::with_mtls(
+        "https://localhost",
+        fixture("client.pem"),
+        fixture("client.pem"),
+        fixture("client-key.pem"),
+    );
+    assert!(r.is_ok());
+
+    let r = Reqwest::with_mtls(
+        "https://localhost",
+        fixture("client.pem"),
+        fixture("missing.pem"),
+        fixture("client-key.pem"),
+    );
+    match r {
+        Err(ClientError::CertificateReadError { path, .. }) => {
+            assert_eq!(path, fixture("missing.pem"))
+        }
+        _ => panic!("expected a CertificateReadError"),
+    }
+
+    let r = Reqwest::with_mtls(
+        "https://localhost",
+        fixture("client.pem"),
+        fixture("client.pem"),
+        fixture("rsa.pem"),
+    );
+    match r {
+        Err(ClientError::CertificateParseError { name, .. }) => {
+            assert_eq!(name, "client identity")
+        }
+        _ => panic!("expected a CertificateParseError"),
+    }
+}
+
+#[test(tokio::test)]
+async fn test_client_min_tls_version() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "TestResponse")]
+    struct Test {}
+
+    let t = TestServer::default();
+    t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(200).json_body(json!({"age": 30}));
+    });
+
+    let config = ClientConfig::default().min_tls_version(TlsVersion::Tls1_2);
+    let client = Reqwest::with_config(&t.server.base_url(), &config).unwrap();
+    assert!(Test {}.exec(&client).await.is_ok());
+
+    // The native-tls backend can't require TLS 1.3
+    if cfg!(not(feature = "rustls-tls")) {
+        let config = ClientConfig::default().min_tls_version(TlsVersion::Tls1_3);
+        let r = Reqwest::with_config(&t.server.base_url(), &config);
+        assert!(matches!(r, Err(ClientError::ClientBuildError { .. })));
+    }
+}
+
+#[test(tokio::test)]
+async fn test_client_resolve() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "TestResponse")]
+    struct Test {}
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET)
+            .path("/test/path")
+            .header("Host", format!("rustify.test:{}", t.server.port()));
+        then.status(200).json_body(json!({"age": 30}));
+    });
+
+    let config = ClientConfig::default().resolve("rustify.test", [127, 0, 0, 1].into());
+    let base = format!("http://rustify.test:{}", t.server.port());
+    let client = Reqwest::with_config(&base, &config).unwrap();
+    let r = Test {}.exec(&client).await.unwrap();
+    assert_eq!(r.parse().unwrap().age, 30);
+    m.assert_hits(1);
+}
+
+#[test(tokio::test)]
+async fn test_client_pool_config() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "TestResponse")]
+    struct Test {}
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(200).json_body(json!({"age": 30}));
+    });
+
+    let config = ClientConfig::default()
+        .pool_idle_timeout(Duration::from_secs(30))
+        .pool_max_idle_per_host(0)
+        .tcp_keepalive(Duration::from_secs(60));
+    let client = Reqwest::with_config(&t.server.base_url(), &config).unwrap();
+    for _ in 0..2 {
+        let r = Test {}.exec(&client).await.unwrap();
+        assert_eq!(r.parse().unwrap().age, 30);
+    }
+    m.assert_hits(2);
+}
+
+#[test(tokio::test)]
+async fn test_client_http_version() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "TestResponse")]
+    struct Test {}
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(200).json_body(json!({"age": 30}));
+    });
+
+    let config = ClientConfig::default().http_version(HttpVersion::Http1Only);
+    let client = Reqwest::with_config(&t.server.base_url(), &config).unwrap();
+    let r = Test {}.exec(&client).await.unwrap();
+    m.assert();
+    assert_eq!(r.parse().unwrap().age, 30);
+    assert_eq!(r.response.version(), http::Version::HTTP_11);
+
+    let config = ClientConfig::default().http_version(HttpVersion::Http2PriorKnowledge);
+    let res = Reqwest::with_config(&t.server.base_url(), &config);
+    if cfg!(feature = "http2") {
+        assert!(res.is_ok());
+    } else {
+        assert!(matches!(res, Err(ClientError::ClientBuildError { .. })));
+    }
+
+    let config = ClientConfig::default().http_version(HttpVersion::Http3);
+    let res = Reqwest::with_config(&t.server.base_url(), &config);
+    assert!(matches!(res, Err(ClientError::ClientBuildError { .. })));
+}
+
+#[test(tokio::test)]
+async fn test_client_config_headers() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "TestResponse")]
+    struct Test {
+        #[endpoint(header)]
+        x_api_version: String,
+    }
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET)
+            .path("/test/path")
+            .header("User-Agent", "my-sdk/1.0")
+            .header("X-API-Token", "mytoken")
+            .header("X-API-Version", "2");
+        then.status(200).json_body(json!({"age": 30}));
+    });
+
+    let config = ClientConfig::default()
+        .user_agent("my-sdk/1.0")
+        .default_header(
+            http::HeaderName::from_static("x-api-token"),
+            http::HeaderValue::from_static("mytoken"),
+        )
+        .default_header(
+            http::HeaderName::from_static("x-api-version"),
+            http::HeaderValue::from_static("1"),
+        )
+        .timeout(Duration::from_secs(5))
+        .connect_timeout(Duration::from_secs(1))
+        .redirect(RedirectPolicy::None);
+    let client = Reqwest::with_config(&t.server.base_url(), &config).unwrap();
+    assert_eq!(client.redirect, Some(RedirectPolicy::None));
+    let r = Test {
+        x_api_version: "2".to_string(),
+    }
+    .exec(&client)
+    .await
+    .unwrap();
+    m.assert();
+    assert_eq!(r.parse().unwrap().age, 30);
+
+    let config = ClientConfig::default().user_agent("invalid\n");
+    let r = Reqwest::with_config(&t.server.base_url(), &config);
+    assert!(matches!(r, Err(ClientError::HeaderParseError { .. })));
+}
+
+#[test(tokio::test)]
+async fn test_client_config_timeout() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path")]
+    struct Test {}
+
+    let t = TestServer::default();
+    t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(200).delay(Duration::from_millis(500));
+    });
+
+    let config = ClientConfig::default().timeout(Duration::from_millis(50));
+    let client = Reqwest::with_config(&t.server.base_url(), &config).unwrap();
+    let r = Test {}.exec(&client).await;
+    assert!(matches!(r, Err(ClientError::RequestError { .. })));
+}
+
+#[cfg(feature = "hyper")]
+#[test(tokio::test)]
+async fn test_hyper_config() {
+    use rustify::clients::hyper::Client as Hyper;
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "TestResponse")]
+    struct Test {}
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET)
+            .path("/test/path")
+            .header("User-Agent", "my-sdk/1.0");
+        then.status(200).json_body(json!({"age": 30}));
+    });
+
+    let config = ClientConfig::default().user_agent("my-sdk/1.0");
+    let client = Hyper::with_config(&t.server.base_url(), &config).unwrap();
+    let r = Test {}.exec(&client).await.unwrap();
+    m.assert();
+    assert_eq!(r.parse().unwrap().age, 30);
+
+    let config = ClientConfig::default().proxy("http://proxy.internal:3128");
+    let r = Hyper::with_config(&t.server.base_url(), &config);
+    assert!(matches!(r, Err(ClientError::ClientBuildError { .. })));
+}
+
+#[cfg(feature = "attohttpc")]
+#[test]
+fn test_attohttpc_config() {
+    use rustify::blocking::clients::attohttpc::Client as Attohttpc;
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "TestResponse")]
+    struct Test {}
+
+    let server = MockServer::start();
+    let m = server.mock(|when, then| {
+        when.method(GET)
+            .path("/test/path")
+            .header("User-Agent", "my-sdk/1.0");
+        then.status(200).json_body(json!({"age": 30}));
+    });
+
+    let config = ClientConfig::default().user_agent("my-sdk/1.0");
+    let client = Attohttpc::with_config(&server.base_url(), &config).unwrap();
+    let r = Test {}.exec_block(&client).unwrap();
+    m.assert();
+    assert_eq!(r.parse().unwrap().age, 30);
+
+    let config = ClientConfig::default().http_version(HttpVersion::Http2PriorKnowledge);
+    let r = Attohttpc::with_config(&server.base_url(), &config);
+    assert!(matches!(r, Err(ClientError::ClientBuildError { .. })));
+}
+
+#[cfg(feature = "isahc")]
+#[test(tokio::test)]
+async fn test_isahc_config() {
+    use rustify::clients::isahc::Client as Isahc;
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "TestResponse")]
+    struct Test {}
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET)
+            .path("/test/path")
+            .header("User-Agent", "my-sdk/1.0");
+        then.status(200).json_body(json!({"age": 30}));
+    });
+
+    let config = ClientConfig::default().user_agent("my-sdk/1.0");
+    let client = Isahc::with_config(&t.server.base_url(), &config).unwrap();
+    let r = Test {}.exec(&client).await.unwrap();
+    m.assert();
+    assert_eq!(r.parse().unwrap().age, 30);
+
+    let config = ClientConfig::default().resolve("myapi.com", [127, 0, 0, 1].into());
+    let r = Isahc::with_config(&t.server.base_url(), &config);
+    assert!(matches!(r, Err(ClientError::ClientBuildError { .. })));
+}
+
+#[test]
+fn test_client_try_new() {
+    assert!(Reqwest::try_default("https://myapi.com/v1").is_ok());
+    assert!(Reqwest::try_new("http://localhost:8200", reqwest::Client::new()).is_ok());
+
+    let r = Reqwest::try_default("myapi.com");
+    assert!(matches!(r, Err(ClientError::UrlParseError { .. })));
+    let r = Reqwest::try_default("ftp://myapi.com");
+    assert!(matches!(r, Err(ClientError::BaseUrlError { .. })));
+    let r = Reqwest::try_new("mailto:admin@myapi.com", reqwest::Client::new());
+    assert!(matches!(r, Err(ClientError::BaseUrlError { .. })));
+    let r = Reqwest::with_config("not a url", &ClientConfig::default());
+    assert!(matches!(r, Err(ClientError::UrlParseError { .. })));
+}
+
+#[test(tokio::test)]
+async fn test_client_with_base() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "TestResponse")]
+    struct Test {}
+
+    let follower = TestServer::default();
+    let leader = TestServer::default();
+    let m = leader.server.mock(|when, then| {
+        when.method(GET)
+            .path("/test/path")
+            .header("Authorization", "Bearer mytoken");
+        then.status(200).json_body(json!({"age": 30}));
+    });
+
+    let mut client = Reqwest::default(&follower.server.base_url());
+    client.set_auth(BearerAuth::new("mytoken"));
+    let leader_client = client.with_base(&leader.server.base_url());
+    assert_eq!(client.base, follower.server.base_url());
+
+    let r = Test {}.exec(&leader_client).await.unwrap();
+    m.assert();
+    assert_eq!(r.parse().unwrap().age, 30);
+
+    client.shutdown(Duration::from_millis(10)).await.unwrap();
+    let r = Test {}.exec(&leader_client).await;
+    assert!(matches!(r, Err(ClientError::ShutdownError)));
+}
+
+#[test(tokio::test)]
+async fn test_client_default_headers() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "TestResponse")]
+    struct Test {
+        #[endpoint(header)]
+        x_api_version: String,
+    }
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET)
+            .path("/test/path")
+            .header("X-API-Token", "mytoken")
+            .header("X-API-Version", "2")
+            .header("X-Middleware", "true");
+        then.status(200).json_body(json!({"age": 30}));
+    });
+
+    struct Check;
+    impl rustify::endpoint::MiddleWare for Check {
+        fn request<E: Endpoint>(
+            &self,
+            _: &E,
+            req: &mut http::Request<Bytes>,
+        ) -> Result<(), ClientError> {
+            assert_eq!(req.headers()["X-API-Token"], "mytoken");
+            req.headers_mut()
+                .insert("X-Middleware", http::HeaderValue::from_static("true"));
+            Ok(())
+        }
+
+        fn response<E: Endpoint>(
+            &self,
+            _: &E,
+            _: &mut http::Response<Bytes>,
+        ) -> Result<(), ClientError> {
+            Ok(())
+        }
+    }
+
+    let mut headers = http::HeaderMap::new();
+    headers.insert("X-API-Token", "mytoken".parse().unwrap());
+    headers.insert("X-API-Version", "1".parse().unwrap());
+    let mut client = Reqwest::default(&t.server.base_url());
+    client.set_default_headers(headers);
+    let client = client.with_middleware(Check);
+
+    let r = Test {
+        x_api_version: "2".to_string(),
+    }
+    .exec(&client)
+    .await
+    .unwrap();
+    m.assert();
+    assert_eq!(r.parse().unwrap().age, 30);
+}
+
+#[test(tokio::test)]
+async fn test_client_default_query() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "TestResponse")]
+    struct Test {
+        #[endpoint(query)]
+        page: u64,
+        #[endpoint(query)]
+        version: String,
+    }
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET)
+            .path("/test/path")
+            .query_param("api_key", "mykey")
+            .query_param("page", "2")
+            .query_param("version", "2");
+        then.status(200).json_body(json!({"age": 30}));
+    });
+
+    let config = ClientConfig::default()
+        .default_query_param("api_key", "mykey")
+        .default_query_param("version", "1");
+    let client = Reqwest::with_config(&t.server.base_url(), &config).unwrap();
+    let r = Test {
+        page: 2,
+        version: "2".to_string(),
+    }
+    .exec(&client)
+    .await
+    .unwrap();
+    m.assert();
+    assert_eq!(r.parse().unwrap().age, 30);
+    assert!(!r.url().unwrap().to_string().contains("version=1"));
+}
+
+#[test(tokio::test)]
+async fn test_client_config_from_env() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "TestResponse")]
+    struct Test {}
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET)
+            .path("/test/path")
+            .header("Authorization", "Bearer mytoken");
+        then.status(200).json_body(json!({"age": 30}));
+    });
+
+    let ca = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/client.pem");
+    std::env::set_var("RUSTIFY_ENV_BASE_URL", t.server.base_url());
+    std::env::set_var("RUSTIFY_ENV_TIMEOUT", "2.5");
+    std::env::set_var("RUSTIFY_ENV_CA_CERT", ca);
+    std::env::set_var("RUSTIFY_ENV_TOKEN", "mytoken");
+    std::env::set_var("RUSTIFY_ENV_PROXY", "");
+
+    let config = ClientConfig::from_env("RUSTIFY_ENV").unwrap();
+    assert_eq!(config.timeout, Some(Duration::from_millis(2500)));
+    assert_eq!(config.root_certificates.len(), 1);
+    assert_eq!(config.proxy, None);
+    assert!(!format!("{:?}", config).contains("mytoken"));
+
+    let base = config.base_url.clone().unwrap();
+    let client = Reqwest::with_config(&base, &config).unwrap();
+    let r = Test {}.exec(&client).await.unwrap();
+    m.assert();
+    assert_eq!(r.parse().unwrap().age, 30);
+
+    std::env::set_var("RUSTIFY_ENV_INVALID_TIMEOUT", "soon");
+    let r = ClientConfig::from_env("RUSTIFY_ENV_INVALID");
+    assert!(
+        matches!(r, Err(ClientError::EnvParseError { name, .. }) if name == "RUSTIFY_ENV_INVALID_TIMEOUT")
+    );
+}
+
+#[test(tokio::test)]
+async fn test_client_user_agent() {
+    use rustify::config::DEFAULT_USER_AGENT;
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path")]
+    struct Test {}
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET)
+            .path("/test/path")
+            .header("User-Agent", DEFAULT_USER_AGENT);
+        then.status(200);
+    });
+    let client = Reqwest::default(&t.server.base_url());
+    Test {}.exec(&client).await.unwrap();
+    m.assert();
+
+    let ua = format!("{} (my-sdk/1.0)", DEFAULT_USER_AGENT);
+    let m = t.server.mock(|when, then| {
+        when.method(GET)
+            .path("/test/path")
+            .header("User-Agent", ua.as_str());
+        then.status(200);
+    });
+    let config = ClientConfig::default().user_agent_product("my-sdk", "1.0");
+    let client = Reqwest::with_config(&t.server.base_url(), &config).unwrap();
+    Test {}.exec(&client).await.unwrap();
+    m.assert();
+    assert!(DEFAULT_USER_AGENT.starts_with("rustify/"));
+}
+
+#[test(tokio::test)]
+async fn test_client_base_credentials() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "TestResponse")]
+    struct Test {}
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET)
+            .path("/test/path")
+            // user:p@ss
+            .header("Authorization", "Basic dXNlcjpwQHNz");
+        then.status(200).json_body(json!({"age": 30}));
+    });
+
+    let base = format!("http://user:p%40ss@{}", t.server.address());
+    let client = Reqwest::default(&base);
+    let r = Test {}.exec(&client).await.unwrap();
+    m.assert();
+    assert_eq!(r.parse().unwrap().age, 30);
+
+    let url = Test {}.url(&base).unwrap();
+    assert_eq!(
+        url.authority().unwrap().as_str(),
+        t.server.address().to_string()
+    );
+}
+
+#[test(tokio::test)]
+async fn test_client_url_join() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "TestResponse")]
+    struct Test {}
+
+    // Copies the path seen by endpoint middleware into a header
+    struct PathHeader;
+
+    impl MiddleWare for PathHeader {
+        fn request<E: Endpoint>(
+            &self,
+            _: &E,
+            req: &mut http::Request<Bytes>,
+        ) -> Result<(), ClientError> {
+            let path = http::HeaderValue::from_str(req.uri().path()).unwrap();
+            req.headers_mut().insert("X-Path", path);
+            Ok(())
+        }
+
+        fn response<E: Endpoint>(
+            &self,
+            _: &E,
+            _: &mut http::Response<Bytes>,
+        ) -> Result<(), ClientError> {
+            Ok(())
+        }
+    }
+
+    let t = TestServer::default();
+    let lenient = t.server.mock(|when, then| {
+        when.method(GET).path("/v1//test/path");
+        then.status(200).json_body(json!({"age": 30}));
+    });
+    let appended = t.server.mock(|when, then| {
+        when.method(GET).path("/v1/test/path");
+        then.status(200).json_body(json!({"age": 30}));
+    });
+    let strict = t.server.mock(|when, then| {
+        when.method(GET)
+            .path("/test/path")
+            .header("X-Path", "/test/path");
+        then.status(200).json_body(json!({"age": 30}));
+    });
+
+    // The lenient default keeps the slashes where the paths meet
+    let base = format!("{}/v1", t.server.base_url());
+    let mut client = Reqwest::default(&format!("{}/", base));
+    Test {}.exec(&client).await.unwrap();
+    lenient.assert();
+
+    client.set_url_join(UrlJoin::Normalized);
+    Test {}.exec(&client).await.unwrap();
+    appended.assert();
+
+    let config = ClientConfig::default().url_join(UrlJoin::Strict);
+    let client = Reqwest::with_config(&base, &config).unwrap();
+    assert_eq!(client.url_join(), UrlJoin::Strict);
+    Test {}
+        .with_middleware(&PathHeader)
+        .exec(&client)
+        .await
+        .unwrap();
+    strict.assert();
+
+    let client = client.with_base(&format!("{}/", base));
+    Test {}.exec(&client).await.unwrap();
+    appended.assert_hits(2);
+
+    assert!(matches!(
+        rustify::http::join_url("mailto:user@myapi.com", "test", None, UrlJoin::Lenient),
+        Err(ClientError::BaseUrlError { .. })
+    ));
+}
@@ -1,10 +1,16 @@
+#![allow(dead_code)]
+
+use async_trait::async_trait;
+use bytes::Bytes;
 use httpmock::prelude::*;
 #[cfg(feature = "blocking")]
 use rustify::blocking::clients::reqwest::Client as ReqwestBlocking;
 use rustify::{
+    client::{ByteStream, Client},
     clients::reqwest::Client as Reqwest,
     endpoint::{Endpoint, MiddleWare, Wrapper},
     errors::ClientError,
+    test::MockClient,
 };
 use serde::{de::DeserializeOwned, Deserialize};
 use serde_json::Value;
@@ -15,6 +21,7 @@ pub struct TestServer {
 }
 
 #[cfg(feature = "blocking")]
+#[allow(dead_code)]
 pub struct TestServerBlocking {
     pub server: MockServer,
     pub client: ReqwestBlocking,
@@ -78,7 +85,7 @@ impl MiddleWare for Middle {
     fn request<E: Endpoint>(
         &self,
         _: &E,
-        req: &mut http::Request<Vec<u8>>,
+        req: &mut http::Request<Bytes>,
     ) -> Result<(), ClientError> {
         req.headers_mut()
             .append("X-API-Token", http::HeaderValue::from_static("mytoken"));
@@ -87,7 +94,7 @@ impl MiddleWare for Middle {
     fn response<E: Endpoint>(
         &self,
         _: &E,
-        resp: &mut http::Response<Vec<u8>>,
+        resp: &mut http::Response<Bytes>,
     ) -> Result<(), ClientError> {
         let resp_body = resp.body().clone();
         let wrapper: TestWrapper =
@@ -96,7 +103,32 @@ impl MiddleWare for Middle {
                 content: String::from_utf8(resp_body.to_vec()).ok(),
             })?;
         let data = wrapper.result.to_string();
-        *resp.body_mut() = data.as_bytes().to_vec();
+        *resp.body_mut() = Bytes::from(data);
         Ok(())
     }
 }
+
+/// Wraps a [MockClient] which only supports streaming, so buffered sends never
+/// reach it.
+pub struct StreamOnly(pub MockClient);
+
+#[cfg_attr(not(feature = "non-send"), async_trait)]
+#[cfg_attr(feature = "non-send", async_trait(?Send))]
+impl Client for StreamOnly {
+    async fn send(&self, _: http::Request<Bytes>) -> Result<http::Response<Bytes>, ClientError> {
+        Err(ClientError::GenericError {
+            source: anyhow::anyhow!("Not streamed"),
+        })
+    }
+
+    async fn send_stream(
+        &self,
+        req: http::Request<Bytes>,
+    ) -> Result<http::Response<ByteStream>, ClientError> {
+        self.0.send_stream(req).await
+    }
+
+    fn base(&self) -> &str {
+        self.0.base()
+    }
+}
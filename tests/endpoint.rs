@@ -2,14 +2,26 @@ mod common;
 
 use std::fmt::Debug;
 
+use bytes::Bytes;
 use common::{Middle, TestGenericWrapper, TestResponse, TestServer};
 use derive_builder::Builder;
+use futures::TryStreamExt;
+use http::{HeaderName, HeaderValue};
 use httpmock::prelude::*;
-use rustify::endpoint::Endpoint;
+use rustify::{
+    batch::exec_batch,
+    deadline::Deadline,
+    endpoint::{DynEndpoint, Endpoint, EndpointResult, ErrorEndpoint, MiddleWare, Wrapper},
+    enums::{RequestMethod, RequestType, ResponseType},
+    errors::{ClientError, EndpointError},
+    http::build_header,
+    pagination::{CursorPaginator, LinkPaginator, PageLimit, PaginatedEndpoint},
+    retry::RetryPolicy,
+};
 use rustify_derive::Endpoint;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::json;
-use std::marker::PhantomData;
+use std::{marker::PhantomData, time::Duration};
 use test_log::test;
 
 #[test(tokio::test)]
@@ -77,6 +89,142 @@ async fn test_query() {
     assert!(r.is_ok());
 }
 
+#[test(tokio::test)]
+async fn test_form() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", method = "POST", request_type = "Form")]
+    struct Test {
+        name: String,
+        age: u64,
+    }
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(POST)
+            .path("/test/path")
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body("name=test&age=30");
+        then.status(200);
+    });
+    let r = Test {
+        name: "test".to_string(),
+        age: 30,
+    }
+    .exec(&t.client)
+    .await;
+
+    m.assert();
+    assert!(r.is_ok());
+}
+
+#[test(tokio::test)]
+async fn test_header() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", method = "POST")]
+    struct Test {
+        #[endpoint(header)]
+        x_api_token: String,
+        #[endpoint(header = "X-Version")]
+        version: u8,
+        #[endpoint(header)]
+        x_optional: Option<String>,
+        name: String,
+    }
+
+    let t = TestServer::default();
+    let e = Test {
+        x_api_token: "mytoken".to_string(),
+        version: 2,
+        x_optional: None,
+        name: "test".to_string(),
+    };
+    let req = e.request(t.client.base.as_str()).unwrap();
+    assert!(req.headers().get("x-optional").is_none());
+
+    let m = t.server.mock(|when, then| {
+        when.method(POST)
+            .path("/test/path")
+            .header("X-API-Token", "mytoken")
+            .header("X-Version", "2")
+            .json_body(json!({ "name": "test" }));
+        then.status(200);
+    });
+    let r = e.exec(&t.client).await;
+
+    m.assert();
+    assert!(r.is_ok());
+}
+
+#[test(tokio::test)]
+async fn test_static_headers() {
+    #[derive(Endpoint)]
+    #[endpoint(
+        path = "test/path",
+        headers("X-API-Version: 2", "Accept: application/json")
+    )]
+    struct Test {
+        #[endpoint(header)]
+        x_api_token: String,
+    }
+
+    let t = TestServer::default();
+    let e = Test {
+        x_api_token: "mytoken".to_string(),
+    };
+    let m = t.server.mock(|when, then| {
+        when.method(GET)
+            .path("/test/path")
+            .header("X-API-Version", "2")
+            .header("Accept", "application/json")
+            .header("X-API-Token", "mytoken");
+        then.status(200);
+    });
+    let r = e.exec(&t.client).await;
+
+    m.assert();
+    assert!(r.is_ok());
+}
+
+#[test(tokio::test)]
+async fn test_headers_manual() {
+    struct Test {
+        token: String,
+    }
+
+    impl Endpoint for Test {
+        type Response = ();
+        const REQUEST_BODY_TYPE: RequestType = RequestType::JSON;
+        const RESPONSE_BODY_TYPE: ResponseType = ResponseType::JSON;
+
+        fn path(&self) -> String {
+            String::from("test/path")
+        }
+
+        fn method(&self) -> RequestMethod {
+            RequestMethod::GET
+        }
+
+        fn headers(&self) -> Result<Vec<(HeaderName, HeaderValue)>, ClientError> {
+            Ok(vec![build_header("X-API-Token", &self.token)?])
+        }
+    }
+
+    let t = TestServer::default();
+    let e = Test {
+        token: "mytoken".to_string(),
+    };
+    let m = t.server.mock(|when, then| {
+        when.method(GET)
+            .path("/test/path")
+            .header("X-API-Token", "mytoken");
+        then.status(200);
+    });
+    let r = e.exec(&t.client).await;
+
+    m.assert();
+    assert!(r.is_ok());
+}
+
 #[test(tokio::test)]
 async fn test_path_with_format() {
     #[derive(Endpoint)]
@@ -125,6 +273,66 @@ async fn test_data() {
     assert!(r.is_ok());
 }
 
+#[test(tokio::test)]
+async fn test_rename_all() {
+    #[derive(Endpoint, Serialize)]
+    #[endpoint(path = "test/path", method = "POST", rename_all = "camelCase")]
+    struct Test {
+        #[endpoint(query)]
+        page_size: u64,
+        first_name: String,
+        #[serde(rename = "surname")]
+        last_name: String,
+    }
+
+    let t = TestServer::default();
+    let e = Test {
+        page_size: 10,
+        first_name: "John".to_string(),
+        last_name: "Doe".to_string(),
+    };
+    let m = t.server.mock(|when, then| {
+        when.method(POST)
+            .path("/test/path")
+            .query_param("pageSize", "10")
+            .json_body(json!({ "firstName": "John", "surname": "Doe" }));
+        then.status(200);
+    });
+    let r = e.exec(&t.client).await;
+
+    m.assert();
+    assert!(r.is_ok());
+}
+
+#[test(tokio::test)]
+async fn test_rename_all_serde() {
+    #[derive(Endpoint, Serialize)]
+    #[endpoint(path = "test/path", method = "POST", rename_all = "camelCase")]
+    #[serde(rename_all = "SCREAMING_SNAKE_CASE", deny_unknown_fields)]
+    struct Test {
+        #[endpoint(query)]
+        page_size: u64,
+        first_name: String,
+    }
+
+    let t = TestServer::default();
+    let e = Test {
+        page_size: 10,
+        first_name: "John".to_string(),
+    };
+    let m = t.server.mock(|when, then| {
+        when.method(POST)
+            .path("/test/path")
+            .query_param("pageSize", "10")
+            .json_body(json!({ "firstName": "John" }));
+        then.status(200);
+    });
+    let r = e.exec(&t.client).await;
+
+    m.assert();
+    assert!(r.is_ok());
+}
+
 #[test(tokio::test)]
 async fn test_raw_data() {
     #[derive(Endpoint)]
@@ -223,6 +431,58 @@ async fn test_mutate() {
     assert_eq!(r.unwrap().parse().unwrap().age, 30);
 }
 
+#[test(tokio::test)]
+async fn test_mutate_chain() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "TestResponse")]
+    struct Test {}
+
+    struct Outer {}
+
+    impl MiddleWare for Outer {
+        fn request<E: Endpoint>(
+            &self,
+            _: &E,
+            req: &mut http::Request<Bytes>,
+        ) -> Result<(), ClientError> {
+            // Inner middleware should have already been applied
+            assert!(req.headers().contains_key("X-API-Token"));
+            req.headers_mut()
+                .append("X-API-Version", HeaderValue::from_static("2"));
+            Ok(())
+        }
+        fn response<E: Endpoint>(
+            &self,
+            _: &E,
+            resp: &mut http::Response<Bytes>,
+        ) -> Result<(), ClientError> {
+            // Outer middleware should see the response first
+            let data = format!(r#"{{"result": {}}}"#, String::from_utf8_lossy(resp.body()));
+            *resp.body_mut() = Bytes::from(data);
+            Ok(())
+        }
+    }
+
+    let t = TestServer::default();
+    let e = Test {};
+    let m = t.server.mock(|when, then| {
+        when.method(GET)
+            .path("/test/path")
+            .header("X-API-Token", "mytoken")
+            .header("X-API-Version", "2");
+        then.status(200).json_body(json!({"age": 30}));
+    });
+    let r = e
+        .with_middleware(&Middle {})
+        .with_middleware(&Outer {})
+        .exec(&t.client)
+        .await;
+
+    m.assert();
+    assert!(r.is_ok());
+    assert_eq!(r.unwrap().parse().unwrap().age, 30);
+}
+
 #[test(tokio::test)]
 async fn test_wrapper() {
     #[derive(Endpoint)]
@@ -246,6 +506,156 @@ async fn test_wrapper() {
     assert_eq!(r.age, 30);
 }
 
+#[test(tokio::test)]
+async fn test_wrapper_pointer() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "Vec<TestResponse>")]
+    struct Test {}
+
+    #[derive(Debug, Deserialize)]
+    #[serde(transparent)]
+    struct Items<T>(T);
+
+    impl<T: DeserializeOwned + Send + Sync> Wrapper for Items<T> {
+        type Value = T;
+        const POINTER: Option<&'static str> = Some("/data/items");
+    }
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(200)
+            .json_body(json!({"meta": {"page": 1}, "data": {"items": [{"age": 30}]}}));
+    });
+    let r = Test {}.exec(&t.client).await.unwrap();
+    let Items(items) = r.wrap::<Items<_>>().unwrap();
+
+    m.assert();
+    assert_eq!(items[0].age, 30);
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(200).json_body(json!({"data": {}}));
+    });
+    let r = Test {}.exec(&t.client).await.unwrap();
+
+    m.assert();
+    assert!(matches!(
+        r.wrap::<Items<_>>(),
+        Err(ClientError::ResponseParseError { .. })
+    ));
+}
+
+#[test(tokio::test)]
+async fn test_parse_field() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "serde_json::Value")]
+    struct Test {}
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(200).json_body(
+            json!({"data": {"users": [{"name": "a"}, {"name": "b", "age": 30}], "a/b": true}}),
+        );
+    });
+    let r = Test {}.exec(&t.client).await.unwrap();
+
+    m.assert();
+    assert_eq!(r.parse_field::<String>("data.users.1.name").unwrap(), "b");
+    assert_eq!(
+        r.parse_field::<TestResponse>("data.users.1").unwrap().age,
+        30
+    );
+    assert!(r.parse_field::<bool>("data.a/b").unwrap());
+    assert!(r.parse_field::<String>("data.users.2.name").is_err());
+}
+
+#[test(tokio::test)]
+async fn test_parse_value() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "TestResponse")]
+    struct Test {}
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(200)
+            .json_body(json!({"age": 30, "name": "test"}));
+    });
+    let r = Test {}.exec(&t.client).await.unwrap();
+
+    m.assert();
+    assert_eq!(r.parse_value().unwrap(), json!({"age": 30, "name": "test"}));
+}
+
+#[test(tokio::test)]
+async fn test_parse_with() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response_type = "Raw", response = "Vec<u8>")]
+    struct Test {}
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(200).body("age=30;name=test");
+    });
+    let r = Test {}.exec(&t.client).await.unwrap();
+
+    m.assert();
+    let age = r.parse_with(|body| {
+        std::str::from_utf8(body)?
+            .split(';')
+            .find_map(|p| p.strip_prefix("age="))
+            .ok_or_else(|| anyhow::anyhow!("missing age"))?
+            .parse::<u8>()
+            .map_err(anyhow::Error::from)
+    });
+    assert_eq!(age.unwrap(), 30);
+
+    let missing = r.parse_with(|body| serde_json::from_slice::<TestResponse>(body));
+    assert!(matches!(
+        missing,
+        Err(ClientError::ResponseParseError { .. })
+    ));
+}
+
+#[test(tokio::test)]
+async fn test_parse_error_path() {
+    #[derive(Debug, Deserialize)]
+    struct User {
+        #[allow(dead_code)]
+        age: u8,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Users {
+        #[allow(dead_code)]
+        users: Vec<User>,
+    }
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "Users")]
+    struct Test {}
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(200)
+            .json_body(json!({"users": [{"age": 30}, {"age": "thirty"}]}));
+    });
+    let r = Test {}.exec(&t.client).await.unwrap();
+
+    m.assert();
+    match r.parse() {
+        Err(ClientError::ResponseParseError { source, .. }) => {
+            assert!(source.to_string().starts_with("users[1].age: "));
+        }
+        _ => panic!("expected a parse error"),
+    }
+}
+
 #[test(tokio::test)]
 async fn test_raw_response() {
     #[derive(Endpoint)]
@@ -349,3 +759,1496 @@ async fn test_complex() {
     assert!(r.is_ok());
     assert_eq!(r.unwrap().parse().unwrap().age, 30);
 }
+
+#[test]
+fn test_to_curl() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", method = "PUT")]
+    struct Test {
+        #[endpoint(header)]
+        x_api_token: String,
+        #[endpoint(query)]
+        page: u64,
+        name: String,
+    }
+
+    let e = Test {
+        x_api_token: "mytoken".to_string(),
+        page: 1,
+        name: "it's".to_string(),
+    };
+
+    assert_eq!(
+        e.to_curl("http://myapi.com").unwrap(),
+        "curl -X PUT 'http://myapi.com/test/path?page=1' \\\n  \
+         -H 'x-api-token: mytoken' \\\n  \
+         -H 'accept: application/json' \\\n  \
+         --data-raw '{\"name\":\"it'\\''s\"}'"
+    );
+}
+
+#[test(tokio::test)]
+async fn test_sensitive() {
+    use rustify::test::snapshot;
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "users/{self.user}/login", method = "POST")]
+    struct Test {
+        #[endpoint(skip, sensitive)]
+        user: String,
+        #[endpoint(header, sensitive)]
+        x_api_token: String,
+        #[endpoint(query, sensitive)]
+        code: Option<String>,
+        name: String,
+        #[endpoint(sensitive)]
+        password: String,
+    }
+
+    let e = Test {
+        user: "jo smith".to_string(),
+        x_api_token: "mytoken".to_string(),
+        code: Some("123456".to_string()),
+        name: "test".to_string(),
+        password: "p@ss\"word".to_string(),
+    };
+    assert_eq!(
+        e.sensitive(),
+        vec!["jo smith", "mytoken", "123456", "p@ss\"word"]
+    );
+
+    assert_eq!(
+        e.to_curl("http://myapi.com").unwrap(),
+        "curl -X POST 'http://myapi.com/users/[REDACTED]/login?code=[REDACTED]' \\\n  \
+         -H 'x-api-token: [REDACTED]' \\\n  \
+         -H 'accept: application/json' \\\n  \
+         --data-raw '{\"name\":\"test\",\"password\":\"[REDACTED]\"}'"
+    );
+
+    let req = e.request("http://myapi.com").unwrap();
+    assert_eq!(
+        req.body().as_ref(),
+        br#"{"name":"test","password":"p@ss\"word"}"#
+    );
+    assert!(!snapshot::render(&req).contains("mytoken"));
+    assert!(snapshot::render(&req).contains(r#""password": "[REDACTED]""#));
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(POST)
+            .path("/users/jo%20smith/login")
+            .query_param("code", "123456")
+            .header("X-API-Token", "mytoken")
+            .json_body(json!({"name": "test", "password": "p@ss\"word"}));
+        then.status(200);
+    });
+    let r = e.exec(&t.client).await;
+    m.assert();
+    assert!(r.is_ok());
+}
+
+#[test]
+fn test_sensitive_scalars() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "users/1/login", method = "POST")]
+    struct Test {
+        #[endpoint(query, sensitive)]
+        pin: u32,
+        #[endpoint(sensitive)]
+        admin: bool,
+        #[endpoint(sensitive)]
+        token: String,
+        active: bool,
+    }
+
+    let e = Test {
+        pin: 1,
+        admin: true,
+        token: "mytoken".to_string(),
+        active: true,
+    };
+    assert_eq!(e.sensitive(), vec!["mytoken"]);
+    assert_eq!(
+        e.to_curl("http://myapi.com").unwrap(),
+        "curl -X POST 'http://myapi.com/users/1/login?pin=1' \\\n  \
+         -H 'accept: application/json' \\\n  \
+         --data-raw '{\"admin\":true,\"token\":\"[REDACTED]\",\"active\":true}'"
+    );
+}
+
+#[test]
+fn test_name() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "users", method = "POST")]
+    struct CreateUserRequest {}
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "users", operation_id = "users.list")]
+    struct ListUsers {}
+
+    struct Manual {}
+
+    impl Endpoint for Manual {
+        type Response = ();
+        const REQUEST_BODY_TYPE: RequestType = RequestType::JSON;
+        const RESPONSE_BODY_TYPE: ResponseType = ResponseType::JSON;
+
+        fn path(&self) -> String {
+            "manual".to_string()
+        }
+
+        fn method(&self) -> RequestMethod {
+            RequestMethod::GET
+        }
+    }
+
+    fn name_of<E: Endpoint>(_: &E) -> &'static str {
+        E::name()
+    }
+
+    assert_eq!(CreateUserRequest::name(), "CreateUserRequest");
+    assert_eq!(ListUsers::name(), "users.list");
+    assert!(Manual::name().ends_with("Manual"));
+    assert_eq!(
+        name_of(&CreateUserRequest {}.with_middleware(&Middle {})),
+        "CreateUserRequest"
+    );
+}
+
+#[test]
+fn test_snapshot() {
+    use rustify::test::snapshot;
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", method = "POST", headers("X-API-Version: 2"))]
+    struct Test {
+        #[endpoint(header)]
+        x_api_token: String,
+        name: String,
+    }
+
+    let e = Test {
+        x_api_token: "mytoken".to_string(),
+        name: "test".to_string(),
+    };
+    let req = e.request("http://myapi.com").unwrap();
+    assert_eq!(
+        snapshot::render(&req),
+        "POST http://myapi.com/test/path\n\
+         accept: application/json\n\
+         x-api-token: mytoken\n\
+         x-api-version: 2\n\
+         \n\
+         {\n  \"name\": \"test\"\n}\n"
+    );
+
+    let path = std::env::temp_dir().join(format!("rustify-snapshot-{}.txt", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    snapshot::assert_endpoint(&e, "http://myapi.com", &path);
+    snapshot::assert_endpoint(&e, "http://myapi.com", &path);
+
+    let changed = Test {
+        x_api_token: "mytoken".to_string(),
+        name: "other".to_string(),
+    };
+    let err =
+        std::panic::catch_unwind(|| snapshot::assert_endpoint(&changed, "http://myapi.com", &path))
+            .unwrap_err();
+    let msg = err.downcast_ref::<String>().unwrap();
+    assert!(msg.contains(r#"-   "name": "test""#));
+    assert!(msg.contains(r#"+   "name": "other""#));
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(feature = "httpmock")]
+#[test(tokio::test)]
+async fn test_matches_endpoint() {
+    use rustify::test::httpmock::MatchesEndpoint;
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path/{self.name}", method = "POST")]
+    struct Test {
+        #[endpoint(skip)]
+        name: String,
+        #[endpoint(header)]
+        x_api_token: String,
+        #[endpoint(query)]
+        page: u64,
+        age: u64,
+    }
+
+    let t = TestServer::default();
+    let e = Test {
+        name: "test".to_string(),
+        x_api_token: "mytoken".to_string(),
+        page: 1,
+        age: 30,
+    };
+    let m = t.server.mock(|when, then| {
+        when.matches_endpoint(&e);
+        then.status(200);
+    });
+
+    assert!(e.exec(&t.client).await.is_ok());
+    let r = Test { age: 31, ..e }.exec(&t.client).await;
+
+    m.assert_hits(1);
+    assert!(r.is_err());
+}
+
+#[cfg(feature = "msgpack")]
+#[test(tokio::test)]
+async fn test_msgpack() {
+    use rustify::test::{Mock, MockClient};
+
+    #[derive(Endpoint)]
+    #[endpoint(
+        path = "test/path",
+        method = "POST",
+        request_type = "MsgPack",
+        response_type = "MsgPack",
+        response = "TestResponse"
+    )]
+    struct Test {
+        name: String,
+    }
+
+    let client = MockClient::new("http://myapi.com");
+    client.mock(
+        Mock::new(http::Method::POST, "/test/path")
+            .body(rmp_serde::to_vec_named(&json!({"name": "test"})).unwrap())
+            .respond(rmp_serde::to_vec_named(&json!({"age": 30})).unwrap()),
+    );
+    let r = Test {
+        name: "test".to_string(),
+    }
+    .exec(&client)
+    .await;
+
+    assert_eq!(r.unwrap().parse().unwrap().age, 30);
+    assert_eq!(
+        client.requests()[0].headers()["Content-Type"],
+        "application/msgpack"
+    );
+}
+
+#[cfg(feature = "cbor")]
+#[test(tokio::test)]
+async fn test_cbor() {
+    use rustify::test::{Mock, MockClient};
+
+    #[derive(Endpoint)]
+    #[endpoint(
+        path = "test/path",
+        method = "POST",
+        request_type = "CBOR",
+        response_type = "CBOR",
+        response = "TestResponse"
+    )]
+    struct Test {
+        name: String,
+    }
+
+    let cbor = |v: serde_json::Value| {
+        let mut data = Vec::new();
+        ciborium::into_writer(&v, &mut data).unwrap();
+        data
+    };
+    let client = MockClient::new("http://myapi.com");
+    client.mock(
+        Mock::new(http::Method::POST, "/test/path")
+            .body(cbor(json!({"name": "test"})))
+            .respond(cbor(json!({"age": 30}))),
+    );
+    let r = Test {
+        name: "test".to_string(),
+    }
+    .exec(&client)
+    .await;
+
+    assert_eq!(r.unwrap().parse().unwrap().age, 30);
+    assert_eq!(
+        client.requests()[0].headers()["Content-Type"],
+        "application/cbor"
+    );
+}
+
+#[cfg(feature = "yaml")]
+#[test(tokio::test)]
+async fn test_yaml() {
+    use rustify::test::{Mock, MockClient};
+
+    #[derive(Endpoint)]
+    #[endpoint(
+        path = "test/path",
+        method = "POST",
+        request_type = "YAML",
+        response_type = "YAML",
+        response = "TestResponse"
+    )]
+    struct Test {
+        name: String,
+    }
+
+    let client = MockClient::new("http://myapi.com");
+    client.mock(
+        Mock::new(http::Method::POST, "/test/path")
+            .body("name: test\n")
+            .respond("age: 30\n"),
+    );
+    let r = Test {
+        name: "test".to_string(),
+    }
+    .exec(&client)
+    .await;
+
+    assert_eq!(r.unwrap().parse().unwrap().age, 30);
+    assert_eq!(
+        client.requests()[0].headers()["Content-Type"],
+        "application/yaml"
+    );
+}
+
+#[test(tokio::test)]
+async fn test_text() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/version", response_type = "Text", response = "String")]
+    struct Version {}
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/count", response_type = "Text", response = "u64")]
+    struct Count {}
+
+    let t = TestServer::default();
+    let m1 = t.server.mock(|when, then| {
+        when.method(GET).path("/test/version");
+        then.status(200).body("1.2.3");
+    });
+    let m2 = t.server.mock(|when, then| {
+        when.method(GET).path("/test/count");
+        then.status(200).body("42\n");
+    });
+    let version = Version {}.exec(&t.client).await.unwrap();
+    let count = Count {}.exec(&t.client).await.unwrap();
+
+    m1.assert();
+    m2.assert();
+    assert_eq!(version.parse().unwrap(), "1.2.3");
+    assert_eq!(version.text().unwrap(), "1.2.3");
+    assert_eq!(count.parse().unwrap(), 42);
+}
+
+#[test(tokio::test)]
+async fn test_success_codes() {
+    #[derive(Endpoint)]
+    #[endpoint(
+        path = "test/path",
+        response = "Option<TestResponse>",
+        success = "200..=204, 404"
+    )]
+    struct Lenient {}
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "Option<TestResponse>", success = "200")]
+    struct Strict {}
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "Option<TestResponse>")]
+    struct Standard {}
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(404);
+    });
+    let r = Lenient {}.exec(&t.client).await.unwrap();
+    assert_eq!(r.response.status(), 404);
+    assert!(Lenient {}.exec_stream(&t.client).await.is_ok());
+    assert!(matches!(
+        Standard {}.exec(&t.client).await,
+        Err(ClientError::ServerResponseError { code: 404, .. })
+    ));
+    m.assert_hits(3);
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(201).json_body(json!({"age": 30}));
+    });
+    assert!(Standard {}.exec(&t.client).await.is_ok());
+    assert!(matches!(
+        Strict {}.exec(&t.client).await,
+        Err(ClientError::ServerResponseError { code: 201, .. })
+    ));
+    m.assert_hits(2);
+}
+
+#[test(tokio::test)]
+async fn test_result_accessors() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "TestResponse")]
+    struct Test {}
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(201)
+            .header("Content-Type", "application/json")
+            .header("X-Request-Id", "abc")
+            .body(r#"{"age":30}"#);
+    });
+    let r = Test {}.exec(&t.client).await.unwrap();
+
+    m.assert();
+    assert_eq!(r.status(), 201);
+    assert_eq!(r.headers()["x-request-id"], "abc");
+    assert_eq!(r.content_type(), Some("application/json"));
+    assert_eq!(r.content_length(), Some(10));
+
+    let (parts, body) = r.into_parts();
+    assert_eq!(parts.status, 201);
+    assert_eq!(body.as_ref(), br#"{"age":30}"#);
+}
+
+#[test(tokio::test)]
+async fn test_unit_response() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", method = "DELETE")]
+    struct Test {}
+
+    #[derive(Endpoint)]
+    #[endpoint(
+        path = "test/path",
+        method = "DELETE",
+        response = "Option<TestResponse>"
+    )]
+    struct Optional {}
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(DELETE).path("/test/path");
+        then.status(200)
+            .header("Content-Type", "text/html")
+            .body("<html>Deleted</html>");
+    });
+    let r = Test {}.exec(&t.client).await.unwrap();
+    assert!(r.parse().is_ok());
+    assert!(r.verify_content_type().parse().is_ok());
+    assert!(Optional {}.exec(&t.client).await.unwrap().parse().is_err());
+    m.assert_hits(2);
+}
+
+#[test(tokio::test)]
+async fn test_exec_parse() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "TestResponse")]
+    struct Test {}
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(200).json_body(json!({"age": 30}));
+    });
+    let r = Test {}.exec_parse(&t.client).await;
+
+    m.assert();
+    assert_eq!(r.unwrap().age, 30);
+}
+
+#[test(tokio::test)]
+async fn test_exec_opt() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/{self.name}", response = "TestResponse")]
+    struct Test {
+        #[endpoint(skip)]
+        name: String,
+    }
+
+    let t = TestServer::default();
+    let m1 = t.server.mock(|when, then| {
+        when.method(GET).path("/test/found");
+        then.status(200).json_body(json!({"age": 30}));
+    });
+    let m2 = t.server.mock(|when, then| {
+        when.method(GET).path("/test/missing");
+        then.status(404);
+    });
+    let m3 = t.server.mock(|when, then| {
+        when.method(GET).path("/test/broken");
+        then.status(500);
+    });
+    let found = Test {
+        name: "found".to_string(),
+    };
+    let missing = Test {
+        name: "missing".to_string(),
+    };
+    let broken = Test {
+        name: "broken".to_string(),
+    };
+
+    let r = found.exec_opt(&t.client).await.unwrap();
+    assert_eq!(r.unwrap().parse().unwrap().age, 30);
+    assert!(missing.exec_opt(&t.client).await.unwrap().is_none());
+    assert!(matches!(
+        broken.exec_opt(&t.client).await,
+        Err(ClientError::ServerResponseError { code: 500, .. })
+    ));
+    m1.assert();
+    m2.assert();
+    m3.assert();
+}
+
+#[test(tokio::test)]
+async fn test_api_error() {
+    #[derive(Debug, Deserialize)]
+    struct ApiError {
+        message: String,
+    }
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", error = "ApiError")]
+    struct Test {}
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path")]
+    struct Untyped {}
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(404).json_body(json!({"message": "not found"}));
+    });
+    let err = Test {}.exec_typed(&t.client).await.err().unwrap();
+    assert!(matches!(
+        err,
+        EndpointError::Api { code: 404, error } if error.message == "not found"
+    ));
+
+    let err = Untyped {}.exec_typed(&t.client).await.err().unwrap();
+    assert!(matches!(
+        err,
+        EndpointError::Api { code: 404, error } if error == json!({"message": "not found"})
+    ));
+
+    let err = Test {}.exec(&t.client).await.err().unwrap();
+    assert!(matches!(
+        err,
+        ClientError::ServerResponseError { code: 404, content: Some(c) } if c.contains("not found")
+    ));
+    m.assert_hits(3);
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(502).body("<html>Bad Gateway</html>");
+    });
+    let err = Test {}.exec_typed(&t.client).await.err().unwrap();
+    m.assert();
+    assert!(matches!(
+        err,
+        EndpointError::Client(ClientError::ApiError { code: 502, body })
+            if body.as_ref() == b"<html>Bad Gateway</html>"
+    ));
+}
+
+#[cfg(feature = "msgpack")]
+#[test(tokio::test)]
+async fn test_api_error_msgpack() {
+    #[derive(Debug, Deserialize)]
+    struct ApiError {
+        message: String,
+    }
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response_type = "MsgPack", error = "ApiError")]
+    struct Test {}
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(400)
+            .body(rmp_serde::to_vec_named(&json!({"message": "bad request"})).unwrap());
+    });
+    let err = Test {}.exec_typed(&t.client).await.err().unwrap();
+    m.assert();
+    assert!(matches!(
+        err,
+        EndpointError::Api { code: 400, error } if error.message == "bad request"
+    ));
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn test_api_error_blocking() {
+    use rustify::blocking::clients::reqwest::Client as ReqwestBlocking;
+
+    #[derive(Debug, Deserialize)]
+    struct ApiError {
+        message: String,
+    }
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", error = "ApiError")]
+    struct Test {}
+
+    let t = TestServer::default();
+    let client = ReqwestBlocking::default(t.server.base_url().as_str());
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(400)
+            .json_body(json!({"message": "bad request"}));
+    });
+    let err = Test {}.exec_block_typed(&client).err().unwrap();
+    m.assert();
+    assert!(matches!(
+        err,
+        EndpointError::Api { code: 400, error } if error.message == "bad request"
+    ));
+}
+
+#[test(tokio::test)]
+async fn test_verify_content_type() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "TestResponse")]
+    struct Test {}
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(200)
+            .header("Content-Type", "application/problem+json; charset=utf-8")
+            .json_body(json!({"age": 30}));
+    });
+    let r = Test {}.exec(&t.client).await.unwrap();
+    m.assert();
+    assert_eq!(r.verify_content_type().parse().unwrap().age, 30);
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(200)
+            .header("Content-Type", "text/html")
+            .body("<html>Bad Gateway</html>");
+    });
+    let r = Test {}.exec(&t.client).await.unwrap();
+    m.assert();
+    assert!(matches!(
+        r.parse(),
+        Err(ClientError::ResponseParseError { .. })
+    ));
+    match r.verify_content_type().parse() {
+        Err(ClientError::ResponseContentTypeError {
+            content_type,
+            content,
+            ..
+        }) => {
+            assert_eq!(content_type, "text/html");
+            assert_eq!(content.unwrap(), "<html>Bad Gateway</html>");
+        }
+        _ => panic!("expected a content type error"),
+    }
+}
+
+#[test]
+fn test_accept() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path")]
+    struct Json {}
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response_type = "Text", response = "String")]
+    struct Text {}
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response_type = "Raw", response = "Vec<u8>")]
+    struct Raw {}
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", accept = "application/vnd.github+json")]
+    struct Custom {}
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", headers("Accept: */*"))]
+    struct Header {}
+
+    let accept = |req: http::Request<Bytes>| {
+        req.headers()
+            .get_all(http::header::ACCEPT)
+            .iter()
+            .map(|v| v.to_str().unwrap().to_string())
+            .collect::<Vec<_>>()
+    };
+    let base = "http://myapi.com";
+    assert_eq!(accept(Json {}.request(base).unwrap()), ["application/json"]);
+    assert_eq!(accept(Text {}.request(base).unwrap()), ["text/plain"]);
+    assert!(accept(Raw {}.request(base).unwrap()).is_empty());
+    assert_eq!(
+        accept(Custom {}.request(base).unwrap()),
+        ["application/vnd.github+json"]
+    );
+    assert_eq!(accept(Header {}.request(base).unwrap()), ["*/*"]);
+}
+
+#[test(tokio::test)]
+async fn test_response_type_raw() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/blob", response_type = "Raw", response = "Vec<u8>")]
+    struct Test {}
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/blob");
+        then.status(200).body(vec![0u8, 159, 146, 150]);
+    });
+    let r = Test {}.exec(&t.client).await;
+
+    m.assert();
+    let r = r.unwrap();
+    assert_eq!(r.parse().unwrap(), vec![0u8, 159, 146, 150]);
+    assert_eq!(r.raw_ref(), &[0u8, 159, 146, 150]);
+    assert_eq!(r.into_raw(), vec![0u8, 159, 146, 150]);
+}
+
+#[test(tokio::test)]
+async fn test_exec_stream() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/blob/{self.name}")]
+    struct Test {
+        #[endpoint(skip)]
+        name: String,
+    }
+
+    let data: Vec<u8> = (0..=255u8).cycle().take(256 * 1024).collect();
+    let t = TestServer::default();
+    let m1 = t.server.mock(|when, then| {
+        when.method(GET).path("/test/blob/data");
+        then.status(200).body(data.clone());
+    });
+    let m2 = t.server.mock(|when, then| {
+        when.method(GET).path("/test/blob/missing");
+        then.status(404).body("not found");
+    });
+
+    let resp = Test {
+        name: "data".to_string(),
+    }
+    .exec_stream(&t.client)
+    .await
+    .unwrap();
+    let body = rustify::http::collect_body(resp.into_body()).await.unwrap();
+    let r = Test {
+        name: "missing".to_string(),
+    }
+    .exec_stream(&t.client)
+    .await;
+
+    m1.assert();
+    m2.assert();
+    assert_eq!(body, data);
+    assert!(matches!(
+        r,
+        Err(ClientError::ServerResponseError { code: 404, content: Some(c) }) if c == "not found"
+    ));
+}
+
+#[test(tokio::test)]
+async fn test_exec_download() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/blob")]
+    struct Test {}
+
+    let data: Vec<u8> = (0..=255u8).cycle().take(64 * 1024).collect();
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/blob");
+        then.status(200).body(data.clone());
+    });
+
+    let mut out = Vec::new();
+    let mut updates = Vec::new();
+    let written = Test {}
+        .exec_download(&t.client, &mut out, |p| updates.push(p))
+        .await
+        .unwrap();
+
+    let mut copy = Vec::new();
+    let r = Test {}.exec(&t.client).await.unwrap();
+    let copied = r.stream_to(&mut copy, |_| {}).await.unwrap();
+
+    m.assert_hits(2);
+    assert_eq!(written, data.len() as u64);
+    assert_eq!(out, data);
+    assert_eq!(copied, data.len() as u64);
+    assert_eq!(copy, data);
+    let last = updates.last().unwrap();
+    assert_eq!(last.written, data.len() as u64);
+    assert_eq!(last.total, Some(data.len() as u64));
+}
+
+#[test(tokio::test)]
+async fn test_exec_resume() {
+    use rustify::http::ByteRange;
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/blob/{self.name}")]
+    struct Test {
+        #[endpoint(skip)]
+        name: String,
+    }
+
+    let data: Vec<u8> = (0..=255u8).cycle().take(1000).collect();
+    let t = TestServer::default();
+    let m1 = t.server.mock(|when, then| {
+        when.method(GET)
+            .path("/test/blob/ranged")
+            .header("Range", "bytes=100-199");
+        then.status(206)
+            .header("Content-Range", "bytes 100-199/1000")
+            .body(&data[100..200]);
+    });
+    let m2 = t.server.mock(|when, then| {
+        when.method(GET)
+            .path("/test/blob/ranged")
+            .header("Range", "bytes=400-");
+        then.status(206)
+            .header("Content-Range", "bytes 400-999/1000")
+            .body(&data[400..]);
+    });
+    let m3 = t.server.mock(|when, then| {
+        when.method(GET).path("/test/blob/full");
+        then.status(200).body(data.clone());
+    });
+
+    let ranged = Test {
+        name: "ranged".to_string(),
+    };
+    let resp = ranged
+        .exec_range(&t.client, ByteRange::new(100, 100))
+        .await
+        .unwrap();
+    let body = rustify::http::collect_body(resp.into_body()).await.unwrap();
+    assert_eq!(body, &data[100..200]);
+
+    let mut out = data[..400].to_vec();
+    let mut last = None;
+    let total = ranged
+        .exec_resume(&t.client, &mut out, 400, |p| last = Some(p))
+        .await
+        .unwrap();
+    assert_eq!(total, 1000);
+    assert_eq!(out, data);
+    assert_eq!(last.unwrap().written, 1000);
+    assert_eq!(last.unwrap().total, Some(1000));
+
+    let full = Test {
+        name: "full".to_string(),
+    };
+    let mut out = data[..400].to_vec();
+    let total = full
+        .exec_resume(&t.client, &mut out, 400, |_| {})
+        .await
+        .unwrap();
+    assert_eq!(total, 1000);
+    assert_eq!(out, data);
+
+    m1.assert();
+    m2.assert();
+    m3.assert();
+}
+
+#[test(tokio::test)]
+async fn test_strict_parsing() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "TestResponse")]
+    struct Test {}
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "TestResponse", strict = "true")]
+    struct StrictTest {}
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(200)
+            .json_body(json!({"age": 30, "extra": {"name": "test"}}));
+    });
+
+    let r = Test {}.exec(&t.client).await.unwrap();
+    assert_eq!(r.parse().unwrap().age, 30);
+
+    match r.strict().parse() {
+        Err(ClientError::ResponseParseError { source, .. }) => {
+            assert_eq!(source.to_string(), "Unknown fields: extra")
+        }
+        _ => panic!("expected a parse error"),
+    }
+
+    let r = StrictTest {}.exec(&t.client).await.unwrap();
+    assert!(matches!(
+        r.parse(),
+        Err(ClientError::ResponseParseError { .. })
+    ));
+
+    m.assert_hits(2);
+}
+
+#[test(tokio::test)]
+async fn test_exec_pages() {
+    #[derive(Debug, Deserialize)]
+    struct Page {
+        next: Option<u64>,
+        items: Vec<u64>,
+    }
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "Page")]
+    struct Test {
+        #[endpoint(query)]
+        page: u64,
+    }
+
+    impl PaginatedEndpoint for Test {
+        fn next_page(&self, result: &EndpointResult<Page>) -> Result<Option<Self>, ClientError> {
+            Ok(result.parse()?.next.map(|page| Test { page }))
+        }
+    }
+
+    let t = TestServer::default();
+    let mocks: Vec<_> = vec![
+        (1, json!({"next": 2, "items": [1, 2]})),
+        (2, json!({"items": [3]})),
+    ]
+    .into_iter()
+    .map(|(page, body)| {
+        t.server.mock(|when, then| {
+            when.method(GET)
+                .path("/test/path")
+                .query_param("page", page.to_string());
+            then.status(200).json_body(body);
+        })
+    })
+    .collect();
+
+    let pages: Vec<Page> = Test { page: 1 }
+        .exec_pages(&t.client)
+        .try_collect()
+        .await
+        .unwrap();
+
+    mocks.iter().for_each(|m| m.assert());
+    let items: Vec<u64> = pages.into_iter().flat_map(|p| p.items).collect();
+    assert_eq!(items, vec![1, 2, 3]);
+
+    let m = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path").query_param("page", "3");
+        then.status(500);
+    });
+    let r: Result<Vec<Page>, ClientError> =
+        Test { page: 3 }.exec_pages(&t.client).try_collect().await;
+
+    m.assert();
+    assert!(r.is_err());
+}
+
+#[test(tokio::test)]
+async fn test_link_paginator() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "Vec<u64>")]
+    struct Test {}
+
+    let t = TestServer::default();
+    let first = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path");
+        then.status(200)
+            .header(
+                "Link",
+                r#"</test/path?page=3>; rel="last", </test/next?page=2>; rel="next""#,
+            )
+            .json_body(json!([1, 2]));
+    });
+    let second = t.server.mock(|when, then| {
+        when.method(GET).path("/test/next").query_param("page", "2");
+        then.status(200)
+            .header("Link", "</test/path>; rel=prev")
+            .json_body(json!([3]));
+    });
+
+    let pages: Vec<Vec<u64>> = LinkPaginator::new(Test {})
+        .exec_pages(&t.client)
+        .try_collect()
+        .await
+        .unwrap();
+
+    first.assert();
+    second.assert();
+    assert_eq!(pages, vec![vec![1, 2], vec![3]]);
+}
+
+#[test(tokio::test)]
+async fn test_cursor_paginator() {
+    #[derive(Debug, Deserialize)]
+    struct Page<T> {
+        items: T,
+    }
+
+    impl<T: DeserializeOwned + Send + Sync> Wrapper for Page<T> {
+        type Value = T;
+        const CURSOR: Option<&'static str> = Some("/meta/next");
+    }
+
+    #[derive(Clone, Endpoint)]
+    #[endpoint(path = "test/path", response = "Vec<u64>")]
+    struct Test {
+        #[endpoint(query)]
+        size: u64,
+        #[endpoint(query, cursor)]
+        after: Option<String>,
+    }
+
+    let t = TestServer::default();
+    let first = t.server.mock(|when, then| {
+        when.method(GET)
+            .path("/test/path")
+            .query_param("size", "2")
+            .matches(|req| req.query_params.iter().flatten().all(|(k, _)| k != "after"));
+        then.status(200)
+            .json_body(json!({"items": [1, 2], "meta": {"next": "abc"}}));
+    });
+    let second = t.server.mock(|when, then| {
+        when.method(GET)
+            .path("/test/path")
+            .query_param("size", "2")
+            .query_param("after", "abc");
+        then.status(200)
+            .json_body(json!({"items": [3], "meta": {"next": null}}));
+    });
+
+    let pages: Vec<Page<Vec<u64>>> = CursorPaginator::<_, Page<_>>::new(Test {
+        size: 2,
+        after: None,
+    })
+    .exec_pages(&t.client)
+    .try_collect()
+    .await
+    .unwrap();
+
+    first.assert();
+    second.assert();
+    let items: Vec<u64> = pages.into_iter().flat_map(|p| p.items).collect();
+    assert_eq!(items, vec![1, 2, 3]);
+}
+
+#[test(tokio::test)]
+async fn test_exec_all() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "Vec<u64>")]
+    struct Test {
+        #[endpoint(query)]
+        page: u64,
+    }
+
+    impl PaginatedEndpoint for Test {
+        fn next_page(
+            &self,
+            result: &EndpointResult<Vec<u64>>,
+        ) -> Result<Option<Self>, ClientError> {
+            Ok((!result.parse()?.is_empty()).then(|| Test {
+                page: self.page + 1,
+            }))
+        }
+    }
+
+    let t = TestServer::default();
+    let mocks: Vec<_> = vec![json!([1, 2]), json!([3, 4]), json!([])]
+        .into_iter()
+        .enumerate()
+        .map(|(i, body)| {
+            t.server.mock(|when, then| {
+                when.method(GET)
+                    .path("/test/path")
+                    .query_param("page", (i + 1).to_string());
+                then.status(200).json_body(body);
+            })
+        })
+        .collect();
+
+    let items = Test { page: 1 }
+        .exec_all(&t.client, PageLimit::default())
+        .await
+        .unwrap();
+    assert_eq!(items, vec![1, 2, 3, 4]);
+    mocks.iter().for_each(|m| m.assert_hits(1));
+
+    let items = Test { page: 1 }
+        .exec_all(&t.client, PageLimit::new(1))
+        .await
+        .unwrap();
+    assert_eq!(items, vec![1, 2]);
+
+    let limit = PageLimit {
+        max_items: Some(3),
+        ..Default::default()
+    };
+    let items = Test { page: 1 }.exec_all(&t.client, limit).await.unwrap();
+    assert_eq!(items, vec![1, 2, 3]);
+    mocks[0].assert_hits(3);
+    mocks[1].assert_hits(2);
+    mocks[2].assert_hits(1);
+}
+
+#[test(tokio::test)]
+async fn test_exec_batch() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path/{self.id}", response = "TestResponse")]
+    struct Test {
+        #[endpoint(skip)]
+        id: u8,
+    }
+
+    let t = TestServer::default();
+    let mocks: Vec<_> = (1..=4u8)
+        .map(|id| {
+            t.server.mock(|when, then| {
+                when.method(GET).path(format!("/test/path/{}", id));
+                match id {
+                    3 => then.status(500),
+                    _ => then.status(200).json_body(json!({ "age": id })),
+                };
+            })
+        })
+        .collect();
+
+    let results = exec_batch(&t.client, (1..=4).map(|id| Test { id }), 2).await;
+
+    mocks.iter().for_each(|m| m.assert());
+    assert_eq!(results.len(), 4);
+    for (i, r) in results.into_iter().enumerate() {
+        match i {
+            2 => assert!(r.is_err()),
+            _ => assert_eq!(r.unwrap().parse().unwrap().age, i as u8 + 1),
+        }
+    }
+}
+
+#[test(tokio::test)]
+async fn test_dyn_endpoint() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/json", response = "TestResponse")]
+    struct Json {}
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/text", response_type = "Text", response = "String")]
+    struct Text {}
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/error", method = "POST")]
+    struct Error {}
+
+    let t = TestServer::default();
+    let json = t.server.mock(|when, then| {
+        when.method(GET).path("/test/json");
+        then.status(200).json_body(json!({"age": 30}));
+    });
+    let text = t.server.mock(|when, then| {
+        when.method(GET).path("/test/text");
+        then.status(200).body("hello");
+    });
+    let error = t.server.mock(|when, then| {
+        when.method(POST).path("/test/error");
+        then.status(400)
+            .json_body(json!({"message": "bad request"}));
+    });
+
+    let endpoints = vec![
+        DynEndpoint::new(Json {}),
+        DynEndpoint::new(Text {}),
+        DynEndpoint::new(Error {}),
+    ];
+    let results = exec_batch(&t.client, endpoints, 3).await;
+
+    json.assert();
+    text.assert();
+    error.assert();
+    let mut results = results.into_iter();
+    let r = results.next().unwrap().unwrap().parse().unwrap();
+    assert_eq!(r, json!({"age": 30}));
+    let r = results.next().unwrap().unwrap().parse().unwrap();
+    assert_eq!(r, json!("hello"));
+    let err = results.next().unwrap().err().unwrap();
+    assert!(matches!(
+        err,
+        ClientError::ServerResponseError { code: 400, .. }
+    ));
+}
+
+#[test(tokio::test)]
+async fn test_dyn_endpoint_exec_with_retry() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/text", response_type = "Text", response = "String")]
+    struct Text {}
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/error", method = "POST")]
+    struct Error {}
+
+    let t = TestServer::default();
+    let text = t.server.mock(|when, then| {
+        when.method(GET).path("/test/text");
+        then.status(200).body("hello");
+    });
+    let error = t.server.mock(|when, then| {
+        when.method(POST).path("/test/error");
+        then.status(400)
+            .json_body(json!({"message": "bad request"}));
+    });
+
+    let policy = RetryPolicy::new(1);
+    let r = DynEndpoint::new(Text {})
+        .exec_with_retry(&t.client, &policy)
+        .await
+        .unwrap();
+    text.assert();
+    assert_eq!(r.parse().unwrap(), json!("hello"));
+
+    let err = DynEndpoint::new(Error {})
+        .exec_with_retry(&t.client, &policy)
+        .await
+        .err()
+        .unwrap();
+    error.assert();
+    assert!(matches!(
+        err,
+        ClientError::ServerResponseError { code: 400, .. }
+    ));
+}
+
+#[test(tokio::test)]
+async fn test_deadline() {
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path", response = "TestResponse")]
+    struct Test {}
+
+    fn header(req: &httpmock::prelude::HttpMockRequest, name: &str) -> Option<String> {
+        req.headers
+            .as_ref()?
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.clone())
+    }
+
+    let t = TestServer::default();
+    let millis = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path").matches(|req| {
+            header(req, "x-request-timeout")
+                .and_then(|v| v.parse::<u64>().ok())
+                .is_some_and(|v| v > 0 && v <= 5000)
+        });
+        then.status(200).json_body(json!({"age": 30}));
+    });
+    let r = Test {}
+        .with_middleware(&Deadline::after(Duration::from_secs(5)))
+        .exec(&t.client)
+        .await;
+    millis.assert();
+    assert_eq!(r.unwrap().parse().unwrap().age, 30);
+
+    let grpc = t.server.mock(|when, then| {
+        when.method(GET).path("/test/path").matches(|req| {
+            header(req, "grpc-timeout").is_some_and(|v| {
+                v.len() <= 9 && v.ends_with('u') && v[..v.len() - 1].parse::<u64>().is_ok()
+            })
+        });
+        then.status(200).json_body(json!({"age": 30}));
+    });
+    let r = Test {}
+        .with_middleware(&Deadline::after(Duration::from_secs(5)).grpc())
+        .exec(&t.client)
+        .await;
+    grpc.assert();
+    assert!(r.is_ok());
+
+    let deadline = Deadline::new(std::time::Instant::now());
+    let r = Test {}.with_middleware(&deadline).exec(&t.client).await;
+    assert!(matches!(r, Err(ClientError::Timeout { .. })));
+    assert_eq!(deadline.remaining(), None);
+}
+
+#[cfg(feature = "hmac")]
+#[test(tokio::test)]
+async fn test_hmac_signer() {
+    use rustify::signing::{HmacAlgorithm, HmacSigner, SignatureEncoding};
+
+    #[derive(Debug, Endpoint, Serialize)]
+    #[endpoint(path = "v1/users", method = "POST")]
+    struct Test {
+        name: String,
+    }
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(POST)
+            .path("/v1/users")
+            .header_exists("X-Timestamp")
+            .header_exists("X-Signature");
+        then.status(200);
+    });
+    let signer = HmacSigner::new(b"mysecret");
+    let r = Test {
+        name: "test".to_string(),
+    }
+    .with_middleware(&signer)
+    .exec(&t.client)
+    .await;
+    m.assert();
+    assert!(r.is_ok());
+
+    let req = http::Request::builder()
+        .method("POST")
+        .uri("http://localhost/v1/users")
+        .body(Bytes::from(r#"{"name":"test"}"#))
+        .unwrap();
+    assert_eq!(
+        signer.sign(&req, 1700000000),
+        "a32bb1ff3014dd89bec1b775a75004232550ce9a9b0eb27fda0dd170c242a4b0"
+    );
+
+    let mut signer = HmacSigner::with_algorithm(b"mysecret", HmacAlgorithm::Sha512).canonical(
+        |req: &http::Request<Bytes>, _: u64| {
+            format!("{} {}", req.method(), req.uri().path()).into_bytes()
+        },
+    );
+    signer.encoding = SignatureEncoding::Base64;
+    assert_eq!(
+        signer.sign(&req, 1700000000),
+        "GirEwnWJjpWxzlKMDc7esbFzjJpSjjFxYO+3eLf5aJ6LFmUC2O5KwT7L/tGtveHn2HlBy8/li9XTfMHO73D5Ow=="
+    );
+}
+
+#[cfg(feature = "hmac")]
+#[test(tokio::test)]
+async fn test_hmac_signer_client_defaults() {
+    use rustify::{
+        clients::reqwest::Client,
+        config::ClientConfig,
+        signing::{Canonical, Component, HmacSigner},
+    };
+
+    #[derive(Debug, Endpoint, Serialize)]
+    #[endpoint(path = "v1/users")]
+    struct Test {
+        #[endpoint(query)]
+        page: u64,
+    }
+
+    let signer = HmacSigner::new(b"mysecret").canonical(Canonical::new(vec![
+        Component::Path,
+        Component::Query,
+        Component::Header(HeaderName::from_static("x-tenant")),
+    ]));
+    let expected = http::Request::builder()
+        .uri("http://localhost/v1/users?page=2&api_key=mykey")
+        .header("X-Tenant", "acme")
+        .body(Bytes::new())
+        .unwrap();
+    let signature = signer.sign(&expected, 0);
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET)
+            .path("/v1/users")
+            .query_param("page", "2")
+            .query_param("api_key", "mykey")
+            .header("X-Tenant", "acme")
+            .header("X-Signature", signature.as_str());
+        then.status(200);
+    });
+    let config = ClientConfig::default()
+        .default_header(
+            HeaderName::from_static("x-tenant"),
+            HeaderValue::from_static("acme"),
+        )
+        .default_query_param("api_key", "mykey");
+    let client = Client::with_config(&t.server.base_url(), &config).unwrap();
+    let r = Test { page: 2 }
+        .with_middleware(&signer)
+        .exec(&client)
+        .await;
+    m.assert();
+    assert!(r.is_ok());
+}
+
+#[cfg(feature = "propagation")]
+#[test(tokio::test)]
+async fn test_trace_propagation() {
+    use rustify::propagation::{PropagationFormat, TraceContext, TracePropagation};
+
+    #[derive(Endpoint)]
+    #[endpoint(path = "test/path")]
+    struct Test {}
+
+    let context = TraceContext::parse(
+        "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+        Some("congo=t61rcWkgMzE"),
+    )
+    .unwrap();
+    assert_eq!(context.trace_id, 0x4bf92f3577b34da6a3ce929d0e0e4736);
+    assert_eq!(context.span_id, 0x00f067aa0ba902b7);
+    assert!(context.sampled);
+    assert!(TraceContext::parse(
+        "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+        None
+    )
+    .is_none());
+    assert!(TraceContext::parse("00-4bf92f3577b34da6-00f067aa0ba902b7-01", None).is_none());
+
+    let t = TestServer::default();
+    let m = t.server.mock(|when, then| {
+        when.method(GET)
+            .path("/test/path")
+            .header(
+                "traceparent",
+                "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            )
+            .header("tracestate", "congo=t61rcWkgMzE");
+        then.status(200);
+    });
+    let source = context.clone();
+    let propagation = TracePropagation::new(move || Some(source.clone()));
+    let r = Test {}.with_middleware(&propagation).exec(&t.client).await;
+    m.assert();
+    assert!(r.is_ok());
+
+    let b3 = t.server.mock(|when, then| {
+        when.method(GET)
+            .path("/test/path")
+            .header("b3", "4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-1");
+        then.status(200);
+    });
+    let source = context.clone();
+    let propagation =
+        TracePropagation::new(move || Some(source.clone())).format(PropagationFormat::B3Single);
+    let r = Test {}.with_middleware(&propagation).exec(&t.client).await;
+    b3.assert();
+    assert!(r.is_ok());
+
+    let b3 = t.server.mock(|when, then| {
+        when.method(GET)
+            .path("/test/path")
+            .header("X-B3-TraceId", "4bf92f3577b34da6a3ce929d0e0e4736")
+            .header("X-B3-SpanId", "00f067aa0ba902b7")
+            .header("X-B3-Sampled", "1");
+        then.status(200);
+    });
+    let source = context.clone();
+    let propagation =
+        TracePropagation::new(move || Some(source.clone())).format(PropagationFormat::B3Multi);
+    let r = Test {}.with_middleware(&propagation).exec(&t.client).await;
+    b3.assert();
+    assert!(r.is_ok());
+
+    let root = TracePropagation::root().context();
+    assert!(root.sampled);
+    assert_eq!(TraceContext::parse(&root.traceparent(), None), Some(root));
+}
@@ -0,0 +1,12 @@
+use rustify::endpoint::Endpoint;
+use rustify_derive::Endpoint;
+use serde::Serialize;
+
+#[derive(Debug, Endpoint, Serialize)]
+#[endpoint(path = "test/path")]
+struct Test {
+    #[endpoint(cursor)]
+    pub cursor: Option<String>,
+}
+
+fn main() {}